@@ -11,8 +11,133 @@ use quote::ToTokens;
 use syn::{Lit, Meta, MetaNameValue, NestedMeta};
 
 decl_derive!([ProcessAnnotatedValue, attributes(process_annotated_value)] => process_item_derive);
+decl_derive!([JsonSchema, attributes(serde, process_annotated_value)] => json_schema_derive);
+decl_derive!([Traverse, attributes(serde)] => traverse_derive);
+
+/// Arguments of a `process_annotated_value` attribute on a container or field.
+#[derive(Default)]
+struct ProcessAttrs {
+    present: bool,
+    pii_kind: Option<TokenStream>,
+    cap: Option<TokenStream>,
+    process_with: Option<TokenStream>,
+}
+
+impl ProcessAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> ProcessAttrs {
+        let mut rv = ProcessAttrs::default();
+        for attr in attrs {
+            let meta = match attr.interpret_meta() {
+                Some(meta) => meta,
+                None => continue,
+            };
+            if meta.name() == "process_annotated_value" {
+                rv.present = true;
+            } else {
+                continue;
+            }
+
+            if let Meta::List(metalist) = meta {
+                for nested_meta in metalist.nested {
+                    match nested_meta {
+                        NestedMeta::Literal(..) => panic!("unexpected literal attribute"),
+                        NestedMeta::Meta(meta) => match meta {
+                            Meta::NameValue(MetaNameValue { ident, lit, .. }) => {
+                                if ident == "pii_kind" {
+                                    match lit {
+                                        Lit::Str(litstr) => {
+                                            rv.pii_kind =
+                                                Some(pii_kind_to_enum_variant(&litstr.value()));
+                                        }
+                                        _ => {
+                                            panic!("Got non string literal for pii_kind");
+                                        }
+                                    }
+                                } else if ident == "cap" {
+                                    match lit {
+                                        Lit::Str(litstr) => {
+                                            rv.cap = Some(cap_to_enum_variant(&litstr.value()));
+                                        }
+                                        _ => {
+                                            panic!("Got non string literal for cap");
+                                        }
+                                    }
+                                } else if ident == "process_with" {
+                                    match lit {
+                                        Lit::Str(litstr) => {
+                                            let path: syn::Path =
+                                                syn::parse_str(&litstr.value()).unwrap();
+                                            rv.process_with = Some(path.into_token_stream());
+                                        }
+                                        _ => {
+                                            panic!("Got non string literal for process_with");
+                                        }
+                                    }
+                                }
+                            }
+                            other => {
+                                panic!("Unexpected or bad attribute {}", other.name());
+                            }
+                        },
+                    }
+                }
+            }
+        }
+        rv
+    }
+
+    /// Fills in the arguments that a field does not specify from the container.
+    ///
+    /// `process_with` only applies to the field it is declared on.
+    fn inherit(self, container: &ProcessAttrs) -> ProcessAttrs {
+        ProcessAttrs {
+            present: self.present || container.present,
+            pii_kind: self.pii_kind.or_else(|| container.pii_kind.clone()),
+            cap: self.cap.or_else(|| container.cap.clone()),
+            process_with: self.process_with,
+        }
+    }
+}
+
+/// Returns the type argument of a path type with the given name, such as `T` in `Annotated<T>`.
+fn type_argument<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    let segment = match *ty {
+        syn::Type::Path(ref type_path) => type_path.path.segments.iter().last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+
+    match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref arguments) => match *arguments.args.iter().next()? {
+            syn::GenericArgument::Type(ref ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `true` if the type is `Annotated<Map<Value>>`, the type of unknown fields.
+fn is_other_map(ty: &syn::Type) -> bool {
+    let value = match type_argument(ty, "Annotated").and_then(|ty| type_argument(ty, "Map")) {
+        Some(value) => value,
+        None => return false,
+    };
+
+    match *value {
+        syn::Type::Path(ref type_path) => match type_path.path.segments.iter().last() {
+            Some(segment) => segment.ident == "Value",
+            None => false,
+        },
+        _ => false,
+    }
+}
 
 fn process_item_derive(s: synstructure::Structure) -> TokenStream {
+    // container attributes apply to all fields that are not skipped by serde
+    let container = ProcessAttrs::parse(&s.ast().attrs);
+
     let mut body = TokenStream::new();
     for variant in s.variants() {
         let mut variant = variant.clone();
@@ -21,65 +146,27 @@ fn process_item_derive(s: synstructure::Structure) -> TokenStream {
         }
         let mut variant_body = TokenStream::new();
         for bi in variant.bindings() {
-            let mut pii_kind = None;
-            let mut cap = None;
-            let mut process_annotated_value = false;
-            for attr in &bi.ast().attrs {
-                let meta = match attr.interpret_meta() {
-                    Some(meta) => meta,
-                    None => continue,
-                };
-                if meta.name() == "process_annotated_value" {
-                    process_annotated_value = true;
-                } else {
-                    continue;
-                }
-
-                if let Meta::List(metalist) = meta {
-                    process_annotated_value = true;
-                    for nested_meta in metalist.nested {
-                        match nested_meta {
-                            NestedMeta::Literal(..) => panic!("unexpected literal attribute"),
-                            NestedMeta::Meta(meta) => match meta {
-                                Meta::NameValue(MetaNameValue { ident, lit, .. }) => {
-                                    if ident == "pii_kind" {
-                                        match lit {
-                                            Lit::Str(litstr) => {
-                                                pii_kind =
-                                                    Some(pii_kind_to_enum_variant(&litstr.value()));
-                                            }
-                                            _ => {
-                                                panic!("Got non string literal for pii_kind");
-                                            }
-                                        }
-                                    } else if ident == "cap" {
-                                        match lit {
-                                            Lit::Str(litstr) => {
-                                                cap = Some(cap_to_enum_variant(&litstr.value()));
-                                            }
-                                            _ => {
-                                                panic!("Got non string literal for cap");
-                                            }
-                                        }
-                                    }
-                                }
-                                other => {
-                                    panic!("Unexpected or bad attribute {}", other.name());
-                                }
-                            },
-                        }
-                    }
-                }
+            let serde_attrs = SerdeFieldAttrs::parse(&bi.ast().attrs);
+            let mut attrs = ProcessAttrs::parse(&bi.ast().attrs);
+            if !serde_attrs.skip {
+                attrs = attrs.inherit(&container);
             }
 
-            if process_annotated_value {
-                let pii_kind = pii_kind
+            if attrs.present {
+                let pii_kind = attrs
+                    .pii_kind
                     .map(|x| quote!(Some(__processor::#x)))
                     .unwrap_or_else(|| quote!(None));
-                let cap = cap.map(|x| quote!(Some(__processor::#x)))
+                let cap = attrs
+                    .cap
+                    .map(|x| quote!(Some(__processor::#x)))
                     .unwrap_or_else(|| quote!(None));
+                // fields can replace the default processing with a function of the same signature
+                let process = attrs.process_with.unwrap_or_else(|| {
+                    quote!(__processor::ProcessAnnotatedValue::process_annotated_value)
+                });
                 (quote! {
-                    #bi = __processor::ProcessAnnotatedValue::process_annotated_value(
+                    #bi = #process(
                         #bi, __processor, &__processor::ValueInfo
                     {
                         pii_kind: #pii_kind,
@@ -92,6 +179,13 @@ fn process_item_derive(s: synstructure::Structure) -> TokenStream {
                     #bi = #bi;
                 }).to_tokens(&mut variant_body);
             }
+
+            // flattened maps collect unknown fields
+            if serde_attrs.flatten && is_other_map(&bi.ast().ty) {
+                (quote! {
+                    #bi = __processor::Processor::process_other(__processor, #bi);
+                }).to_tokens(&mut variant_body);
+            }
         }
 
         let pat = variant.pat();
@@ -139,6 +233,10 @@ fn pii_kind_to_enum_variant(name: &str) -> TokenStream {
         "name" => quote!(PiiKind::Name),
         "email" => quote!(PiiKind::Email),
         "databag" => quote!(PiiKind::Databag),
+        "vars" => quote!(PiiKind::Vars),
+        "auth_token" => quote!(PiiKind::AuthToken),
+        "location" => quote!(PiiKind::Location),
+        "phone" => quote!(PiiKind::Phone),
         _ => panic!("invalid pii_kind variant '{}'", name),
     }
 }
@@ -153,3 +251,242 @@ fn cap_to_enum_variant(name: &str) -> TokenStream {
         _ => panic!("invalid cap variant '{}'", name),
     }
 }
+
+/// Serde attributes of a field relevant for processing, traversal and schema generation.
+#[derive(Default)]
+struct SerdeFieldAttrs {
+    rename: Option<String>,
+    default: bool,
+    flatten: bool,
+    skip: bool,
+    skip_serializing_if: bool,
+}
+
+impl SerdeFieldAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> SerdeFieldAttrs {
+        let mut rv = SerdeFieldAttrs::default();
+        for attr in attrs {
+            let metalist = match attr.interpret_meta() {
+                Some(Meta::List(metalist)) => metalist,
+                _ => continue,
+            };
+            if metalist.ident != "serde" {
+                continue;
+            }
+
+            for nested_meta in metalist.nested {
+                match nested_meta {
+                    NestedMeta::Meta(Meta::Word(ident)) => {
+                        if ident == "default" {
+                            rv.default = true;
+                        } else if ident == "flatten" {
+                            rv.flatten = true;
+                        } else if ident == "skip" || ident == "skip_serializing" {
+                            rv.skip = true;
+                        }
+                    }
+                    NestedMeta::Meta(Meta::NameValue(MetaNameValue { ident, lit, .. })) => {
+                        if ident == "rename" {
+                            match lit {
+                                Lit::Str(litstr) => rv.rename = Some(litstr.value()),
+                                _ => panic!("Got non string literal for rename"),
+                            }
+                        } else if ident == "default" {
+                            rv.default = true;
+                        } else if ident == "skip_serializing_if" {
+                            rv.skip_serializing_if = true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        rv
+    }
+}
+
+fn json_schema_derive(s: synstructure::Structure) -> TokenStream {
+    if s.variants().len() != 1 {
+        panic!("JsonSchema can only be derived for structs");
+    }
+
+    let container = ProcessAttrs::parse(&s.ast().attrs);
+    let bindings = s.variants()[0].bindings();
+    let mut body = TokenStream::new();
+    let mut register_body = TokenStream::new();
+
+    if bindings.len() == 1 && bindings[0].ast().ident.is_none() {
+        // newtypes have the same schema as their inner value
+        let ty = &bindings[0].ast().ty;
+        (quote! {
+            <#ty as __protocol::JsonSchema>::json_schema()
+        }).to_tokens(&mut body);
+        (quote! {
+            <#ty as __protocol::JsonSchema>::register_fields(__path, __registry);
+        }).to_tokens(&mut register_body);
+    } else {
+        let mut properties = TokenStream::new();
+        for bi in bindings {
+            let field = bi.ast();
+            let attrs = SerdeFieldAttrs::parse(&field.attrs);
+            if attrs.skip || attrs.flatten {
+                continue;
+            }
+
+            let name = match attrs.rename {
+                Some(name) => name,
+                None => field
+                    .ident
+                    .as_ref()
+                    .expect("JsonSchema cannot be derived for tuple structs")
+                    .to_string(),
+            };
+
+            // fields that are always serialized and have no default are required
+            let required = !attrs.default && !attrs.skip_serializing_if;
+            let ty = &field.ty;
+            (quote! {
+                __properties.push((
+                    #name,
+                    <#ty as __protocol::JsonSchema>::json_schema(),
+                    #required,
+                ));
+            }).to_tokens(&mut properties);
+
+            let process_attrs = ProcessAttrs::parse(&field.attrs).inherit(&container);
+            let (pii_kind, cap) = if process_attrs.present {
+                (process_attrs.pii_kind, process_attrs.cap)
+            } else {
+                (None, None)
+            };
+            let pii_kind = pii_kind
+                .map(|x| quote!(Some(__processor::#x)))
+                .unwrap_or_else(|| quote!(None));
+            let cap = cap
+                .map(|x| quote!(Some(__processor::#x)))
+                .unwrap_or_else(|| quote!(None));
+            (quote! {
+                __protocol::register_field::<#ty>(
+                    __registry,
+                    __path,
+                    #name,
+                    #pii_kind,
+                    #cap,
+                    #required,
+                );
+            }).to_tokens(&mut register_body);
+        }
+
+        (quote! {
+            let mut __properties = Vec::new();
+            #properties
+            __protocol::object_schema(__properties)
+        }).to_tokens(&mut body);
+    }
+
+    s.gen_impl(quote! {
+        use processor as __processor;
+        use protocol as __protocol;
+
+        gen impl __protocol::JsonSchema for @Self {
+            fn json_schema() -> __protocol::Schema {
+                #body
+            }
+
+            fn register_fields(
+                __path: &str,
+                __registry: &mut Vec<__protocol::FieldSchema>,
+            ) {
+                #register_body
+            }
+        }
+    })
+}
+
+fn traverse_derive(s: synstructure::Structure) -> TokenStream {
+    if s.variants().len() != 1 {
+        panic!("Traverse can only be derived for structs");
+    }
+
+    let bindings = s.variants()[0].bindings();
+    let mut child_body = TokenStream::new();
+    let mut child_mut_body = TokenStream::new();
+
+    if bindings.len() == 1 && bindings[0].ast().ident.is_none() {
+        // newtypes expose the children of their inner value
+        (quote! {
+            __protocol::Traverse::child(&self.0, __key)
+        }).to_tokens(&mut child_body);
+        (quote! {
+            __protocol::Traverse::child_mut(&mut self.0, __key)
+        }).to_tokens(&mut child_mut_body);
+    } else {
+        let mut arms = TokenStream::new();
+        let mut mut_arms = TokenStream::new();
+        let mut fallback = quote!(None);
+        let mut mut_fallback = quote!(None);
+
+        for bi in bindings {
+            let field = bi.ast();
+            let attrs = SerdeFieldAttrs::parse(&field.attrs);
+            let ident = field
+                .ident
+                .as_ref()
+                .expect("Traverse cannot be derived for tuple structs");
+
+            if attrs.skip {
+                continue;
+            } else if attrs.flatten {
+                // unknown keys are looked up in the flattened map
+                fallback = quote!(__protocol::PathNode::child(&self.#ident, __key));
+                mut_fallback = quote!(__protocol::PathNode::child_mut(&mut self.#ident, __key));
+                continue;
+            }
+
+            let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+            (quote! {
+                #name => Some(&self.#ident as &__protocol::PathNode),
+            }).to_tokens(&mut arms);
+            (quote! {
+                #name => Some(&mut self.#ident as &mut __protocol::PathNode),
+            }).to_tokens(&mut mut_arms);
+        }
+
+        (quote! {
+            match __key {
+                #arms
+                _ => #fallback,
+            }
+        }).to_tokens(&mut child_body);
+        (quote! {
+            match __key {
+                #mut_arms
+                _ => #mut_fallback,
+            }
+        }).to_tokens(&mut child_mut_body);
+    }
+
+    s.gen_impl(quote! {
+        use protocol as __protocol;
+
+        gen impl __protocol::Traverse for @Self {
+            fn child(&self, __key: &str) -> Option<&__protocol::PathNode> {
+                #child_body
+            }
+
+            fn child_mut(&mut self, __key: &str) -> Option<&mut __protocol::PathNode> {
+                #child_mut_body
+            }
+
+            fn to_value(&self) -> Result<__protocol::Value, __protocol::PathError> {
+                __protocol::serde_to_value(self)
+            }
+
+            fn from_value(
+                __value: __protocol::Value,
+            ) -> Result<Self, __protocol::PathError> {
+                __protocol::serde_from_value(__value)
+            }
+        }
+    })
+}
@@ -0,0 +1,61 @@
+extern crate failure;
+extern crate marshal;
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use failure::Error;
+
+use marshal::protocol::{Annotated, Event};
+
+const ITERATIONS: u32 = 1000;
+
+fn main() {
+    run().unwrap();
+}
+
+fn measure<F: FnMut() -> Result<(), Error>>(mut f: F) -> Result<Duration, Error> {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f()?;
+    }
+    Ok(start.elapsed() / ITERATIONS)
+}
+
+fn run() -> Result<(), Error> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "tests/payloads/cocoa.json".to_string());
+    let value: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+    let from_json = measure(|| {
+        let json = serde_json::to_string(&value)?;
+        Annotated::<Event>::from_json(&json)?;
+        Ok(())
+    })?;
+
+    let from_value = measure(|| {
+        Annotated::<Event>::from_value(value.clone())?;
+        Ok(())
+    })?;
+
+    let event = Annotated::<Event>::from_value(value.clone())?;
+
+    let to_json = measure(|| {
+        serde_json::from_str::<serde_json::Value>(&event.to_json()?)?;
+        Ok(())
+    })?;
+
+    let to_value = measure(|| {
+        event.to_value()?;
+        Ok(())
+    })?;
+
+    println!("from_json(to_string(value)): {:?}", from_json);
+    println!("from_value(value):           {:?}", from_value);
+    println!("from_str(to_json()):         {:?}", to_json);
+    println!("to_value():                  {:?}", to_value);
+    Ok(())
+}
@@ -0,0 +1,69 @@
+/* C bindings for marshal, built as a dynamic library with `make cabi`. */
+
+#ifndef MARSHAL_H_INCLUDED
+#define MARSHAL_H_INCLUDED
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* Indicates the type of the last error. */
+enum MarshalErrorCode {
+  MARSHAL_ERROR_CODE_NO_ERROR = 0,
+  MARSHAL_ERROR_CODE_PANIC = 1,
+  MARSHAL_ERROR_CODE_UNKNOWN = 2,
+  MARSHAL_ERROR_CODE_INVALID_UTF8 = 101,
+  MARSHAL_ERROR_CODE_INVALID_JSON = 102,
+  MARSHAL_ERROR_CODE_NULL_POINTER = 103,
+};
+typedef uint32_t MarshalErrorCode;
+
+/* Opaque handle to an annotated event. */
+typedef struct MarshalEvent MarshalEvent;
+
+/* Opaque handle to a PII config. */
+typedef struct MarshalPiiConfig MarshalPiiConfig;
+
+/*
+ * A length-prefixed UTF-8 string.
+ *
+ * Strings created by the library are not null terminated.  If `owned` is set, the string must
+ * be freed with `marshal_str_free`.
+ */
+typedef struct {
+  char *data;
+  size_t len;
+  bool owned;
+} MarshalStr;
+
+/* Returns the code of the last error on this thread. */
+MarshalErrorCode marshal_err_get_last_code(void);
+
+/* Returns the message of the last error on this thread. */
+MarshalStr marshal_err_get_last_message(void);
+
+/* Clears the last error on this thread. */
+void marshal_err_clear(void);
+
+/* Frees a string returned by the library. */
+void marshal_str_free(MarshalStr *string);
+
+/* Parses an event from a JSON string, including its `_meta` data. */
+MarshalEvent *marshal_event_parse(const MarshalStr *json);
+
+/* Frees an event. */
+void marshal_event_free(MarshalEvent *event);
+
+/* Serializes an event into a JSON string, including its `_meta` data. */
+MarshalStr marshal_event_to_json(const MarshalEvent *event);
+
+/* Parses a PII config from a JSON string. */
+MarshalPiiConfig *marshal_pii_config_parse(const MarshalStr *json);
+
+/* Frees a PII config. */
+void marshal_pii_config_free(MarshalPiiConfig *config);
+
+/* Applies the rules of a PII config to an event in place. */
+bool marshal_pii_config_apply(const MarshalPiiConfig *config, MarshalEvent *event);
+
+#endif /* MARSHAL_H_INCLUDED */
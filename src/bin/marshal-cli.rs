@@ -0,0 +1,80 @@
+//! Scrubs an event read from stdin and writes the result to stdout.
+//!
+//! ```text
+//! marshal-cli --config rules.json [--canonical] [--with-meta] < event.json
+//! ```
+extern crate clap;
+extern crate failure;
+extern crate marshal;
+extern crate serde_json;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use clap::{App, Arg, ArgMatches};
+use failure::Error;
+
+use marshal::processor::{normalize_event, NormalizeConfig, PiiConfig};
+use marshal::protocol::{Annotated, Event};
+use marshal::Error as MarshalError;
+
+fn app() -> App<'static, 'static> {
+    App::new("marshal-cli")
+        .about("Scrubs an event read from stdin and writes the result to stdout.")
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the PII config file in JSON format."),
+        )
+        .arg(
+            Arg::with_name("with_meta")
+                .long("with-meta")
+                .help("Includes meta data in the `_meta` key of the output."),
+        )
+        .arg(
+            Arg::with_name("canonical")
+                .long("canonical")
+                .help("Normalizes the event into the canonical protocol before scrubbing."),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let config_path = matches.value_of("config").unwrap();
+    let config = PiiConfig::from_json(&fs::read_to_string(config_path)?)
+        .map_err(MarshalError::invalid_config)?;
+
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let mut event =
+        Annotated::<Event>::from_json(&input).map_err(|e| MarshalError::parse(e, &input))?;
+
+    if matches.is_present("canonical") {
+        if let Some(event) = event.value_mut() {
+            normalize_event(event, &NormalizeConfig::default());
+        }
+    }
+
+    let event = config.processor().process_event(event);
+    let output = if matches.is_present("with_meta") {
+        event.to_json()?
+    } else {
+        serde_json::to_string(&event)?
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(stdout, "{}", output)?;
+    Ok(())
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(error) = run(&matches) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,333 @@
+//! C bindings for parsing, processing and serializing events.
+//!
+//! All functions are prefixed with `marshal_` and declared in `include/marshal.h`.  Build the
+//! dynamic library with `make cabi`, which compiles the crate as a `cdylib`.
+//!
+//! # Ownership
+//!
+//! - Pointers passed into a function are borrowed for the duration of the call only.
+//! - Strings returned as `MarshalStr` are owned by the caller if `owned` is set and must be
+//!   released with `marshal_str_free`.
+//! - Events and configs are opaque heap objects that must be released with
+//!   `marshal_event_free` and `marshal_pii_config_free` respectively.
+//!
+//! # Errors
+//!
+//! Functions never unwind into C.  On failure, they return a null pointer, an empty string or
+//! `false` and record the error in thread local storage.  It can be inspected with
+//! `marshal_err_get_last_code` and `marshal_err_get_last_message`.
+
+use std::cell::RefCell;
+use std::mem;
+use std::os::raw::c_char;
+use std::panic;
+use std::ptr;
+use std::slice;
+use std::str;
+
+use error::Error;
+use processor::PiiConfig;
+use protocol::{Annotated, Event};
+
+/// Indicates the type of the last error.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarshalErrorCode {
+    /// No error occurred.
+    NoError = 0,
+    /// The library panicked.
+    Panic = 1,
+    /// An unknown error occurred.
+    Unknown = 2,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 101,
+    /// A JSON payload could not be parsed or serialized.
+    InvalidJson = 102,
+    /// A required pointer argument was null.
+    NullPointer = 103,
+}
+
+/// A length-prefixed UTF-8 string.
+///
+/// Strings created by the library are not null terminated.
+#[repr(C)]
+pub struct MarshalStr {
+    /// Pointer to the UTF-8 encoded data.
+    pub data: *mut c_char,
+    /// Length of the data in bytes.
+    pub len: usize,
+    /// Indicates that the string is owned and must be freed with `marshal_str_free`.
+    pub owned: bool,
+}
+
+impl MarshalStr {
+    fn empty() -> MarshalStr {
+        MarshalStr {
+            data: ptr::null_mut(),
+            len: 0,
+            owned: false,
+        }
+    }
+
+    fn from_string(string: String) -> MarshalStr {
+        let mut string = string.into_boxed_str();
+        let rv = MarshalStr {
+            data: string.as_mut_ptr() as *mut c_char,
+            len: string.len(),
+            owned: true,
+        };
+        mem::forget(string);
+        rv
+    }
+
+    unsafe fn as_str(&self) -> Result<&str, FfiError> {
+        if self.data.is_null() {
+            return Ok("");
+        }
+
+        let bytes = slice::from_raw_parts(self.data as *const u8, self.len);
+        str::from_utf8(bytes).map_err(|e| FfiError::new(MarshalErrorCode::InvalidUtf8, e))
+    }
+}
+
+/// Opaque handle to an annotated event.
+pub struct MarshalEvent(Annotated<Event>);
+
+/// Opaque handle to a PII config.
+pub struct MarshalPiiConfig(PiiConfig);
+
+/// An error with an error code for C.
+struct FfiError {
+    code: MarshalErrorCode,
+    message: String,
+}
+
+impl FfiError {
+    fn new<E: ToString>(code: MarshalErrorCode, error: E) -> FfiError {
+        FfiError {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<FfiError>> = RefCell::new(None);
+}
+
+fn set_last_error(error: FfiError) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+}
+
+/// Runs the body, converting errors and panics into the last error.
+fn landingpad<F, T>(default: T, f: F) -> T
+where
+    F: FnOnce() -> Result<T, FfiError>,
+{
+    match panic::catch_unwind(panic::AssertUnwindSafe(f)) {
+        Ok(Ok(rv)) => rv,
+        Ok(Err(error)) => {
+            set_last_error(error);
+            default
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(FfiError::new(MarshalErrorCode::Panic, message));
+            default
+        }
+    }
+}
+
+fn null_pointer() -> FfiError {
+    FfiError::new(MarshalErrorCode::NullPointer, "null pointer passed")
+}
+
+unsafe fn borrow<'a, T>(pointer: *const T) -> Result<&'a T, FfiError> {
+    pointer.as_ref().ok_or_else(null_pointer)
+}
+
+unsafe fn borrow_mut<'a, T>(pointer: *mut T) -> Result<&'a mut T, FfiError> {
+    pointer.as_mut().ok_or_else(null_pointer)
+}
+
+/// Returns the code of the last error on this thread.
+#[no_mangle]
+pub extern "C" fn marshal_err_get_last_code() -> MarshalErrorCode {
+    LAST_ERROR.with(|last| match *last.borrow() {
+        Some(ref error) => error.code,
+        None => MarshalErrorCode::NoError,
+    })
+}
+
+/// Returns the message of the last error on this thread.
+///
+/// The returned string must be freed with `marshal_str_free`.
+#[no_mangle]
+pub extern "C" fn marshal_err_get_last_message() -> MarshalStr {
+    LAST_ERROR.with(|last| match *last.borrow() {
+        Some(ref error) => MarshalStr::from_string(error.message.clone()),
+        None => MarshalStr::empty(),
+    })
+}
+
+/// Clears the last error on this thread.
+#[no_mangle]
+pub extern "C" fn marshal_err_clear() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// Frees a string returned by the library.
+///
+/// Borrowed strings are left untouched.  Afterwards, the string is empty.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_str_free(string: *mut MarshalStr) {
+    if let Some(string) = string.as_mut() {
+        if string.owned && !string.data.is_null() {
+            let bytes = slice::from_raw_parts_mut(string.data as *mut u8, string.len);
+            drop(Box::from_raw(bytes as *mut [u8]));
+        }
+        *string = MarshalStr::empty();
+    }
+}
+
+/// Parses an event from a JSON string, including its `_meta` data.
+///
+/// Returns null on error.  The event must be freed with `marshal_event_free`.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_event_parse(json: *const MarshalStr) -> *mut MarshalEvent {
+    landingpad(ptr::null_mut(), || {
+        let json = borrow(json)?.as_str()?;
+        let event = Annotated::<Event>::from_json(json)
+            .map_err(|e| FfiError::new(MarshalErrorCode::InvalidJson, Error::parse(e, json)))?;
+        Ok(Box::into_raw(Box::new(MarshalEvent(event))))
+    })
+}
+
+/// Frees an event.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_event_free(event: *mut MarshalEvent) {
+    if !event.is_null() {
+        drop(Box::from_raw(event));
+    }
+}
+
+/// Serializes an event into a JSON string, including its `_meta` data.
+///
+/// Returns an empty string on error.  The string must be freed with `marshal_str_free`.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_event_to_json(event: *const MarshalEvent) -> MarshalStr {
+    landingpad(MarshalStr::empty(), || {
+        let json = borrow(event)?
+            .0
+            .to_json()
+            .map_err(|e| FfiError::new(MarshalErrorCode::InvalidJson, e))?;
+        Ok(MarshalStr::from_string(json))
+    })
+}
+
+/// Parses a PII config from a JSON string.
+///
+/// Returns null on error.  The config must be freed with `marshal_pii_config_free`.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_pii_config_parse(
+    json: *const MarshalStr,
+) -> *mut MarshalPiiConfig {
+    landingpad(ptr::null_mut(), || {
+        let json = borrow(json)?.as_str()?;
+        let config = PiiConfig::from_json(json)
+            .map_err(|e| FfiError::new(MarshalErrorCode::InvalidJson, Error::invalid_config(e)))?;
+        Ok(Box::into_raw(Box::new(MarshalPiiConfig(config))))
+    })
+}
+
+/// Frees a PII config.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_pii_config_free(config: *mut MarshalPiiConfig) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Applies the rules of a PII config to an event in place.
+///
+/// Returns `false` on error, in which case the event is left unmodified.
+#[no_mangle]
+pub unsafe extern "C" fn marshal_pii_config_apply(
+    config: *const MarshalPiiConfig,
+    event: *mut MarshalEvent,
+) -> bool {
+    landingpad(false, || {
+        let config = &borrow(config)?.0;
+        let event = borrow_mut(event)?;
+        let processed = config.processor().process_event(event.0.clone());
+        event.0 = processed;
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borrowed(string: &str) -> MarshalStr {
+        MarshalStr {
+            data: string.as_ptr() as *mut c_char,
+            len: string.len(),
+            owned: false,
+        }
+    }
+
+    unsafe fn take_string(mut string: MarshalStr) -> String {
+        let rv = string.as_str().unwrap().to_string();
+        marshal_str_free(&mut string);
+        rv
+    }
+
+    #[test]
+    fn test_process_event() {
+        unsafe {
+            let event = marshal_event_parse(&borrowed(r#"{"message":"hi foo@example.com"}"#));
+            assert!(!event.is_null());
+
+            let config = marshal_pii_config_parse(&borrowed(
+                r#"{"applications": {"freeform": ["@email"]}}"#,
+            ));
+            assert!(!config.is_null());
+
+            assert!(marshal_pii_config_apply(config, event));
+            let json = take_string(marshal_event_to_json(event));
+            assert!(json.contains(r#""message":"hi [email]""#));
+
+            marshal_pii_config_free(config);
+            marshal_event_free(event);
+        }
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        unsafe {
+            marshal_err_clear();
+            let event = marshal_event_parse(&borrowed(r#"{"user": {"id": tru}}"#));
+
+            assert!(event.is_null());
+            assert_eq!(marshal_err_get_last_code(), MarshalErrorCode::InvalidJson);
+            let message = take_string(marshal_err_get_last_message());
+            assert!(message.starts_with("invalid json in user.id: "));
+
+            marshal_err_clear();
+            assert_eq!(marshal_err_get_last_code(), MarshalErrorCode::NoError);
+        }
+    }
+
+    #[test]
+    fn test_null_pointer() {
+        unsafe {
+            assert!(marshal_event_parse(ptr::null()).is_null());
+            assert_eq!(marshal_err_get_last_code(), MarshalErrorCode::NullPointer);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! The error type of this crate.
+//!
+//! Errors of individual modules, such as `PathError` or `TomlConfigError`, convert into `Error`,
+//! which allows to handle all failures of a pipeline with a single type.  Parsing functions like
+//! `Annotated::from_json` return `serde_json::Error`, which `Error::parse` converts while
+//! attaching the path of the failing field.  The C and WebAssembly bindings as well as the CLI
+//! report their errors this way.
+
+use std::fmt;
+
+use failure::{self, Fail};
+use regex;
+use serde_json;
+
+#[cfg(feature = "fixtures")]
+use fixtures::FixtureError;
+#[cfg(feature = "toml")]
+use processor::TomlConfigError;
+use protocol::{json_path_at, ParseProtocolVersionError, PathError};
+
+/// An error returned when parsing, processing or serializing events.
+#[derive(Debug)]
+pub enum Error {
+    /// A JSON document could not be parsed or serialized.
+    Parse {
+        /// The line of the error, starting at `1`.  Serialization errors have line `0`.
+        line: usize,
+        /// The column of the error, starting at `1`.
+        column: usize,
+        /// The path to the field containing the error, if known.
+        path: Option<String>,
+        /// The underlying JSON error.
+        error: serde_json::Error,
+    },
+    /// A processor configuration is invalid.
+    InvalidConfig(failure::Error),
+    /// An event could not be processed.
+    ProcessingFailed {
+        /// The path to the value that could not be processed, if known.
+        path: Option<String>,
+        /// The underlying error.
+        error: failure::Error,
+    },
+}
+
+impl Error {
+    /// Creates a parse error and attaches the path at the error location in the JSON source.
+    pub fn parse(error: serde_json::Error, source: &str) -> Error {
+        let path = json_path_at(source, error.line(), error.column());
+        Error::Parse {
+            line: error.line(),
+            column: error.column(),
+            path,
+            error,
+        }
+    }
+
+    /// Creates an error for an invalid processor configuration.
+    pub fn invalid_config<E: Into<failure::Error>>(error: E) -> Error {
+        Error::InvalidConfig(error.into())
+    }
+
+    /// Returns the path to the field that caused the error, if known.
+    pub fn path(&self) -> Option<&str> {
+        match *self {
+            Error::Parse { ref path, .. } | Error::ProcessingFailed { ref path, .. } => {
+                path.as_ref().map(|x| x.as_str())
+            }
+            Error::InvalidConfig(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse {
+                path: Some(ref path),
+                ref error,
+                ..
+            } => write!(f, "invalid json in {}: {}", path, error),
+            Error::Parse { ref error, .. } => write!(f, "invalid json: {}", error),
+            Error::InvalidConfig(ref error) => write!(f, "invalid config: {}", error),
+            Error::ProcessingFailed {
+                path: Some(ref path),
+                ref error,
+            } => write!(f, "processing failed at {}: {}", path, error),
+            Error::ProcessingFailed { ref error, .. } => write!(f, "processing failed: {}", error),
+        }
+    }
+}
+
+impl Fail for Error {
+    fn cause(&self) -> Option<&Fail> {
+        match *self {
+            Error::Parse { ref error, .. } => Some(error),
+            Error::InvalidConfig(ref error) | Error::ProcessingFailed { ref error, .. } => {
+                Some(error.as_fail())
+            }
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Parse {
+            line: error.line(),
+            column: error.column(),
+            path: None,
+            error,
+        }
+    }
+}
+
+impl From<PathError> for Error {
+    fn from(error: PathError) -> Error {
+        let path = match error {
+            PathError::NotFound(ref path) => Some(path.clone()),
+            PathError::InvalidValue(_) => None,
+        };
+        Error::ProcessingFailed {
+            path,
+            error: error.into(),
+        }
+    }
+}
+
+#[cfg(feature = "fixtures")]
+impl From<FixtureError> for Error {
+    fn from(error: FixtureError) -> Error {
+        Error::ProcessingFailed {
+            path: None,
+            error: error.into(),
+        }
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(error: regex::Error) -> Error {
+        Error::invalid_config(error)
+    }
+}
+
+impl From<ParseProtocolVersionError> for Error {
+    fn from(error: ParseProtocolVersionError) -> Error {
+        Error::invalid_config(error)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<TomlConfigError> for Error {
+    fn from(error: TomlConfigError) -> Error {
+        Error::invalid_config(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Error {
+        let error = serde_json::from_str::<serde_json::Value>(json).unwrap_err();
+        Error::parse(error, json)
+    }
+
+    #[test]
+    fn test_parse_error_path() {
+        let error = parse(r#"{"user": {"id": tru}}"#);
+        assert_eq!(error.path(), Some("user.id"));
+
+        let error = parse("{\n  \"breadcrumbs\": [\n    {},\n    {\"level\": x}\n  ]\n}");
+        assert_eq!(error.path(), Some("breadcrumbs.1.level"));
+        match error {
+            Error::Parse { line, .. } => assert_eq!(line, 4),
+            _ => panic!("expected a parse error"),
+        }
+
+        let error = parse("nope");
+        assert_eq!(error.path(), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let error = parse(r#"{"tags": [1, 2, x]}"#);
+        assert!(error.to_string().starts_with("invalid json in tags.2: "));
+
+        let error = Error::from(PathError::NotFound("user.id".into()));
+        assert_eq!(error.path(), Some("user.id"));
+        assert_eq_str!(
+            error.to_string(),
+            "processing failed at user.id: no value at path 'user.id'"
+        );
+
+        let error = Error::from(regex::Regex::new("(").unwrap_err());
+        assert!(error.to_string().starts_with("invalid config: "));
+        assert!(error.cause().is_some());
+    }
+}
@@ -0,0 +1,251 @@
+//! Snapshot tests of event processing on corpora of real-world payloads.
+//!
+//! A fixture directory contains event payloads as `.json` files.  Each payload is parsed,
+//! processed with a `PipelineConfig` and serialized with its meta data.  The result is compared
+//! to a snapshot with the same file name in the `snapshots` subdirectory, which detects changes
+//! in the behavior of this crate on the payloads.
+//!
+//! Fixtures without a snapshot fail the check, so that snapshots have to be committed along with
+//! their fixtures.  To record new snapshots or accept changed behavior, run the checks with the
+//! `MARSHAL_UPDATE_SNAPSHOTS` environment variable set, which writes all missing and mismatching
+//! snapshots.
+//!
+//! This module is only available with the `fixtures` feature.
+//!
+//! The `run_fixture_dir!` macro declares a test that checks all fixtures in a directory relative
+//! to the manifest of the crate that invokes it:
+//!
+//! ```no_run
+//! #[macro_use]
+//! extern crate marshal;
+//!
+//! run_fixture_dir!(payloads, "tests/payloads");
+//! # fn main() {}
+//! ```
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{TimeZone, Utc};
+use serde_json;
+
+use processor::{process_event, PipelineConfig};
+use protocol::{Annotated, Event, Provenance};
+
+/// Environment variable that records missing and overwrites mismatching snapshots if set.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "MARSHAL_UPDATE_SNAPSHOTS";
+
+/// An error raised when checking fixtures.
+#[derive(Debug, Fail)]
+pub enum FixtureError {
+    /// A fixture or snapshot cannot be read or written.
+    #[fail(display = "could not access {}", _0)]
+    Io(String, #[cause] io::Error),
+    /// A processed fixture cannot be serialized.
+    #[fail(display = "could not serialize {}", _0)]
+    Serialize(String, #[cause] serde_json::Error),
+}
+
+/// The result of checking a fixture against its snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FixtureOutcome {
+    /// The processed fixture matches the snapshot.
+    Matched,
+    /// The snapshot was missing and has been recorded.
+    Recorded,
+    /// The snapshot is missing.
+    Missing,
+    /// The snapshot did not match and has been overwritten.
+    Updated,
+    /// The processed fixture does not match the snapshot.
+    Mismatched {
+        /// The contents of the snapshot.
+        expected: String,
+        /// The serialization of the processed fixture.
+        actual: String,
+    },
+}
+
+impl FixtureOutcome {
+    /// Returns `true` unless the fixture does not match its snapshot or the snapshot is missing.
+    pub fn is_ok(&self) -> bool {
+        match *self {
+            FixtureOutcome::Mismatched { .. } | FixtureOutcome::Missing => false,
+            _ => true,
+        }
+    }
+}
+
+fn io_error(path: &Path, error: io::Error) -> FixtureError {
+    FixtureError::Io(path.display().to_string(), error)
+}
+
+fn write_snapshot(path: &Path, contents: &str) -> Result<(), FixtureError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+    }
+
+    fs::write(path, format!("{}\n", contents)).map_err(|e| io_error(path, e))
+}
+
+/// Parses, processes and serializes a payload like it is stored in snapshots.
+///
+/// Payloads that cannot be parsed result in an empty event with an error.  Events without a
+/// received time are considered received at their timestamp, or at 2000-01-01 if they do not
+/// have one, so that snapshots do not depend on the current time.
+pub fn process_fixture(json: &str, config: &PipelineConfig) -> Result<String, serde_json::Error> {
+    let mut event = Annotated::<Event>::from_json(json)
+        .unwrap_or_else(|error| Annotated::from_error(error.to_string()));
+
+    if let Some(event) = event.value_mut() {
+        if event.received.map_or(true, Option::is_none) {
+            let received = event
+                .timestamp
+                .value()
+                .and_then(|timestamp| *timestamp)
+                .unwrap_or_else(|| Utc.ymd(2000, 1, 1).and_hms(0, 0, 0));
+            event.received.set_value(Some(Some(received)));
+            event.received.meta_mut().set_provenance(Provenance::Server);
+        }
+    }
+
+    let (event, _) = process_event(event, config);
+    event.to_json_pretty()
+}
+
+/// Checks a single fixture against the snapshot at the given path.
+pub fn check_fixture(
+    path: &Path,
+    snapshot_path: &Path,
+    config: &PipelineConfig,
+) -> Result<FixtureOutcome, FixtureError> {
+    let input = fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+    let actual = process_fixture(&input, config)
+        .map_err(|e| FixtureError::Serialize(path.display().to_string(), e))?;
+
+    let update = env::var_os(UPDATE_SNAPSHOTS_VAR).is_some();
+    let expected = match fs::read_to_string(snapshot_path) {
+        Ok(expected) => expected,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            if !update {
+                return Ok(FixtureOutcome::Missing);
+            }
+            write_snapshot(snapshot_path, &actual)?;
+            return Ok(FixtureOutcome::Recorded);
+        }
+        Err(e) => return Err(io_error(snapshot_path, e)),
+    };
+
+    if expected.trim() == actual.trim() {
+        Ok(FixtureOutcome::Matched)
+    } else if update {
+        write_snapshot(snapshot_path, &actual)?;
+        Ok(FixtureOutcome::Updated)
+    } else {
+        Ok(FixtureOutcome::Mismatched { expected, actual })
+    }
+}
+
+/// Checks all `.json` fixtures in a directory against their snapshots.
+///
+/// Snapshots are stored in the `snapshots` subdirectory.  Returns the outcome for every
+/// fixture, sorted by path.
+pub fn check_fixture_dir(
+    dir: &Path,
+    config: &PipelineConfig,
+) -> Result<Vec<(PathBuf, FixtureOutcome)>, FixtureError> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let snapshot_dir = dir.join("snapshots");
+    let mut rv = vec![];
+    for path in paths {
+        let snapshot_path = snapshot_dir.join(path.file_name().unwrap_or_default());
+        let outcome = check_fixture(&path, &snapshot_path, config)?;
+        rv.push((path, outcome));
+    }
+
+    Ok(rv)
+}
+
+/// Checks all fixtures in a directory and panics if any of them does not match its snapshot.
+///
+/// The panic message lists fixtures without a snapshot and the first differing line of every
+/// mismatching fixture.  This is invoked by tests declared with `run_fixture_dir!`.
+pub fn assert_fixture_dir(dir: &Path, config: &PipelineConfig) {
+    let outcomes = check_fixture_dir(dir, config)
+        .unwrap_or_else(|error| panic!("could not check fixtures: {}", error));
+
+    let mut message = String::new();
+    for (path, outcome) in outcomes {
+        if outcome == FixtureOutcome::Missing {
+            message.push_str(&format!("{} has no snapshot\n", path.display()));
+        } else if let FixtureOutcome::Mismatched { expected, actual } = outcome {
+            let mut expected_lines = expected.trim().lines();
+            let mut actual_lines = actual.trim().lines();
+            let mut line = 1;
+            loop {
+                let (left, right) = (expected_lines.next(), actual_lines.next());
+                if left != right {
+                    message.push_str(&format!(
+                        "{} differs in line {}:\n- {}\n+ {}\n",
+                        path.display(),
+                        line,
+                        left.unwrap_or_default(),
+                        right.unwrap_or_default()
+                    ));
+                    break;
+                }
+                line += 1;
+            }
+        }
+    }
+
+    if !message.is_empty() {
+        panic!(
+            "fixtures do not match their snapshots:\n{}set {} to record the snapshots",
+            message, UPDATE_SNAPSHOTS_VAR
+        );
+    }
+}
+
+/// Declares a test that checks all fixtures in a directory against their snapshots.
+///
+/// The directory is relative to the manifest of the invoking crate.  Fixtures are processed with
+/// the default `PipelineConfig`, unless a config is passed as third argument.  See the `fixtures`
+/// module for more information.
+#[macro_export]
+macro_rules! run_fixture_dir {
+    ($name:ident, $dir:expr) => {
+        run_fixture_dir!($name, $dir, $crate::processor::PipelineConfig::default());
+    };
+    ($name:ident, $dir:expr, $config:expr) => {
+        #[test]
+        fn $name() {
+            let dir = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($dir);
+            $crate::fixtures::assert_fixture_dir(&dir, &$config);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_fixture() {
+        let json = process_fixture(r#"{"timestamp": 946684800}"#, &Default::default()).unwrap();
+        assert!(json.contains(r#""received": 946684800"#));
+
+        let json = process_fixture("42", &Default::default()).unwrap();
+        assert!(json.contains("_meta"));
+    }
+}
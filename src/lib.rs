@@ -5,6 +5,7 @@
 
 #![warn(missing_docs)]
 
+extern crate base64;
 extern crate chrono;
 extern crate cookie;
 extern crate debugid;
@@ -14,15 +15,29 @@ extern crate failure_derive;
 #[macro_use]
 extern crate lazy_static;
 extern crate hmac;
+extern crate lru_cache;
+#[cfg(feature = "preserve_order")]
+extern crate indexmap;
+#[cfg(feature = "fuzzing")]
+#[macro_use]
+extern crate proptest;
 extern crate queryst;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate regex;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[cfg_attr(feature = "schema", macro_use)]
 extern crate serde_json;
 extern crate sha1;
 extern crate sha2;
+#[cfg(feature = "toml")]
+extern crate toml;
+extern crate unicode_normalization;
 extern crate uuid;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
 
 #[macro_use]
 extern crate marshal_derive;
@@ -34,5 +49,14 @@ extern crate difference;
 #[macro_use]
 mod testutils;
 
+#[cfg(feature = "cffi")]
+pub mod cffi;
+pub mod error;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
 pub mod processor;
 pub mod protocol;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::Error;
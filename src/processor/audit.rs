@@ -0,0 +1,165 @@
+//! Audit records of PII rules applied to events.
+
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::ser::Serializer;
+use serde_json;
+use uuid::Uuid;
+
+use super::trace::{TraceEntry, TraceReason};
+
+/// The modification of a value by a rule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Matching parts of a string were redacted.
+    Redacted,
+    /// The entire value was replaced or removed.
+    Replaced,
+}
+
+/// A record of a rule that modified a value of an event.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditRecord {
+    /// The ID of the processed event, if it has one.
+    pub event_id: Option<Uuid>,
+    /// The time at which the event was processed.
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    /// The path of the modified value, if known.
+    pub path: Option<String>,
+    /// The ID of the applied rule.
+    pub rule_id: String,
+    /// How the rule modified the value.
+    pub action: AuditAction,
+}
+
+impl AuditRecord {
+    /// Creates a record from a trace entry, unless the rule did not modify the value.
+    pub(crate) fn from_trace_entry(
+        entry: TraceEntry,
+        event_id: Option<Uuid>,
+        timestamp: DateTime<Utc>,
+    ) -> Option<AuditRecord> {
+        let action = match entry.reason {
+            TraceReason::Redacted => AuditAction::Redacted,
+            TraceReason::Replaced => AuditAction::Replaced,
+            _ => return None,
+        };
+
+        Some(AuditRecord {
+            event_id,
+            timestamp,
+            path: entry.path,
+            rule_id: entry.rule_id,
+            action,
+        })
+    }
+}
+
+fn serialize_timestamp<S: Serializer>(
+    timestamp: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// A receiver of audit records.
+///
+/// Attach a sink to a processor with `RuleBasedPiiProcessor::with_audit`.  When processing an
+/// event, the sink receives a record for every rule that modified a value.
+pub trait AuditSink {
+    /// Receives the record of an applied rule.
+    fn record(&self, record: &AuditRecord);
+}
+
+/// An audit sink that writes every record as a line of JSON.
+///
+/// Since sinks cannot fail processing, write errors are retained and can be inspected with
+/// `take_error`.  Records are not written after an error.
+#[derive(Debug)]
+pub struct JsonLinesAuditSink<W: Write> {
+    state: Mutex<(W, Option<io::Error>)>,
+}
+
+impl<W: Write> JsonLinesAuditSink<W> {
+    /// Creates a sink that writes to the given writer.
+    pub fn new(writer: W) -> JsonLinesAuditSink<W> {
+        JsonLinesAuditSink {
+            state: Mutex::new((writer, None)),
+        }
+    }
+
+    /// Returns the first error that occurred when writing records and resets it.
+    pub fn take_error(&self) -> Option<io::Error> {
+        match self.state.lock() {
+            Ok(mut state) => state.1.take(),
+            Err(_) => Some(io::Error::new(io::ErrorKind::Other, "audit sink poisoned")),
+        }
+    }
+
+    /// Flushes the writer and returns it.
+    pub fn into_inner(self) -> io::Result<W> {
+        let (mut writer, error) = self
+            .state
+            .into_inner()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "audit sink poisoned"))?;
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> AuditSink for JsonLinesAuditSink<W> {
+    fn record(&self, record: &AuditRecord) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        if state.1.is_some() {
+            return;
+        }
+
+        let result = serde_json::to_writer(&mut state.0, record)
+            .map_err(io::Error::from)
+            .and_then(|()| state.0.write_all(b"\n"));
+
+        if let Err(error) = result {
+            state.1 = Some(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_json_lines() {
+        let sink = JsonLinesAuditSink::new(Vec::new());
+        sink.record(&AuditRecord {
+            event_id: Some("52df9022835246eeb317dbd739ccd059".parse().unwrap()),
+            timestamp: Utc.ymd(2000, 1, 1).and_hms(0, 0, 0),
+            path: Some("user.email".to_string()),
+            rule_id: "@email".to_string(),
+            action: AuditAction::Replaced,
+        });
+
+        assert!(sink.take_error().is_none());
+        let output = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+        assert_eq_str!(
+            output,
+            "{\"event_id\":\"52df9022-8352-46ee-b317-dbd739ccd059\",\
+             \"timestamp\":\"2000-01-01T00:00:00.000Z\",\
+             \"path\":\"user.email\",\"rule_id\":\"@email\",\"action\":\"replaced\"}\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Utilities for dealing with annotated strings.
 
+use std::cmp;
+
 use protocol::{Meta, Remark, RemarkType};
 
 /// A type for dealing with chunks of annotated text.
@@ -18,6 +20,8 @@ pub enum Chunk {
         rule_id: String,
         /// Type type of remark for this redaction
         ty: RemarkType,
+        /// An optional note with details on the redaction
+        note: Option<String>,
     },
 }
 
@@ -30,37 +34,69 @@ impl Chunk {
         }
     }
 
-    /// Effective length of the text in this chunk.
+    /// Effective length of the text in this chunk in unicode scalar values.
     pub fn len(&self) -> usize {
-        self.as_str().len()
+        self.as_str().chars().count()
+    }
+}
+
+/// Converts an index in unicode scalar values into a byte index.
+///
+/// Returns `None` if the index is out of bounds.  The length of the string in characters maps
+/// to the length in bytes.
+fn byte_index(text: &str, char_index: usize) -> Option<usize> {
+    text.char_indices()
+        .map(|(index, _)| index)
+        .chain(Some(text.len()))
+        .nth(char_index)
+}
+
+/// Returns a slice of the text between the given unicode scalar value indices.
+fn char_slice(text: &str, from: usize, to: Option<usize>) -> Option<&str> {
+    let start = byte_index(text, from)?;
+    match to {
+        Some(to) => text.get(start..byte_index(text, to)?),
+        None => text.get(start..),
     }
 }
 
 /// Chunks the given text based on remarks.
+///
+/// Remark ranges are interpreted as indices of unicode scalar values.  Remarks are applied in
+/// the order of their ranges.  If ranges overlap, the overlapping part belongs to the remark
+/// that starts first.
 pub fn chunks_from_str(text: &str, meta: &Meta) -> Vec<Chunk> {
     let mut rv = vec![];
     let mut pos = 0;
 
-    for remark in meta.remarks() {
-        let (from, to) = match remark.range() {
-            Some(range) => *range,
-            None => continue,
-        };
+    let mut remarks: Vec<_> = meta
+        .remarks()
+        .filter_map(|remark| remark.range().map(|range| (*range, remark)))
+        .collect();
+    remarks.sort_by_key(|&(range, _)| range);
+
+    for ((from, to), remark) in remarks {
+        let from = cmp::max(from, pos);
+        if to <= from {
+            continue;
+        }
 
         if from > pos {
-            if let Some(piece) = text.get(pos..from) {
+            if let Some(piece) = char_slice(text, pos, Some(from)) {
                 rv.push(Chunk::Text {
                     text: piece.to_string(),
                 });
+                pos = from;
             } else {
                 break;
             }
         }
-        if let Some(piece) = text.get(from..to) {
+        if let Some(piece) = char_slice(text, from, Some(to)) {
             rv.push(Chunk::Redaction {
                 text: piece.to_string(),
                 rule_id: remark.rule_id().into(),
                 ty: remark.ty(),
+                note: remark.note().map(|note| note.to_string()),
             });
         } else {
             break;
@@ -68,8 +104,8 @@ pub fn chunks_from_str(text: &str, meta: &Meta) -> Vec<Chunk> {
         pos = to;
     }
 
-    if pos < text.len() {
-        if let Some(piece) = text.get(pos..) {
+    if let Some(piece) = char_slice(text, pos, None) {
+        if !piece.is_empty() {
             rv.push(Chunk::Text {
                 text: piece.to_string(),
             });
@@ -80,6 +116,8 @@ pub fn chunks_from_str(text: &str, meta: &Meta) -> Vec<Chunk> {
 }
 
 /// Concatenates chunks into a string and places remarks inside the given meta.
+///
+/// Remark ranges are computed in unicode scalar values.
 pub fn chunks_to_string(chunks: Vec<Chunk>, mut meta: Meta) -> (String, Meta) {
     let mut rv = String::new();
     let mut remarks = vec![];
@@ -89,10 +127,17 @@ pub fn chunks_to_string(chunks: Vec<Chunk>, mut meta: Meta) -> (String, Meta) {
         let new_pos = pos + chunk.len();
         rv.push_str(chunk.as_str());
         if let Chunk::Redaction {
-            ref rule_id, ty, ..
+            ref rule_id,
+            ty,
+            ref note,
+            ..
         } = chunk
         {
-            remarks.push(Remark::with_range(ty, rule_id.clone(), (pos, new_pos)));
+            let remark = Remark::with_range(ty, rule_id.clone(), (pos, new_pos));
+            remarks.push(match *note {
+                Some(ref note) => remark.with_note(note.clone()),
+                None => remark,
+            });
         }
         pos = new_pos;
     }
@@ -129,6 +174,7 @@ mod tests {
                     ty: RemarkType::Masked,
                     text: "****@*****.com".into(),
                     rule_id: "@email:strip".into(),
+                    note: None,
                 },
                 Chunk::Text {
                     text: ". See you".into(),
@@ -152,4 +198,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_chunking_multibyte() {
+        let meta = Meta {
+            remarks: vec![Remark::with_range(RemarkType::Masked, "@test", (3, 7))],
+            ..Default::default()
+        };
+
+        let chunks = chunks_from_str("Hi 山田🎉* you", &meta);
+        assert_eq_dbg!(
+            chunks,
+            vec![
+                Chunk::Text { text: "Hi ".into() },
+                Chunk::Redaction {
+                    ty: RemarkType::Masked,
+                    text: "山田🎉*".into(),
+                    rule_id: "@test".into(),
+                    note: None,
+                },
+                Chunk::Text {
+                    text: " you".into(),
+                },
+            ]
+        );
+
+        assert_eq_dbg!(
+            chunks_to_string(chunks, Default::default()),
+            ("Hi 山田🎉* you".into(), meta)
+        );
+    }
+
+    #[test]
+    fn test_chunking_out_of_bounds() {
+        let meta = Meta {
+            remarks: vec![Remark::with_range(RemarkType::Masked, "@test", (3, 42))],
+            ..Default::default()
+        };
+
+        let chunks = chunks_from_str("Hi 山田", &meta);
+        assert_eq_dbg!(
+            chunks,
+            vec![
+                Chunk::Text { text: "Hi ".into() },
+                Chunk::Text { text: "山田".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunking_overlapping() {
+        let meta = Meta {
+            remarks: vec![
+                Remark::with_range(RemarkType::Masked, "@second", (3, 8)),
+                Remark::with_range(RemarkType::Masked, "@first", (0, 5)),
+                Remark::with_range(RemarkType::Masked, "@nested", (1, 2)),
+            ],
+            ..Default::default()
+        };
+
+        let chunks = chunks_from_str("0123456789", &meta);
+        assert_eq_dbg!(
+            chunks,
+            vec![
+                Chunk::Redaction {
+                    ty: RemarkType::Masked,
+                    text: "01234".into(),
+                    rule_id: "@first".into(),
+                    note: None,
+                },
+                Chunk::Redaction {
+                    ty: RemarkType::Masked,
+                    text: "567".into(),
+                    rule_id: "@second".into(),
+                    note: None,
+                },
+                Chunk::Text { text: "89".into() },
+            ]
+        );
+
+        let (text, meta) = chunks_to_string(chunks, Default::default());
+        assert_eq_str!(text, "0123456789");
+        assert_eq_dbg!(
+            meta.remarks,
+            vec![
+                Remark::with_range(RemarkType::Masked, "@first", (0, 5)),
+                Remark::with_range(RemarkType::Masked, "@second", (5, 8)),
+            ]
+        );
+    }
 }
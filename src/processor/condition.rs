@@ -0,0 +1,145 @@
+//! Conditions for applying rules based on event attributes.
+
+use protocol::Event;
+
+/// An event attribute that rule conditions can compare.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventField {
+    /// The platform of the event.
+    Platform,
+    /// The environment of the event.
+    Environment,
+    /// The release of the event.
+    Release,
+    /// The logger of the event.
+    Logger,
+}
+
+/// A condition on event attributes that decides whether a rule applies.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum RuleCondition {
+    /// Matches if all of the conditions match.
+    And {
+        /// The conditions to combine.
+        and: Vec<RuleCondition>,
+    },
+    /// Matches if any of the conditions matches.
+    Or {
+        /// The conditions to combine.
+        or: Vec<RuleCondition>,
+    },
+    /// Matches if the condition does not match.
+    Not {
+        /// The condition to negate.
+        not: Box<RuleCondition>,
+    },
+    /// Matches if the event attribute equals the given value.
+    Eq {
+        /// The event attribute to compare.
+        field: EventField,
+        /// The expected value.
+        eq: String,
+    },
+}
+
+impl RuleCondition {
+    /// Evaluates the condition against the attributes of an event.
+    pub(crate) fn matches(&self, context: &EventContext) -> bool {
+        match *self {
+            RuleCondition::And { ref and } => and.iter().all(|c| c.matches(context)),
+            RuleCondition::Or { ref or } => or.iter().any(|c| c.matches(context)),
+            RuleCondition::Not { ref not } => !not.matches(context),
+            RuleCondition::Eq { field, ref eq } => context.get(field) == Some(eq.as_str()),
+        }
+    }
+}
+
+/// Attributes of an event that rule conditions are evaluated against.
+///
+/// When processing values outside of an event, there is no context and rules with conditions
+/// are skipped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventContext {
+    /// The platform of the event.
+    pub platform: Option<String>,
+    /// The environment of the event.
+    pub environment: Option<String>,
+    /// The release of the event.
+    pub release: Option<String>,
+    /// The logger of the event.
+    pub logger: Option<String>,
+}
+
+impl EventContext {
+    /// Extracts the attributes of an event.
+    pub fn from_event(event: &Event) -> EventContext {
+        EventContext {
+            platform: event.platform.value().cloned(),
+            environment: event.environment.value().and_then(Clone::clone),
+            release: event.release.value().and_then(Clone::clone),
+            logger: event.logger.value().and_then(Clone::clone),
+        }
+    }
+
+    fn get(&self, field: EventField) -> Option<&str> {
+        let value = match field {
+            EventField::Platform => &self.platform,
+            EventField::Environment => &self.environment,
+            EventField::Release => &self.release,
+            EventField::Logger => &self.logger,
+        };
+
+        value.as_ref().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn context() -> EventContext {
+        EventContext {
+            platform: Some("javascript".to_string()),
+            environment: Some("production".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_eq() {
+        let condition: RuleCondition =
+            serde_json::from_str(r#"{"field": "platform", "eq": "javascript"}"#).unwrap();
+        assert!(condition.matches(&context()));
+        assert!(!condition.matches(&EventContext::default()));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let condition: RuleCondition = serde_json::from_str(
+            r#"{
+            "and": [
+                {"field": "platform", "eq": "javascript"},
+                {"not": {"field": "environment", "eq": "development"}},
+                {"or": [
+                    {"field": "logger", "eq": "console"},
+                    {"field": "environment", "eq": "production"}
+                ]}
+            ]
+        }"#,
+        ).unwrap();
+
+        assert!(condition.matches(&context()));
+        assert!(!condition.matches(&EventContext {
+            environment: Some("development".to_string()),
+            ..context()
+        }));
+    }
+
+    #[test]
+    fn test_invalid_field() {
+        assert!(serde_json::from_str::<RuleCondition>(r#"{"field": "foo", "eq": "bar"}"#).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Processing errors derived from the meta data of events.
+
+use protocol::{Annotated, Event, EventProcessingError, Meta, RemarkType, Value};
+
+use super::trim::TRIM_RULE_ID;
+
+/// The type of a processing error recorded in the `errors` of an event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventErrorType {
+    /// A value was invalid and has been removed or replaced.
+    InvalidData,
+    /// An attribute was removed or moved elsewhere during normalization.
+    InvalidAttribute,
+    /// A value exceeded its maximum size and has been trimmed.
+    ValueTooLong,
+    /// A value was modified by a PII rule.
+    ScrubbedData,
+}
+
+impl EventErrorType {
+    /// Returns the name of the error type as written to the `type` of processing errors.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventErrorType::InvalidData => "invalid_data",
+            EventErrorType::InvalidAttribute => "invalid_attribute",
+            EventErrorType::ValueTooLong => "value_too_long",
+            EventErrorType::ScrubbedData => "scrubbed_data",
+        }
+    }
+}
+
+/// Appends processing errors for all fields with errors or modifications to the event.
+///
+/// Errors are derived from the meta data of all values, so this should run after all other
+/// processing steps:
+///
+///  - Every error becomes an `invalid_data` error with the error message as value.
+///  - Trimmed values become `value_too_long` errors with the original length as value.
+///  - Values removed during normalization become `invalid_attribute` errors.
+///  - Values modified by PII rules become `scrubbed_data` errors with the rule ID as value.
+///
+/// The name of each error is the path of the field.  Errors that are already present are not
+/// added again, so that events can be processed repeatedly.
+pub fn collect_event_errors(event: &mut Annotated<Event>) {
+    let mut collected = vec![];
+    let walked = event.for_each_meta(|path, meta| {
+        // errors reported in a previous run are not collected again
+        if path != "errors" && !path.starts_with("errors.") {
+            collect_meta_errors(path, &meta, &mut collected);
+        }
+    });
+
+    if walked.is_err() {
+        return;
+    }
+
+    let event = match event.value_mut() {
+        Some(event) => event,
+        None => return,
+    };
+
+    let errors = event.errors.0.get_or_insert_with(Vec::new);
+    for error in collected {
+        if !errors.iter().any(|e| e.value() == Some(&error)) {
+            errors.push(Annotated::from(error));
+        }
+    }
+}
+
+fn collect_meta_errors(path: &str, meta: &Meta, errors: &mut Vec<EventProcessingError>) {
+    let mut push = |ty: EventErrorType, value: Value| {
+        let name = if path.is_empty() {
+            None
+        } else {
+            Some(path.to_string())
+        };
+
+        let error = EventProcessingError::new(ty.as_str(), name, Some(value));
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    };
+
+    for error in meta.errors() {
+        push(
+            EventErrorType::InvalidData,
+            Value::String(error.to_string()),
+        );
+    }
+
+    if let Some(original_length) = meta.original_length() {
+        push(
+            EventErrorType::ValueTooLong,
+            Value::U64(original_length as u64),
+        );
+    }
+
+    for remark in meta.remarks() {
+        let rule_id = remark.rule_id();
+        let ty = if !rule_id.starts_with('!') {
+            EventErrorType::ScrubbedData
+        } else if remark.ty() != RemarkType::Removed {
+            continue;
+        } else if rule_id == TRIM_RULE_ID {
+            EventErrorType::ValueTooLong
+        } else {
+            EventErrorType::InvalidAttribute
+        };
+
+        let value = remark.note().unwrap_or(rule_id);
+        push(ty, Value::String(value.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use processor::{process_event_json, PiiConfig, PipelineConfig};
+
+    fn error(ty: EventErrorType, name: &str, value: Value) -> EventProcessingError {
+        EventProcessingError::new(ty.as_str(), Some(name.to_string()), Some(value))
+    }
+
+    #[test]
+    fn test_collect_event_errors() {
+        let pii = PiiConfig::from_json(r#"{"applications": {"freeform": ["@email"]}}"#).unwrap();
+        let config = PipelineConfig {
+            pii: Some(pii),
+            collect_errors: true,
+            ..Default::default()
+        };
+
+        let json = r#"{
+            "message": "mail foo@example.com",
+            "type": "transaction",
+            "tags": {"environment": "production"}
+        }"#;
+        let (mut event, _) = process_event_json(json, &config);
+
+        let expected = vec![
+            error(
+                EventErrorType::ScrubbedData,
+                "message",
+                Value::String("@email".to_string()),
+            ),
+            error(
+                EventErrorType::InvalidData,
+                "start_timestamp",
+                Value::String("missing start_timestamp for transaction".to_string()),
+            ),
+            error(
+                EventErrorType::InvalidAttribute,
+                "tags",
+                Value::String("moved tag environment to environment".to_string()),
+            ),
+        ];
+
+        let errors: Vec<_> = {
+            let errors = event.value().unwrap().errors.value().unwrap();
+            errors.iter().filter_map(|e| e.value().cloned()).collect()
+        };
+        assert_eq_dbg!(errors, expected);
+
+        collect_event_errors(&mut event);
+        let errors = event.value().unwrap().errors.value().unwrap();
+        assert_eq!(errors.len(), expected.len());
+    }
+}
@@ -0,0 +1,728 @@
+//! Inbound data filters that drop unwanted events.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use regex::Regex;
+
+use protocol::{Event, Exception, Request};
+
+use super::glob::{glob_match, glob_match_any, GlobOptions};
+
+lazy_static! {
+    static ref WEB_CRAWLERS_REGEX: Regex = Regex::new(
+        r#"(?ix)
+            Mediapartners-Google|AdsBot-Google|Googlebot|FeedFetcher-Google|
+            BingBot|BingPreview|Baiduspider|Slurp|Sogou|facebook|ia_archiver|
+            bots?[/\s);]|spider[/\s);]|Slack|Calypso\ AppCrawler|pingdom|lyticsbot|
+            AWS\ Security\ Scanner|HubSpot\ Crawler
+        "#
+    ).unwrap();
+    static ref EXTENSION_VALUES_REGEX: Regex = Regex::new(
+        r#"(?ix)
+            # random plugins/extensions
+            top\.GLOBALS|
+            # http://blog.errorception.com/2012/03/tale-of-unfindable-js-error.html
+            originalCreateNotification|
+            canvas\.contentDocument|
+            MyApp_RemoveAllHighlights|
+            http://tt\.epicplay\.com|
+            Can't\ find\ variable:\ ZiteReader|
+            jigsaw\ is\ not\ defined|
+            ComboSearch\ is\ not\ defined|
+            http://loading\.retry\.widdit\.com/|
+            atomicFindClose|
+            # facebook borked
+            fb_xd_fragment|
+            # ISP "optimizing" proxy - `Cache-Control: no-transform` seems to reduce this.
+            bmi_SafeAddOnload|
+            EBCallBackMessageReceived|
+            # See http://toolbar.conduit.com/Developer/HtmlAndGadget/Methods/JSInjection.aspx
+            conduitPage|
+            # Generic error code from errors outside the security sandbox
+            Script\ error\.
+        "#
+    ).unwrap();
+    static ref EXTENSION_SOURCES_REGEX: Regex = Regex::new(
+        r#"(?ix)
+            graph\.facebook\.com|
+            connect\.facebook\.net|
+            eatdifferent\.com\.woopra-ns\.com|
+            static\.woopra\.com/js/woopra\.js|
+            ^chrome(?:-extension)?://|
+            127\.0\.0\.1:4001/isrunning|
+            webappstoolbarba\.texthelp\.com/|
+            metrics\.itunes\.apple\.com\.edgesuite\.net/|
+            ^moz-extension://|
+            ^safari-extension://
+        "#
+    ).unwrap();
+    static ref MSIE_REGEX: Regex = Regex::new(r"MSIE (\d+)").unwrap();
+    static ref IE11_REGEX: Regex = Regex::new(r"Trident/7\.0;.*rv:11\.0").unwrap();
+    static ref OPERA_MINI_REGEX: Regex = Regex::new(r"Opera Mini/(\d+)").unwrap();
+    static ref ANDROID_REGEX: Regex = Regex::new(r"Android (\d+)").unwrap();
+    static ref SAFARI_REGEX: Regex =
+        Regex::new(r"Version/(\d+)[\d.]* (?:Mobile/\S+ )?Safari/").unwrap();
+}
+
+/// IP addresses of the local host.
+const LOCAL_IPS: &[&str] = &["127.0.0.1", "::1"];
+
+/// Host names of the local host.
+const LOCAL_DOMAINS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+/// Identifies the filter that dropped an event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilterStatKey {
+    /// The event originates from the local host.
+    Localhost,
+    /// The error was caused by a browser extension.
+    BrowserExtensions,
+    /// The event was sent by a legacy browser.
+    LegacyBrowsers,
+    /// The event was sent by a web crawler.
+    WebCrawlers,
+    /// The release of the event is filtered.
+    ReleaseVersion,
+    /// The error message of the event is filtered.
+    ErrorMessage,
+    /// The event was sent from an origin that is not allowed.
+    InvalidOrigin,
+}
+
+impl FilterStatKey {
+    /// Returns the name of the filter as reported in outcomes.
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterStatKey::Localhost => "localhost",
+            FilterStatKey::BrowserExtensions => "browser-extensions",
+            FilterStatKey::LegacyBrowsers => "legacy-browsers",
+            FilterStatKey::WebCrawlers => "web-crawlers",
+            FilterStatKey::ReleaseVersion => "release-version",
+            FilterStatKey::ErrorMessage => "error-message",
+            FilterStatKey::InvalidOrigin => "invalid-origin",
+        }
+    }
+}
+
+impl fmt::Display for FilterStatKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Configuration for a filter that can only be switched on and off.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct FilterConfig {
+    /// Whether the filter is applied.
+    #[serde(default)]
+    pub is_enabled: bool,
+}
+
+/// A family of legacy browsers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegacyBrowser {
+    /// All legacy browsers except Internet Explorer 11.
+    Default,
+    /// Internet Explorer before version 9.
+    IePre9,
+    /// Internet Explorer 9.
+    Ie9,
+    /// Internet Explorer 10.
+    Ie10,
+    /// Internet Explorer 11.
+    Ie11,
+    /// Opera before version 15.
+    OperaPre15,
+    /// Opera Mini before version 8.
+    OperaMiniPre8,
+    /// The Android browser before Android 4.
+    AndroidPre4,
+    /// Safari before version 6.
+    SafariPre6,
+}
+
+impl LegacyBrowser {
+    /// Returns `true` if the browser belongs to this family.
+    fn matches(self, browser: LegacyBrowser) -> bool {
+        match self {
+            LegacyBrowser::Default => browser != LegacyBrowser::Ie11,
+            family => family == browser,
+        }
+    }
+}
+
+/// Configuration for the legacy browsers filter.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct LegacyBrowsersFilterConfig {
+    /// Whether the filter is applied.
+    #[serde(default)]
+    pub is_enabled: bool,
+    /// The browsers to filter.  Filters the `default` browsers if empty.
+    #[serde(default)]
+    pub browsers: BTreeSet<LegacyBrowser>,
+}
+
+/// Configuration for the release filter.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ReleasesFilterConfig {
+    /// Glob patterns of releases to filter, see `Glob`.
+    #[serde(default)]
+    pub releases: Vec<String>,
+}
+
+/// Configuration for the error message filter.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ErrorMessagesFilterConfig {
+    /// Glob patterns of error messages to filter.  Patterns are case insensitive.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// Configuration for the filter of browser events from disallowed origins.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct OriginFilterConfig {
+    /// Glob patterns of allowed domains.  All origins are allowed if empty.
+    ///
+    /// Patterns are matched case insensitively against the host name of the origin.  A pattern
+    /// starting with `*.` also matches the domain itself, so that `*.example.com` allows
+    /// `example.com` and all of its subdomains.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+/// Configuration of all inbound filters.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct FiltersConfig {
+    /// Filters errors caused by browser extensions.
+    #[serde(default)]
+    pub browser_extensions: FilterConfig,
+    /// Filters events sent by legacy browsers.
+    #[serde(default)]
+    pub legacy_browsers: LegacyBrowsersFilterConfig,
+    /// Filters events sent by web crawlers.
+    #[serde(default)]
+    pub web_crawlers: FilterConfig,
+    /// Filters events sent from the local host.
+    #[serde(default)]
+    pub localhost: FilterConfig,
+    /// Filters events of certain releases.
+    #[serde(default)]
+    pub releases: ReleasesFilterConfig,
+    /// Filters events with certain error messages.
+    #[serde(default)]
+    pub error_messages: ErrorMessagesFilterConfig,
+    /// Filters browser events sent from origins that are not allowed.
+    #[serde(default)]
+    pub origins: OriginFilterConfig,
+}
+
+/// Checks whether the event should be dropped by one of the inbound filters.
+///
+/// Returns the key of the first filter that matches, or `None` if the event should be kept.
+pub fn should_filter(event: &Event, config: &FiltersConfig) -> Option<FilterStatKey> {
+    if config.localhost.is_enabled && is_localhost(event) {
+        return Some(FilterStatKey::Localhost);
+    }
+
+    if config.browser_extensions.is_enabled && is_browser_extension(event) {
+        return Some(FilterStatKey::BrowserExtensions);
+    }
+
+    if config.legacy_browsers.is_enabled && is_legacy_browser(event, &config.legacy_browsers) {
+        return Some(FilterStatKey::LegacyBrowsers);
+    }
+
+    if config.web_crawlers.is_enabled && is_web_crawler(event) {
+        return Some(FilterStatKey::WebCrawlers);
+    }
+
+    if is_filtered_release(event, &config.releases) {
+        return Some(FilterStatKey::ReleaseVersion);
+    }
+
+    if is_filtered_error_message(event, &config.error_messages) {
+        return Some(FilterStatKey::ErrorMessage);
+    }
+
+    if is_invalid_origin(event, &config.origins) {
+        return Some(FilterStatKey::InvalidOrigin);
+    }
+
+    None
+}
+
+fn request(event: &Event) -> Option<&Request> {
+    event.request.value().and_then(Option::as_ref)
+}
+
+fn user_agent(event: &Event) -> Option<&str> {
+    let headers = request(event)?.headers.value()?;
+    headers.get("User-Agent")?.value().map(String::as_str)
+}
+
+fn exceptions(event: &Event) -> impl Iterator<Item = &Exception> {
+    event
+        .exceptions
+        .value()
+        .and_then(|values| values.values.value())
+        .into_iter()
+        .flat_map(|values| values.iter().filter_map(|exception| exception.value()))
+}
+
+/// Returns the host name of a URL without the port.
+fn url_host(url: &str) -> &str {
+    let rest = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    let authority = rest.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or("");
+    let host = match authority.rfind('@') {
+        Some(index) => &authority[index + 1..],
+        None => authority,
+    };
+
+    if host.starts_with('[') {
+        match host.find(']') {
+            Some(index) => &host[..index + 1],
+            None => host,
+        }
+    } else {
+        host.split(':').next().unwrap_or("")
+    }
+}
+
+fn is_localhost(event: &Event) -> bool {
+    let ip_address = event
+        .user
+        .value()
+        .and_then(Option::as_ref)
+        .and_then(|user| user.ip_address.value())
+        .and_then(Option::as_ref);
+
+    if let Some(ip_address) = ip_address {
+        if LOCAL_IPS.contains(&ip_address.as_str()) {
+            return true;
+        }
+    }
+
+    let url = request(event)
+        .and_then(|request| request.url.value())
+        .and_then(Option::as_ref);
+
+    url.map_or(false, |url| {
+        let host = url_host(url).to_lowercase();
+        LOCAL_DOMAINS.contains(&host.as_str())
+    })
+}
+
+fn is_browser_extension(event: &Event) -> bool {
+    exceptions(event).any(|exception| {
+        let message = exception_message(exception);
+        if EXTENSION_VALUES_REGEX.is_match(&message) {
+            return true;
+        }
+
+        // the source of the error is the most recent frame
+        let source = exception
+            .stacktrace
+            .value()
+            .and_then(Option::as_ref)
+            .and_then(|stacktrace| stacktrace.frames.value())
+            .and_then(|frames| frames.last())
+            .and_then(|frame| frame.value())
+            .and_then(|frame| frame.abs_path.value())
+            .and_then(Option::as_ref);
+
+        source.map_or(false, |source| EXTENSION_SOURCES_REGEX.is_match(source))
+    })
+}
+
+/// Detects the legacy browser family from a user agent.
+fn legacy_browser(user_agent: &str) -> Option<LegacyBrowser> {
+    let version = |regex: &Regex| -> Option<u32> {
+        regex
+            .captures(user_agent)
+            .and_then(|captures| captures[1].parse().ok())
+    };
+
+    if let Some(version) = version(&MSIE_REGEX) {
+        return match version {
+            0...8 => Some(LegacyBrowser::IePre9),
+            9 => Some(LegacyBrowser::Ie9),
+            10 => Some(LegacyBrowser::Ie10),
+            _ => None,
+        };
+    }
+
+    if IE11_REGEX.is_match(user_agent) {
+        return Some(LegacyBrowser::Ie11);
+    }
+
+    if let Some(version) = version(&OPERA_MINI_REGEX) {
+        return if version < 8 {
+            Some(LegacyBrowser::OperaMiniPre8)
+        } else {
+            None
+        };
+    }
+
+    // Opera switched to the Chromium engine and the `OPR` token in version 15
+    if user_agent.starts_with("Opera/") || user_agent.contains(" Opera ") {
+        return Some(LegacyBrowser::OperaPre15);
+    }
+
+    if let Some(version) = version(&ANDROID_REGEX) {
+        return if version < 4 {
+            Some(LegacyBrowser::AndroidPre4)
+        } else {
+            None
+        };
+    }
+
+    if !user_agent.contains("Chrome/") {
+        if let Some(version) = version(&SAFARI_REGEX) {
+            if version < 6 {
+                return Some(LegacyBrowser::SafariPre6);
+            }
+        }
+    }
+
+    None
+}
+
+fn is_legacy_browser(event: &Event, config: &LegacyBrowsersFilterConfig) -> bool {
+    let browser = match user_agent(event).and_then(legacy_browser) {
+        Some(browser) => browser,
+        None => return false,
+    };
+
+    if config.browsers.is_empty() {
+        LegacyBrowser::Default.matches(browser)
+    } else {
+        config.browsers.iter().any(|family| family.matches(browser))
+    }
+}
+
+fn is_web_crawler(event: &Event) -> bool {
+    user_agent(event).map_or(false, |user_agent| WEB_CRAWLERS_REGEX.is_match(user_agent))
+}
+
+fn is_filtered_release(event: &Event, config: &ReleasesFilterConfig) -> bool {
+    if config.releases.is_empty() {
+        return false;
+    }
+
+    let release = event.release.value().and_then(Option::as_ref);
+    release.map_or(false, |release| {
+        glob_match_any(&config.releases, release, GlobOptions::default())
+    })
+}
+
+/// Formats the message of an exception like the server, from its type and value.
+fn exception_message(exception: &Exception) -> String {
+    let ty = exception.ty.value().map_or("", String::as_str);
+    let value = exception.value.value().and_then(Option::as_ref);
+    match value {
+        Some(value) if !ty.is_empty() => format!("{}: {}", ty, value),
+        Some(value) => value.clone(),
+        None => ty.to_string(),
+    }
+}
+
+fn is_filtered_error_message(event: &Event, config: &ErrorMessagesFilterConfig) -> bool {
+    if config.patterns.is_empty() {
+        return false;
+    }
+
+    let mut messages: Vec<String> = exceptions(event).map(exception_message).collect();
+
+    if let Some(message) = event.message.value().and_then(Option::as_ref) {
+        messages.push(message.clone());
+    }
+
+    let logentry = event.logentry.value().and_then(Option::as_ref);
+    if let Some(message) = logentry.and_then(|logentry| logentry.message.value()) {
+        messages.push(message.clone());
+    }
+
+    let options = GlobOptions {
+        case_insensitive: true,
+        prefix: false,
+    };
+    messages
+        .iter()
+        .any(|message| glob_match_any(&config.patterns, message, options))
+}
+
+/// Returns the origin of a browser event.
+///
+/// Browsers send the `Origin` header with cross-origin requests and the `Referer` header
+/// otherwise.  If neither is present, the URL of the request is used.
+fn origin(event: &Event) -> Option<&str> {
+    let request = request(event)?;
+    let header = |name: &str| {
+        let headers = request.headers.value()?;
+        headers.get(name)?.value().map(String::as_str)
+    };
+
+    let url = request.url.value().and_then(Option::as_ref);
+    header("Origin")
+        .or_else(|| header("Referer"))
+        .or_else(|| url.map(String::as_str))
+}
+
+/// Checks whether a host name is allowed by a domain pattern.
+fn is_allowed_domain(pattern: &str, host: &str) -> bool {
+    let options = GlobOptions {
+        case_insensitive: true,
+        prefix: false,
+    };
+
+    if pattern.starts_with("*.") && host.eq_ignore_ascii_case(&pattern[2..]) {
+        return true;
+    }
+
+    glob_match(pattern, host, options)
+}
+
+fn is_invalid_origin(event: &Event, config: &OriginFilterConfig) -> bool {
+    if config.allowed_domains.is_empty() {
+        return false;
+    }
+
+    // events without an origin are not sent by browsers
+    let host = match origin(event) {
+        Some(origin) => url_host(origin),
+        None => return false,
+    };
+
+    !config
+        .allowed_domains
+        .iter()
+        .any(|pattern| is_allowed_domain(pattern, host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Annotated;
+
+    fn event(json: &str) -> Event {
+        Annotated::<Event>::from_json(json).unwrap().0.unwrap()
+    }
+
+    fn ua_event(user_agent: &str) -> Event {
+        event(&format!(
+            r#"{{"request": {{"headers": {{"User-Agent": "{}"}}}}}}"#,
+            user_agent
+        ))
+    }
+
+    #[test]
+    fn test_disabled() {
+        let event = event(r#"{"user": {"ip_address": "127.0.0.1"}}"#);
+        assert_eq!(should_filter(&event, &FiltersConfig::default()), None);
+    }
+
+    #[test]
+    fn test_localhost() {
+        let config = FiltersConfig {
+            localhost: FilterConfig { is_enabled: true },
+            ..Default::default()
+        };
+
+        let ip_event = event(r#"{"user": {"ip_address": "127.0.0.1"}}"#);
+        assert_eq!(should_filter(&ip_event, &config), Some(FilterStatKey::Localhost));
+
+        let url_event = event(r#"{"request": {"url": "http://localhost:8000/index"}}"#);
+        assert_eq!(should_filter(&url_event, &config), Some(FilterStatKey::Localhost));
+
+        let remote_event = event(r#"{"request": {"url": "https://example.com/localhost"}}"#);
+        assert_eq!(should_filter(&remote_event, &config), None);
+    }
+
+    #[test]
+    fn test_browser_extensions() {
+        let config = FiltersConfig {
+            browser_extensions: FilterConfig { is_enabled: true },
+            ..Default::default()
+        };
+
+        let value_event = event(
+            r#"{"exception": {"values": [{"type": "Error", "value": "fb_xd_fragment"}]}}"#,
+        );
+        assert_eq!(
+            should_filter(&value_event, &config),
+            Some(FilterStatKey::BrowserExtensions)
+        );
+
+        let source_event = event(
+            r#"{
+  "exception": {
+    "values": [{
+      "type": "TypeError",
+      "stacktrace": {"frames": [{"abs_path": "chrome-extension://abcdef/content.js"}]}
+    }]
+  }
+}"#,
+        );
+        assert_eq!(
+            should_filter(&source_event, &config),
+            Some(FilterStatKey::BrowserExtensions)
+        );
+
+        let app_event = event(
+            r#"{"exception": {"values": [{"type": "TypeError", "value": "x is undefined"}]}}"#,
+        );
+        assert_eq!(should_filter(&app_event, &config), None);
+    }
+
+    #[test]
+    fn test_legacy_browsers() {
+        assert_eq!(
+            legacy_browser("Mozilla/4.0 (compatible; MSIE 8.0; Windows NT 6.1; Trident/4.0)"),
+            Some(LegacyBrowser::IePre9)
+        );
+        assert_eq!(
+            legacy_browser("Mozilla/5.0 (Windows NT 6.1; Trident/7.0; rv:11.0) like Gecko"),
+            Some(LegacyBrowser::Ie11)
+        );
+        assert_eq!(
+            legacy_browser("Opera/9.80 (Windows NT 6.1) Presto/2.12.388 Version/12.16"),
+            Some(LegacyBrowser::OperaPre15)
+        );
+        assert_eq!(
+            legacy_browser(
+                "Mozilla/5.0 (Linux; U; Android 2.3.5; en-us) AppleWebKit/533.1 \
+                 (KHTML, like Gecko) Version/4.0 Mobile Safari/533.1"
+            ),
+            Some(LegacyBrowser::AndroidPre4)
+        );
+        assert_eq!(
+            legacy_browser(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_6_8) AppleWebKit/534.59.10 \
+                 (KHTML, like Gecko) Version/5.1.9 Safari/534.59.10"
+            ),
+            Some(LegacyBrowser::SafariPre6)
+        );
+        assert_eq!(
+            legacy_browser(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                 (KHTML, like Gecko) Chrome/68.0.3440.106 Safari/537.36"
+            ),
+            None
+        );
+
+        let ie11 = ua_event("Mozilla/5.0 (Windows NT 6.1; Trident/7.0; rv:11.0) like Gecko");
+        let mut config = FiltersConfig {
+            legacy_browsers: LegacyBrowsersFilterConfig {
+                is_enabled: true,
+                browsers: BTreeSet::new(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(should_filter(&ie11, &config), None);
+
+        config.legacy_browsers.browsers.insert(LegacyBrowser::Ie11);
+        assert_eq!(should_filter(&ie11, &config), Some(FilterStatKey::LegacyBrowsers));
+    }
+
+    #[test]
+    fn test_web_crawlers() {
+        let config = FiltersConfig {
+            web_crawlers: FilterConfig { is_enabled: true },
+            ..Default::default()
+        };
+
+        let crawler =
+            ua_event("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)");
+        assert_eq!(should_filter(&crawler, &config), Some(FilterStatKey::WebCrawlers));
+
+        let browser =
+            ua_event("Mozilla/5.0 (X11; Linux x86_64; rv:61.0) Gecko/20100101 Firefox/61.0");
+        assert_eq!(should_filter(&browser, &config), None);
+    }
+
+    #[test]
+    fn test_releases() {
+        let config = FiltersConfig {
+            releases: ReleasesFilterConfig {
+                releases: vec!["1.0.*".to_string(), "beta-?".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let filtered = event(r#"{"release": "1.0.42"}"#);
+        assert_eq!(should_filter(&filtered, &config), Some(FilterStatKey::ReleaseVersion));
+
+        let filtered = event(r#"{"release": "beta-2"}"#);
+        assert_eq!(should_filter(&filtered, &config), Some(FilterStatKey::ReleaseVersion));
+
+        let kept = event(r#"{"release": "1.1.0"}"#);
+        assert_eq!(should_filter(&kept, &config), None);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let config = FiltersConfig {
+            error_messages: ErrorMessagesFilterConfig {
+                patterns: vec!["*connection reset*".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let exception = event(
+            r#"{
+  "exception": {"values": [{"type": "IOError", "value": "Connection Reset by peer"}]}
+}"#,
+        );
+        assert_eq!(should_filter(&exception, &config), Some(FilterStatKey::ErrorMessage));
+
+        let message = event(r#"{"message": "connection reset"}"#);
+        assert_eq!(should_filter(&message, &config), Some(FilterStatKey::ErrorMessage));
+
+        let kept = event(r#"{"message": "timeout"}"#);
+        assert_eq!(should_filter(&kept, &config), None);
+    }
+
+    #[test]
+    fn test_origins() {
+        let config = FiltersConfig {
+            origins: OriginFilterConfig {
+                allowed_domains: vec!["*.example.com".to_string(), "sentry.io".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let origin = event(r#"{"request": {"headers": {"Origin": "https://App.Example.com"}}}"#);
+        assert_eq!(should_filter(&origin, &config), None);
+
+        let referer = event(r#"{"request": {"headers": {"Referer": "http://example.com:80/"}}}"#);
+        assert_eq!(should_filter(&referer, &config), None);
+
+        let url = event(r#"{"request": {"url": "https://evil.com/sentry.io"}}"#);
+        assert_eq!(
+            should_filter(&url, &config),
+            Some(FilterStatKey::InvalidOrigin)
+        );
+
+        let precedence = event(
+            r#"{
+  "request": {
+    "url": "https://sentry.io/",
+    "headers": {"Origin": "https://notexample.com"}
+  }
+}"#,
+        );
+        assert_eq!(
+            should_filter(&precedence, &config),
+            Some(FilterStatKey::InvalidOrigin)
+        );
+
+        let server = event(r#"{"platform": "python"}"#);
+        assert_eq!(should_filter(&server, &config), None);
+    }
+}
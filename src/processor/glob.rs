@@ -0,0 +1,243 @@
+//! Glob patterns for matching releases, messages and paths.
+
+use std::fmt;
+
+use regex::{self, Regex};
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, Serializer};
+
+use super::regex_cache::compile_regex;
+
+/// Options for matching glob patterns.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GlobOptions {
+    /// Ignores the case of letters when matching.
+    pub case_insensitive: bool,
+    /// Matches all values starting with the pattern, instead of only the entire value.
+    pub prefix: bool,
+}
+
+/// Translates a glob pattern into an anchored regular expression.
+///
+/// Supports `*` for any sequence of characters, `?` for a single character and character
+/// classes in brackets, which are negated by a leading `!` or `^`.  A backslash escapes the
+/// following character.  Unterminated brackets match literally.
+fn translate(pattern: &str, options: GlobOptions) -> String {
+    let mut rv = String::from(if options.case_insensitive {
+        "(?is)^"
+    } else {
+        "(?s)^"
+    });
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut buf = [0; 4];
+    let mut index = 0;
+
+    while index < chars.len() {
+        match chars[index] {
+            '*' => rv.push_str(".*"),
+            '?' => rv.push('.'),
+            '\\' if index + 1 < chars.len() => {
+                index += 1;
+                rv.push_str(&regex::escape(chars[index].encode_utf8(&mut buf)));
+            }
+            '[' => match class_end(&chars, index) {
+                Some(end) => {
+                    rv.push('[');
+                    let mut start = index + 1;
+                    if chars[start] == '!' || chars[start] == '^' {
+                        rv.push('^');
+                        start += 1;
+                    }
+                    for &c in &chars[start..end] {
+                        if c == '-' {
+                            rv.push('-');
+                        } else {
+                            rv.push_str(&regex::escape(c.encode_utf8(&mut buf)));
+                        }
+                    }
+                    rv.push(']');
+                    index = end;
+                }
+                None => rv.push_str("\\["),
+            },
+            c => rv.push_str(&regex::escape(c.encode_utf8(&mut buf))),
+        }
+        index += 1;
+    }
+
+    if !options.prefix {
+        rv.push('$');
+    }
+    rv
+}
+
+/// Returns the index of the bracket closing a character class that starts at `start`.
+///
+/// A closing bracket directly after the opening bracket or negation is part of the class.
+fn class_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut index = start + 1;
+    if index < chars.len() && (chars[index] == '!' || chars[index] == '^') {
+        index += 1;
+    }
+    if index < chars.len() && chars[index] == ']' {
+        index += 1;
+    }
+
+    chars[index.min(chars.len())..]
+        .iter()
+        .position(|&c| c == ']')
+        .map(|offset| index + offset)
+}
+
+/// A compiled glob pattern.
+///
+/// Compiled patterns are shared through the global regex cache, so that creating the same glob
+/// repeatedly is cheap.
+#[derive(Clone)]
+pub struct Glob {
+    pattern: String,
+    options: GlobOptions,
+    regex: Regex,
+}
+
+impl Glob {
+    /// Compiles a case sensitive glob pattern.
+    pub fn new(pattern: &str) -> Result<Glob, regex::Error> {
+        Glob::with_options(pattern, GlobOptions::default())
+    }
+
+    /// Compiles a glob pattern with the given options.
+    pub fn with_options(pattern: &str, options: GlobOptions) -> Result<Glob, regex::Error> {
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            options,
+            regex: compile_regex(&translate(pattern, options))?,
+        })
+    }
+
+    /// Returns the original pattern.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the options this pattern was compiled with.
+    pub fn options(&self) -> GlobOptions {
+        self.options
+    }
+
+    /// Returns `true` if the value matches the pattern.
+    ///
+    /// Unless the pattern was compiled as prefix, the entire value must match.
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl fmt::Debug for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.pattern, f)
+    }
+}
+
+impl PartialEq for Glob {
+    fn eq(&self, other: &Glob) -> bool {
+        self.pattern == other.pattern && self.options == other.options
+    }
+}
+
+impl Serialize for Glob {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for Glob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Glob::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
+/// Returns `true` if the value matches the glob pattern.
+///
+/// Patterns that cannot be compiled never match.
+pub fn glob_match(pattern: &str, value: &str, options: GlobOptions) -> bool {
+    Glob::with_options(pattern, options)
+        .map(|glob| glob.is_match(value))
+        .unwrap_or(false)
+}
+
+/// Returns `true` if the value matches any of the glob patterns.
+pub fn glob_match_any<S: AsRef<str>>(patterns: &[S], value: &str, options: GlobOptions) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glob_match(pattern.as_ref(), value, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, value: &str) -> bool {
+        glob_match(pattern, value, GlobOptions::default())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(is_match("1.0.*", "1.0.42"));
+        assert!(is_match("*", ""));
+        assert!(is_match("beta-?", "beta-2"));
+        assert!(!is_match("beta-?", "beta-10"));
+        assert!(!is_match("1.0", "1.0.1"));
+        assert!(is_match("*error*", "an error\noccurred"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(is_match("v[0-9].*", "v1.2"));
+        assert!(!is_match("v[0-9].*", "vx.2"));
+        assert!(is_match("v[!0-9]", "vx"));
+        assert!(!is_match("v[^0-9]", "v1"));
+        assert!(is_match("[]]", "]"));
+        assert!(is_match("a[b", "a[b"));
+        assert!(is_match("[.+]", "+"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(is_match("\\*", "*"));
+        assert!(!is_match("\\*", "a"));
+        assert!(is_match("a.b", "a.b"));
+        assert!(!is_match("a.b", "axb"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let options = GlobOptions {
+            case_insensitive: true,
+            prefix: false,
+        };
+        assert!(glob_match("*Connection Reset*", "connection reset by peer", options));
+        assert!(!is_match("*Connection Reset*", "connection reset by peer"));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let options = GlobOptions {
+            case_insensitive: false,
+            prefix: true,
+        };
+        assert!(glob_match("myapp.", "myapp.views", options));
+        assert!(glob_match("*/lib/", "app/lib/react.js", options));
+        assert!(!glob_match("myapp.", "other.myapp.views", options));
+        assert!(!is_match("myapp.", "myapp.views"));
+    }
+
+    #[test]
+    fn test_glob_serde() {
+        let glob: Glob = ::serde_json::from_str(r#""release-*""#).unwrap();
+        assert!(glob.is_match("release-1"));
+        assert_eq_str!(::serde_json::to_string(&glob).unwrap(), r#""release-*""#);
+    }
+}
@@ -1,9 +1,40 @@
 //! Implements a processing system for the protocol.
 
+mod audit;
 mod builtin;
 mod chunk;
+mod condition;
+mod errors;
+mod filters;
+mod glob;
+mod normalize;
 mod pii;
+mod pipeline;
+mod pseudonym;
+mod quarantine;
+mod regex_cache;
 mod rule;
+mod sanitize;
+mod stats;
+mod trace;
+mod trim;
+mod url;
 
+pub use self::audit::*;
+pub use self::chunk::Chunk;
+pub use self::condition::*;
+pub use self::errors::*;
+pub use self::filters::*;
+pub use self::glob::*;
+pub use self::normalize::*;
 pub use self::pii::*;
+pub use self::pipeline::*;
+pub use self::pseudonym::*;
+pub use self::quarantine::*;
+pub use self::regex_cache::*;
 pub use self::rule::*;
+pub use self::sanitize::*;
+pub use self::stats::*;
+pub use self::trace::*;
+pub use self::trim::*;
+pub use self::url::*;
@@ -0,0 +1,3109 @@
+//! Normalization of events into the canonical protocol.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use regex::{Captures, Regex};
+use serde::de::{self, Deserialize, Deserializer};
+
+use protocol::{
+    remove_key, Addr, Annotated, Array, Breadcrumb, DebugImage, Event, EventType, Exception,
+    ExceptionBuilder, Frame, Ip, LogEntry, Map, Mechanism, Meta, Provenance, Remark, RemarkType,
+    Stacktrace, ThreadId, Value, Values,
+};
+
+use super::glob::{glob_match_any, GlobOptions};
+use super::trim::{trim_string, TRIM_RULE_ID};
+
+lazy_static! {
+    static ref VERSION_REGEX: Regex =
+        Regex::new(r"^\d+(\.\d+)*([-+.]?[0-9A-Za-z][0-9A-Za-z.+-]*)?$").unwrap();
+    static ref GIT_SHA_REGEX: Regex = Regex::new(r"^[0-9a-fA-F]{7,40}$").unwrap();
+    static ref DIST_REGEX: Regex = Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap();
+    static ref PRINTF_REGEX: Regex =
+        Regex::new(r"%(?:%|[-+#0]*\d*(?:\.\d+)?[sdifrxXeEgGcou])").unwrap();
+    static ref BRACE_REGEX: Regex = Regex::new(r"\{\{|\}\}|\{(\d*)\}").unwrap();
+}
+
+/// HTTP request methods accepted in `http` breadcrumbs.
+const HTTP_METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// The rule ID reported in remarks of normalized logger names.
+const LOGGER_RULE_ID: &str = "!logger";
+
+/// The rule ID reported in remarks of removed distributions.
+const DIST_RULE_ID: &str = "!dist";
+
+/// Maximum number of characters in the distribution of an event.
+const MAX_DIST_LENGTH: usize = 64;
+
+/// The rule ID reported in remarks of reserved tags.
+const RESERVED_TAG_RULE_ID: &str = "!reserved_tag";
+
+/// Tags that correspond to attributes of the event and are promoted to them.
+const RESERVED_TAGS: &[&str] = &[
+    "release",
+    "dist",
+    "environment",
+    "server_name",
+    "transaction",
+    "user",
+];
+
+/// Platforms for which exceptions are synthesized from messages by default.
+const SYNTHETIC_EXCEPTION_PLATFORMS: &[&str] = &[
+    "csharp",
+    "go",
+    "java",
+    "javascript",
+    "node",
+    "php",
+    "python",
+    "ruby",
+];
+
+/// Configuration for detecting frames that belong to the application.
+///
+/// Both lists contain prefixes that are matched against the module and the absolute path of
+/// frames.  Prefixes are glob patterns, see `Glob`, so that `*/node_modules/` matches vendored
+/// code in any directory.  Includes take precedence over excludes.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct InAppConfig {
+    /// Prefixes of frames that belong to the application.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Prefixes of frames that do not belong to the application.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl InAppConfig {
+    /// Decides whether a frame is in-app based on its module and absolute path.
+    ///
+    /// Returns `None` if the frame matches neither list.
+    pub fn is_in_app(&self, frame: &Frame) -> Option<bool> {
+        if matches_prefix(frame, &self.include) {
+            Some(true)
+        } else if matches_prefix(frame, &self.exclude) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+fn matches_prefix(frame: &Frame, prefixes: &[String]) -> bool {
+    let options = GlobOptions {
+        case_insensitive: false,
+        prefix: true,
+    };
+
+    let module = frame.module.value().and_then(Option::as_ref);
+    let abs_path = frame.abs_path.value().and_then(Option::as_ref);
+
+    module.map_or(false, |m| glob_match_any(prefixes, m, options))
+        || abs_path.map_or(false, |p| glob_match_any(prefixes, p, options))
+}
+
+/// How to resolve keys of `tags` and `extra` that differ only in case.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCollisionStrategy {
+    /// Keeps the first of the colliding keys in the order of the map.
+    KeepFirst,
+    /// Keeps the last of the colliding keys in the order of the map.
+    KeepLast,
+    /// Merges the values into the first of the colliding keys.
+    ///
+    /// Distinct tag values are joined with commas.  Extra values are merged if all of them are
+    /// objects, with earlier keys taking precedence.  Otherwise, distinct values are collected in
+    /// an array.
+    Merge,
+}
+
+/// Configuration for event normalization.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct NormalizeConfig {
+    /// Detection of frames that belong to the application.
+    #[serde(default)]
+    pub in_app: InAppConfig,
+    /// Maximum number of seconds an event timestamp may lie before the received time.
+    #[serde(default = "default_max_secs_in_past")]
+    pub max_secs_in_past: i64,
+    /// Maximum number of seconds an event timestamp may lie after the received time.
+    #[serde(default = "default_max_secs_in_future")]
+    pub max_secs_in_future: i64,
+    /// Earliest valid timestamp in seconds since the UNIX epoch.
+    #[serde(
+        default = "default_min_timestamp",
+        deserialize_with = "deserialize_min_timestamp"
+    )]
+    pub min_timestamp: i64,
+    /// Maximum number of seconds a timestamp may lie after the current time.
+    #[serde(default = "default_max_secs_after_now")]
+    pub max_secs_after_now: i64,
+    /// Maximum number of entries in the modules map.
+    #[serde(default = "default_max_modules")]
+    pub max_modules: usize,
+    /// Maximum number of characters in the name of the logger.
+    #[serde(default = "default_max_logger_length")]
+    pub max_logger_length: usize,
+    /// Replaces characters other than letters, digits and `._-:$/` in the name of the logger
+    /// with underscores.  Since this rewrites names such as `<anonymous>`, it is disabled by
+    /// default.
+    #[serde(default)]
+    pub replace_invalid_logger_chars: bool,
+    /// Maximum number of exceptions in a chain of exceptions.
+    #[serde(default = "default_max_exceptions")]
+    pub max_exceptions: usize,
+    /// Maximum number of characters in a line of source context.
+    #[serde(default = "default_max_context_line_length")]
+    pub max_context_line_length: usize,
+    /// Maximum number of lines of source context before and after the current line.
+    #[serde(default = "default_max_context_lines")]
+    pub max_context_lines: usize,
+    /// Number of frames retained at the start of stack traces exceeding the frame limit.
+    #[serde(default = "default_max_frames_head")]
+    pub max_frames_head: usize,
+    /// Number of frames retained at the end of stack traces exceeding the frame limit.
+    #[serde(default = "default_max_frames_tail")]
+    pub max_frames_tail: usize,
+    /// Maximum number of parameters of a log entry.
+    #[serde(default = "default_max_params")]
+    pub max_params: usize,
+    /// Maximum number of characters in a string parameter of a log entry.
+    #[serde(default = "default_max_param_length")]
+    pub max_param_length: usize,
+    /// Resolution of case-insensitive key collisions in `tags` and `extra`.  Keys are retained
+    /// as they are if missing.
+    #[serde(default)]
+    pub key_collisions: Option<KeyCollisionStrategy>,
+    /// Creates an exception from the message of events without exceptions, stack traces or
+    /// threads.  Since this changes the shape of events, it is disabled by default.
+    #[serde(default)]
+    pub synthesize_exceptions: bool,
+    /// Platforms of events for which exceptions are synthesized.
+    #[serde(default = "default_synthetic_exception_platforms")]
+    pub synthetic_exception_platforms: Vec<String>,
+    /// Copies `REMOTE_ADDR` from the environment of the request to the IP address of the user if
+    /// it is missing or `{{auto}}`.
+    #[serde(default)]
+    pub infer_ip_address: bool,
+    /// Removes spans of transactions that do not lie within the transaction's interval instead
+    /// of only recording an error on them.
+    #[serde(default)]
+    pub remove_invalid_spans: bool,
+}
+
+fn default_max_secs_in_past() -> i64 {
+    30 * 24 * 3600
+}
+
+fn default_max_secs_in_future() -> i64 {
+    60
+}
+
+fn default_min_timestamp() -> i64 {
+    // 1990-01-01T00:00:00Z
+    631_152_000
+}
+
+/// Deserializes `min_timestamp` and rejects timestamps outside the range of valid dates.
+fn deserialize_min_timestamp<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = i64::deserialize(deserializer)?;
+    match Utc.timestamp_opt(timestamp, 0).single() {
+        Some(_) => Ok(timestamp),
+        None => Err(de::Error::custom("min_timestamp is out of range")),
+    }
+}
+
+fn default_max_secs_after_now() -> i64 {
+    3600
+}
+
+fn default_max_modules() -> usize {
+    250
+}
+
+fn default_max_logger_length() -> usize {
+    64
+}
+
+fn default_max_exceptions() -> usize {
+    25
+}
+
+fn default_max_context_line_length() -> usize {
+    200
+}
+
+fn default_max_context_lines() -> usize {
+    5
+}
+
+fn default_max_frames_head() -> usize {
+    125
+}
+
+fn default_max_frames_tail() -> usize {
+    125
+}
+
+fn default_max_params() -> usize {
+    100
+}
+
+fn default_max_param_length() -> usize {
+    512
+}
+
+fn default_synthetic_exception_platforms() -> Vec<String> {
+    SYNTHETIC_EXCEPTION_PLATFORMS
+        .iter()
+        .map(|platform| platform.to_string())
+        .collect()
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> NormalizeConfig {
+        NormalizeConfig {
+            in_app: InAppConfig::default(),
+            max_secs_in_past: default_max_secs_in_past(),
+            max_secs_in_future: default_max_secs_in_future(),
+            min_timestamp: default_min_timestamp(),
+            max_secs_after_now: default_max_secs_after_now(),
+            max_modules: default_max_modules(),
+            max_logger_length: default_max_logger_length(),
+            replace_invalid_logger_chars: false,
+            max_exceptions: default_max_exceptions(),
+            max_context_line_length: default_max_context_line_length(),
+            max_context_lines: default_max_context_lines(),
+            max_frames_head: default_max_frames_head(),
+            max_frames_tail: default_max_frames_tail(),
+            max_params: default_max_params(),
+            max_param_length: default_max_param_length(),
+            key_collisions: None,
+            synthesize_exceptions: false,
+            synthetic_exception_platforms: default_synthetic_exception_platforms(),
+            infer_ip_address: false,
+            remove_invalid_spans: false,
+        }
+    }
+}
+
+/// Normalizes an event in place.
+pub fn normalize_event(event: &mut Event, config: &NormalizeConfig) {
+    validate_event_type(event);
+    normalize_timestamp(event, config);
+    normalize_timestamp_range(event, config);
+    validate_spans(event, config);
+    normalize_modules(event, config);
+    normalize_logger(&mut event.logger, config);
+    normalize_key_collisions(event, config);
+    normalize_reserved_tags(event);
+    infer_ip_address(event, config);
+    normalize_dist(event);
+    normalize_exceptions(event, config);
+    normalize_debug_meta(event);
+    normalize_breadcrumbs(event);
+
+    if let Some(&mut Some(ref mut logentry)) = event.logentry.value_mut() {
+        normalize_logentry(logentry);
+    }
+    limit_logentry_params(event, config);
+    synthesize_exception(event, config);
+
+    let javascript = match event.platform.value().map(String::as_str) {
+        Some("javascript") | Some("node") => true,
+        _ => false,
+    };
+
+    for stacktrace in stacktraces_mut(event) {
+        normalize_stacktrace(stacktrace, config);
+
+        if javascript {
+            let frames = stacktrace.frames.value_mut();
+            for frame in frames.into_iter().flat_map(|frames| frames.iter_mut()) {
+                if let Some(frame) = frame.value_mut() {
+                    normalize_js_paths(&mut frame.abs_path, &mut frame.filename);
+                }
+            }
+        }
+    }
+
+    if let Some(&mut Some(ref mut template_info)) = event.template_info.value_mut() {
+        normalize_source_context(
+            &mut template_info.pre_lines,
+            &mut template_info.current_line,
+            &mut template_info.post_lines,
+            config,
+        );
+
+        if javascript {
+            normalize_js_paths(&mut template_info.abs_path, &mut template_info.filename);
+        }
+    }
+
+    // the transaction of transaction events is their name, not a location in code
+    if event.event_type() != EventType::Transaction {
+        compute_culprit(event);
+    }
+}
+
+/// Validates that an event carries the data required by its type.
+///
+/// Transactions require a `start_timestamp` and security reports require their respective
+/// interface, such as `csp`.  Missing data is recorded as error in the meta data of the missing
+/// start timestamp or of the event type, respectively.
+pub fn validate_event_type(event: &mut Event) {
+    let ty = event.event_type();
+
+    if ty == EventType::Transaction
+        && event.start_timestamp.map_or(true, Option::is_none)
+        && !event.start_timestamp.meta().has_errors()
+    {
+        event
+            .start_timestamp
+            .meta_mut()
+            .errors_mut()
+            .push("missing start_timestamp for transaction".to_string());
+    }
+
+    if let Some(interface) = ty.security_interface() {
+        let has_interface = event
+            .other
+            .value()
+            .map_or(false, |other| other.contains_key(interface));
+
+        if !has_interface {
+            let error = format!("missing {} interface for event type {}", interface, ty);
+            event.ty.meta_mut().errors_mut().push(error);
+        }
+    }
+}
+
+/// Corrects the event timestamp for clock drift of the client.
+///
+/// The received time defaults to the current time.  Missing timestamps and timestamps outside
+/// of the configured window around the received time are replaced with the received time.  If
+/// the window exceeds the range of valid dates, timestamps are considered outside of it.
+/// Replaced invalid timestamps are recorded as error in the meta data.
+pub fn normalize_timestamp(event: &mut Event, config: &NormalizeConfig) {
+    if event.received.map_or(true, Option::is_none) {
+        event.received.set_value(Some(Some(Utc::now())));
+        event.received.meta_mut().set_provenance(Provenance::Server);
+    }
+
+    let received = match event.received.value() {
+        Some(&Some(received)) => received,
+        _ => return,
+    };
+
+    let max = received.checked_add_signed(Duration::seconds(config.max_secs_in_future));
+    let min = received.checked_sub_signed(Duration::seconds(config.max_secs_in_past));
+
+    let error = match event.timestamp.value() {
+        Some(&Some(timestamp)) => {
+            if max.map_or(true, |max| timestamp > max) {
+                Some(format!("clock drift: timestamp {} is in the future", timestamp.to_rfc3339()))
+            } else if min.map_or(true, |min| timestamp < min) {
+                Some(format!("clock drift: timestamp {} is too old", timestamp.to_rfc3339()))
+            } else {
+                return;
+            }
+        }
+        _ => None,
+    };
+
+    event.timestamp.set_value(Some(Some(received)));
+    event.timestamp.meta_mut().set_provenance(Provenance::Server);
+    if let Some(error) = error {
+        event.timestamp.meta_mut().merge(Meta::from_error(error));
+    }
+}
+
+/// Clamps all timestamps of the event into the configured range of valid dates.
+///
+/// Clients on devices without a synchronized clock send dates long before or after the actual
+/// time of the event.  Timestamps before `min_timestamp` or more than `max_secs_after_now`
+/// seconds after the current time are clamped to the nearest valid date, and the original
+/// timestamp is recorded as error in the meta data.  This applies to the event, its breadcrumbs
+/// and spans.  Timestamps are left unchanged if the range exceeds the range of valid dates.
+pub fn normalize_timestamp_range(event: &mut Event, config: &NormalizeConfig) {
+    let min = match Utc.timestamp_opt(config.min_timestamp, 0).single() {
+        Some(min) => min,
+        None => return,
+    };
+    let max = match Utc::now().checked_add_signed(Duration::seconds(config.max_secs_after_now)) {
+        Some(max) => max,
+        None => return,
+    };
+
+    for timestamp in &mut [&mut event.timestamp, &mut event.start_timestamp] {
+        if let Annotated(Some(Some(ref mut datetime)), ref mut meta) = **timestamp {
+            clamp_datetime(datetime, meta, min, max);
+        }
+    }
+
+    if let Some(breadcrumbs) = event
+        .breadcrumbs
+        .value_mut()
+        .and_then(|values| values.values.value_mut())
+    {
+        for breadcrumb in breadcrumbs.iter_mut().filter_map(Annotated::value_mut) {
+            if let Annotated(Some(ref mut datetime), ref mut meta) = breadcrumb.timestamp {
+                clamp_datetime(datetime, meta, min, max);
+            }
+        }
+    }
+
+    if let Some(spans) = event.spans.value_mut() {
+        for span in spans.iter_mut().filter_map(Annotated::value_mut) {
+            for timestamp in &mut [&mut span.timestamp, &mut span.start_timestamp] {
+                if let Annotated(Some(ref mut datetime), ref mut meta) = **timestamp {
+                    clamp_datetime(datetime, meta, min, max);
+                }
+            }
+        }
+    }
+}
+
+/// Clamps a timestamp into the given range and records clamped values as error.
+fn clamp_datetime(
+    datetime: &mut DateTime<Utc>,
+    meta: &mut Meta,
+    min: DateTime<Utc>,
+    max: DateTime<Utc>,
+) {
+    let clamped = if *datetime < min {
+        min
+    } else if *datetime > max {
+        max
+    } else {
+        return;
+    };
+
+    meta.merge(Meta::from_error(format!(
+        "timestamp {} is out of range",
+        datetime.to_rfc3339()
+    )));
+    meta.set_provenance(Provenance::Server);
+    *datetime = clamped;
+}
+
+/// Validates that the spans of a transaction lie within the transaction's interval.
+///
+/// Spans ending before they start or lying outside of the interval between `start_timestamp`
+/// and `timestamp` of the transaction are recorded as error in the meta data of the span.  If
+/// `remove_invalid_spans` is set, these spans are removed as well.
+pub fn validate_spans(event: &mut Event, config: &NormalizeConfig) {
+    if event.event_type() != EventType::Transaction {
+        return;
+    }
+
+    let start = event.start_timestamp.value().and_then(|t| *t);
+    let end = event.timestamp.value().and_then(|t| *t);
+
+    let spans = match event.spans.value_mut() {
+        Some(spans) => spans,
+        None => return,
+    };
+
+    for span in spans.iter_mut() {
+        let error = match span.value() {
+            Some(span) => match (span.start_timestamp.value(), span.timestamp.value()) {
+                (Some(span_start), Some(span_end)) => {
+                    if span_start > span_end {
+                        "span ends before it starts"
+                    } else if start.map_or(false, |start| *span_start < start)
+                        || end.map_or(false, |end| *span_end > end)
+                    {
+                        "span is not within the transaction interval"
+                    } else {
+                        continue;
+                    }
+                }
+                _ => continue,
+            },
+            None => continue,
+        };
+
+        if config.remove_invalid_spans {
+            span.set_value(None);
+        }
+        span.meta_mut().errors_mut().push(error.to_string());
+    }
+}
+
+/// Normalizes the names and versions of installed modules.
+///
+/// Entries with empty names are removed and the map is truncated to the configured maximum
+/// number of entries.  Versions are trimmed, a `v` prefix is removed from numeric versions and
+/// git commit hashes are lowercased.  Changed versions and versions that are neither numeric
+/// nor commit hashes are recorded as errors in the meta data of the version.
+pub fn normalize_modules(event: &mut Event, config: &NormalizeConfig) {
+    let original_length = match event.modules.value_mut() {
+        Some(modules) => {
+            let original_length = modules.len();
+            let mut truncated = false;
+            let mut normalized = Map::new();
+            for (name, version) in mem::replace(modules, Map::new()) {
+                if name.trim().is_empty() {
+                    continue;
+                }
+
+                if normalized.len() >= config.max_modules {
+                    truncated = true;
+                    break;
+                }
+
+                normalized.insert(name, normalize_version(version));
+            }
+
+            *modules = normalized;
+            if truncated {
+                Some(original_length as u32)
+            } else {
+                None
+            }
+        }
+        None => return,
+    };
+
+    if original_length.is_some() {
+        event.modules.meta_mut().original_length = original_length;
+    }
+}
+
+fn normalize_version(version: Annotated<String>) -> Annotated<String> {
+    version.and_then(|original| {
+        let normalized = {
+            let trimmed = original.trim();
+            let stripped = if (trimmed.starts_with('v') || trimmed.starts_with('V'))
+                && trimmed[1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                &trimmed[1..]
+            } else {
+                trimmed
+            };
+
+            if GIT_SHA_REGEX.is_match(stripped) {
+                Some(stripped.to_lowercase())
+            } else if VERSION_REGEX.is_match(stripped) {
+                Some(stripped.to_string())
+            } else {
+                None
+            }
+        };
+
+        let normalized = match normalized {
+            Some(normalized) => normalized,
+            None => {
+                let error = format!("invalid version {:?}", original);
+                return Annotated::new(original, Meta::from_error(error));
+            }
+        };
+
+        if normalized != original {
+            let error = format!("normalized version {:?}", original);
+            return Annotated::new(normalized, Meta::from_error(error));
+        }
+
+        Annotated::from(normalized)
+    })
+}
+
+/// Returns `true` for characters allowed in the names of loggers.
+fn is_valid_logger_char(c: char) -> bool {
+    c.is_alphanumeric() || "._-:$/".contains(c)
+}
+
+/// Validates and trims the name of a logger.
+///
+/// Surrounding whitespace is removed.  If `replace_invalid_logger_chars` is enabled, other
+/// characters than letters, digits and `._-:$/` are replaced with underscores.  Empty names are
+/// removed.  Names exceeding the maximum length are shortened in the middle, so that both ends of
+/// dotted paths remain, as in `a.b...y.z`.  All modifications are recorded in remarks.
+pub fn normalize_logger(logger: &mut Annotated<Option<String>>, config: &NormalizeConfig) {
+    let original = match logger.value() {
+        Some(&Some(ref original)) => original.clone(),
+        _ => return,
+    };
+
+    let trimmed = original.trim();
+    let mut name: String = if config.replace_invalid_logger_chars {
+        trimmed
+            .chars()
+            .map(|c| if is_valid_logger_char(c) { c } else { '_' })
+            .collect()
+    } else {
+        trimmed.to_string()
+    };
+
+    if name.is_empty() {
+        logger.set_value(None);
+        logger
+            .meta_mut()
+            .remarks_mut()
+            .push(Remark::new(RemarkType::Removed, LOGGER_RULE_ID).with_note("empty logger"));
+        return;
+    }
+
+    if name != original {
+        let note = if name != trimmed {
+            "replaced invalid characters"
+        } else {
+            "removed surrounding whitespace"
+        };
+        logger
+            .meta_mut()
+            .remarks_mut()
+            .push(Remark::new(RemarkType::Substituted, LOGGER_RULE_ID).with_note(note));
+    }
+
+    let length = name.chars().count();
+    let max_length = config.max_logger_length;
+    if length > max_length {
+        if max_length > 3 {
+            let head = (max_length - 2) / 2;
+            let tail = max_length - 3 - head;
+            name = format!(
+                "{}...{}",
+                name.chars().take(head).collect::<String>(),
+                name.chars().skip(length - tail).collect::<String>()
+            );
+            logger.meta_mut().remarks_mut().push(Remark::with_range(
+                RemarkType::Substituted,
+                TRIM_RULE_ID,
+                (head, head + 3),
+            ));
+        } else {
+            name = name.chars().take(max_length).collect();
+        }
+
+        if logger.meta().original_length.is_none() {
+            logger.meta_mut().original_length = Some(original.chars().count() as u32);
+        }
+    }
+
+    logger.set_value(Some(Some(name)));
+}
+
+/// Resolves keys of `tags` and `extra` that differ only in case.
+///
+/// Colliding keys are resolved with the configured strategy, and every dropped or merged key is
+/// recorded as an error in the meta data of the map.
+pub fn normalize_key_collisions(event: &mut Event, config: &NormalizeConfig) {
+    if let Some(strategy) = config.key_collisions {
+        resolve_key_collisions(&mut event.tags, strategy, merge_tags);
+        resolve_key_collisions(&mut event.extra, strategy, merge_extra);
+    }
+}
+
+/// Moves reserved tags to the corresponding attributes of the event.
+///
+/// Reserved tags, such as `release` or `environment`, are always removed from the tags.  Their
+/// value is promoted to the attribute if it is empty, and discarded otherwise.  The `user` tag
+/// sets the user ID, or the field named by a prefix like `email:`.  Every removed tag with a value
+/// is recorded as a remark in the meta data of the tags.
+pub fn normalize_reserved_tags(event: &mut Event) {
+    let reserved: Vec<_> = match event.tags.value_mut() {
+        Some(tags) => RESERVED_TAGS
+            .iter()
+            .filter_map(|&key| remove_key(tags, key).map(|value| (key, value)))
+            .collect(),
+        None => return,
+    };
+
+    for (key, Annotated(value, _)) in reserved {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let (target, promoted) = promote_reserved_tag(event, key, value);
+        let note = if promoted {
+            format!("moved tag {} to {}", key, target)
+        } else {
+            format!("dropped tag {}, {} is already set", key, target)
+        };
+        let remark = Remark::new(RemarkType::Removed, RESERVED_TAG_RULE_ID).with_note(note);
+        event.tags.meta_mut().remarks_mut().push(remark);
+    }
+}
+
+/// Sets an attribute of the event from a reserved tag, unless it is already set.
+///
+/// Returns the path of the attribute and whether it was set.
+fn promote_reserved_tag(
+    event: &mut Event,
+    key: &'static str,
+    value: String,
+) -> (&'static str, bool) {
+    if key == "user" {
+        return promote_user_tag(event, value);
+    }
+
+    let field = match key {
+        "release" => &mut event.release,
+        "dist" => &mut event.dist,
+        "environment" => &mut event.environment,
+        "server_name" => &mut event.server_name,
+        "transaction" => &mut event.transaction,
+        _ => return (key, false),
+    };
+
+    (key, set_if_empty(field, value))
+}
+
+/// Sets a field of the user from the `user` tag, unless it is already set.
+///
+/// The tag value can be prefixed with the name of the field, as in `email:jane@example.com`.
+/// Values without a known prefix are the ID of the user.
+fn promote_user_tag(event: &mut Event, value: String) -> (&'static str, bool) {
+    let user = user_mut(event);
+
+    let (prefix, rest) = match value.find(':') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => ("", value.as_str()),
+    };
+
+    let (target, field, value) = match prefix {
+        "id" => ("user.id", &mut user.id, rest),
+        "email" => ("user.email", &mut user.email, rest),
+        "username" => ("user.username", &mut user.username, rest),
+        "ip" => ("user.ip_address", &mut user.ip_address, rest),
+        _ => ("user.id", &mut user.id, value.as_str()),
+    };
+
+    (target, set_if_empty(field, value.to_string()))
+}
+
+/// Returns the user of the event, creating an empty user if it is missing.
+fn user_mut(event: &mut Event) -> &mut User {
+    event
+        .user
+        .0
+        .get_or_insert(None)
+        .get_or_insert_with(|| User {
+            id: None.into(),
+            email: None.into(),
+            ip_address: None.into(),
+            username: None.into(),
+            geo: None.into(),
+            other: Map::new().into(),
+        })
+}
+
+fn set_if_empty(field: &mut Annotated<Option<String>>, value: String) -> bool {
+    if field.value().map_or(false, Option::is_some) {
+        return false;
+    }
+
+    field.set_value(Some(Some(value)));
+    field.meta_mut().set_provenance(Provenance::Inferred);
+    true
+}
+
+/// Copies the IP address of the client from the request to the user if enabled in the config.
+///
+/// The `REMOTE_ADDR` of the request environment is used if the IP address of the user is missing
+/// or `{{auto}}`, which requests the address of the client.  The copied address is marked as
+/// inferred, so that it is subject to the same PII rules as IP addresses sent by clients.
+pub fn infer_ip_address(event: &mut Event, config: &NormalizeConfig) {
+    if !config.infer_ip_address {
+        return;
+    }
+
+    let remote_addr = {
+        let entry = match event.request.value() {
+            Some(&Some(ref request)) => request.env.value().and_then(|env| env.get("REMOTE_ADDR")),
+            _ => None,
+        };
+
+        match entry.and_then(Annotated::value) {
+            Some(&Value::String(ref addr)) if addr.parse::<Ip>().is_ok() => addr.clone(),
+            _ => return,
+        }
+    };
+
+    let ip_address = &mut user_mut(event).ip_address;
+    match ip_address.value() {
+        Some(&Some(ref ip)) if ip != "{{auto}}" => return,
+        _ => (),
+    }
+
+    ip_address.set_value(Some(Some(remote_addr)));
+    ip_address.meta_mut().set_provenance(Provenance::Inferred);
+}
+
+/// Validates the distribution of an event.
+///
+/// Surrounding whitespace is removed from the distribution.  Since a distribution identifies a
+/// build of a release, it is removed with a remark if the event has no release.  Distributions
+/// longer than 64 characters or with other characters than ASCII letters, digits and `_.-` are
+/// removed with an error.
+pub fn normalize_dist(event: &mut Event) {
+    let (original, dist) = match event.dist.value() {
+        Some(&Some(ref dist)) => (dist.clone(), dist.trim().to_string()),
+        _ => return,
+    };
+
+    let has_release = match event.release.value() {
+        Some(&Some(ref release)) => !release.trim().is_empty(),
+        _ => false,
+    };
+
+    if dist.is_empty() || !has_release {
+        let note = if dist.is_empty() {
+            "empty dist"
+        } else {
+            "dist requires a release"
+        };
+        event.dist.set_value(None);
+        event
+            .dist
+            .meta_mut()
+            .remarks_mut()
+            .push(Remark::new(RemarkType::Removed, DIST_RULE_ID).with_note(note));
+    } else if dist.len() > MAX_DIST_LENGTH || !DIST_REGEX.is_match(&dist) {
+        event.dist.set_value(None);
+        event
+            .dist
+            .meta_mut()
+            .errors_mut()
+            .push(format!("invalid dist {:?}", original));
+    } else if dist != original {
+        event.dist.set_value(Some(Some(dist)));
+    }
+}
+
+/// Resolves case-insensitive key collisions in a map.
+///
+/// The values of colliding keys are passed to `merge` in the order of the map.
+fn resolve_key_collisions<T, F>(
+    annotated: &mut Annotated<Map<T>>,
+    strategy: KeyCollisionStrategy,
+    merge: F,
+) where
+    F: Fn(Vec<Annotated<T>>) -> Annotated<T>,
+{
+    let Annotated(ref mut map, ref mut meta) = *annotated;
+    let map = match *map {
+        Some(ref mut map) => map,
+        None => return,
+    };
+
+    let mut groups: Vec<Vec<(String, Annotated<T>)>> = vec![];
+    let mut indices = BTreeMap::new();
+    for (key, value) in mem::replace(map, Map::new()) {
+        let index = *indices.entry(key.to_lowercase()).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[index].push((key, value));
+    }
+
+    for mut group in groups {
+        if group.len() == 1 {
+            let (key, value) = group.remove(0);
+            map.insert(key, value);
+            continue;
+        }
+
+        let (key, value) = match strategy {
+            KeyCollisionStrategy::KeepFirst | KeyCollisionStrategy::KeepLast => {
+                let index = match strategy {
+                    KeyCollisionStrategy::KeepFirst => 0,
+                    _ => group.len() - 1,
+                };
+                let (key, value) = group.remove(index);
+                for (dropped, _) in group {
+                    meta.errors_mut()
+                        .push(format!("dropped key {} colliding with {}", dropped, key));
+                }
+                (key, value)
+            }
+            KeyCollisionStrategy::Merge => {
+                let key = group[0].0.clone();
+                let error = {
+                    let merged: Vec<_> = group[1..].iter().map(|&(ref k, _)| k.as_str()).collect();
+                    format!("merged keys {} into {}", merged.join(", "), key)
+                };
+                meta.errors_mut().push(error);
+                let values = group.into_iter().map(|(_, value)| value).collect();
+                (key, merge(values))
+            }
+        };
+
+        map.insert(key, value);
+    }
+}
+
+/// Joins the distinct values of colliding tags with commas.
+fn merge_tags(values: Vec<Annotated<String>>) -> Annotated<String> {
+    let mut distinct: Vec<String> = vec![];
+    let mut meta = None;
+    for Annotated(value, value_meta) in values {
+        meta = meta.or(Some(value_meta));
+        if let Some(value) = value {
+            if !distinct.contains(&value) {
+                distinct.push(value);
+            }
+        }
+    }
+
+    let value = if distinct.is_empty() {
+        None
+    } else {
+        Some(distinct.join(", "))
+    };
+    Annotated(value, meta.unwrap_or_default())
+}
+
+/// Merges the values of colliding extra keys.
+///
+/// Objects are merged with earlier keys taking precedence, other values are collected in an
+/// array unless they are all equal.
+fn merge_extra(values: Vec<Annotated<Value>>) -> Annotated<Value> {
+    let mut distinct: Vec<Annotated<Value>> = vec![];
+    for value in values {
+        if value.value().is_some() && !distinct.iter().any(|v| v.value() == value.value()) {
+            distinct.push(value);
+        }
+    }
+
+    if distinct.len() <= 1 {
+        return distinct.pop().unwrap_or_else(Annotated::empty);
+    }
+
+    let all_maps = distinct.iter().all(|value| match value.value() {
+        Some(&Value::Map(_)) => true,
+        _ => false,
+    });
+
+    if !all_maps {
+        return Annotated::from(Value::Array(distinct));
+    }
+
+    let mut merged = Map::new();
+    for value in distinct {
+        if let Some(Value::Map(map)) = value.0 {
+            for (key, value) in map {
+                if !merged.contains_key(&key) {
+                    merged.insert(key, value);
+                }
+            }
+        }
+    }
+    Annotated::from(Value::Map(merged))
+}
+
+/// Normalizes a chain of exceptions.
+///
+/// Exceptions are ordered with the oldest cause first.  If the mechanisms of all exceptions carry
+/// an `exception_id`, exceptions referencing a `parent_id` are moved before their parent.
+/// Identical adjacent exceptions are removed, and chains longer than the configured maximum
+/// are truncated by removing the oldest causes.  Exceptions without a thread are linked to the
+/// crashed thread, or to the only thread of the event.
+pub fn normalize_exceptions(event: &mut Event, config: &NormalizeConfig) {
+    let thread_id = crashed_thread_id(event);
+
+    let exceptions = match event.exceptions.value_mut() {
+        Some(values) => &mut values.values,
+        None => return,
+    };
+
+    let original_length = match exceptions.value_mut() {
+        Some(exceptions) => {
+            sort_exception_chain(exceptions);
+            exceptions.dedup();
+
+            let original_length = exceptions.len();
+            if original_length > config.max_exceptions {
+                exceptions.drain(..original_length - config.max_exceptions);
+            }
+
+            if let Some(ref thread_id) = thread_id {
+                for exception in exceptions.iter_mut().filter_map(Annotated::value_mut) {
+                    if exception.thread_id.map_or(true, Option::is_none) {
+                        exception.thread_id.set_value(Some(Some(thread_id.clone())));
+                        exception.thread_id.meta_mut().set_provenance(Provenance::Inferred);
+                    }
+                }
+            }
+
+            original_length
+        }
+        None => return,
+    };
+
+    if original_length > config.max_exceptions && exceptions.meta().original_length.is_none() {
+        exceptions.meta_mut().original_length = Some(original_length as u32);
+    }
+}
+
+/// Returns the id of the crashed thread, or of the only thread.
+fn crashed_thread_id(event: &Event) -> Option<ThreadId> {
+    let threads: Vec<_> = values(&event.threads).collect();
+    let mut crashed = threads
+        .iter()
+        .filter(|thread| thread.crashed.value() == Some(&true));
+
+    let thread = match (crashed.next(), crashed.next()) {
+        (Some(thread), None) => thread,
+        (None, None) if threads.len() == 1 => &threads[0],
+        _ => return None,
+    };
+
+    thread.id.value().and_then(Clone::clone)
+}
+
+/// Reads an id from the mechanism of an exception.
+fn mechanism_id(exception: &Annotated<Exception>, key: &str) -> Option<u64> {
+    let mechanism = exception.value()?.mechanism.value()?.as_ref()?;
+    mechanism.other.value()?.get(key)?.value()?.as_u64_lossy()
+}
+
+/// Sorts exceptions so that causes come before the exceptions referencing them as parent.
+fn sort_exception_chain(exceptions: &mut Array<Exception>) {
+    let ids: Option<Vec<u64>> = exceptions
+        .iter()
+        .map(|exception| mechanism_id(exception, "exception_id"))
+        .collect();
+
+    let ids = match ids {
+        Some(ids) => ids,
+        None => return,
+    };
+
+    let parents: Vec<Option<usize>> = exceptions
+        .iter()
+        .map(|exception| {
+            let parent_id = mechanism_id(exception, "parent_id")?;
+            ids.iter().position(|&id| id == parent_id)
+        }).collect();
+
+    let mut depths = Vec::with_capacity(ids.len());
+    for index in 0..ids.len() {
+        let mut depth = 0;
+        let mut current = index;
+        // bounded by the number of exceptions to guard against cycles
+        while depth < ids.len() {
+            match parents[current] {
+                Some(parent) => {
+                    current = parent;
+                    depth += 1;
+                }
+                None => break,
+            }
+        }
+        depths.push(depth);
+    }
+
+    let mut indexed: Vec<_> = exceptions.drain(..).zip(depths).collect();
+    // stable sort keeps the original order for exceptions at the same depth
+    indexed.sort_by(|a, b| b.1.cmp(&a.1));
+    exceptions.extend(indexed.into_iter().map(|(exception, _)| exception));
+}
+
+/// Normalizes the list of debug images.
+///
+/// Images with a missing or invalid debug identifier are converted into an unknown image type
+/// with an error, so that their data is retained.  Images whose address range overflows receive
+/// an error on their size.  Afterwards, duplicate images are removed and the remaining images
+/// are sorted by their start address.
+pub fn normalize_debug_meta(event: &mut Event) {
+    let images = match event.debug_meta.value_mut() {
+        Some(&mut Some(ref mut debug_meta)) => &mut debug_meta.images,
+        _ => return,
+    };
+
+    let images = match images.value_mut() {
+        Some(images) => images,
+        None => return,
+    };
+
+    for image in images.iter_mut() {
+        quarantine_invalid_image(image);
+        validate_image_range(image);
+    }
+
+    let mut seen = BTreeSet::new();
+    images.retain(|image| match image_identifier(image) {
+        Some(identifier) => seen.insert(identifier),
+        None => true,
+    });
+
+    // images without an address are moved to the end
+    images.sort_by_key(|image| match image_addr(image) {
+        Some(addr) => (false, addr),
+        None => (true, Addr(0)),
+    });
+}
+
+/// Converts an image without valid debug identifier into `DebugImage::Other`.
+fn quarantine_invalid_image(image: &mut Annotated<DebugImage>) {
+    let (ty, field) = match image.value() {
+        Some(&DebugImage::Apple(ref apple)) if apple.uuid.value().is_none() => ("apple", "uuid"),
+        Some(&DebugImage::Symbolic(ref symbolic)) if symbolic.id.value().is_none() => {
+            ("symbolic", "id")
+        }
+        Some(&DebugImage::Proguard(ref proguard)) if proguard.uuid.value().is_none() => {
+            ("proguard", "uuid")
+        }
+        _ => return,
+    };
+
+    let Annotated(map, mut meta) = image
+        .to_json()
+        .ok()
+        .and_then(|json| Annotated::<Map<Value>>::from_json(&json).ok())
+        .unwrap_or_else(Annotated::empty);
+
+    let mut map = map.unwrap_or_default();
+    remove_key(&mut map, "type");
+
+    meta.errors_mut()
+        .push(format!("invalid {} debug image: missing or invalid {}", ty, field));
+    *image = Annotated::new(DebugImage::Other(ty.to_string(), map), meta);
+}
+
+/// Adds an error to the image size if the end address of an image overflows.
+fn validate_image_range(image: &mut Annotated<DebugImage>) {
+    let (image_addr, image_size) = match image.value_mut() {
+        Some(&mut DebugImage::Apple(ref mut apple)) => {
+            (apple.image_addr.value().cloned(), &mut apple.image_size)
+        }
+        Some(&mut DebugImage::Symbolic(ref mut symbolic)) => {
+            (symbolic.image_addr.value().cloned(), &mut symbolic.image_size)
+        }
+        _ => return,
+    };
+
+    let size = match (image_addr, image_size.value().cloned()) {
+        (Some(Addr(addr)), Some(size)) if addr.checked_add(size).is_none() => size,
+        _ => return,
+    };
+
+    image_size.set_value(None);
+    image_size
+        .meta_mut()
+        .errors_mut()
+        .push(format!("image size {} overflows the address space", size));
+}
+
+/// Returns the start address of an image.
+fn image_addr(image: &Annotated<DebugImage>) -> Option<Addr> {
+    match *image.value()? {
+        DebugImage::Apple(ref apple) => apple.image_addr.value().cloned(),
+        DebugImage::Symbolic(ref symbolic) => symbolic.image_addr.value().cloned(),
+        _ => None,
+    }
+}
+
+/// Returns the debug identifier and code identifier of an image for deduplication.
+fn image_identifier(image: &Annotated<DebugImage>) -> Option<(String, Option<String>)> {
+    let (debug_id, other) = match *image.value()? {
+        DebugImage::Apple(ref apple) => (apple.uuid.value()?.to_string(), &apple.other),
+        DebugImage::Symbolic(ref symbolic) => (symbolic.id.value()?.to_string(), &symbolic.other),
+        DebugImage::Proguard(ref proguard) => (proguard.uuid.value()?.to_string(), &proguard.other),
+        DebugImage::Other(..) => return None,
+    };
+
+    let code_id = match other.value().and_then(|other| other.get("code_id")) {
+        Some(&Annotated(Some(Value::String(ref code_id)), _)) => Some(code_id.to_lowercase()),
+        _ => None,
+    };
+
+    Some((debug_id.to_lowercase(), code_id))
+}
+
+/// Validates the structured data of `http` breadcrumbs.
+///
+/// Status codes sent as strings are converted to numbers and request methods are uppercased.
+/// Invalid status codes and unknown request methods are removed with an error.
+pub fn normalize_breadcrumbs(event: &mut Event) {
+    let breadcrumbs = match event.breadcrumbs.value_mut() {
+        Some(breadcrumbs) => breadcrumbs,
+        None => return,
+    };
+
+    for breadcrumb in breadcrumbs.iter_valid_mut() {
+        if breadcrumb.is_http() {
+            normalize_http_breadcrumb(breadcrumb);
+        }
+    }
+}
+
+/// Normalizes the status code and method in the data of an `http` breadcrumb.
+fn normalize_http_breadcrumb(breadcrumb: &mut Breadcrumb) {
+    let status_code = breadcrumb.http_status_code();
+    let data = match breadcrumb.data.value_mut() {
+        Some(data) => data,
+        None => return,
+    };
+
+    if let Some(value) = data.get_mut("status_code") {
+        match status_code {
+            Some(_) => {
+                value.coerce_u64();
+            }
+            None => remove_invalid(value, "invalid http status code"),
+        }
+    }
+
+    if let Some(value) = data.get_mut("method") {
+        let method = match value.value() {
+            Some(&Value::String(ref method)) => Some(method.to_uppercase()),
+            _ => None,
+        };
+
+        match method {
+            Some(ref method) if HTTP_METHODS.contains(&method.as_str()) => {
+                value.set_value(Some(Value::String(method.clone())))
+            }
+            _ => remove_invalid(value, "unknown http method"),
+        }
+    }
+}
+
+/// Removes an invalid value and records it in an error.
+fn remove_invalid(value: &mut Annotated<Value>, reason: &str) {
+    let error = match value.value() {
+        Some(invalid) => format!("{} {}", reason, invalid),
+        None => return,
+    };
+
+    value.set_value(None);
+    value.meta_mut().errors_mut().push(error);
+}
+
+/// Fills in the formatted message of a log entry if it is missing.
+///
+/// The parameters are interpolated into the message with `format_logentry`.  The formatted
+/// message is marked as inferred.
+pub fn normalize_logentry(logentry: &mut LogEntry) {
+    if logentry.formatted.map_or(false, Option::is_some) {
+        return;
+    }
+
+    let formatted = match logentry.message.value() {
+        Some(message) => {
+            let params: Vec<_> = logentry.params.value().map_or(vec![], |params| {
+                params.iter().map(Annotated::value).collect()
+            });
+            format_logentry(message, &params)
+        }
+        None => return,
+    };
+
+    logentry.formatted.set_value(Some(Some(formatted)));
+    logentry
+        .formatted
+        .meta_mut()
+        .set_provenance(Provenance::Inferred);
+}
+
+/// Limits the number and size of log entry parameters.
+///
+/// Parameters exceeding the maximum count are removed and string parameters are trimmed to the
+/// maximum length.  Arrays and objects are moved to `extra` with the key `logentry.params.<index>`,
+/// leaving a removed parameter with a note in their place so that the indices of the remaining
+/// parameters do not change.  If `extra` already contains that key, a numeric suffix is appended
+/// instead of overwriting it, and the note mentions the collision.
+pub fn limit_logentry_params(event: &mut Event, config: &NormalizeConfig) {
+    let params = match event.logentry.value_mut() {
+        Some(&mut Some(ref mut logentry)) => &mut logentry.params,
+        _ => return,
+    };
+
+    let Annotated(ref mut params, ref mut meta) = *params;
+    let params = match *params {
+        Some(ref mut params) => params,
+        None => return,
+    };
+
+    if params.len() > config.max_params {
+        if meta.original_length.is_none() {
+            meta.original_length = Some(params.len() as u32);
+        }
+        params.truncate(config.max_params);
+    }
+
+    for (index, param) in params.iter_mut().enumerate() {
+        *param = match mem::replace(param, Annotated::empty()) {
+            Annotated(Some(Value::String(string)), meta) => {
+                trim_string(Annotated::new(string, meta), config.max_param_length)
+                    .map(Value::String)
+            }
+            Annotated(Some(value @ Value::Array(_)), mut meta)
+            | Annotated(Some(value @ Value::Map(_)), mut meta) => {
+                let extra = event.extra.0.get_or_insert_with(Map::new);
+                let base = format!("logentry.params.{}", index);
+                let mut key = base.clone();
+                let mut suffix = 0;
+                while extra.contains_key(&key) {
+                    suffix += 1;
+                    key = format!("{}_{}", base, suffix);
+                }
+
+                let note = if suffix > 0 {
+                    format!("moved to extra.{}, extra.{} already exists", key, base)
+                } else {
+                    format!("moved to extra.{}", key)
+                };
+                let remark = Remark::new(RemarkType::Removed, TRIM_RULE_ID).with_note(note);
+                meta.remarks_mut().push(remark);
+
+                extra.insert(key, Annotated::from(value));
+                Annotated(None, meta)
+            }
+            annotated => annotated,
+        };
+    }
+}
+
+/// Creates an exception from the message of an event, if enabled in the config.
+///
+/// This applies to events of the configured platforms that do not have exceptions, a stack
+/// trace or threads.  The value of the exception is the formatted log entry, falling back to
+/// the log message and the `message` attribute.  The exception has the type `Error` and a
+/// synthetic mechanism, so that it is grouped like other exceptions but can be told apart from
+/// exceptions raised by the application.
+pub fn synthesize_exception(event: &mut Event, config: &NormalizeConfig) {
+    if !config.synthesize_exceptions {
+        return;
+    }
+
+    let supported = event.platform.value().map_or(false, |platform| {
+        config
+            .synthetic_exception_platforms
+            .iter()
+            .any(|p| p == platform)
+    });
+
+    if !supported
+        || values(&event.exceptions).next().is_some()
+        || event.stacktrace.value().map_or(false, Option::is_some)
+        || values(&event.threads).next().is_some()
+    {
+        return;
+    }
+
+    let message = {
+        let logentry = event.logentry.value().and_then(Option::as_ref);
+        logentry
+            .and_then(|logentry| logentry.formatted.value().and_then(Option::as_ref))
+            .or_else(|| logentry.and_then(|logentry| logentry.message.value()))
+            .or_else(|| event.message.value().and_then(Option::as_ref))
+            .cloned()
+    };
+
+    let message = match message {
+        Some(message) => message,
+        None => return,
+    };
+
+    let mut mechanism = Annotated::from(Some(Mechanism {
+        ty: "generic".to_string().into(),
+        description: None.into(),
+        help_link: None.into(),
+        handled: None.into(),
+        synthetic: Some(true).into(),
+        data: Map::new().into(),
+        meta: Default::default(),
+        other: Map::new().into(),
+    }));
+    mechanism.meta_mut().set_provenance(Provenance::Inferred);
+
+    let mut exception = ExceptionBuilder::new("Error").value(message).finish();
+    if let Some(exception) = exception.value_mut() {
+        exception.mechanism = mechanism;
+    }
+
+    let mut exceptions = Values::new();
+    exceptions.values.set_value(Some(vec![exception]));
+    event.exceptions = Annotated::from(exceptions);
+    event
+        .exceptions
+        .meta_mut()
+        .set_provenance(Provenance::Inferred);
+}
+
+/// Interpolates positional parameters into a log message.
+///
+/// If the message contains printf-style placeholders such as `%s` or `%d`, every placeholder
+/// consumes the next parameter and `%%` is an escaped percent sign.  Otherwise, brace-style
+/// placeholders are replaced: `{}` consumes the next parameter, `{0}` refers to a parameter by
+/// index, and `{{` and `}}` are escaped braces.  Parameters are inserted as strings regardless
+/// of the conversion type.  Placeholders without a parameter are retained.  The space flag of
+/// printf is not supported, so that text such as `50% done` contains no placeholder.
+pub fn format_logentry(message: &str, params: &[Option<&Value>]) -> String {
+    let format_param = |index: usize, placeholder: &str| match params.get(index) {
+        Some(&Some(param)) => param.to_string(),
+        Some(&None) => "null".to_string(),
+        None => placeholder.to_string(),
+    };
+
+    let mut next = 0;
+    if PRINTF_REGEX.find_iter(message).any(|m| m.as_str() != "%%") {
+        PRINTF_REGEX
+            .replace_all(message, |caps: &Captures| {
+                if &caps[0] == "%%" {
+                    return "%".to_string();
+                }
+                next += 1;
+                format_param(next - 1, &caps[0])
+            })
+            .into_owned()
+    } else {
+        BRACE_REGEX
+            .replace_all(message, |caps: &Captures| match &caps[0] {
+                "{{" => "{".to_string(),
+                "}}" => "}".to_string(),
+                placeholder => match caps[1].parse() {
+                    Ok(index) => format_param(index, placeholder),
+                    Err(_) => {
+                        next += 1;
+                        format_param(next - 1, placeholder)
+                    }
+                },
+            })
+            .into_owned()
+    }
+}
+
+/// Normalizes a stack trace in place.
+///
+/// Frames without an explicit `in_app` flag are marked according to the in-app config.  The
+/// source context of all frames is truncated, see `normalize_source_context`.  Stack traces
+/// exceeding the frame limit are trimmed, see `trim_frames`.
+pub fn normalize_stacktrace(stacktrace: &mut Stacktrace, config: &NormalizeConfig) {
+    {
+        let frames = match stacktrace.frames.value_mut() {
+            Some(frames) => frames,
+            None => return,
+        };
+
+        for frame in frames.iter_mut().filter_map(Annotated::value_mut) {
+            if frame.in_app.map_or(true, Option::is_none) {
+                if let Some(in_app) = config.in_app.is_in_app(frame) {
+                    frame.in_app.set_value(Some(Some(in_app)));
+                }
+            }
+
+            normalize_source_context(
+                &mut frame.pre_lines,
+                &mut frame.current_line,
+                &mut frame.post_lines,
+                config,
+            );
+        }
+    }
+
+    trim_frames(stacktrace, config);
+}
+
+/// Removes frames from the middle of stack traces exceeding the frame limit.
+///
+/// The configured number of frames is retained at the start and the end of the stack trace.  The
+/// removed frames are replaced with a single `...` frame annotated with a remark.  The original
+/// number of frames is recorded in the meta data, and the range of removed frames is stored in
+/// `frames_omitted` unless the client already omitted frames.
+pub fn trim_frames(stacktrace: &mut Stacktrace, config: &NormalizeConfig) {
+    let head = config.max_frames_head;
+    let tail = config.max_frames_tail;
+
+    let original_length = match stacktrace.frames.value_mut() {
+        Some(frames) if frames.len() > head + tail => {
+            let original_length = frames.len();
+            let omitted = original_length - head - tail;
+
+            let mut marker = Annotated::from(Frame {
+                function: Some("...".to_string()).into(),
+                ..Default::default()
+            });
+            marker
+                .meta_mut()
+                .remarks_mut()
+                .push(Remark::new(RemarkType::Removed, TRIM_RULE_ID));
+            frames.drain(head..head + omitted);
+            frames.insert(head, marker);
+
+            original_length
+        }
+        _ => return,
+    };
+
+    if stacktrace.frames.meta().original_length.is_none() {
+        stacktrace.frames.meta_mut().original_length = Some(original_length as u32);
+    }
+
+    if stacktrace.frames_omitted.map_or(true, Option::is_none) {
+        let omitted = (head as u64, (original_length - tail) as u64);
+        stacktrace.frames_omitted.set_value(Some(Some(omitted)));
+        stacktrace
+            .frames_omitted
+            .meta_mut()
+            .set_provenance(Provenance::Server);
+    }
+}
+
+/// Normalizes the paths of a JavaScript frame or template the same way as the server.
+///
+/// Query strings and fragments are removed from the absolute path.  If the file name is missing,
+/// it is inferred from the absolute path: `webpack://` prefixes and a leading `./` are removed,
+/// and URLs are reduced to their path.
+pub fn normalize_js_paths(
+    abs_path: &mut Annotated<Option<String>>,
+    filename: &mut Annotated<Option<String>>,
+) {
+    let abs_path = match abs_path.value_mut() {
+        Some(&mut Some(ref mut abs_path)) => {
+            if let Some(index) = abs_path.find(|c: char| c == '?' || c == '#') {
+                abs_path.truncate(index);
+            }
+            abs_path
+        }
+        _ => return,
+    };
+
+    if filename.map_or(true, Option::is_none) {
+        let inferred = js_filename(abs_path);
+        if !inferred.is_empty() {
+            filename.set_value(Some(Some(inferred.to_string())));
+            filename.meta_mut().set_provenance(Provenance::Inferred);
+        }
+    }
+}
+
+/// Infers the file name of a JavaScript frame from its absolute path.
+fn js_filename(abs_path: &str) -> &str {
+    if abs_path.starts_with("webpack:") {
+        let mut path = &abs_path["webpack:".len()..];
+        while path.starts_with('/') {
+            path = &path[1..];
+        }
+        if path.starts_with("./") {
+            path = &path[2..];
+        }
+        return path;
+    }
+
+    if let Some(scheme_end) = abs_path.find("://") {
+        let host_start = scheme_end + 3;
+        if let Some(path_start) = abs_path[host_start..].find('/') {
+            return &abs_path[host_start + path_start..];
+        }
+    }
+
+    abs_path
+}
+
+/// Truncates source context around the current line of a frame or template.
+///
+/// Lines exceeding the maximum line length are trimmed with an ellipsis that is annotated with a
+/// remark.  Only the configured number of lines closest to the current line are retained before
+/// and after it, and the original number of lines is recorded in the meta data.
+pub fn normalize_source_context(
+    pre_lines: &mut Annotated<Array<String>>,
+    current_line: &mut Annotated<Option<String>>,
+    post_lines: &mut Annotated<Array<String>>,
+    config: &NormalizeConfig,
+) {
+    limit_context_lines(pre_lines, true, config);
+    limit_context_lines(post_lines, false, config);
+
+    *current_line = match mem::replace(current_line, Annotated::empty()) {
+        Annotated(Some(Some(line)), meta) => {
+            trim_string(Annotated::new(line, meta), config.max_context_line_length).map(Some)
+        }
+        annotated => annotated,
+    };
+}
+
+/// Limits the number of context lines and trims each line.
+///
+/// Lines before the current line retain the last lines, all other lines retain the first.
+fn limit_context_lines(
+    lines: &mut Annotated<Array<String>>,
+    before_current: bool,
+    config: &NormalizeConfig,
+) {
+    let original_length = match lines.value_mut() {
+        Some(lines) => {
+            let original_length = lines.len();
+            if original_length > config.max_context_lines {
+                if before_current {
+                    lines.drain(..original_length - config.max_context_lines);
+                } else {
+                    lines.truncate(config.max_context_lines);
+                }
+            }
+
+            for line in lines.iter_mut() {
+                let trimmed = trim_string(
+                    mem::replace(line, Annotated::empty()),
+                    config.max_context_line_length,
+                );
+                *line = trimmed;
+            }
+
+            original_length
+        }
+        None => return,
+    };
+
+    if original_length > config.max_context_lines && lines.meta().original_length.is_none() {
+        lines.meta_mut().original_length = Some(original_length as u32);
+    }
+}
+
+/// Derives `culprit` and `transaction` from the stack trace if they are missing.
+///
+/// Like on the server, the stack trace of the most recent exception is used, falling back to
+/// the event's stack trace and then to thread stack traces.  The culprit is computed from the
+/// most recent in-app frame, or the most recent frame if no frame is in-app.  A missing culprit
+/// prefers an existing transaction over the stack trace.
+///
+/// Frames are in-app if their `in_app` flag is set, see `normalize_stacktrace`.
+pub fn compute_culprit(event: &mut Event) {
+    let has_culprit = event.culprit.map_or(false, Option::is_some);
+    let has_transaction = event.transaction.map_or(false, Option::is_some);
+    if has_culprit && has_transaction {
+        return;
+    }
+
+    let frame_culprit = {
+        let platform = event.platform.value().map_or("other", String::as_str);
+        stacktraces(event)
+            .into_iter()
+            .filter_map(culprit_frame)
+            .map(|frame| frame_culprit(frame, platform))
+            .find(|culprit| !culprit.is_empty())
+    };
+
+    if !has_culprit {
+        let transaction = event.transaction.value().and_then(Clone::clone);
+        if let Some(culprit) = transaction.or_else(|| frame_culprit.clone()) {
+            event.culprit.set_value(Some(Some(culprit)));
+            event.culprit.meta_mut().set_provenance(Provenance::Inferred);
+        }
+    }
+
+    if !has_transaction {
+        if let Some(culprit) = frame_culprit {
+            event.transaction.set_value(Some(Some(culprit)));
+            event.transaction.meta_mut().set_provenance(Provenance::Inferred);
+        }
+    }
+}
+
+/// Returns all stack traces of the event in the order they are considered for the culprit.
+fn stacktraces(event: &Event) -> Vec<&Stacktrace> {
+    let mut rv = vec![];
+
+    for exception in values(&event.exceptions).rev() {
+        rv.extend(exception.stacktrace.value().and_then(Option::as_ref));
+    }
+
+    rv.extend(event.stacktrace.value().and_then(Option::as_ref));
+
+    for thread in values(&event.threads) {
+        rv.extend(thread.stacktrace.value().and_then(Option::as_ref));
+    }
+
+    rv
+}
+
+/// Returns all stack traces of the event mutably.
+fn stacktraces_mut(event: &mut Event) -> Vec<&mut Stacktrace> {
+    let mut rv = vec![];
+
+    let exceptions = event
+        .exceptions
+        .value_mut()
+        .and_then(|values| values.values.value_mut());
+    for exception in exceptions.into_iter().flat_map(|array| array.iter_mut()) {
+        if let Some(exception) = exception.value_mut() {
+            rv.extend(exception.stacktrace.value_mut().and_then(Option::as_mut));
+        }
+    }
+
+    rv.extend(event.stacktrace.value_mut().and_then(Option::as_mut));
+
+    let threads = event
+        .threads
+        .value_mut()
+        .and_then(|values| values.values.value_mut());
+    for thread in threads.into_iter().flat_map(|array| array.iter_mut()) {
+        if let Some(thread) = thread.value_mut() {
+            rv.extend(thread.stacktrace.value_mut().and_then(Option::as_mut));
+        }
+    }
+
+    rv
+}
+
+fn values<T>(values: &Annotated<Values<T>>) -> impl DoubleEndedIterator<Item = &T> {
+    values
+        .value()
+        .and_then(|values| values.values.value())
+        .into_iter()
+        .flat_map(array_values)
+}
+
+fn array_values<T>(array: &Array<T>) -> impl DoubleEndedIterator<Item = &T> {
+    array.iter().filter_map(Annotated::value)
+}
+
+/// Returns the most recent in-app frame, or the most recent frame if none is in-app.
+fn culprit_frame(stacktrace: &Stacktrace) -> Option<&Frame> {
+    let frames = stacktrace.frames.value()?;
+    array_values(frames)
+        .rev()
+        .find(|frame| frame.in_app.value() == Some(&Some(true)))
+        .or_else(|| array_values(frames).next_back())
+}
+
+/// Formats the culprit of a frame the same way as the server.
+fn frame_culprit(frame: &Frame, platform: &str) -> String {
+    let location = frame
+        .module
+        .value()
+        .and_then(Option::as_ref)
+        .or_else(|| frame.filename.value().and_then(Option::as_ref));
+
+    let location = match location {
+        Some(location) => location,
+        None => return String::new(),
+    };
+
+    let function = frame
+        .function
+        .value()
+        .and_then(Option::as_ref)
+        .map_or("?", String::as_str);
+
+    match platform {
+        "javascript" | "node" => format!("{}({})", function, location),
+        _ => format!("{} in {}", location, function),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn culprit_and_transaction(json: &str, config: &NormalizeConfig) -> (String, String) {
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, config);
+        (
+            event.culprit.0.unwrap().unwrap_or_default(),
+            event.transaction.0.unwrap().unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn test_in_app_frame() {
+        let json = r#"{
+  "exception": {
+    "values": [
+      {
+        "type": "ValueError",
+        "stacktrace": {
+          "frames": [
+            {"module": "app.views", "function": "index", "in_app": true},
+            {"module": "django.core", "function": "handle", "in_app": false}
+          ]
+        }
+      }
+    ]
+  }
+}"#;
+
+        assert_eq_dbg!(
+            culprit_and_transaction(json, &NormalizeConfig::default()),
+            (
+                "app.views in index".to_string(),
+                "app.views in index".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_in_app_prefixes() {
+        let json = r#"{
+  "platform": "javascript",
+  "stacktrace": {
+    "frames": [
+      {"abs_path": "webpack:///app/index.js", "filename": "app/index.js", "function": "main"},
+      {"abs_path": "webpack:///node_modules/react.js", "filename": "react.js"}
+    ]
+  }
+}"#;
+
+        assert_eq_dbg!(
+            culprit_and_transaction(json, &NormalizeConfig::default()),
+            ("?(react.js)".to_string(), "?(react.js)".to_string())
+        );
+
+        let config = NormalizeConfig {
+            in_app: InAppConfig {
+                include: vec!["webpack:///app/".to_string()],
+                exclude: vec![],
+            },
+            ..Default::default()
+        };
+        assert_eq_dbg!(
+            culprit_and_transaction(json, &config),
+            ("main(app/index.js)".to_string(), "main(app/index.js)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_existing_transaction() {
+        let json = r#"{
+  "transaction": "/users/{id}",
+  "stacktrace": {
+    "frames": [{"module": "app.views", "function": "index"}]
+  }
+}"#;
+
+        assert_eq_dbg!(
+            culprit_and_transaction(json, &NormalizeConfig::default()),
+            ("/users/{id}".to_string(), "/users/{id}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_culprit_provenance() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "transaction": "/users/{id}",
+  "stacktrace": {
+    "frames": [{"module": "app.views", "function": "index"}]
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        compute_culprit(&mut event);
+        assert_eq_dbg!(event.culprit.meta().provenance(), Provenance::Inferred);
+        assert_eq_dbg!(event.transaction.meta().provenance(), Provenance::Sdk);
+
+        let json = Annotated::from(event).to_json().unwrap();
+        assert!(json.contains(r#""culprit":{"":{"src":"inferred"}}"#));
+    }
+
+    #[test]
+    fn test_no_stacktrace() {
+        let mut event = Annotated::<Event>::from_json("{}").unwrap().0.unwrap();
+        compute_culprit(&mut event);
+        assert_eq_dbg!(event.culprit, Annotated::from(None));
+        assert_eq_dbg!(event.transaction, Annotated::from(None));
+    }
+
+    #[test]
+    fn test_in_app_config() {
+        let mut stacktrace = Annotated::<Stacktrace>::from_json(
+            r#"{
+  "frames": [
+    {"module": "myapp.vendor.requests"},
+    {"module": "myapp.views"},
+    {"module": "requests.adapters"},
+    {"abs_path": "/usr/lib/python3/json.py"},
+    {"module": "myapp.utils", "in_app": false},
+    {"module": "unknown"}
+  ]
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            in_app: InAppConfig {
+                include: vec!["myapp.".to_string()],
+                exclude: vec![
+                    "myapp.vendor.".to_string(),
+                    "requests.".to_string(),
+                    "/usr/".to_string(),
+                ],
+            },
+            ..Default::default()
+        };
+        normalize_stacktrace(&mut stacktrace, &config);
+
+        let in_app: Vec<_> = stacktrace
+            .frames
+            .value()
+            .unwrap()
+            .iter()
+            .map(|frame| frame.value().unwrap().in_app.value().cloned().unwrap())
+            .collect();
+        assert_eq_dbg!(
+            in_app,
+            vec![Some(true), Some(true), Some(false), Some(false), Some(false), None]
+        );
+    }
+
+    #[test]
+    fn test_in_app_globs() {
+        let mut stacktrace = Annotated::<Stacktrace>::from_json(
+            r#"{
+  "frames": [
+    {"abs_path": "webpack:///app/index.js"},
+    {"abs_path": "webpack:///app/node_modules/react.js"},
+    {"abs_path": "/usr/lib/python3/json.py"},
+    {"abs_path": "/usr/local/lib/site.py"}
+  ]
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            in_app: InAppConfig {
+                include: vec!["webpack:///app/[!n]".to_string()],
+                exclude: vec![
+                    "*/node_modules/".to_string(),
+                    "/usr/lib/python?/".to_string(),
+                ],
+            },
+            ..Default::default()
+        };
+        normalize_stacktrace(&mut stacktrace, &config);
+
+        let in_app: Vec<_> = stacktrace
+            .frames
+            .value()
+            .unwrap()
+            .iter()
+            .map(|frame| frame.value().unwrap().in_app.value().cloned().unwrap())
+            .collect();
+        assert_eq_dbg!(in_app, vec![Some(true), Some(false), Some(false), None]);
+    }
+
+    #[test]
+    fn test_js_paths() {
+        let json = r#"{
+  "platform": "javascript",
+  "stacktrace": {
+    "frames": [
+      {"abs_path": "https://example.com/static/app.js?v=3#main"},
+      {"abs_path": "webpack:///./src/components/App.js"},
+      {"abs_path": "app:///bundle.js?x", "filename": "bundle.js"},
+      {"function": "anonymous"}
+    ]
+  },
+  "template": {
+    "abs_path": "file:///srv/app/views/index.html#top"
+  }
+}"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &NormalizeConfig::default());
+
+        let stacktrace = event.stacktrace.value().unwrap().as_ref().unwrap();
+        let paths: Vec<_> = array_values(stacktrace.frames.value().unwrap())
+            .map(|frame| {
+                (
+                    frame.abs_path.value().cloned(),
+                    frame.filename.value().cloned(),
+                )
+            })
+            .collect();
+        assert_eq_dbg!(
+            paths,
+            vec![
+                (
+                    Some(Some("https://example.com/static/app.js".to_string())),
+                    Some(Some("/static/app.js".to_string())),
+                ),
+                (
+                    Some(Some("webpack:///./src/components/App.js".to_string())),
+                    Some(Some("src/components/App.js".to_string())),
+                ),
+                (
+                    Some(Some("app:///bundle.js".to_string())),
+                    Some(Some("bundle.js".to_string())),
+                ),
+                (Some(None), Some(None)),
+            ]
+        );
+
+        let template_info = event.template_info.value().unwrap().as_ref().unwrap();
+        assert_eq_dbg!(
+            template_info.filename.value(),
+            Some(&Some("/srv/app/views/index.html".to_string()))
+        );
+        assert_eq!(
+            template_info.filename.meta().provenance(),
+            Provenance::Inferred
+        );
+    }
+
+    #[test]
+    fn test_js_paths_other_platform() {
+        let json = r#"{"stacktrace": {"frames": [{"abs_path": "/srv/app.py?x"}]}}"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &NormalizeConfig::default());
+
+        let stacktrace = event.stacktrace.value().unwrap().as_ref().unwrap();
+        let frame = stacktrace.frames.value().unwrap()[0].value().unwrap();
+        assert_eq_dbg!(
+            frame.abs_path.value(),
+            Some(&Some("/srv/app.py?x".to_string()))
+        );
+        assert_eq_dbg!(frame.filename.value(), Some(&None));
+    }
+
+    #[test]
+    fn test_format_logentry() {
+        let world = Value::String("World".to_string());
+        let one = Value::U64(1);
+        let params = [Some(&world), Some(&one)];
+
+        assert_eq_str!(
+            format_logentry("Hello, %s! %d%% (%s)", &params),
+            "Hello, World! 1% (%s)"
+        );
+        assert_eq_str!(
+            format_logentry("{{{}}} {} {0} {5}", &params),
+            "{World} 1 World {5}"
+        );
+        assert_eq_str!(format_logentry("100%% {}", &params), "100%% World");
+        assert_eq_str!(format_logentry("%s", &[None]), "null");
+        assert_eq_str!(
+            format_logentry("50% done with %s", &params),
+            "50% done with World"
+        );
+    }
+
+    #[test]
+    fn test_normalize_logentry() {
+        let json = r#"{"logentry": {"message": "Hello, %s!", "params": ["World"]}}"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &NormalizeConfig::default());
+
+        let logentry = event.logentry.value().unwrap().as_ref().unwrap();
+        assert_eq_dbg!(
+            logentry.formatted.value(),
+            Some(&Some("Hello, World!".to_string()))
+        );
+        assert_eq_dbg!(logentry.formatted.meta().provenance(), Provenance::Inferred);
+
+        let json = r#"{"message": "Hello, %s!", "formatted": "Hello!"}"#;
+        let mut logentry = Annotated::<LogEntry>::from_json(json).unwrap().0.unwrap();
+        normalize_logentry(&mut logentry);
+        assert_eq_dbg!(
+            logentry.formatted.value(),
+            Some(&Some("Hello!".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_limit_logentry_params() {
+        let json = r#"{
+            "logentry": {
+                "message": "%s %s %s",
+                "params": ["short", "a long parameter", {"nested": [1]}, "dropped"]
+            }
+        }"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        let config = NormalizeConfig {
+            max_params: 3,
+            max_param_length: 10,
+            ..Default::default()
+        };
+        limit_logentry_params(&mut event, &config);
+
+        let logentry = event.logentry.value().unwrap().as_ref().unwrap();
+        assert_eq!(logentry.params.meta().original_length(), Some(4));
+
+        let params = logentry.params.value().unwrap();
+        assert_eq!(params.len(), 3);
+        assert_eq_dbg!(params[0].value(), Some(&Value::String("short".to_string())));
+        assert_eq_dbg!(
+            params[1].value(),
+            Some(&Value::String("a long ...".to_string()))
+        );
+        assert_eq!(params[1].meta().original_length(), Some(16));
+
+        assert!(params[2].value().is_none());
+        let remark = params[2].meta().remarks().next().unwrap();
+        assert_eq_str!(remark.note().unwrap(), "moved to extra.logentry.params.2");
+
+        let extra = event.extra.value().unwrap();
+        assert!(extra["logentry.params.2"].value().is_some());
+    }
+
+    #[test]
+    fn test_limit_logentry_params_collision() {
+        let json = r#"{
+            "logentry": {
+                "message": "%s",
+                "params": [[1, 2]]
+            },
+            "extra": {
+                "logentry.params.0": "user value"
+            }
+        }"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        limit_logentry_params(&mut event, &NormalizeConfig::default());
+
+        let logentry = event.logentry.value().unwrap().as_ref().unwrap();
+        let params = logentry.params.value().unwrap();
+        assert!(params[0].value().is_none());
+        let remark = params[0].meta().remarks().next().unwrap();
+        assert_eq_str!(
+            remark.note().unwrap(),
+            "moved to extra.logentry.params.0_1, extra.logentry.params.0 already exists"
+        );
+
+        let extra = event.extra.value().unwrap();
+        assert_eq_dbg!(
+            extra["logentry.params.0"].value(),
+            Some(&Value::String("user value".to_string()))
+        );
+        assert_eq_dbg!(
+            extra["logentry.params.0_1"].value(),
+            Some(&Value::Array(vec![
+                Annotated::from(Value::U64(1)),
+                Annotated::from(Value::U64(2)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_synthesize_exception() {
+        let json = r#"{
+            "platform": "python",
+            "message": "fallback",
+            "logentry": {"message": "Hello, %s!", "params": ["World"]}
+        }"#;
+        let config = NormalizeConfig {
+            synthesize_exceptions: true,
+            ..Default::default()
+        };
+
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &config);
+        assert_eq_dbg!(event.exceptions.meta().provenance(), Provenance::Inferred);
+
+        let exception = values(&event.exceptions).next().unwrap();
+        assert_eq_str!(exception.ty.value().unwrap(), "Error");
+        assert_eq_dbg!(
+            exception.value.value(),
+            Some(&Some("Hello, World!".to_string()))
+        );
+        let mechanism = exception.mechanism.value().unwrap().as_ref().unwrap();
+        assert_eq_str!(mechanism.ty.value().unwrap(), "generic");
+        assert_eq_dbg!(mechanism.synthetic.value(), Some(&Some(true)));
+
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &NormalizeConfig::default());
+        assert!(values(&event.exceptions).next().is_none());
+
+        let json = r#"{"platform": "native", "message": "Hello"}"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &config);
+        assert!(values(&event.exceptions).next().is_none());
+
+        let json = r#"{
+            "platform": "python",
+            "message": "Hello",
+            "exception": {"values": [{"type": "ValueError"}]}
+        }"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_event(&mut event, &config);
+        let types: Vec<_> = values(&event.exceptions)
+            .map(|exception| exception.ty.value().unwrap().as_str())
+            .collect();
+        assert_eq_dbg!(types, vec!["ValueError"]);
+    }
+
+    #[test]
+    fn test_reserved_tags() {
+        let json = r#"{
+            "release": "1.0",
+            "user": {"id": "42"},
+            "tags": {
+                "environment": "production",
+                "release": "2.0",
+                "user": "email:jane@example.com",
+                "custom": "value"
+            }
+        }"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_reserved_tags(&mut event);
+
+        let tags: Vec<_> = event.tags.value().unwrap().keys().collect();
+        assert_eq_dbg!(tags, vec!["custom"]);
+        assert_eq_dbg!(
+            event.environment.value(),
+            Some(&Some("production".to_string()))
+        );
+        assert_eq_dbg!(event.environment.meta().provenance(), Provenance::Inferred);
+        assert_eq_dbg!(event.release.value(), Some(&Some("1.0".to_string())));
+
+        let user = event.user.value().unwrap().as_ref().unwrap();
+        assert_eq_dbg!(user.id.value(), Some(&Some("42".to_string())));
+        assert_eq_dbg!(
+            user.email.value(),
+            Some(&Some("jane@example.com".to_string()))
+        );
+
+        let mut notes: Vec<_> = event
+            .tags
+            .meta()
+            .remarks()
+            .map(|remark| remark.note().unwrap())
+            .collect();
+        notes.sort();
+        assert_eq_dbg!(
+            notes,
+            vec![
+                "dropped tag release, release is already set",
+                "moved tag environment to environment",
+                "moved tag user to user.email",
+            ]
+        );
+
+        let json = r#"{"tags": {"user": "42"}}"#;
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        normalize_reserved_tags(&mut event);
+        let user = event.user.value().unwrap().as_ref().unwrap();
+        assert_eq_dbg!(user.id.value(), Some(&Some("42".to_string())));
+    }
+
+    #[test]
+    fn test_infer_ip_address() {
+        let config = NormalizeConfig {
+            infer_ip_address: true,
+            ..Default::default()
+        };
+        let infer = |json: &str| {
+            let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+            infer_ip_address(&mut event, &config);
+            let user = event.user.0.and_then(|user| user);
+            user.map(|user| user.ip_address)
+        };
+
+        let ip_address = infer(r#"{"request": {"env": {"REMOTE_ADDR": "192.168.0.1"}}}"#).unwrap();
+        assert_eq_dbg!(ip_address.value(), Some(&Some("192.168.0.1".to_string())));
+        assert_eq!(ip_address.meta().provenance(), Provenance::Inferred);
+
+        let ip_address = infer(
+            r#"{
+                "request": {"env": {"REMOTE_ADDR": "::1"}},
+                "user": {"ip_address": "{{auto}}"}
+            }"#,
+        ).unwrap();
+        assert_eq_dbg!(ip_address.value(), Some(&Some("::1".to_string())));
+
+        let ip_address = infer(
+            r#"{
+                "request": {"env": {"REMOTE_ADDR": "192.168.0.1"}},
+                "user": {"ip_address": "10.0.0.1"}
+            }"#,
+        ).unwrap();
+        assert_eq_dbg!(ip_address.value(), Some(&Some("10.0.0.1".to_string())));
+
+        assert!(infer(r#"{"request": {"env": {"REMOTE_ADDR": "unknown"}}}"#).is_none());
+    }
+
+    #[test]
+    fn test_dist() {
+        let normalize = |json: &str| {
+            let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+            normalize_dist(&mut event);
+            event.dist
+        };
+
+        let dist = normalize(r#"{"release": "1.0", "dist": " 42 "}"#);
+        assert_eq_dbg!(dist, Annotated::from(Some("42".to_string())));
+
+        let dist = normalize(r#"{"dist": "42"}"#);
+        assert!(dist.value().is_none());
+        let remark = dist.meta().remarks().next().unwrap();
+        assert_eq!(remark.ty(), RemarkType::Removed);
+        assert_eq_str!(remark.note().unwrap(), "dist requires a release");
+
+        let dist = normalize(r#"{"release": "1.0", "dist": "  "}"#);
+        assert!(dist.value().is_none());
+        assert_eq_str!(
+            dist.meta().remarks().next().unwrap().note().unwrap(),
+            "empty dist"
+        );
+
+        let dist = normalize(r#"{"release": "1.0", "dist": "a/b"}"#);
+        assert!(dist.value().is_none());
+        assert_eq_str!(
+            dist.meta().errors().next().unwrap(),
+            r#"invalid dist "a/b""#
+        );
+    }
+
+    #[test]
+    fn test_key_collisions() {
+        let json = r#"{
+            "tags": {"ENV": "prod", "Env": "staging", "env": "prod", "release": "1.0"},
+            "extra": {"Foo": {"a": 1}, "foo": {"a": 2, "b": 3}, "X": 1, "x": 2}
+        }"#;
+        let event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+
+        let mut first = event.clone();
+        let config = NormalizeConfig {
+            key_collisions: Some(KeyCollisionStrategy::KeepFirst),
+            ..Default::default()
+        };
+        normalize_key_collisions(&mut first, &config);
+        let tags = first.tags.value().unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq_str!(tags["ENV"].value().unwrap(), "prod");
+        assert_eq_dbg!(
+            first.tags.meta().errors().collect::<Vec<_>>(),
+            vec![
+                "dropped key Env colliding with ENV",
+                "dropped key env colliding with ENV",
+            ]
+        );
+
+        let mut last = event.clone();
+        let config = NormalizeConfig {
+            key_collisions: Some(KeyCollisionStrategy::KeepLast),
+            ..Default::default()
+        };
+        normalize_key_collisions(&mut last, &config);
+        let extra = last.extra.value().unwrap();
+        assert_eq!(extra.len(), 2);
+        assert_eq_dbg!(extra["x"].value(), Some(&Value::U64(2)));
+
+        let mut merged = event;
+        let config = NormalizeConfig {
+            key_collisions: Some(KeyCollisionStrategy::Merge),
+            ..Default::default()
+        };
+        normalize_key_collisions(&mut merged, &config);
+        let tags = merged.tags.value().unwrap();
+        assert_eq_str!(tags["ENV"].value().unwrap(), "prod, staging");
+        assert_eq_dbg!(
+            merged.tags.meta().errors().collect::<Vec<_>>(),
+            vec!["merged keys Env, env into ENV"]
+        );
+
+        let extra = merged.extra.value().unwrap();
+        match extra["Foo"].value() {
+            Some(&Value::Map(ref map)) => {
+                assert_eq_dbg!(map["a"].value(), Some(&Value::U64(1)));
+                assert_eq_dbg!(map["b"].value(), Some(&Value::U64(3)));
+            }
+            other => panic!("expected a merged object, got {:?}", other),
+        }
+        match extra["X"].value() {
+            Some(&Value::Array(ref values)) => assert_eq!(values.len(), 2),
+            other => panic!("expected an array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_trim_frames() {
+        let frames: Vec<_> = (0..10)
+            .map(|i| format!(r#"{{"function": "f{}"}}"#, i))
+            .collect();
+        let json = format!(r#"{{"frames": [{}]}}"#, frames.join(","));
+        let mut stacktrace = Annotated::<Stacktrace>::from_json(&json)
+            .unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            max_frames_head: 2,
+            max_frames_tail: 3,
+            ..Default::default()
+        };
+        normalize_stacktrace(&mut stacktrace, &config);
+
+        let functions: Vec<_> = stacktrace
+            .frames
+            .value()
+            .unwrap()
+            .iter()
+            .map(|frame| frame.value().unwrap().function.value().cloned().unwrap())
+            .collect();
+        assert_eq_dbg!(
+            functions,
+            vec!["f0", "f1", "...", "f7", "f8", "f9"]
+                .into_iter()
+                .map(|name| Some(name.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        let frames = &stacktrace.frames;
+        assert_eq!(frames.meta().original_length(), Some(10));
+        assert_eq!(frames.value().unwrap()[2].meta().remarks().count(), 1);
+        assert_eq_dbg!(stacktrace.frames_omitted.value(), Some(&Some((2, 7))));
+    }
+
+    #[test]
+    fn test_trim_frames_within_limit() {
+        let mut stacktrace = Annotated::<Stacktrace>::from_json(r#"{"frames": [{}, {}, {}]}"#)
+            .unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            max_frames_head: 1,
+            max_frames_tail: 2,
+            ..Default::default()
+        };
+        trim_frames(&mut stacktrace, &config);
+
+        assert_eq!(stacktrace.frames.value().unwrap().len(), 3);
+        assert_eq_dbg!(stacktrace.frames_omitted.value(), Some(&None));
+    }
+
+    fn normalized_timestamp(timestamp: &str) -> Annotated<Option<DateTime<Utc>>> {
+        let json = format!(
+            r#"{{"received": "2018-07-01T12:00:00Z", "timestamp": {}}}"#,
+            timestamp
+        );
+        let mut event = Annotated::<Event>::from_json(&json).unwrap().0.unwrap();
+        normalize_timestamp(&mut event, &NormalizeConfig::default());
+        event.timestamp
+    }
+
+    fn server_meta(error: Option<&str>) -> Meta {
+        let mut meta = error.map(Meta::from_error).unwrap_or_default();
+        meta.set_provenance(Provenance::Server);
+        meta
+    }
+
+    #[test]
+    fn test_timestamp_valid() {
+        assert_eq_dbg!(
+            normalized_timestamp(r#""2018-07-01T11:00:00Z""#),
+            Annotated::from(Some(Utc.ymd(2018, 7, 1).and_hms(11, 0, 0)))
+        );
+    }
+
+    #[test]
+    fn test_timestamp_missing() {
+        assert_eq_dbg!(
+            normalized_timestamp("null"),
+            Annotated::new(Some(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0)), server_meta(None))
+        );
+    }
+
+    #[test]
+    fn test_timestamp_future() {
+        assert_eq_dbg!(
+            normalized_timestamp(r#""2018-07-01T12:05:00Z""#),
+            Annotated::new(
+                Some(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0)),
+                server_meta(Some(
+                    "clock drift: timestamp 2018-07-01T12:05:00+00:00 is in the future"
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn test_timestamp_past() {
+        assert_eq_dbg!(
+            normalized_timestamp("0"),
+            Annotated::new(
+                Some(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0)),
+                server_meta(Some("clock drift: timestamp 1970-01-01T00:00:00+00:00 is too old"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_timestamp_received_out_of_range() {
+        let received = ::chrono::MAX_DATE.and_hms(23, 59, 59);
+        let mut event = Event::default();
+        event.received.set_value(Some(Some(received)));
+        event
+            .timestamp
+            .set_value(Some(Some(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0))));
+
+        normalize_timestamp(&mut event, &NormalizeConfig::default());
+        assert_eq_dbg!(event.timestamp.value(), Some(&Some(received)));
+        assert_eq!(event.timestamp.meta().errors().count(), 1);
+    }
+
+    #[test]
+    fn test_min_timestamp_out_of_range() {
+        let json = r#"{"min_timestamp": 9223372036854775807}"#;
+        assert!(::serde_json::from_str::<NormalizeConfig>(json).is_err());
+    }
+
+    #[test]
+    fn test_received_default() {
+        let mut event = Event::default();
+        normalize_timestamp(&mut event, &NormalizeConfig::default());
+        assert!(event.received.value().unwrap().is_some());
+        assert_eq_dbg!(event.received.meta().provenance(), Provenance::Server);
+        assert_eq_dbg!(event.timestamp.value(), event.received.value());
+    }
+
+    #[test]
+    fn test_event_type_validation() {
+        let mut event = Annotated::<Event>::from_json(r#"{"type": "transaction"}"#)
+            .unwrap()
+            .0
+            .unwrap();
+        normalize_event(&mut event, &NormalizeConfig::default());
+        assert_eq_dbg!(
+            event.start_timestamp.meta().errors().collect::<Vec<_>>(),
+            vec!["missing start_timestamp for transaction"]
+        );
+
+        let mut event = Annotated::<Event>::from_json(r#"{"type": "csp"}"#)
+            .unwrap()
+            .0
+            .unwrap();
+        validate_event_type(&mut event);
+        assert_eq_dbg!(
+            event.ty.meta().errors().collect::<Vec<_>>(),
+            vec!["missing csp interface for event type csp"]
+        );
+
+        let mut event = Annotated::<Event>::from_json(r#"{"type": "csp", "csp": {}}"#)
+            .unwrap()
+            .0
+            .unwrap();
+        validate_event_type(&mut event);
+        assert!(!event.ty.meta().has_errors());
+    }
+
+    #[test]
+    fn test_transaction_culprit() {
+        let json = r#"{
+  "type": "transaction",
+  "start_timestamp": 946684800,
+  "stacktrace": {"frames": [{"module": "app.views", "function": "index"}]}
+}"#;
+
+        assert_eq_dbg!(
+            culprit_and_transaction(json, &NormalizeConfig::default()),
+            (String::new(), String::new())
+        );
+    }
+
+    #[test]
+    fn test_timestamp_range() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "timestamp": -86400,
+  "breadcrumbs": [
+    {"timestamp": 946684800},
+    {"timestamp": 315532800.5},
+    {"timestamp": 32503680000}
+  ]
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_timestamp_range(&mut event, &NormalizeConfig::default());
+
+        let min = Utc.ymd(1990, 1, 1).and_hms(0, 0, 0);
+        assert_eq_dbg!(
+            event.timestamp,
+            Annotated::new(
+                Some(min),
+                server_meta(Some("timestamp 1969-12-31T00:00:00+00:00 is out of range"))
+            )
+        );
+
+        let breadcrumbs = event
+            .breadcrumbs
+            .value()
+            .and_then(|values| values.values.value())
+            .unwrap();
+        let timestamps: Vec<_> = breadcrumbs
+            .iter()
+            .map(|breadcrumb| &breadcrumb.value().unwrap().timestamp)
+            .collect();
+
+        assert_eq_dbg!(
+            *timestamps[0],
+            Annotated::from(Utc.ymd(2000, 1, 1).and_hms(0, 0, 0))
+        );
+        assert_eq_dbg!(
+            *timestamps[1],
+            Annotated::new(
+                min,
+                server_meta(Some("timestamp 1980-01-01T00:00:00.500+00:00 is out of range"))
+            )
+        );
+        assert!(*timestamps[2].value().unwrap() < Utc.ymd(3000, 1, 1).and_hms(0, 0, 0));
+        assert_eq!(timestamps[2].meta().errors().count(), 1);
+    }
+
+    #[test]
+    fn test_validate_spans() {
+        let json = r#"{
+  "type": "transaction",
+  "start_timestamp": 946684800,
+  "timestamp": 946684860,
+  "spans": [
+    {"start_timestamp": 946684810, "timestamp": 946684820, "op": "inside"},
+    {"start_timestamp": 946684790, "timestamp": 946684820, "op": "early"},
+    {"start_timestamp": 946684830, "timestamp": 946684820, "op": "reversed"}
+  ]
+}"#;
+
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        validate_spans(&mut event, &NormalizeConfig::default());
+        let spans = event.spans.value().unwrap();
+        assert_eq!(spans.len(), 3);
+        assert!(spans.iter().all(|span| span.value().is_some()));
+        assert!(!spans[0].meta().has_errors());
+        assert_eq_dbg!(
+            spans[1].meta().errors().collect::<Vec<_>>(),
+            vec!["span is not within the transaction interval"]
+        );
+        assert_eq_dbg!(
+            spans[2].meta().errors().collect::<Vec<_>>(),
+            vec!["span ends before it starts"]
+        );
+
+        let config = NormalizeConfig {
+            remove_invalid_spans: true,
+            ..NormalizeConfig::default()
+        };
+        let mut event = Annotated::<Event>::from_json(json).unwrap().0.unwrap();
+        validate_spans(&mut event, &config);
+        let spans = event.spans.value().unwrap();
+        assert!(spans[0].value().is_some());
+        assert_eq_dbg!(
+            spans[1],
+            Annotated::from_error("span is not within the transaction interval")
+        );
+        assert_eq_dbg!(
+            spans[2],
+            Annotated::from_error("span ends before it starts")
+        );
+    }
+
+    #[test]
+    fn test_modules() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "modules": {
+    "": "1.0",
+    "a": "1.2.3",
+    "b": " v1.2 ",
+    "c": "ABCDEF1234567",
+    "d": "latest",
+    "e": "2.0.0-rc.1"
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_modules(&mut event, &NormalizeConfig::default());
+
+        let mut expected = Map::new();
+        expected.insert("a".to_string(), Annotated::from("1.2.3".to_string()));
+        expected.insert(
+            "b".to_string(),
+            Annotated::new(
+                "1.2".to_string(),
+                Meta::from_error(r#"normalized version " v1.2 ""#),
+            ),
+        );
+        expected.insert(
+            "c".to_string(),
+            Annotated::new(
+                "abcdef1234567".to_string(),
+                Meta::from_error(r#"normalized version "ABCDEF1234567""#),
+            ),
+        );
+        expected.insert(
+            "d".to_string(),
+            Annotated::new(
+                "latest".to_string(),
+                Meta::from_error(r#"invalid version "latest""#),
+            ),
+        );
+        expected.insert("e".to_string(), Annotated::from("2.0.0-rc.1".to_string()));
+
+        assert_eq_dbg!(event.modules, Annotated::from(expected));
+    }
+
+    #[test]
+    fn test_modules_limit() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{"modules": {"a": "1.0", "b": "2.0", "c": "3.0"}}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            max_modules: 2,
+            ..Default::default()
+        };
+        normalize_modules(&mut event, &config);
+
+        let modules = event.modules.value().unwrap();
+        assert_eq!(modules.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(event.modules.meta().original_length(), Some(3));
+    }
+
+    #[test]
+    fn test_logger() {
+        let config = NormalizeConfig {
+            max_logger_length: 9,
+            replace_invalid_logger_chars: true,
+            ..Default::default()
+        };
+        let normalize = |logger: &str| {
+            let mut logger = Annotated::from(Some(logger.to_string()));
+            normalize_logger(&mut logger, &config);
+            logger
+        };
+
+        assert_eq_dbg!(
+            normalize("a.b.c"),
+            Annotated::from(Some("a.b.c".to_string()))
+        );
+
+        let logger = normalize("my logger\n");
+        assert_eq_dbg!(logger.value(), Some(&Some("my_logger".to_string())));
+        assert_eq_str!(
+            logger.meta().remarks().next().unwrap().note().unwrap(),
+            "replaced invalid characters"
+        );
+
+        let logger = normalize("a.b.c.d.e.y.z");
+        assert_eq_dbg!(logger.value(), Some(&Some("a.b...y.z".to_string())));
+        assert_eq!(logger.meta().original_length(), Some(13));
+        assert_eq_dbg!(
+            logger.meta().remarks().collect::<Vec<_>>(),
+            vec![&Remark::with_range(
+                RemarkType::Substituted,
+                TRIM_RULE_ID,
+                (3, 6)
+            )]
+        );
+
+        let logger = normalize("  ");
+        assert!(logger.value().is_none());
+        assert_eq_str!(
+            logger.meta().remarks().next().unwrap().note().unwrap(),
+            "empty logger"
+        );
+
+        let logger = normalize("ä.ö.ü.日本.語");
+        assert_eq_dbg!(logger.value(), Some(&Some("ä.ö...本.語".to_string())));
+    }
+
+    #[test]
+    fn test_logger_keep_chars() {
+        let config = NormalizeConfig::default();
+        let mut logger = Annotated::from(Some(" <anonymous>".to_string()));
+        normalize_logger(&mut logger, &config);
+
+        assert_eq_dbg!(logger.value(), Some(&Some("<anonymous>".to_string())));
+        assert_eq_str!(
+            logger.meta().remarks().next().unwrap().note().unwrap(),
+            "removed surrounding whitespace"
+        );
+    }
+
+    fn exception_types(event: &Event) -> Vec<String> {
+        values(&event.exceptions)
+            .map(|exception| exception.ty.value().cloned().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn test_exception_chain_order() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "exception": {
+    "values": [
+      {"type": "Outer", "mechanism": {"type": "chained", "exception_id": 0}},
+      {"type": "Inner", "mechanism": {"type": "chained", "exception_id": 2, "parent_id": 1}},
+      {"type": "Middle", "mechanism": {"type": "chained", "exception_id": 1, "parent_id": 0}}
+    ]
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_exceptions(&mut event, &NormalizeConfig::default());
+        assert_eq!(exception_types(&event), vec!["Inner", "Middle", "Outer"]);
+    }
+
+    #[test]
+    fn test_exception_chain_without_ids() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "exception": {
+    "values": [
+      {"type": "A", "mechanism": {"type": "chained", "exception_id": 1, "parent_id": 0}},
+      {"type": "B"}
+    ]
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_exceptions(&mut event, &NormalizeConfig::default());
+        assert_eq!(exception_types(&event), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_exception_dedup_and_limit() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "exception": {
+    "values": [
+      {"type": "A", "value": "a"},
+      {"type": "B", "value": "b"},
+      {"type": "B", "value": "b"},
+      {"type": "C", "value": "c"},
+      {"type": "B", "value": "b"}
+    ]
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        let config = NormalizeConfig {
+            max_exceptions: 2,
+            ..Default::default()
+        };
+        normalize_exceptions(&mut event, &config);
+
+        assert_eq!(exception_types(&event), vec!["C", "B"]);
+        let values = event.exceptions.value().unwrap();
+        assert_eq!(values.values.meta().original_length(), Some(4));
+    }
+
+    #[test]
+    fn test_exception_thread_id() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "exception": {"values": [{"type": "A"}, {"type": "B", "thread_id": "main"}]},
+  "threads": {"values": [{"id": 1}, {"id": 2, "crashed": true}]}
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_exceptions(&mut event, &NormalizeConfig::default());
+
+        let thread_ids: Vec<_> = values(&event.exceptions)
+            .map(|exception| exception.thread_id.value().cloned().unwrap())
+            .collect();
+        assert_eq_dbg!(
+            thread_ids,
+            vec![
+                Some(ThreadId::Int(2)),
+                Some(ThreadId::String("main".to_string())),
+            ]
+        );
+
+        let provenances: Vec<_> = values(&event.exceptions)
+            .map(|exception| exception.thread_id.meta().provenance())
+            .collect();
+        assert_eq_dbg!(provenances, vec![Provenance::Inferred, Provenance::Sdk]);
+    }
+
+    #[test]
+    fn test_debug_images() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "debug_meta": {
+    "images": [
+      {
+        "type": "symbolic",
+        "name": "libb.so",
+        "image_addr": "0x2000",
+        "image_size": 4096,
+        "id": "494f3aea-88fa-4296-9644-fa8ef5d139b6-1234"
+      },
+      {
+        "type": "apple",
+        "name": "liba.dylib",
+        "image_addr": "0x1000",
+        "image_size": 4096,
+        "uuid": "395835f4-03e0-4436-80d3-136f0749a893"
+      },
+      {
+        "type": "apple",
+        "name": "liba.dylib",
+        "image_addr": "0x1000",
+        "image_size": 4096,
+        "uuid": "395835F4-03E0-4436-80D3-136F0749A893"
+      },
+      {
+        "type": "apple",
+        "name": "libc.dylib",
+        "image_addr": "0xffffffffffffff00",
+        "image_size": 4096,
+        "uuid": "04e9f6b2-3a35-4cbd-bd57-5e9a3d2ac5d6"
+      },
+      {
+        "type": "apple",
+        "name": "libd.dylib",
+        "image_addr": "0x0",
+        "image_size": 4096,
+        "uuid": "invalid"
+      }
+    ]
+  }
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_debug_meta(&mut event);
+
+        let images = event
+            .debug_meta
+            .value()
+            .and_then(|debug_meta| debug_meta.as_ref())
+            .and_then(|debug_meta| debug_meta.images.value())
+            .unwrap();
+
+        let addrs: Vec<_> = images.iter().map(image_addr).collect();
+        assert_eq_dbg!(
+            addrs,
+            vec![
+                Some(Addr(0x1000)),
+                Some(Addr(0x2000)),
+                Some(Addr(0xffff_ffff_ffff_ff00)),
+                None,
+            ]
+        );
+
+        match images[2].value() {
+            Some(&DebugImage::Apple(ref apple)) => {
+                assert_eq!(apple.image_size.value(), None);
+                assert_eq!(apple.image_size.meta().errors().count(), 1);
+            }
+            other => panic!("unexpected image {:?}", other),
+        }
+
+        match images[3] {
+            Annotated(Some(DebugImage::Other(ref ty, ref other)), ref meta) => {
+                assert_eq_str!(ty, "apple");
+                assert!(other.contains_key("uuid"));
+                assert_eq!(meta.errors().count(), 1);
+            }
+            ref other => panic!("unexpected image {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_breadcrumbs() {
+        let mut event = Annotated::<Event>::from_json(
+            r#"{
+  "breadcrumbs": [
+    {"timestamp": 0, "type": "http", "data": {"method": "post", "status_code": "201"}},
+    {"timestamp": 0, "type": "http", "data": {"method": "FETCH", "status_code": 1000}},
+    {"timestamp": 0, "data": {"method": "post", "status_code": "201"}}
+  ]
+}"#,
+        ).unwrap()
+            .0
+            .unwrap();
+
+        normalize_breadcrumbs(&mut event);
+
+        let breadcrumbs = event
+            .breadcrumbs
+            .value()
+            .and_then(|values| values.values.value())
+            .unwrap();
+        let data: Vec<_> = breadcrumbs
+            .iter()
+            .map(|breadcrumb| breadcrumb.value().unwrap().data.value().unwrap())
+            .collect();
+
+        assert_eq_dbg!(
+            data[0].get("method").unwrap().value(),
+            Some(&Value::String("POST".to_string()))
+        );
+        assert_eq_dbg!(
+            data[0].get("status_code").unwrap().value(),
+            Some(&Value::U64(201))
+        );
+
+        for key in &["method", "status_code"] {
+            let value = data[1].get(*key).unwrap();
+            assert_eq!(value.value(), None);
+            assert_eq!(value.meta().errors().count(), 1);
+        }
+
+        assert_eq_dbg!(
+            data[2].get("status_code").unwrap().value(),
+            Some(&Value::String("201".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_source_context() {
+        let long_line = "x".repeat(300);
+        let json = format!(
+            r#"{{
+  "template": {{
+    "pre_context": ["1", "2", "3", "4"],
+    "context_line": "{}",
+    "post_context": ["5", "6", "7"]
+  }}
+}}"#,
+            long_line
+        );
+        let mut event = Annotated::<Event>::from_json(&json).unwrap().0.unwrap();
+
+        let config = NormalizeConfig {
+            max_context_lines: 2,
+            ..Default::default()
+        };
+        normalize_event(&mut event, &config);
+
+        let template_info = event.template_info.value().unwrap().as_ref().unwrap();
+        assert_eq_dbg!(
+            template_info.pre_lines.value().unwrap(),
+            &vec![
+                Annotated::from("3".to_string()),
+                Annotated::from("4".to_string()),
+            ]
+        );
+        assert_eq!(template_info.pre_lines.meta().original_length(), Some(4));
+        assert_eq_dbg!(
+            template_info.post_lines.value().unwrap(),
+            &vec![
+                Annotated::from("5".to_string()),
+                Annotated::from("6".to_string()),
+            ]
+        );
+
+        let current_line = &template_info.current_line;
+        let line = current_line.value().unwrap().as_ref().unwrap();
+        assert_eq!(line.len(), 200);
+        assert!(line.ends_with("..."));
+        assert_eq!(current_line.meta().original_length(), Some(300));
+        assert_eq!(current_line.meta().remarks().count(), 1);
+    }
+}
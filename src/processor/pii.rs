@@ -1,7 +1,8 @@
 //! Implements a processing system for the protocol.
-use std::collections::BTreeMap;
 
-use protocol::{Annotated, Array, Map, Meta, Value, Values};
+use serde::de::DeserializeOwned;
+
+use protocol::{Annotated, Array, Lazy, Map, Meta, Value, Values};
 
 use super::chunk::{self, Chunk};
 
@@ -27,10 +28,42 @@ pub enum PiiKind {
     Email,
     /// An arbitrary structured data bag
     Databag,
+    /// Local variables captured in stack frames, which are a databag with stricter defaults.
+    ///
+    /// Rules applied to databags are applied to local variables as well, after the rules for
+    /// local variables.
+    Vars,
+    /// A secret, token or other credential (API keys, session cookies)
+    AuthToken,
+    /// Location data (coordinates, city, region or country)
+    Location,
+    /// A phone number
+    Phone,
+}
+
+impl PiiKind {
+    /// Returns the kind of PII whose rules also apply to this kind.
+    pub fn fallback(self) -> Option<PiiKind> {
+        match self {
+            PiiKind::Vars => Some(PiiKind::Databag),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if values of this kind are structured data bags.
+    pub fn is_databag(self) -> bool {
+        match self {
+            PiiKind::Databag | PiiKind::Vars => true,
+            _ => false,
+        }
+    }
 }
 
 /// The type of cap applied to the value.
-#[derive(Copy, Clone, Debug)]
+///
+/// The maximum length for each cap is configured in `CapConfig`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Cap {
     /// A summary text
     Summary,
@@ -59,6 +92,7 @@ impl ValueInfo {
         ValueInfo {
             pii_kind: match self.pii_kind {
                 Some(PiiKind::Databag) => Some(PiiKind::Databag),
+                Some(PiiKind::Vars) => Some(PiiKind::Vars),
                 _ => None,
             },
             cap: match self.cap {
@@ -67,6 +101,18 @@ impl ValueInfo {
             },
         }
     }
+
+    /// Derives a value info for the child of a map with the given key.
+    ///
+    /// If the processor infers a kind of PII from the key, it replaces the kind of PII derived
+    /// with `derive`.
+    pub fn derive_for_key<P: Processor + ?Sized>(&self, key: &str, processor: &P) -> ValueInfo {
+        let mut info = self.derive();
+        if let Some(pii_kind) = processor.infer_pii_kind(key) {
+            info.pii_kind = Some(pii_kind);
+        }
+        info
+    }
 }
 
 macro_rules! declare_primitive_process {
@@ -91,64 +137,162 @@ pub trait Processor {
     declare_primitive_process!(i32, process_i32);
     declare_primitive_process!(u64, process_u64);
     declare_primitive_process!(i64, process_i64);
+    declare_primitive_process!(String, process_big_number, "BigNumber");
     declare_primitive_process!(f32, process_f32);
     declare_primitive_process!(f64, process_f64);
     declare_primitive_process!(String, process_string);
 
+    /// Processes the map of unknown fields of an interface.
+    ///
+    /// This is invoked after the map has been processed like any other value.
+    fn process_other(&self, annotated: Annotated<Map<Value>>) -> Annotated<Map<Value>> {
+        annotated
+    }
+
+    /// Infers the kind of PII of a map entry from its key.
+    ///
+    /// This is consulted by `ValueInfo::derive_for_key` for all children of maps.
+    fn infer_pii_kind(&self, key: &str) -> Option<PiiKind> {
+        let _key = key;
+        None
+    }
+
+    /// Indicates whether `process_map_key` is invoked for the keys of a map with the value info.
+    fn should_process_map_keys(&self, info: &ValueInfo) -> bool {
+        let _info = info;
+        false
+    }
+
+    /// Processes a key of a map with the value info of the map.
+    ///
+    /// This is invoked after the value of the key has been processed, if
+    /// `should_process_map_keys` returns `true`.  Remarks of renamed keys are recorded in the
+    /// meta data of the map.
+    fn process_map_key(&self, key: Annotated<String>, info: &ValueInfo) -> Annotated<String> {
+        let _info = info;
+        key
+    }
+
     /// Processes an annotated `Value`.
+    ///
+    /// By default, this dispatches to the processing function of the value's type and processes
+    /// all children of arrays and maps, see `process_value_children`.
     fn process_value(&self, annotated: Annotated<Value>, info: &ValueInfo) -> Annotated<Value> {
-        match annotated {
-            Annotated(Some(Value::Bool(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_bool(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::Bool), meta)
-            }
-            Annotated(Some(Value::U32(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_u32(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::U32), meta)
-            }
-            Annotated(Some(Value::I32(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_i32(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::I32), meta)
-            }
-            Annotated(Some(Value::U64(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_u64(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::U64), meta)
-            }
-            Annotated(Some(Value::I64(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_i64(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::I64), meta)
-            }
-            Annotated(Some(Value::F32(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_f32(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::F32), meta)
-            }
-            Annotated(Some(Value::F64(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_f64(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::F64), meta)
-            }
-            Annotated(Some(Value::String(val)), meta) => {
-                let Annotated(val_opt, meta) = self.process_string(Annotated::new(val, meta), info);
-                Annotated(val_opt.map(Value::String), meta)
+        process_value_children(self, annotated, info)
+    }
+}
+
+/// Processes a `Value` with the processing function of its type.
+///
+/// This is the default implementation of `Processor::process_value`.  Items of arrays and maps are
+/// processed with `Processor::process_value`, so that processors overriding it can fall back to
+/// this function.
+pub(crate) fn process_value_children<P: Processor + ?Sized>(
+    processor: &P,
+    annotated: Annotated<Value>,
+    info: &ValueInfo,
+) -> Annotated<Value> {
+    match annotated {
+        Annotated(Some(Value::Bool(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_bool(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::Bool), meta)
+        }
+        Annotated(Some(Value::U32(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_u32(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::U32), meta)
+        }
+        Annotated(Some(Value::I32(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_i32(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::I32), meta)
+        }
+        Annotated(Some(Value::U64(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_u64(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::U64), meta)
+        }
+        Annotated(Some(Value::I64(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_i64(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::I64), meta)
+        }
+        Annotated(Some(Value::BigNumber(val)), meta) => {
+            let Annotated(val_opt, meta) =
+                processor.process_big_number(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::BigNumber), meta)
+        }
+        Annotated(Some(Value::F32(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_f32(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::F32), meta)
+        }
+        Annotated(Some(Value::F64(val)), meta) => {
+            let Annotated(val_opt, meta) = processor.process_f64(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::F64), meta)
+        }
+        Annotated(Some(Value::String(val)), meta) => {
+            let Annotated(val_opt, meta) =
+                processor.process_string(Annotated::new(val, meta), info);
+            Annotated(val_opt.map(Value::String), meta)
+        }
+        Annotated(Some(Value::Array(val)), meta) => {
+            let mut rv = Vec::with_capacity(val.len());
+            for item in val {
+                rv.push(processor.process_value(item, &info.derive()));
             }
-            Annotated(Some(Value::Array(val)), meta) => {
-                let mut rv = Vec::with_capacity(val.len());
-                for item in val {
-                    rv.push(self.process_value(item, &info.derive()));
-                }
-                Annotated(Some(Value::Array(rv)), meta)
+            Annotated(Some(Value::Array(rv)), meta)
+        }
+        Annotated(Some(Value::Map(val)), mut meta) => {
+            let mut rv = Map::new();
+            for (key, value) in val {
+                let value = processor.process_value(value, &info.derive_for_key(&key, processor));
+                rv.insert(key, value);
             }
-            Annotated(Some(Value::Map(val)), meta) => {
-                let mut rv = BTreeMap::new();
-                for (key, value) in val {
-                    let value = self.process_value(value, &info.derive());
-                    rv.insert(key, value);
-                }
-                Annotated(Some(Value::Map(rv)), meta)
+            let rv = process_map_keys(rv, &mut meta, processor, info);
+            Annotated(Some(Value::Map(rv)), meta)
+        }
+        other @ Annotated(Some(Value::Null), _) => other,
+        other @ Annotated(None, _) => other,
+    }
+}
+
+/// The key of a map entry whose key has been removed entirely by a processor.
+const REDACTED_KEY: &str = "[redacted]";
+
+/// Processes the keys of a map with `Processor::process_map_key`.
+///
+/// Remarks of renamed keys are moved to the meta data of the map, noting the new key.  If a
+/// renamed key collides with another key, a numeric suffix is appended so that both values are
+/// retained.
+fn process_map_keys<T, P: Processor + ?Sized>(
+    map: Map<T>,
+    meta: &mut Meta,
+    processor: &P,
+    info: &ValueInfo,
+) -> Map<T> {
+    if !processor.should_process_map_keys(info) {
+        return map;
+    }
+
+    let mut rv = Map::new();
+    for (key, value) in map {
+        let Annotated(processed, key_meta) = processor.process_map_key(Annotated::from(key), info);
+        let mut key = processed
+            .filter(|key| !key.is_empty())
+            .unwrap_or_else(|| REDACTED_KEY.to_string());
+
+        if rv.contains_key(&key) {
+            let mut suffix = 2;
+            while rv.contains_key(&format!("{}#{}", key, suffix)) {
+                suffix += 1;
             }
-            other @ Annotated(Some(Value::Null), _) => other,
-            other @ Annotated(None, _) => other,
+            key = format!("{}#{}", key, suffix);
         }
+
+        for remark in key_meta.remarks {
+            meta.remarks_mut()
+                .push(remark.with_note(format!("key {}", key)));
+        }
+
+        rv.insert(key, value);
     }
+    rv
 }
 
 /// A trait implemented for annotated types that support processing.
@@ -189,6 +333,18 @@ pub trait PiiProcessor {
         let _kind = kind;
         value
     }
+
+    /// Indicates whether keys of maps with the given kind of PII are processed like strings.
+    fn pii_process_keys(&self, pii_kind: PiiKind) -> bool {
+        let _pii_kind = pii_kind;
+        false
+    }
+
+    /// Infers the kind of PII of a map entry from its key, overriding the kind of the map.
+    fn pii_kind_for_key(&self, key: &str) -> Option<PiiKind> {
+        let _key = key;
+        None
+    }
 }
 
 macro_rules! impl_primitive_pii_process {
@@ -217,12 +373,13 @@ impl<T: PiiProcessor> Processor for T {
         match (annotated, info.pii_kind) {
             (annotated, None) | (annotated @ Annotated(None, _), _) => annotated,
             (Annotated(Some(value), meta), Some(pii_kind)) => {
-                let original_length = value.len();
+                let original_length = value.chars().count();
                 let chunks = chunk::chunks_from_str(&value, &meta);
                 match PiiProcessor::pii_process_chunks(self, chunks, meta, pii_kind) {
                     Ok((chunks, meta)) => {
                         let (value, mut meta) = chunk::chunks_to_string(chunks, meta);
-                        if value.len() != original_length && meta.original_length.is_none() {
+                        let length = value.chars().count();
+                        if length != original_length && meta.original_length.is_none() {
                             meta.original_length = Some(original_length as u32);
                         }
                         Annotated(Some(value), meta)
@@ -231,8 +388,8 @@ impl<T: PiiProcessor> Processor for T {
                         let annotated = Annotated(Some(Value::String(value)), meta);
                         match self.pii_process_value(annotated, pii_kind) {
                             Annotated(Some(Value::String(value)), mut meta) => {
-                                if value.len() != original_length && meta.original_length.is_none()
-                                {
+                                let length = value.chars().count();
+                                if length != original_length && meta.original_length.is_none() {
                                     meta.original_length = Some(original_length as u32);
                                 }
                                 Annotated(Some(value), meta)
@@ -245,11 +402,29 @@ impl<T: PiiProcessor> Processor for T {
         }
     }
 
+    fn infer_pii_kind(&self, key: &str) -> Option<PiiKind> {
+        self.pii_kind_for_key(key)
+    }
+
+    fn should_process_map_keys(&self, info: &ValueInfo) -> bool {
+        info.pii_kind
+            .map_or(false, |pii_kind| self.pii_process_keys(pii_kind))
+    }
+
+    fn process_map_key(&self, key: Annotated<String>, info: &ValueInfo) -> Annotated<String> {
+        if self.should_process_map_keys(info) {
+            self.process_string(key, info)
+        } else {
+            key
+        }
+    }
+
     impl_primitive_pii_process!(bool, Bool, process_bool);
     impl_primitive_pii_process!(u32, U32, process_u32);
     impl_primitive_pii_process!(i32, I32, process_i32);
     impl_primitive_pii_process!(u64, U64, process_u64);
     impl_primitive_pii_process!(i64, I64, process_i64);
+    impl_primitive_pii_process!(String, BigNumber, process_big_number);
     impl_primitive_pii_process!(f32, F32, process_f32);
     impl_primitive_pii_process!(f64, F64, process_f64);
 }
@@ -313,19 +488,36 @@ impl<T: ProcessAnnotatedValue> ProcessAnnotatedValue for Box<T> {
     }
 }
 
+impl<T: ProcessAnnotatedValue + DeserializeOwned> ProcessAnnotatedValue for Lazy<T> {
+    fn process_annotated_value(
+        annotated: Annotated<Self>,
+        processor: &Processor,
+        info: &ValueInfo,
+    ) -> Annotated<Self> {
+        ProcessAnnotatedValue::process_annotated_value(annotated.resolve(), processor, info)
+            .map(Lazy::new)
+    }
+}
+
 impl<T: ProcessAnnotatedValue> ProcessAnnotatedValue for Values<T> {
     fn process_annotated_value(
         annotated: Annotated<Self>,
         processor: &Processor,
         info: &ValueInfo,
     ) -> Annotated<Self> {
-        annotated.map(|Values { values, other }| Values {
+        annotated.map(|values| Values {
             values: ProcessAnnotatedValue::process_annotated_value(
-                values,
+                values.values,
                 processor,
                 &info.derive(),
             ),
-            other: ProcessAnnotatedValue::process_annotated_value(other, processor, &info.derive()),
+            truncated: values.truncated,
+            other: processor.process_other(ProcessAnnotatedValue::process_annotated_value(
+                values.other,
+                processor,
+                &info.derive(),
+            )),
+            shape: values.shape,
         })
     }
 }
@@ -353,21 +545,20 @@ impl<T: ProcessAnnotatedValue> ProcessAnnotatedValue for Map<T> {
         processor: &Processor,
         info: &ValueInfo,
     ) -> Annotated<Self> {
-        annotated.map(|value| {
-            value
+        let Annotated(value, mut meta) = annotated;
+        let value = value.map(|value| {
+            let value = value
                 .into_iter()
                 .map(|(key, value)| {
-                    (
-                        key,
-                        ProcessAnnotatedValue::process_annotated_value(
-                            value,
-                            processor,
-                            &info.derive(),
-                        ),
-                    )
+                    let info = info.derive_for_key(&key, processor);
+                    let value =
+                        ProcessAnnotatedValue::process_annotated_value(value, processor, &info);
+                    (key, value)
                 })
-                .collect()
-        })
+                .collect();
+            process_map_keys(value, &mut meta, processor, info)
+        });
+        Annotated(value, meta)
     }
 }
 
@@ -459,4 +650,45 @@ mod tests {
         assert!(id.value().is_none());
         assert_eq_str!(id.meta().remarks().next().unwrap().rule_id(), "@id-removed");
     }
+
+    #[test]
+    fn test_container_attributes() {
+        #[derive(ProcessAnnotatedValue)]
+        #[process_annotated_value(pii_kind = "id")]
+        struct Event {
+            id: Annotated<u32>,
+            #[process_annotated_value(pii_kind = "freeform")]
+            count: Annotated<u32>,
+        }
+
+        struct MyPiiProcessor;
+
+        impl PiiProcessor for MyPiiProcessor {
+            fn pii_process_value(
+                &self,
+                annotated: Annotated<Value>,
+                pii_kind: PiiKind,
+            ) -> Annotated<Value> {
+                match (annotated, pii_kind) {
+                    (annotated, PiiKind::Id) => annotated
+                        .with_removed_value(Remark::new(RemarkType::Removed, "@id-removed")),
+                    (annotated, _) => annotated,
+                }
+            }
+        }
+
+        let event = Annotated::from(Event {
+            id: Annotated::from(42),
+            count: Annotated::from(23),
+        });
+
+        let new_event = ProcessAnnotatedValue::process_annotated_value(
+            event,
+            &MyPiiProcessor,
+            &ValueInfo::default(),
+        ).0
+            .unwrap();
+        assert!(new_event.id.value().is_none());
+        assert_eq!(new_event.count.value(), Some(&23));
+    }
 }
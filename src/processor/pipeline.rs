@@ -0,0 +1,326 @@
+//! Processing of entire events from parsing to PII stripping.
+
+use std::fmt;
+use std::time::Duration;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use protocol::{Annotated, Event};
+
+use super::errors::collect_event_errors;
+use super::filters::{should_filter, FilterStatKey, FiltersConfig};
+use super::normalize::{normalize_event, NormalizeConfig};
+use super::quarantine::{QuarantineConfig, QuarantineProcessor};
+use super::rule::PiiConfig;
+use super::sanitize::{SanitizeConfig, SanitizingProcessor};
+use super::trim::{CapConfig, TrimmingProcessor};
+
+/// The result of processing an event.
+///
+/// Relays translate outcomes into HTTP responses with `http_status` and `retry_after`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessingOutcome {
+    /// The event was processed and should be stored.
+    Accepted,
+    /// The event was dropped by an inbound filter.
+    Filtered(FilterStatKey),
+    /// The event payload is invalid and cannot be processed.
+    Invalid(String),
+    /// The event was dropped because a quota was exceeded.
+    ///
+    /// Contains the time after which clients may retry, if known.
+    RateLimited(Option<Duration>),
+}
+
+impl ProcessingOutcome {
+    /// Returns `true` if the event should be stored.
+    pub fn is_accepted(&self) -> bool {
+        *self == ProcessingOutcome::Accepted
+    }
+
+    /// Returns the HTTP status code of the response to the client.
+    pub fn http_status(&self) -> u16 {
+        match *self {
+            ProcessingOutcome::Accepted => 200,
+            ProcessingOutcome::Filtered(_) => 403,
+            ProcessingOutcome::Invalid(_) => 400,
+            ProcessingOutcome::RateLimited(_) => 429,
+        }
+    }
+
+    /// Returns the value of the `Retry-After` header in seconds for rate limited events.
+    pub fn retry_after(&self) -> Option<u64> {
+        match *self {
+            ProcessingOutcome::RateLimited(Some(retry_after)) => {
+                // round up partial seconds so that clients do not retry too early
+                let secs = retry_after.as_secs();
+                Some(if retry_after.subsec_nanos() > 0 {
+                    secs + 1
+                } else {
+                    secs
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProcessingOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProcessingOutcome::Accepted => write!(f, "accepted"),
+            ProcessingOutcome::Filtered(ref reason) => write!(f, "filtered: {}", reason),
+            ProcessingOutcome::Invalid(ref reason) => write!(f, "invalid: {}", reason),
+            ProcessingOutcome::RateLimited(_) => write!(f, "rate limited"),
+        }
+    }
+}
+
+/// Configuration for processing events with `process_event`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PipelineConfig {
+    /// Inbound filters that drop events before processing.
+    #[serde(default)]
+    pub filters: FiltersConfig,
+    /// Normalization of the event.
+    #[serde(default)]
+    pub normalize: NormalizeConfig,
+    /// Sanitation of all strings.  Strings are not sanitized if missing.
+    #[serde(default)]
+    pub sanitize: Option<SanitizeConfig>,
+    /// Limits for unknown fields.  Unknown fields are not quarantined if missing.
+    #[serde(default)]
+    pub quarantine: Option<QuarantineConfig>,
+    /// Maximum lengths of values.  Values are not trimmed if missing.
+    #[serde(default)]
+    pub caps: Option<CapConfig>,
+    /// PII stripping rules.  PII is not stripped if missing.
+    #[serde(default)]
+    pub pii: Option<PiiConfig>,
+    /// Reports fields modified during processing in the `errors` of the event.
+    #[serde(default)]
+    pub collect_errors: bool,
+}
+
+/// Filters, normalizes, sanitizes, quarantines, trims and strips PII from an event.
+///
+/// Returns the processed event along with the outcome.  Events without a value, for instance
+/// because the payload is not an object, are `Invalid`.  Filtered events are returned
+/// unprocessed.  With `collect_errors`, fields modified during processing are reported in the
+/// `errors` of accepted events, see `collect_event_errors`.
+pub fn process_event(
+    mut event: Annotated<Event>,
+    config: &PipelineConfig,
+) -> (Annotated<Event>, ProcessingOutcome) {
+    if event.value().is_none() {
+        let reason = event
+            .meta()
+            .errors()
+            .next()
+            .unwrap_or("missing event payload")
+            .to_string();
+        return (event, ProcessingOutcome::Invalid(reason));
+    }
+
+    let filter = event
+        .value()
+        .and_then(|event| should_filter(event, &config.filters));
+    if let Some(key) = filter {
+        return (event, ProcessingOutcome::Filtered(key));
+    }
+
+    if let Some(event) = event.value_mut() {
+        normalize_event(event, &config.normalize);
+    }
+
+    if let Some(ref sanitize) = config.sanitize {
+        event = SanitizingProcessor::new(sanitize.clone()).process_root_value(event);
+    }
+
+    if let Some(ref quarantine) = config.quarantine {
+        event = QuarantineProcessor::new(quarantine.clone()).process_root_value(event);
+    }
+
+    if let Some(ref caps) = config.caps {
+        event = TrimmingProcessor::new(caps.clone()).process_root_value(event);
+    }
+
+    if let Some(ref pii) = config.pii {
+        event = pii.processor().process_event(event);
+    }
+
+    if config.collect_errors {
+        collect_event_errors(&mut event);
+    }
+
+    (event, ProcessingOutcome::Accepted)
+}
+
+/// Processes a batch of events with `process_event`.
+///
+/// Returns the processed events along with their outcomes in the order of the input.  With the
+/// `parallel` feature, events are processed concurrently on the global rayon thread pool.
+/// Otherwise, they are processed one after another on the current thread.
+///
+/// The config is shared between all threads, since it is `Send` and `Sync`.  The regular
+/// expressions of PII rules are compiled once when the config is loaded, but the rules applying
+/// to an event are selected for every event, since rule conditions depend on its attributes.
+/// Every event is moved to and processed on exactly one thread, so no event is accessed
+/// concurrently.
+pub fn process_batch(
+    events: Vec<Annotated<Event>>,
+    config: &PipelineConfig,
+) -> Vec<(Annotated<Event>, ProcessingOutcome)> {
+    #[cfg(feature = "parallel")]
+    {
+        events
+            .into_par_iter()
+            .map(|event| process_event(event, config))
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        events
+            .into_iter()
+            .map(|event| process_event(event, config))
+            .collect()
+    }
+}
+
+/// Parses and processes an event from JSON.
+///
+/// Payloads that cannot be parsed are `Invalid`.
+pub fn process_event_json(
+    json: &str,
+    config: &PipelineConfig,
+) -> (Annotated<Event>, ProcessingOutcome) {
+    match Annotated::<Event>::from_json(json) {
+        Ok(event) => process_event(event, config),
+        Err(error) => {
+            let reason = error.to_string();
+            (
+                Annotated::from_error(reason.clone()),
+                ProcessingOutcome::Invalid(reason),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Value;
+
+    #[test]
+    fn test_accepted() {
+        let pii = PiiConfig::from_json(r#"{"applications": {"freeform": ["@email"]}}"#).unwrap();
+        let config = PipelineConfig {
+            pii: Some(pii),
+            ..Default::default()
+        };
+
+        let json = r#"{"message": "mail foo@example.com"}"#;
+        let (event, outcome) = process_event_json(json, &config);
+        assert_eq_dbg!(outcome, ProcessingOutcome::Accepted);
+        assert_eq!(outcome.http_status(), 200);
+
+        let message = event.value().unwrap().message.value().unwrap();
+        assert_eq_str!(message.as_ref().unwrap(), "mail [email]");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let config = PipelineConfig::default();
+
+        let (_, outcome) = process_event_json("{", &config);
+        assert_eq!(outcome.http_status(), 400);
+
+        let (_, outcome) = process_event_json("42", &config);
+        assert_eq!(outcome.http_status(), 400);
+    }
+
+    #[test]
+    fn test_filtered() {
+        let mut config = PipelineConfig::default();
+        config.filters.localhost.is_enabled = true;
+
+        let json = r#"{"user": {"ip_address": "127.0.0.1"}}"#;
+        let (_, outcome) = process_event_json(json, &config);
+        assert_eq_dbg!(outcome, ProcessingOutcome::Filtered(FilterStatKey::Localhost));
+        assert_eq!(outcome.http_status(), 403);
+    }
+
+    #[test]
+    fn test_process_batch() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<Annotated<Event>>();
+        assert_sync::<PipelineConfig>();
+
+        let pii = PiiConfig::from_json(r#"{"applications": {"freeform": ["@email"]}}"#).unwrap();
+        let mut config = PipelineConfig {
+            pii: Some(pii),
+            ..Default::default()
+        };
+        config.filters.localhost.is_enabled = true;
+
+        let events = vec![
+            Annotated::<Event>::from_json(r#"{"message": "mail foo@example.com"}"#).unwrap(),
+            Annotated::<Event>::from_json(r#"{"user": {"ip_address": "127.0.0.1"}}"#).unwrap(),
+            Annotated::from_error("invalid"),
+        ];
+
+        let results = process_batch(events, &config);
+        let outcomes: Vec<_> = results.iter().map(|&(_, ref outcome)| outcome).collect();
+        assert_eq_dbg!(
+            outcomes,
+            vec![
+                &ProcessingOutcome::Accepted,
+                &ProcessingOutcome::Filtered(FilterStatKey::Localhost),
+                &ProcessingOutcome::Invalid("invalid".to_string()),
+            ]
+        );
+
+        let message = results[0].0.value().unwrap().message.value().unwrap();
+        assert_eq_str!(message.as_ref().unwrap(), "mail [email]");
+    }
+
+    #[test]
+    fn test_caps_with_pii() {
+        let pii = PiiConfig::from_json(r#"{"applications": {"databag": ["@password"]}}"#).unwrap();
+        let config = PipelineConfig {
+            caps: Some(CapConfig {
+                databag: 10,
+                ..Default::default()
+            }),
+            pii: Some(pii),
+            ..Default::default()
+        };
+
+        let json = r#"{"extra": {"db": {"password": "hunter2", "host": "db.example.com"}}}"#;
+        let (event, outcome) = process_event_json(json, &config);
+        assert_eq_dbg!(outcome, ProcessingOutcome::Accepted);
+
+        let extra = event.value().unwrap().extra.value().unwrap();
+        let db = match extra["db"].value() {
+            Some(&Value::Map(ref db)) => db,
+            other => panic!("expected a map, found {:?}", other),
+        };
+        assert_eq_dbg!(db["password"].value(), None);
+        assert_eq_dbg!(
+            db["host"].value(),
+            Some(&Value::String("db.exam...".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let outcome = ProcessingOutcome::RateLimited(Some(Duration::from_millis(1500)));
+        assert_eq!(outcome.http_status(), 429);
+        assert_eq!(outcome.retry_after(), Some(2));
+        assert_eq!(ProcessingOutcome::RateLimited(None).retry_after(), None);
+        assert_eq!(ProcessingOutcome::Accepted.retry_after(), None);
+    }
+}
@@ -0,0 +1,69 @@
+//! Correlated placeholders for replaced values.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// Assigns numbered placeholders to values replaced by `replace` redactions.
+///
+/// Without a registry, every replaced value receives the same replacement text, for instance
+/// `[email]`.  With a registry, identical original values receive the same numbered placeholder
+/// (`[email#1]`) and different values receive different ones (`[email#2]`).  A registry should
+/// be used for a single event only, so that placeholders cannot be correlated across events.
+#[derive(Debug, Default)]
+pub struct PseudonymRegistry {
+    placeholders: RefCell<BTreeMap<String, BTreeMap<String, usize>>>,
+}
+
+impl PseudonymRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> PseudonymRegistry {
+        PseudonymRegistry::default()
+    }
+
+    /// Returns the numbered placeholder for an original value.
+    ///
+    /// Numbers are assigned per replacement text in the order values are first seen.  If the
+    /// replacement text ends with a closing bracket, the number is inserted before it.
+    pub fn placeholder(&self, replacement: &str, original: &str) -> String {
+        let mut placeholders = self.placeholders.borrow_mut();
+        let originals = placeholders
+            .entry(replacement.to_string())
+            .or_insert_with(BTreeMap::new);
+
+        let next = originals.len() + 1;
+        let number = *originals.entry(original.to_string()).or_insert(next);
+
+        if replacement.ends_with(']') {
+            let prefix = &replacement[..replacement.len() - 1];
+            format!("{}#{}]", prefix, number)
+        } else {
+            format!("{}#{}", replacement, number)
+        }
+    }
+
+    /// Returns the number of distinct values that received a placeholder.
+    pub fn len(&self) -> usize {
+        self.placeholders.borrow().values().map(BTreeMap::len).sum()
+    }
+
+    /// Returns `true` if no placeholders have been assigned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder() {
+        let registry = PseudonymRegistry::new();
+        assert_eq_str!(registry.placeholder("[email]", "a@example.com"), "[email#1]");
+        assert_eq_str!(registry.placeholder("[email]", "b@example.com"), "[email#2]");
+        assert_eq_str!(registry.placeholder("[email]", "a@example.com"), "[email#1]");
+        assert_eq_str!(registry.placeholder("[ip]", "127.0.0.1"), "[ip#1]");
+        assert_eq_str!(registry.placeholder("redacted", "secret"), "redacted#1");
+        assert_eq!(registry.len(), 4);
+    }
+}
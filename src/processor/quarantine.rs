@@ -0,0 +1,207 @@
+//! Quarantine of unknown fields that bloat events.
+
+use base64;
+use regex::Regex;
+
+use protocol::{Annotated, Map, Remark, RemarkType, Value};
+
+use super::pii::{ProcessAnnotatedValue, Processor, ValueInfo};
+
+lazy_static! {
+    static ref BASE64_REGEX: Regex = Regex::new(r"^[A-Za-z0-9+/_-]+={0,2}$").unwrap();
+}
+
+/// The rule ID reported in remarks of dropped fields.
+const QUARANTINE_RULE_ID: &str = "!quarantine";
+
+/// Limits for unknown fields in the `other` map of interfaces.
+///
+/// Fields missing from a serialized config fall back to their defaults.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct QuarantineConfig {
+    /// Maximum estimated size in bytes of the JSON serialization of each `other` map.
+    pub max_other_size: usize,
+    /// Maximum length in bytes of strings that look like binary or base64 encoded data.
+    pub max_blob_size: usize,
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> QuarantineConfig {
+        QuarantineConfig {
+            max_other_size: 8192,
+            max_blob_size: 256,
+        }
+    }
+}
+
+/// Returns `true` if the string looks like binary or base64 encoded data.
+fn is_blob(string: &str) -> bool {
+    string
+        .chars()
+        .any(|c| c == '\u{fffd}' || (c.is_control() && !c.is_whitespace()))
+        || is_base64(string)
+}
+
+/// Returns `true` if the string looks like base64 encoded data.
+///
+/// Since slugs, hashes and identifiers consist of base64 characters as well, the string must
+/// have a multiple of four characters and decode successfully.  Additionally, it must either
+/// end with padding or contain digits, as well as upper and lower case letters.
+fn is_base64(string: &str) -> bool {
+    if string.len() % 4 != 0 || !BASE64_REGEX.is_match(string) {
+        return false;
+    }
+
+    let config = if string.contains(|c| c == '-' || c == '_') {
+        base64::URL_SAFE
+    } else {
+        base64::STANDARD
+    };
+
+    if base64::decode_config(string, config).is_err() {
+        return false;
+    }
+
+    string.ends_with('=')
+        || (string.bytes().any(|b| b.is_ascii_digit())
+            && string.bytes().any(|b| b.is_ascii_uppercase())
+            && string.bytes().any(|b| b.is_ascii_lowercase()))
+}
+
+/// A processor that drops unknown fields exceeding the limits of a `QuarantineConfig`.
+///
+/// Unknown fields are dropped if they contain large blobs of binary or base64 encoded data, or
+/// if they exceed the maximum size of the `other` map.  Fields are retained in the order of
+/// their keys as long as they fit.  Every dropped field is recorded as a remark in the meta data
+/// of the `other` map.
+#[derive(Debug, Default)]
+pub struct QuarantineProcessor {
+    config: QuarantineConfig,
+}
+
+impl QuarantineProcessor {
+    /// Creates a quarantine processor with the given limits.
+    pub fn new(config: QuarantineConfig) -> QuarantineProcessor {
+        QuarantineProcessor { config }
+    }
+
+    /// Returns the limits applied by this processor.
+    pub fn config(&self) -> &QuarantineConfig {
+        &self.config
+    }
+
+    /// Quarantines unknown fields of all interfaces below the given root value.
+    pub fn process_root_value<T: ProcessAnnotatedValue>(
+        &self,
+        value: Annotated<T>,
+    ) -> Annotated<T> {
+        ProcessAnnotatedValue::process_annotated_value(value, self, &ValueInfo::default())
+    }
+}
+
+impl Processor for QuarantineProcessor {
+    fn process_other(&self, annotated: Annotated<Map<Value>>) -> Annotated<Map<Value>> {
+        let (map, mut meta) = match annotated {
+            Annotated(Some(map), meta) => (map, meta),
+            annotated @ Annotated(None, _) => return annotated,
+        };
+
+        let mut size = 0;
+        let mut retained = Map::new();
+        for (key, value) in map {
+            if let Some(&Value::String(ref string)) = value.value() {
+                if string.len() > self.config.max_blob_size && is_blob(string) {
+                    let note = format!(
+                        "dropped field {}: binary data of {} bytes",
+                        key,
+                        string.len()
+                    );
+                    meta.remarks_mut()
+                        .push(Remark::new(RemarkType::Removed, QUARANTINE_RULE_ID).with_note(note));
+                    continue;
+                }
+            }
+
+            let value_size = key.len() + value.value().map_or(0, Value::deep_size);
+            if size + value_size > self.config.max_other_size {
+                let note = format!(
+                    "dropped field {}: exceeds the limit of {} bytes",
+                    key, self.config.max_other_size
+                );
+                meta.remarks_mut()
+                    .push(Remark::new(RemarkType::Removed, QUARANTINE_RULE_ID).with_note(note));
+                continue;
+            }
+
+            size += value_size;
+            retained.insert(key, value);
+        }
+
+        Annotated(Some(retained), meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Event;
+
+    #[test]
+    fn test_is_blob() {
+        assert!(is_blob("SGVsbG8gV29ybGQh"));
+        assert!(is_blob("SGVsbG8="));
+        assert!(is_blob("_-8_aP__"));
+        assert!(is_blob("\u{0}\u{1}binary"));
+        assert!(!is_blob("Hello World!"));
+        assert!(!is_blob("line\nbreak"));
+    }
+
+    #[test]
+    fn test_is_blob_identifiers() {
+        assert!(!is_blob(&"0123456789abcdef".repeat(20)));
+        assert!(!is_blob(&"some_snake_case_name".repeat(20)));
+        assert!(!is_blob(&"a-long-slug".repeat(32)));
+        assert!(!is_blob("SGVsbG8gV29ybGQ"));
+        assert!(!is_blob("SGVsbG8gV2+ybGQ-"));
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let blob = "SGVsbG8gV29ybGQh".repeat(20);
+        let json = format!(
+            r#"{{"blob": "{}", "large": "{}", "small": "ok", "user": {{"blob": "{}"}}}}"#,
+            blob,
+            "a large text ".repeat(10),
+            blob
+        );
+        let event = Annotated::<Event>::from_json(&json).unwrap();
+
+        let processor = QuarantineProcessor::new(QuarantineConfig {
+            max_other_size: 100,
+            ..Default::default()
+        });
+        let event = processor.process_root_value(event);
+        let event = event.value().unwrap();
+
+        let other = &event.other;
+        let keys: Vec<_> = other.value().unwrap().keys().collect();
+        assert_eq!(keys, vec!["small"]);
+        assert!(!other.meta().has_errors());
+        assert_eq_dbg!(
+            other
+                .meta()
+                .remarks()
+                .map(|remark| remark.note().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "dropped field blob: binary data of 320 bytes",
+                "dropped field large: exceeds the limit of 100 bytes",
+            ]
+        );
+
+        let user = event.user.value().unwrap().as_ref().unwrap();
+        assert!(user.other.value().unwrap().is_empty());
+        assert_eq!(user.other.meta().remarks().count(), 1);
+    }
+}
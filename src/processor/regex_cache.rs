@@ -0,0 +1,110 @@
+//! A global cache for compiled regular expressions of pattern rules.
+
+use std::sync::{Mutex, RwLock};
+
+use lru_cache::LruCache;
+use regex::{Error, Regex, RegexBuilder};
+
+/// The maximum number of compiled regular expressions kept in the cache.
+const CACHE_CAPACITY: usize = 512;
+
+/// The size limit of compiled regular expressions in pattern rules.
+const SIZE_LIMIT: usize = 262_144;
+
+/// The outcome of a lookup in the regex cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegexCacheEvent {
+    /// The regex was served from the cache.
+    Hit,
+    /// The regex was not cached and had to be compiled.
+    Miss,
+}
+
+/// A hook receiving the outcome of every lookup in the regex cache.
+type RegexCacheHook = Box<Fn(RegexCacheEvent) + Send + Sync>;
+
+lazy_static! {
+    static ref REGEX_CACHE: Mutex<LruCache<String, Regex>> =
+        Mutex::new(LruCache::new(CACHE_CAPACITY));
+    static ref REGEX_CACHE_HOOK: RwLock<Option<RegexCacheHook>> = RwLock::new(None);
+}
+
+/// Installs a hook that is invoked for every lookup in the global regex cache.
+///
+/// The regex cache is shared by all PII configs and avoids recompiling pattern rules when
+/// configs are reloaded.  Use the hook to report hit and miss counts to a metrics system.  The
+/// hook replaces any previously installed hook and must not compile pattern rules itself.
+pub fn set_regex_cache_hook<F>(hook: F)
+where
+    F: Fn(RegexCacheEvent) + Send + Sync + 'static,
+{
+    *REGEX_CACHE_HOOK.write().unwrap() = Some(Box::new(hook));
+}
+
+/// Reports the outcome of a lookup to the installed hook.
+fn report(event: RegexCacheEvent) {
+    if let Some(ref hook) = *REGEX_CACHE_HOOK.read().unwrap() {
+        hook(event);
+    }
+}
+
+/// Compiles a regex for a pattern rule or returns a cached copy.
+///
+/// All pattern rules are compiled with the same flags, so the cache is keyed by the pattern.
+/// Compile errors are not cached.
+pub(crate) fn compile_regex(pattern: &str) -> Result<Regex, Error> {
+    let cached = REGEX_CACHE.lock().unwrap().get_mut(pattern).cloned();
+    if let Some(regex) = cached {
+        report(RegexCacheEvent::Hit);
+        return Ok(regex);
+    }
+
+    report(RegexCacheEvent::Miss);
+    let regex = RegexBuilder::new(pattern).size_limit(SIZE_LIMIT).build()?;
+    REGEX_CACHE
+        .lock()
+        .unwrap()
+        .insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_compile_cached() {
+        // other tests compile patterns concurrently, so only record lookups of this thread
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let hook_events = events.clone();
+        let test_thread = thread::current().id();
+        set_regex_cache_hook(move |event| {
+            if thread::current().id() == test_thread {
+                hook_events.lock().unwrap().push(event);
+            }
+        });
+
+        let first = compile_regex("test_compile_cached\\d+").unwrap();
+        let second = compile_regex("test_compile_cached\\d+").unwrap();
+
+        assert_eq!(first.as_str(), second.as_str());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![RegexCacheEvent::Miss, RegexCacheEvent::Hit]
+        );
+        assert!(REGEX_CACHE
+            .lock()
+            .unwrap()
+            .contains_key("test_compile_cached\\d+"));
+    }
+
+    #[test]
+    fn test_compile_error() {
+        assert!(compile_regex("(unclosed").is_err());
+        assert!(compile_regex("(unclosed").is_err());
+        assert!(!REGEX_CACHE.lock().unwrap().contains_key("(unclosed"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Sanitation of text that breaks the display of events.
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+use protocol::{Annotated, Remark, RemarkType};
+
+use super::pii::{ProcessAnnotatedValue, Processor, ValueInfo};
+
+/// The rule ID reported in remarks of sanitized strings.
+pub(crate) const SANITIZE_RULE_ID: &str = "!sanitize";
+
+lazy_static! {
+    static ref ANSI_ESCAPE_REGEX: Regex =
+        Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-Z\\-_])").unwrap();
+}
+
+/// Modifications applied to all strings by the `SanitizingProcessor`.
+///
+/// Unpaired surrogates are not handled here, since they are rejected when parsing JSON.  Fields
+/// missing from a serialized config fall back to their defaults.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct SanitizeConfig {
+    /// Removes ANSI escape sequences, such as terminal colors.
+    pub strip_ansi: bool,
+    /// Removes control characters other than tabs and line breaks, as well as invisible
+    /// characters such as zero-width spaces and bidirectional overrides.
+    pub strip_control: bool,
+    /// Normalizes strings to Unicode normalization form C.
+    pub normalize_nfc: bool,
+}
+
+impl Default for SanitizeConfig {
+    fn default() -> SanitizeConfig {
+        SanitizeConfig {
+            strip_ansi: true,
+            strip_control: true,
+            normalize_nfc: true,
+        }
+    }
+}
+
+/// Returns `true` for characters that are stripped with `strip_control`.
+///
+/// Zero-width joiners and non-joiners are retained, since they are required by emoji sequences
+/// and some scripts.
+fn is_stripped_control(c: char) -> bool {
+    match c {
+        '\t' | '\n' | '\r' => false,
+        '\u{200b}' | '\u{200e}' | '\u{200f}' | '\u{2060}' | '\u{feff}' => true,
+        '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => true,
+        c => c.is_control(),
+    }
+}
+
+/// Applies the modifications of the config to a string.
+///
+/// Returns `None` if the string does not need to be modified.
+pub fn sanitize_text(text: &str, config: &SanitizeConfig) -> Option<String> {
+    let mut rv = None;
+
+    if config.strip_ansi && text.contains('\x1b') {
+        rv = Some(ANSI_ESCAPE_REGEX.replace_all(text, "").into_owned());
+    }
+
+    if config.strip_control {
+        let stripped = {
+            let current = rv.as_ref().map_or(text, String::as_str);
+            if current.chars().any(is_stripped_control) {
+                Some(
+                    current
+                        .chars()
+                        .filter(|&c| !is_stripped_control(c))
+                        .collect(),
+                )
+            } else {
+                None
+            }
+        };
+        if stripped.is_some() {
+            rv = stripped;
+        }
+    }
+
+    if config.normalize_nfc {
+        let normalized = {
+            let current = rv.as_ref().map_or(text, String::as_str);
+            if current.is_ascii() {
+                None
+            } else {
+                let normalized: String = current.nfc().collect();
+                if normalized != current {
+                    Some(normalized)
+                } else {
+                    None
+                }
+            }
+        };
+        if normalized.is_some() {
+            rv = normalized;
+        }
+    }
+
+    rv.filter(|sanitized| sanitized != text)
+}
+
+/// A processor that removes control characters and escape sequences from all strings.
+///
+/// Sanitized strings are annotated with a remark and their original length is recorded in the
+/// meta data.  Remarks referring to ranges after the first modified character are removed.
+#[derive(Debug, Default)]
+pub struct SanitizingProcessor {
+    config: SanitizeConfig,
+}
+
+impl SanitizingProcessor {
+    /// Creates a sanitizing processor with the given config.
+    pub fn new(config: SanitizeConfig) -> SanitizingProcessor {
+        SanitizingProcessor { config }
+    }
+
+    /// Returns the config applied by this processor.
+    pub fn config(&self) -> &SanitizeConfig {
+        &self.config
+    }
+
+    /// Sanitizes all strings below the given root value.
+    pub fn process_root_value<T: ProcessAnnotatedValue>(
+        &self,
+        value: Annotated<T>,
+    ) -> Annotated<T> {
+        ProcessAnnotatedValue::process_annotated_value(value, self, &ValueInfo::default())
+    }
+}
+
+impl Processor for SanitizingProcessor {
+    fn process_string(&self, annotated: Annotated<String>, info: &ValueInfo) -> Annotated<String> {
+        let _info = info;
+        let (value, mut meta) = match annotated {
+            Annotated(Some(value), meta) => (value, meta),
+            annotated @ Annotated(None, _) => return annotated,
+        };
+
+        let sanitized = match sanitize_text(&value, &self.config) {
+            Some(sanitized) => sanitized,
+            None => return Annotated(Some(value), meta),
+        };
+
+        let unchanged = value
+            .chars()
+            .zip(sanitized.chars())
+            .take_while(|&(a, b)| a == b)
+            .count();
+        meta.remarks_mut()
+            .retain(|remark| remark.range().map_or(true, |range| range.1 <= unchanged));
+        meta.remarks_mut()
+            .push(Remark::new(RemarkType::Substituted, SANITIZE_RULE_ID));
+        if meta.original_length.is_none() {
+            meta.original_length = Some(value.chars().count() as u32);
+        }
+
+        Annotated(Some(sanitized), meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::{Event, Value};
+
+    #[test]
+    fn test_sanitize_text() {
+        let config = SanitizeConfig::default();
+        assert_eq!(sanitize_text("plain text", &config), None);
+        assert_eq!(sanitize_text("tab\tand\nlines", &config), None);
+        assert_eq_dbg!(
+            sanitize_text("\x1b[1;31merror\x1b[0m: failed", &config),
+            Some("error: failed".to_string())
+        );
+        assert_eq_dbg!(
+            sanitize_text("zero\u{200b}width\u{0}null", &config),
+            Some("zerowidthnull".to_string())
+        );
+        assert_eq_dbg!(
+            sanitize_text("cafe\u{301}", &config),
+            Some("caf\u{e9}".to_string())
+        );
+        assert_eq!(
+            sanitize_text("family \u{1f468}\u{200d}\u{1f466}", &config),
+            None
+        );
+
+        let config = SanitizeConfig {
+            strip_ansi: false,
+            ..Default::default()
+        };
+        assert_eq_dbg!(
+            sanitize_text("\x1b[0mreset", &config),
+            Some("[0mreset".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sanitize_event() {
+        let event = Annotated::<Event>::from_json(
+            r#"{
+                "message": "\u001b[32mok\u001b[0m",
+                "logger": "clean",
+                "extra": {"key": "a\u202eb"}
+            }"#,
+        ).unwrap();
+
+        let event = SanitizingProcessor::default().process_root_value(event);
+        let event = event.value().unwrap();
+
+        let message = &event.message;
+        assert_eq_str!(message.value().unwrap().as_ref().unwrap(), "ok");
+        assert_eq!(message.meta().original_length, Some(11));
+        assert_eq_dbg!(
+            message.meta().remarks().collect::<Vec<_>>(),
+            vec![&Remark::new(RemarkType::Substituted, SANITIZE_RULE_ID)]
+        );
+
+        assert!(event.logger.meta().is_empty());
+
+        let extra = event.extra.value().unwrap();
+        assert_eq_dbg!(extra["key"].value(), Some(&Value::String("ab".to_string())));
+    }
+}
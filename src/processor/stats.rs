@@ -0,0 +1,216 @@
+//! Metrics collection for PII processing.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use protocol::{Annotated, Meta, Remark, Value};
+
+use super::chunk::Chunk;
+use super::pii::{PiiKind, PiiProcessor};
+
+/// Counters collected while processing values.
+///
+/// Stats are created by `RuleBasedPiiProcessor::process_root_value_with_stats`.  Stats of
+/// multiple runs can be combined with `merge`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessingStats {
+    /// Number of values modified by each rule.
+    pub rule_matches: BTreeMap<String, u64>,
+    /// Number of values modified for each kind of PII.
+    pub pii_kind_matches: BTreeMap<PiiKind, u64>,
+    /// Wall time spent processing.
+    pub duration: Duration,
+}
+
+impl ProcessingStats {
+    /// Creates empty stats.
+    pub fn new() -> ProcessingStats {
+        ProcessingStats::default()
+    }
+
+    /// Returns the number of values modified by the given rule.
+    pub fn rule_count(&self, rule_id: &str) -> u64 {
+        self.rule_matches.get(rule_id).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of values modified for the given kind of PII.
+    pub fn pii_kind_count(&self, pii_kind: PiiKind) -> u64 {
+        self.pii_kind_matches.get(&pii_kind).cloned().unwrap_or(0)
+    }
+
+    /// Adds the counters and duration of other stats to these stats.
+    pub fn merge(&mut self, other: ProcessingStats) {
+        for (rule_id, count) in other.rule_matches {
+            *self.rule_matches.entry(rule_id).or_insert(0) += count;
+        }
+
+        for (pii_kind, count) in other.pii_kind_matches {
+            *self.pii_kind_matches.entry(pii_kind).or_insert(0) += count;
+        }
+
+        self.duration += other.duration;
+    }
+
+    fn record(&mut self, rule_ids: Vec<String>, pii_kind: PiiKind) {
+        if rule_ids.is_empty() {
+            return;
+        }
+
+        for rule_id in rule_ids {
+            *self.rule_matches.entry(rule_id).or_insert(0) += 1;
+        }
+
+        *self.pii_kind_matches.entry(pii_kind).or_insert(0) += 1;
+    }
+}
+
+/// Returns the IDs of rules that occur more often in the processed list than in the original.
+///
+/// Rules are compared by count rather than by the redacted text, since a rule may redact a value
+/// with the same replacement that already occurs in the original.
+fn added_rules<'a, I, J>(original: I, processed: J) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+    J: IntoIterator<Item = &'a str>,
+{
+    let mut counts = BTreeMap::new();
+    for rule_id in original {
+        *counts.entry(rule_id).or_insert(0) -= 1;
+    }
+
+    let mut rule_ids: Vec<String> = vec![];
+    for rule_id in processed {
+        let count = counts.entry(rule_id).or_insert(0);
+        *count += 1;
+        if *count > 0 && !rule_ids.iter().any(|id| id == rule_id) {
+            rule_ids.push(rule_id.to_string());
+        }
+    }
+    rule_ids
+}
+
+/// Returns the rule IDs of all redaction chunks.
+fn redaction_rules<'a>(chunks: &'a [Chunk]) -> impl Iterator<Item = &'a str> {
+    chunks.iter().filter_map(|chunk| match *chunk {
+        Chunk::Redaction { ref rule_id, .. } => Some(rule_id.as_str()),
+        Chunk::Text { .. } => None,
+    })
+}
+
+/// Returns the IDs of rules that added redactions to the original chunks.
+pub(crate) fn applied_chunk_rules(original: &[Chunk], chunks: &[Chunk]) -> Vec<String> {
+    added_rules(redaction_rules(original), redaction_rules(chunks))
+}
+
+/// Returns the IDs of rules that added remarks to the original remarks.
+pub(crate) fn applied_remark_rules(original: &[Remark], meta: &Meta) -> Vec<String> {
+    added_rules(
+        original.iter().map(Remark::rule_id),
+        meta.remarks().map(Remark::rule_id),
+    )
+}
+
+/// A PII processor that counts the matches of another processor.
+pub(crate) struct StatsProcessor<'a, P: PiiProcessor + 'a> {
+    processor: &'a P,
+    stats: RefCell<ProcessingStats>,
+}
+
+impl<'a, P: PiiProcessor + 'a> StatsProcessor<'a, P> {
+    /// Wraps a processor.
+    pub(crate) fn new(processor: &'a P) -> StatsProcessor<'a, P> {
+        StatsProcessor {
+            processor,
+            stats: RefCell::new(ProcessingStats::new()),
+        }
+    }
+
+    /// Returns the collected stats.
+    pub(crate) fn into_stats(self) -> ProcessingStats {
+        self.stats.into_inner()
+    }
+}
+
+impl<'a, P: PiiProcessor + 'a> PiiProcessor for StatsProcessor<'a, P> {
+    fn pii_process_chunks(
+        &self,
+        chunks: Vec<Chunk>,
+        meta: Meta,
+        pii_kind: PiiKind,
+    ) -> Result<(Vec<Chunk>, Meta), (Vec<Chunk>, Meta)> {
+        let original = chunks.clone();
+        let rv = self.processor.pii_process_chunks(chunks, meta, pii_kind);
+
+        if let Ok((ref chunks, _)) = rv {
+            let rule_ids = applied_chunk_rules(&original, chunks);
+            self.stats.borrow_mut().record(rule_ids, pii_kind);
+        }
+
+        rv
+    }
+
+    fn pii_process_value(&self, value: Annotated<Value>, kind: PiiKind) -> Annotated<Value> {
+        let original = value.meta().remarks.clone();
+        let rv = self.processor.pii_process_value(value, kind);
+
+        let rule_ids = applied_remark_rules(&original, rv.meta());
+        self.stats.borrow_mut().record(rule_ids, kind);
+
+        rv
+    }
+
+    fn pii_process_keys(&self, pii_kind: PiiKind) -> bool {
+        self.processor.pii_process_keys(pii_kind)
+    }
+
+    fn pii_kind_for_key(&self, key: &str) -> Option<PiiKind> {
+        self.processor.pii_kind_for_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::RemarkType;
+
+    #[test]
+    fn test_merge() {
+        let mut stats = ProcessingStats::new();
+        stats.record(vec!["@email".to_string()], PiiKind::Freeform);
+
+        let mut other = ProcessingStats::new();
+        other.record(
+            vec!["@email".to_string(), "@ip".to_string()],
+            PiiKind::Databag,
+        );
+        other.duration = Duration::from_millis(5);
+
+        stats.merge(other);
+        assert_eq!(stats.rule_count("@email"), 2);
+        assert_eq!(stats.rule_count("@ip"), 1);
+        assert_eq!(stats.rule_count("@creditcard"), 0);
+        assert_eq!(stats.pii_kind_count(PiiKind::Freeform), 1);
+        assert_eq!(stats.pii_kind_count(PiiKind::Databag), 1);
+        assert_eq!(stats.duration, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_applied_chunk_rules() {
+        let redaction = Chunk::Redaction {
+            text: "[email]".to_string(),
+            rule_id: "@email".to_string(),
+            ty: RemarkType::Substituted,
+            note: None,
+        };
+        let text = Chunk::Text {
+            text: " and ".to_string(),
+        };
+
+        let original = vec![redaction.clone(), text.clone()];
+        assert!(applied_chunk_rules(&original, &original).is_empty());
+
+        let chunks = vec![redaction.clone(), text, redaction];
+        assert_eq!(applied_chunk_rules(&original, &chunks), vec!["@email"]);
+    }
+}
@@ -0,0 +1,157 @@
+//! Tracing of rule evaluations for debugging PII configs.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use super::pii::PiiKind;
+
+/// The reason for the outcome of a rule evaluation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceReason {
+    /// The rule redacted matching parts of a string.
+    Redacted,
+    /// The rule replaced or removed the entire value.
+    Replaced,
+    /// The rule did not match anything in the value.
+    NoMatch,
+    /// The rule does not apply to text and the value was processed as a whole instead.
+    NotApplicable,
+    /// An earlier rule already replaced the entire value.
+    AlreadyProcessed,
+    /// The condition of the rule does not match the event.
+    ConditionNotMet,
+    /// The rule is listed in `applications` but not defined.
+    UnknownRule,
+}
+
+impl fmt::Display for TraceReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            TraceReason::Redacted => "redacted matching text",
+            TraceReason::Replaced => "replaced the value",
+            TraceReason::NoMatch => "no match",
+            TraceReason::NotApplicable => "does not apply to text",
+            TraceReason::AlreadyProcessed => "value already processed by an earlier rule",
+            TraceReason::ConditionNotMet => "condition does not match the event",
+            TraceReason::UnknownRule => "rule is not defined",
+        })
+    }
+}
+
+/// A single evaluation of a rule.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TraceEntry {
+    /// The path of the value, if known.  Rules skipped for the entire event have no path.
+    pub path: Option<String>,
+    /// The kind of PII the value was processed as.
+    pub pii_kind: PiiKind,
+    /// The ID of the evaluated rule.
+    pub rule_id: String,
+    /// Whether the rule modified the value.
+    pub matched: bool,
+    /// Why the rule did or did not modify the value.
+    pub reason: TraceReason,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{:?}] {}: {} ({})",
+            self.path.as_ref().map_or("<event>", |x| x.as_str()),
+            self.pii_kind,
+            self.rule_id,
+            if self.matched { "matched" } else { "skipped" },
+            self.reason
+        )
+    }
+}
+
+/// Records every rule evaluation of a PII processor.
+///
+/// Attach a trace to a processor with `RuleBasedPiiProcessor::with_trace`.  After processing,
+/// the trace contains an entry for every rule that was evaluated on a value, as well as for
+/// rules that were skipped entirely.  Its `Display` implementation prints one entry per line.
+/// Use a new trace for every event.
+#[derive(Debug, Default)]
+pub struct ProcessorTrace {
+    entries: RefCell<Vec<TraceEntry>>,
+}
+
+impl ProcessorTrace {
+    /// Creates an empty trace.
+    pub fn new() -> ProcessorTrace {
+        ProcessorTrace::default()
+    }
+
+    /// Records the evaluation of a rule.
+    pub(crate) fn record(
+        &self,
+        path: Option<&str>,
+        pii_kind: PiiKind,
+        rule_id: &str,
+        reason: TraceReason,
+    ) {
+        self.entries.borrow_mut().push(TraceEntry {
+            path: path.map(|x| x.to_string()),
+            pii_kind,
+            rule_id: rule_id.to_string(),
+            matched: reason == TraceReason::Redacted || reason == TraceReason::Replaced,
+            reason,
+        });
+    }
+
+    /// Returns all recorded entries in the order of evaluation.
+    pub fn entries(&self) -> Vec<TraceEntry> {
+        self.entries.borrow().clone()
+    }
+
+    /// Returns all entries of rules that modified a value.
+    pub fn matches(&self) -> Vec<TraceEntry> {
+        self.entries
+            .borrow()
+            .iter()
+            .filter(|entry| entry.matched)
+            .cloned()
+            .collect()
+    }
+
+    /// Consumes the trace and returns the recorded entries.
+    pub fn into_entries(self) -> Vec<TraceEntry> {
+        self.entries.into_inner()
+    }
+}
+
+impl fmt::Display for ProcessorTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in self.entries.borrow().iter() {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let trace = ProcessorTrace::new();
+        trace.record(
+            Some("message"),
+            PiiKind::Freeform,
+            "@email",
+            TraceReason::Redacted,
+        );
+        trace.record(None, PiiKind::Databag, "@ip", TraceReason::ConditionNotMet);
+
+        assert_eq!(trace.matches().len(), 1);
+        assert_eq_str!(
+            trace.to_string(),
+            "message [Freeform] @email: matched (redacted matching text)\n\
+             <event> [Databag] @ip: skipped (condition does not match the event)\n"
+        );
+    }
+}
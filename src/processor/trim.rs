@@ -0,0 +1,291 @@
+//! Trimming of values that exceed size limits.
+
+use serde_json;
+
+use protocol::{Annotated, Remark, RemarkType, Value};
+
+use super::pii::{process_value_children, Cap, ProcessAnnotatedValue, Processor, ValueInfo};
+
+/// The rule ID reported in remarks of trimmed values.
+pub(crate) const TRIM_RULE_ID: &str = "!limit";
+
+/// The text appended to trimmed strings.
+const ELLIPSIS: &str = "...";
+
+/// Maximum lengths of values for every `Cap`.
+///
+/// Lengths are measured in unicode scalar values.  Fields missing from a serialized config fall
+/// back to their defaults.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct CapConfig {
+    /// Maximum length of summary texts.
+    pub summary: usize,
+    /// Maximum length of message texts.
+    pub message: usize,
+    /// Maximum length of paths.
+    pub path: usize,
+    /// Maximum length of short paths, such as file names.
+    pub short_path: usize,
+    /// Maximum length of individual values in databags.  Larger numbers and booleans are
+    /// converted to strings before trimming.
+    pub databag: usize,
+}
+
+impl Default for CapConfig {
+    fn default() -> CapConfig {
+        CapConfig {
+            summary: 256,
+            message: 8192,
+            path: 256,
+            short_path: 128,
+            databag: 512,
+        }
+    }
+}
+
+impl CapConfig {
+    /// Returns the maximum length of values with the given cap.
+    pub fn max_length(&self, cap: Cap) -> usize {
+        match cap {
+            Cap::Summary => self.summary,
+            Cap::Message => self.message,
+            Cap::Path => self.path,
+            Cap::ShortPath => self.short_path,
+            Cap::Databag => self.databag,
+        }
+    }
+}
+
+/// A processor that trims strings exceeding the maximum length of their cap.
+///
+/// Trimmed strings end in an ellipsis that is annotated with a remark.  The original length of
+/// the string is recorded in the meta data.  In databags, numbers and booleans exceeding the
+/// maximum length are stringified and trimmed as well, noting their original type in a remark.
+/// Arrays and maps retain their structure, so that their children can still be processed by key.
+#[derive(Debug, Default)]
+pub struct TrimmingProcessor {
+    config: CapConfig,
+}
+
+impl TrimmingProcessor {
+    /// Creates a trimming processor with the given limits.
+    pub fn new(config: CapConfig) -> TrimmingProcessor {
+        TrimmingProcessor { config }
+    }
+
+    /// Returns the limits applied by this processor.
+    pub fn config(&self) -> &CapConfig {
+        &self.config
+    }
+
+    /// Trims all values below the given root value.
+    pub fn process_root_value<T: ProcessAnnotatedValue>(
+        &self,
+        value: Annotated<T>,
+    ) -> Annotated<T> {
+        ProcessAnnotatedValue::process_annotated_value(value, self, &ValueInfo::default())
+    }
+}
+
+impl Processor for TrimmingProcessor {
+    fn process_string(&self, annotated: Annotated<String>, info: &ValueInfo) -> Annotated<String> {
+        let max_length = match info.cap {
+            Some(cap) => self.config.max_length(cap),
+            None => return annotated,
+        };
+
+        trim_string(annotated, max_length)
+    }
+
+    fn process_value(&self, annotated: Annotated<Value>, info: &ValueInfo) -> Annotated<Value> {
+        let annotated = match info.cap {
+            Some(Cap::Databag) => stringify_value(annotated, self.config.databag),
+            _ => annotated,
+        };
+
+        process_value_children(self, annotated, info)
+    }
+}
+
+/// Converts a scalar value to a JSON string if its serialization exceeds the maximum length.
+///
+/// Only numbers and booleans are converted.  Arrays and maps are never converted, since PII rules
+/// matching on keys could no longer be applied to their children.  The converted value is
+/// annotated with a remark noting its original type, but it is not trimmed yet.
+fn stringify_value(annotated: Annotated<Value>, max_length: usize) -> Annotated<Value> {
+    let (value, mut meta) = match annotated {
+        annotated @ Annotated(Some(Value::Null), _)
+        | annotated @ Annotated(Some(Value::String(_)), _)
+        | annotated @ Annotated(Some(Value::Array(_)), _)
+        | annotated @ Annotated(Some(Value::Map(_)), _)
+        | annotated @ Annotated(None, _) => return annotated,
+        Annotated(Some(value), meta) => (value, meta),
+    };
+
+    let json = match serde_json::to_string(&value) {
+        Ok(json) => json,
+        Err(_) => return Annotated(Some(value), meta),
+    };
+
+    if json.chars().count() <= max_length {
+        return Annotated(Some(value), meta);
+    }
+
+    let note = format!("stringified {}", value.type_name());
+    meta.remarks_mut()
+        .push(Remark::new(RemarkType::Substituted, TRIM_RULE_ID).with_note(note));
+    Annotated(Some(Value::String(json)), meta)
+}
+
+/// Trims a string to the maximum length, ending it in an ellipsis.
+///
+/// Remarks in the trimmed part of the string are removed and the ellipsis is annotated with a
+/// remark.  The original length of the string is recorded in the meta data.
+pub(crate) fn trim_string(annotated: Annotated<String>, max_length: usize) -> Annotated<String> {
+    match annotated {
+        Annotated(Some(value), mut meta) => {
+            let length = value.chars().count();
+            if length <= max_length {
+                return Annotated(Some(value), meta);
+            }
+
+            // limits too short for an ellipsis cut the string without one
+            let ellipsis = if max_length > ELLIPSIS.len() {
+                ELLIPSIS
+            } else {
+                ""
+            };
+            let cut = max_length - ellipsis.len();
+            let mut trimmed: String = value.chars().take(cut).collect();
+            trimmed.push_str(ellipsis);
+
+            meta.remarks_mut()
+                .retain(|remark| remark.range().map_or(true, |range| range.1 <= cut));
+            meta.remarks_mut().push(Remark::with_range(
+                RemarkType::Substituted,
+                TRIM_RULE_ID,
+                (cut, max_length),
+            ));
+            if meta.original_length.is_none() {
+                meta.original_length = Some(length as u32);
+            }
+
+            Annotated(Some(trimmed), meta)
+        }
+        annotated @ Annotated(None, _) => annotated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::{Event, Meta, Provenance};
+
+    #[test]
+    fn test_max_length() {
+        let config = CapConfig::default();
+        assert_eq!(config.max_length(Cap::Summary), 256);
+        assert_eq!(config.max_length(Cap::Message), 8192);
+        assert_eq!(config.max_length(Cap::ShortPath), 128);
+
+        let config: CapConfig = ::serde_json::from_str(r#"{"summary": 10}"#).unwrap();
+        assert_eq!(config.max_length(Cap::Summary), 10);
+        assert_eq!(config.max_length(Cap::Databag), 512);
+    }
+
+    #[test]
+    fn test_trim_message() {
+        let event = Annotated::<Event>::from_json(
+            r#"{"message": "Hello World!", "logger": "short"}"#,
+        ).unwrap();
+
+        let processor = TrimmingProcessor::new(CapConfig {
+            message: 8,
+            ..CapConfig::default()
+        });
+        let event = processor.process_root_value(event);
+        let event = event.value().unwrap();
+
+        assert_eq_str!(event.message.value().unwrap().as_ref().unwrap(), "Hello...");
+        assert_eq_dbg!(
+            event.message.meta(),
+            &Meta {
+                remarks: vec![Remark::with_range(
+                    RemarkType::Substituted,
+                    "!limit",
+                    (5, 8),
+                )],
+                errors: vec![],
+                original_length: Some(12),
+                provenance: Provenance::Sdk,
+                path: None,
+            }
+        );
+        assert_eq_str!(event.logger.value().unwrap().as_ref().unwrap(), "short");
+    }
+
+    #[test]
+    fn test_stringify_extra() {
+        let event = Annotated::<Event>::from_json(
+            r#"{
+                "extra": {
+                    "map": {"a": "hello world"},
+                    "array": [1, 2],
+                    "number": 12345678901,
+                    "string": "short"
+                }
+            }"#,
+        ).unwrap();
+
+        let processor = TrimmingProcessor::new(CapConfig {
+            databag: 10,
+            ..CapConfig::default()
+        });
+        let event = processor.process_root_value(event);
+        let extra = event.value().unwrap().extra.value().unwrap();
+
+        let map = extra["map"].value().unwrap();
+        let inner = match *map {
+            Value::Map(ref inner) => &inner["a"],
+            ref other => panic!("expected a map, found {:?}", other),
+        };
+        assert_eq_dbg!(
+            inner.value(),
+            Some(&Value::String("hello w...".to_string()))
+        );
+        assert!(extra["map"].meta().is_empty());
+
+        let number = &extra["number"];
+        assert_eq_dbg!(
+            number.value(),
+            Some(&Value::String("1234567...".to_string()))
+        );
+        assert_eq_dbg!(
+            number.meta(),
+            &Meta {
+                remarks: vec![
+                    Remark::new(RemarkType::Substituted, "!limit").with_note("stringified integer"),
+                    Remark::with_range(RemarkType::Substituted, "!limit", (7, 10)),
+                ],
+                errors: vec![],
+                original_length: Some(11),
+                provenance: Provenance::Sdk,
+                path: None,
+            }
+        );
+
+        assert_eq_dbg!(
+            extra["array"].value(),
+            Some(&Value::Array(vec![
+                Annotated::from(Value::U64(1)),
+                Annotated::from(Value::U64(2)),
+            ]))
+        );
+        assert!(extra["array"].meta().is_empty());
+        assert_eq_dbg!(
+            extra["string"].value(),
+            Some(&Value::String("short".to_string()))
+        );
+    }
+}
@@ -0,0 +1,525 @@
+//! Builders for constructing protocol types.
+//!
+//! Builders start out with the same defaults that apply when deserializing an empty payload.
+//! Setters consume and return the builder, and `finish` returns the annotated value:
+//!
+//! ```
+//! # use marshal::protocol::*;
+//! let event = EventBuilder::new()
+//!     .message("Hello World!")
+//!     .level(Level::Warning)
+//!     .tag("server", "web-1")
+//!     .user(UserBuilder::new().id("42").finish())
+//!     .finish();
+//! ```
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::common::{Map, Value, Values};
+use super::meta::Annotated;
+use super::types::{
+    Breadcrumb, Cookies, Event, Exception, Headers, Level, Query, Request, Stacktrace, User,
+};
+
+fn insert<V, K: Into<String>>(map: &mut Annotated<Map<V>>, key: K, value: Annotated<V>) {
+    map.0.get_or_insert_with(Map::new).insert(key.into(), value);
+}
+
+fn push<T>(values: &mut Annotated<Values<T>>, value: Annotated<T>) {
+    values
+        .0
+        .get_or_insert_with(Values::new)
+        .values
+        .0
+        .get_or_insert_with(Vec::new)
+        .push(value);
+}
+
+/// Builds an `Event`.
+#[derive(Debug, Clone)]
+pub struct EventBuilder {
+    event: Event,
+}
+
+impl Default for EventBuilder {
+    fn default() -> EventBuilder {
+        EventBuilder::new()
+    }
+}
+
+impl EventBuilder {
+    /// Creates a builder for an empty event.
+    pub fn new() -> EventBuilder {
+        EventBuilder {
+            event: Event {
+                fingerprint: vec!["{{ default }}".to_string()].into(),
+                platform: "other".to_string().into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets the event identifier.
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.event.id = Some(id).into();
+        self
+    }
+
+    /// Sets the severity level.
+    pub fn level(mut self, level: Level) -> Self {
+        self.event.level = Some(level).into();
+        self
+    }
+
+    /// Sets the custom fingerprint.
+    pub fn fingerprint<S: Into<String>>(mut self, fingerprint: Vec<S>) -> Self {
+        let fingerprint = fingerprint.into_iter().map(Into::into).collect::<Vec<String>>();
+        self.event.fingerprint = fingerprint.into();
+        self
+    }
+
+    /// Sets the culprit.
+    pub fn culprit<S: Into<String>>(mut self, culprit: S) -> Self {
+        self.event.culprit = Some(culprit.into()).into();
+        self
+    }
+
+    /// Sets the transaction name.
+    pub fn transaction<S: Into<String>>(mut self, transaction: S) -> Self {
+        self.event.transaction = Some(transaction.into()).into();
+        self
+    }
+
+    /// Sets the log message.
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.event.message = Some(message.into()).into();
+        self
+    }
+
+    /// Sets the logger name.
+    pub fn logger<S: Into<String>>(mut self, logger: S) -> Self {
+        self.event.logger = Some(logger.into()).into();
+        self
+    }
+
+    /// Adds a loaded module and its version.
+    pub fn module<K: Into<String>, S: Into<String>>(mut self, name: K, version: S) -> Self {
+        insert(&mut self.event.modules, name, Annotated::from(version.into()));
+        self
+    }
+
+    /// Sets the platform identifier.
+    pub fn platform<S: Into<String>>(mut self, platform: S) -> Self {
+        self.event.platform = Annotated::from(platform.into());
+        self
+    }
+
+    /// Sets the time at which the event was created.
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.event.timestamp = Some(timestamp).into();
+        self
+    }
+
+    /// Sets the server name.
+    pub fn server_name<S: Into<String>>(mut self, server_name: S) -> Self {
+        self.event.server_name = Some(server_name.into()).into();
+        self
+    }
+
+    /// Sets the release identifier.
+    pub fn release<S: Into<String>>(mut self, release: S) -> Self {
+        self.event.release = Some(release.into()).into();
+        self
+    }
+
+    /// Sets the distribution identifier.
+    pub fn dist<S: Into<String>>(mut self, dist: S) -> Self {
+        self.event.dist = Some(dist.into()).into();
+        self
+    }
+
+    /// Sets the environment name.
+    pub fn environment<S: Into<String>>(mut self, environment: S) -> Self {
+        self.event.environment = Some(environment.into()).into();
+        self
+    }
+
+    /// Sets the user interface.
+    pub fn user(mut self, user: Annotated<User>) -> Self {
+        self.event.user = user.map(Some);
+        self
+    }
+
+    /// Sets the request interface.
+    pub fn request(mut self, request: Annotated<Request>) -> Self {
+        self.event.request = request.map(Some);
+        self
+    }
+
+    /// Appends a breadcrumb.
+    pub fn breadcrumb(mut self, breadcrumb: Annotated<Breadcrumb>) -> Self {
+        push(&mut self.event.breadcrumbs, breadcrumb);
+        self
+    }
+
+    /// Appends an exception.  The most recent exception is added last.
+    pub fn exception(mut self, exception: Annotated<Exception>) -> Self {
+        push(&mut self.event.exceptions, exception);
+        self
+    }
+
+    /// Sets the stack trace of a log message.
+    pub fn stacktrace(mut self, stacktrace: Annotated<Stacktrace>) -> Self {
+        self.event.stacktrace = stacktrace.map(Some);
+        self
+    }
+
+    /// Adds a tag.
+    pub fn tag<K: Into<String>, S: Into<String>>(mut self, key: K, value: S) -> Self {
+        insert(&mut self.event.tags, key, Annotated::from(value.into()));
+        self
+    }
+
+    /// Adds arbitrary extra data.
+    pub fn extra<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        insert(&mut self.event.extra, key, value.into());
+        self
+    }
+
+    /// Returns the event.
+    pub fn finish(self) -> Annotated<Event> {
+        self.event.into()
+    }
+}
+
+/// Builds a `Breadcrumb`.
+#[derive(Debug, Clone)]
+pub struct BreadcrumbBuilder {
+    breadcrumb: Breadcrumb,
+}
+
+impl Default for BreadcrumbBuilder {
+    fn default() -> BreadcrumbBuilder {
+        BreadcrumbBuilder::new()
+    }
+}
+
+impl BreadcrumbBuilder {
+    /// Creates a builder for a default breadcrumb recorded now.
+    pub fn new() -> BreadcrumbBuilder {
+        BreadcrumbBuilder {
+            breadcrumb: Breadcrumb {
+                timestamp: Utc::now().into(),
+                ty: "default".to_string().into(),
+                category: None.into(),
+                level: Level::default().into(),
+                message: None.into(),
+                data: Map::new().into(),
+                other: Map::new().into(),
+            },
+        }
+    }
+
+    /// Sets the time at which the breadcrumb was recorded.
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.breadcrumb.timestamp = timestamp.into();
+        self
+    }
+
+    /// Sets the breadcrumb type.
+    pub fn ty<S: Into<String>>(mut self, ty: S) -> Self {
+        self.breadcrumb.ty = Annotated::from(ty.into());
+        self
+    }
+
+    /// Sets the category.
+    pub fn category<S: Into<String>>(mut self, category: S) -> Self {
+        self.breadcrumb.category = Some(category.into()).into();
+        self
+    }
+
+    /// Sets the severity level.
+    pub fn level(mut self, level: Level) -> Self {
+        self.breadcrumb.level = level.into();
+        self
+    }
+
+    /// Sets the message.
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.breadcrumb.message = Some(message.into()).into();
+        self
+    }
+
+    /// Adds custom data.
+    pub fn data<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        insert(&mut self.breadcrumb.data, key, value.into());
+        self
+    }
+
+    /// Returns the breadcrumb.
+    pub fn finish(self) -> Annotated<Breadcrumb> {
+        self.breadcrumb.into()
+    }
+}
+
+/// Builds a `Request`.
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    request: Request,
+}
+
+impl Default for RequestBuilder {
+    fn default() -> RequestBuilder {
+        RequestBuilder::new()
+    }
+}
+
+impl RequestBuilder {
+    /// Creates a builder for an empty request.
+    pub fn new() -> RequestBuilder {
+        RequestBuilder {
+            request: Request {
+                url: None.into(),
+                method: None.into(),
+                data: None.into(),
+                query_string: Query::default().into(),
+                cookies: Cookies::default().into(),
+                headers: Headers::default().into(),
+                env: Map::new().into(),
+                other: Map::new().into(),
+            },
+        }
+    }
+
+    /// Sets the URL.
+    pub fn url<S: Into<String>>(mut self, url: S) -> Self {
+        self.request.url = Some(url.into()).into();
+        self
+    }
+
+    /// Sets the HTTP method.
+    pub fn method<S: Into<String>>(mut self, method: S) -> Self {
+        self.request.method = Some(method.into()).into();
+        self
+    }
+
+    /// Sets the request body.
+    pub fn data(mut self, data: Value) -> Self {
+        self.request.data = Some(data).into();
+        self
+    }
+
+    /// Adds a query string parameter.
+    pub fn query<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        let query = self.request.query_string.0.get_or_insert_with(Query::default);
+        query.0.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a cookie.
+    pub fn cookie<K: Into<String>, S: Into<String>>(mut self, name: K, value: S) -> Self {
+        let cookies = self.request.cookies.0.get_or_insert_with(Cookies::default);
+        cookies.0.insert(name.into(), Annotated::from(value.into()));
+        self
+    }
+
+    /// Adds a header.
+    pub fn header<K: Into<String>, S: Into<String>>(mut self, name: K, value: S) -> Self {
+        let headers = self.request.headers.0.get_or_insert_with(Headers::default);
+        headers.insert(name, Annotated::from(value.into()));
+        self
+    }
+
+    /// Adds a server environment variable.
+    pub fn env<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        insert(&mut self.request.env, key, value.into());
+        self
+    }
+
+    /// Returns the request.
+    pub fn finish(self) -> Annotated<Request> {
+        self.request.into()
+    }
+}
+
+/// Builds a `User`.
+#[derive(Debug, Clone)]
+pub struct UserBuilder {
+    user: User,
+}
+
+impl Default for UserBuilder {
+    fn default() -> UserBuilder {
+        UserBuilder::new()
+    }
+}
+
+impl UserBuilder {
+    /// Creates a builder for an empty user.
+    pub fn new() -> UserBuilder {
+        UserBuilder {
+            user: User {
+                id: None.into(),
+                email: None.into(),
+                ip_address: None.into(),
+                username: None.into(),
+                geo: None.into(),
+                other: Map::new().into(),
+            },
+        }
+    }
+
+    /// Sets the user identifier.
+    pub fn id<S: Into<String>>(mut self, id: S) -> Self {
+        self.user.id = Some(id.into()).into();
+        self
+    }
+
+    /// Sets the email address.
+    pub fn email<S: Into<String>>(mut self, email: S) -> Self {
+        self.user.email = Some(email.into()).into();
+        self
+    }
+
+    /// Sets the IP address.
+    pub fn ip_address<S: Into<String>>(mut self, ip_address: S) -> Self {
+        self.user.ip_address = Some(ip_address.into()).into();
+        self
+    }
+
+    /// Sets the username.
+    pub fn username<S: Into<String>>(mut self, username: S) -> Self {
+        self.user.username = Some(username.into()).into();
+        self
+    }
+
+    /// Adds an arbitrary field.
+    pub fn other<K: Into<String>>(mut self, key: K, value: Value) -> Self {
+        insert(&mut self.user.other, key, value.into());
+        self
+    }
+
+    /// Returns the user.
+    pub fn finish(self) -> Annotated<User> {
+        self.user.into()
+    }
+}
+
+/// Builds an `Exception`.
+#[derive(Debug, Clone)]
+pub struct ExceptionBuilder {
+    exception: Exception,
+}
+
+impl ExceptionBuilder {
+    /// Creates a builder for an exception of the given type.
+    pub fn new<S: Into<String>>(ty: S) -> ExceptionBuilder {
+        ExceptionBuilder {
+            exception: Exception {
+                ty: Annotated::from(ty.into()),
+                value: None.into(),
+                module: None.into(),
+                stacktrace: None.into(),
+                raw_stacktrace: None.into(),
+                thread_id: None.into(),
+                mechanism: None.into(),
+                other: Map::new().into(),
+            },
+        }
+    }
+
+    /// Sets the exception value.
+    pub fn value<S: Into<String>>(mut self, value: S) -> Self {
+        self.exception.value = Some(value.into()).into();
+        self
+    }
+
+    /// Sets the module of the exception type.
+    pub fn module<S: Into<String>>(mut self, module: S) -> Self {
+        self.exception.module = Some(module.into()).into();
+        self
+    }
+
+    /// Sets the stack trace.
+    pub fn stacktrace(mut self, stacktrace: Annotated<Stacktrace>) -> Self {
+        self.exception.stacktrace = stacktrace.map(Some);
+        self
+    }
+
+    /// Returns the exception.
+    pub fn finish(self) -> Annotated<Exception> {
+        self.exception.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_event() {
+        assert_eq_dbg!(
+            EventBuilder::new().finish(),
+            Annotated::<Event>::from_json("{}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_event() {
+        let json = r#"{
+  "event_id": "52df9022835246eeb317dbd739ccd059",
+  "level": "warning",
+  "message": "Hello World!",
+  "platform": "python",
+  "timestamp": 946684800,
+  "release": "1.0.0",
+  "user": {"id": "42", "email": "foo@example.com"},
+  "request": {"url": "https://example.com/", "method": "GET"},
+  "breadcrumbs": {
+    "values": [{"timestamp": 946684800, "type": "http", "message": "GET /"}]
+  },
+  "exception": {
+    "values": [{"type": "ValueError", "value": "bad value"}]
+  },
+  "tags": {"server": "web-1"},
+  "extra": {"debug": true}
+}"#;
+
+        let timestamp = Utc.ymd(2000, 1, 1).and_hms(0, 0, 0);
+        let event = EventBuilder::new()
+            .id("52df9022-8352-46ee-b317-dbd739ccd059".parse().unwrap())
+            .level(Level::Warning)
+            .message("Hello World!")
+            .platform("python")
+            .timestamp(timestamp)
+            .release("1.0.0")
+            .user(
+                UserBuilder::new()
+                    .id("42")
+                    .email("foo@example.com")
+                    .finish(),
+            )
+            .request(
+                RequestBuilder::new()
+                    .url("https://example.com/")
+                    .method("GET")
+                    .finish(),
+            )
+            .breadcrumb(
+                BreadcrumbBuilder::new()
+                    .timestamp(timestamp)
+                    .ty("http")
+                    .message("GET /")
+                    .finish(),
+            )
+            .exception(ExceptionBuilder::new("ValueError").value("bad value").finish())
+            .tag("server", "web-1")
+            .extra("debug", Value::Bool(true))
+            .finish();
+
+        assert_eq_dbg!(event, Annotated::<Event>::from_json(json).unwrap());
+    }
+}
@@ -1,19 +1,68 @@
 //! Common data structures.
 
+#[cfg(not(feature = "preserve_order"))]
 use std::collections::BTreeMap;
 use std::fmt;
+#[cfg(feature = "preserve_order")]
+use std::mem;
+use std::rc::Rc;
 
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
+use super::lenient::BigIntegers;
 use super::meta::Annotated;
+use super::ser_utils;
+use super::size::estimate_size;
+use super::tracked::Path;
 
 /// A list of annotated values.
 pub type Array<V> = Vec<Annotated<V>>;
 
 /// A map of annotated values.
+///
+/// Keys are sorted unless the `preserve_order` feature is enabled.
+#[cfg(not(feature = "preserve_order"))]
 pub type Map<V> = BTreeMap<String, Annotated<V>>;
 
+/// A map of annotated values.
+///
+/// Keys retain the order in which they were inserted, so that serialization reproduces the key
+/// order of the original payload.
+#[cfg(feature = "preserve_order")]
+pub type Map<V> = IndexMap<String, Annotated<V>>;
+
+/// Removes a key from a map while retaining the order of all other keys.
+#[cfg(not(feature = "preserve_order"))]
+pub(crate) fn remove_key<V>(map: &mut Map<V>, key: &str) -> Option<Annotated<V>> {
+    map.remove(key)
+}
+
+/// Removes a key from a map while retaining the order of all other keys.
+#[cfg(feature = "preserve_order")]
+pub(crate) fn remove_key<V>(map: &mut Map<V>, key: &str) -> Option<Annotated<V>> {
+    if !map.contains_key(key) {
+        return None;
+    }
+
+    // `IndexMap::remove` moves the last entry into the gap, so rebuild the map instead
+    let mut removed = None;
+    for (k, v) in mem::replace(map, Map::new()) {
+        if k == key {
+            removed = Some(v);
+        } else {
+            map.insert(k, v);
+        }
+    }
+    removed
+}
+
 /// Holds an arbitrary type supported by the protocol.
+///
+/// Integers beyond 64 bits, such as numeric snowflake IDs, are deserialized into `BigNumber` with
+/// all their digits, so that their precision is not lost.  This requires `Annotated::from_json`
+/// or a deserializer that supports 128 bit integers.  Other deserializers yield lossy floats.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     /// A null value (None, unit).
@@ -28,6 +77,10 @@ pub enum Value {
     U64(u64),
     /// A signed int 64.
     I64(i64),
+    /// An integer beyond 64 bits, as its decimal digits.
+    ///
+    /// This is serialized as a 128 bit integer, or as a string if the digits do not fit.
+    BigNumber(String),
     /// A 32bit float.
     F32(f32),
     /// A 64bit float.
@@ -59,6 +112,160 @@ declare_from!(f32, F32);
 declare_from!(f64, F64);
 declare_from!(String, String);
 
+impl Value {
+    /// Estimates the size of this value's JSON serialization in bytes.
+    ///
+    /// This walks into nested arrays and maps but ignores all meta data.
+    pub fn deep_size(&self) -> usize {
+        estimate_size(self)
+    }
+
+    /// Returns the name of this value's type for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::U32(_) | Value::I32(_) | Value::U64(_) | Value::I64(_) | Value::BigNumber(_) => {
+                "integer"
+            }
+            Value::F32(_) | Value::F64(_) => "float",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+        }
+    }
+
+    /// Coerces this value into an unsigned integer.
+    ///
+    /// Integers are converted unless they are negative.  Floats are converted if they have no
+    /// fractional part and fit into a `u64`.  Strings are parsed as unsigned integers, ignoring
+    /// surrounding whitespace.  All other values return `None`.
+    pub fn as_u64_lossy(&self) -> Option<u64> {
+        match *self {
+            Value::U32(val) => Some(val.into()),
+            Value::U64(val) => Some(val),
+            Value::I32(val) if val >= 0 => Some(val as u64),
+            Value::I64(val) if val >= 0 => Some(val as u64),
+            Value::F32(val) => float_to_u64(val.into()),
+            Value::F64(val) => float_to_u64(val),
+            Value::String(ref val) => val.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Coerces this value into a string.
+    ///
+    /// Strings are returned as is, while booleans and numbers are formatted with their `Display`
+    /// implementation.  Null, arrays and maps return `None`.
+    pub fn as_string_lossy(&self) -> Option<String> {
+        match *self {
+            Value::Null | Value::Array(_) | Value::Map(_) => None,
+            Value::String(ref val) => Some(val.clone()),
+            ref other => Some(other.to_string()),
+        }
+    }
+
+    /// Coerces this value into a boolean.
+    ///
+    /// Numbers are converted if they are `0` or `1`.  Strings are converted if they contain
+    /// `true`, `false`, `yes`, `no`, `on`, `off`, `1` or `0`, ignoring case and surrounding
+    /// whitespace.  All other values return `None`.
+    pub fn as_bool_lossy(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(val) => Some(val),
+            Value::String(ref val) => match val.trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(true),
+                "false" | "no" | "off" | "0" => Some(false),
+                _ => None,
+            },
+            ref other => match other.as_u64_lossy() {
+                Some(0) => Some(false),
+                Some(1) => Some(true),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Converts a float into an unsigned integer if it has no fractional part.
+fn float_to_u64(val: f64) -> Option<u64> {
+    if val >= 0.0 && val < u64::max_value() as f64 && val.fract() == 0.0 {
+        Some(val as u64)
+    } else {
+        None
+    }
+}
+
+impl Annotated<Value> {
+    /// Replaces the value with its coercion and records an error if the type changed.
+    fn set_coerced(&mut self, coerced: Value, lossless: bool) {
+        if !lossless {
+            let error = match self.value() {
+                Some(original) => format!(
+                    "coerced {} to {}",
+                    original.type_name(),
+                    coerced.type_name()
+                ),
+                None => return,
+            };
+            self.meta_mut().errors_mut().push(error);
+        }
+        self.set_value(Some(coerced));
+    }
+
+    /// Coerces the value into `Value::U64` in place and returns it.
+    ///
+    /// See `Value::as_u64_lossy` for the coercion rules.  Converting values other than integers
+    /// is recorded as error in the meta data.  Values that cannot be coerced remain unchanged.
+    pub fn coerce_u64(&mut self) -> Option<u64> {
+        let (coerced, lossless) = match self.value() {
+            Some(&Value::U64(val)) => return Some(val),
+            Some(value) => (value.as_u64_lossy()?, value.type_name() == "integer"),
+            None => return None,
+        };
+
+        self.set_coerced(Value::U64(coerced), lossless);
+        Some(coerced)
+    }
+
+    /// Coerces the value into `Value::String` in place and returns it.
+    ///
+    /// See `Value::as_string_lossy` for the coercion rules.  Converting values other than
+    /// strings is recorded as error in the meta data.  Values that cannot be coerced remain
+    /// unchanged.
+    pub fn coerce_string(&mut self) -> Option<&str> {
+        let coerced = match self.value() {
+            Some(&Value::String(_)) => None,
+            Some(value) => Some(value.as_string_lossy()?),
+            None => return None,
+        };
+
+        if let Some(coerced) = coerced {
+            self.set_coerced(Value::String(coerced), false);
+        }
+
+        match self.value() {
+            Some(&Value::String(ref val)) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Coerces the value into `Value::Bool` in place and returns it.
+    ///
+    /// See `Value::as_bool_lossy` for the coercion rules.  Converting values other than booleans
+    /// is recorded as error in the meta data.  Values that cannot be coerced remain unchanged.
+    pub fn coerce_bool(&mut self) -> Option<bool> {
+        let coerced = match self.value() {
+            Some(&Value::Bool(val)) => return Some(val),
+            Some(value) => value.as_bool_lossy()?,
+            None => return None,
+        };
+
+        self.set_coerced(Value::Bool(coerced), false);
+        Some(coerced)
+    }
+}
+
 impl<'a> From<&'a str> for Value {
     fn from(string: &'a str) -> Value {
         Value::String(string.to_string())
@@ -76,6 +283,7 @@ impl fmt::Display for Value {
             Value::I32(val) => write!(f, "{}", val),
             Value::U64(val) => write!(f, "{}", val),
             Value::I64(val) => write!(f, "{}", val),
+            Value::BigNumber(ref val) => write!(f, "{}", val),
             Value::F32(val) => write!(f, "{}", val),
             Value::F64(val) => write!(f, "{}", val),
             Value::String(ref val) => write!(f, "{}", val),
@@ -181,6 +389,30 @@ impl<'de> de::Visitor<'de> for ValueVisitor {
         Ok(Value::U64(v))
     }
 
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if v <= u128::from(u64::max_value()) {
+            Ok(Value::U64(v as u64))
+        } else {
+            Ok(Value::BigNumber(v.to_string()))
+        }
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if v >= i128::from(i64::min_value()) && v <= i128::from(i64::max_value()) {
+            Ok(Value::I64(v as i64))
+        } else if v >= 0 {
+            self.visit_u128(v as u128)
+        } else {
+            Ok(Value::BigNumber(v.to_string()))
+        }
+    }
+
     fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -284,7 +516,20 @@ impl<'de> de::Visitor<'de> for ValueVisitor {
 
 impl<'de> Deserialize<'de> for Value {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        deserializer.deserialize_any(ValueVisitor)
+        let digits = {
+            let integers: Option<&Rc<BigIntegers>> = deserializer.state().get();
+            let path: Option<&Rc<Path>> = deserializer.state().get();
+            match (integers, path) {
+                (Some(integers), Some(path)) => integers.get(&path.segments()).map(str::to_owned),
+                _ => None,
+            }
+        };
+
+        // JSON parsers read big integers as lossy floats, so restore their original digits
+        match (deserializer.deserialize_any(ValueVisitor)?, digits) {
+            (Value::F64(_), Some(digits)) => Ok(Value::BigNumber(digits)),
+            (value, _) => Ok(value),
+        }
     }
 }
 
@@ -297,6 +542,18 @@ impl Serialize for Value {
             Value::I32(i) => serializer.serialize_i32(i),
             Value::U64(u) => serializer.serialize_u64(u),
             Value::I64(i) => serializer.serialize_i64(i),
+            Value::BigNumber(ref digits) => {
+                if let Ok(u) = digits.parse() {
+                    serializer.serialize_u128(u)
+                } else if let Ok(i) = digits.parse() {
+                    serializer.serialize_i128(i)
+                } else {
+                    serializer.serialize_str(digits)
+                }
+            }
+            // JSON cannot represent NaN and infinity
+            Value::F32(f) if !f.is_finite() => serializer.serialize_none(),
+            Value::F64(f) if !f.is_finite() => serializer.serialize_none(),
             Value::F32(f) => serializer.serialize_f32(f),
             Value::F64(f) => serializer.serialize_f64(f),
             Value::String(ref s) => serializer.serialize_str(s),
@@ -320,19 +577,54 @@ impl Serialize for Value {
     }
 }
 
+/// The shape in which a `Values` collection was sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ValuesShape {
+    /// An object containing a `values` field.
+    Qualified,
+    /// A bare array of values.
+    Array,
+    /// A single value without a wrapping array.
+    Single,
+}
+
+impl Default for ValuesShape {
+    fn default() -> ValuesShape {
+        ValuesShape::Qualified
+    }
+}
+
 /// A wrapper type for collections with attached meta data.
 ///
-/// The JSON payload can either directly be an array or an object containing a `values` field and
-/// arbitrary other fields. All other fields will be collected into `Values::data` when
-/// deserializing and re-serialized in the same place. The shorthand array notation is always
-/// reserialized as object.
-#[derive(Serialize, Clone, Debug, PartialEq)]
+/// The JSON payload can either directly be an array, a single value or an object containing a
+/// `values` field and arbitrary other fields. All other fields will be collected into
+/// `Values::other` when deserializing and re-serialized in the same place. The shorthand
+/// notations are always reserialized as object, and their original shape is retained in
+/// `Values::shape` for diagnostics.
+///
+/// Values compare equal regardless of their `shape`.
+#[derive(Serialize, Clone, Debug)]
 pub struct Values<T> {
     /// The values of the collection.
     pub values: Annotated<Array<T>>,
+    /// Indicates that values were removed from the collection.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub truncated: Annotated<Option<bool>>,
     /// Additional arbitrary fields for forwards compatibility.
     #[serde(flatten, default)]
     pub other: Annotated<Map<Value>>,
+    /// The shape in which the collection was sent.  This is not serialized.
+    #[serde(skip)]
+    pub shape: ValuesShape,
+}
+
+impl<T: PartialEq> PartialEq for Values<T> {
+    fn eq(&self, other: &Values<T>) -> bool {
+        // the shape describes the layout of the original payload, not the values.
+        self.values == other.values
+            && self.truncated == other.truncated
+            && self.other == other.other
+    }
 }
 
 impl<T> Values<T> {
@@ -340,7 +632,17 @@ impl<T> Values<T> {
     pub fn new() -> Values<T> {
         Values {
             values: Array::new().into(),
+            truncated: None.into(),
             other: Map::new().into(),
+            shape: ValuesShape::Qualified,
+        }
+    }
+
+    /// Creates a values struct from the shorthand notation of the given shape.
+    fn from_shape(values: Array<T>, shape: ValuesShape) -> Values<T> {
+        Values {
+            shape,
+            ..Values::from(values)
         }
     }
 
@@ -349,6 +651,76 @@ impl<T> Values<T> {
         self.values.value().map_or(false, Array::is_empty)
             && self.other.value().map_or(false, Map::is_empty)
     }
+
+    /// Truncates the collection to the `n` most recent values.
+    ///
+    /// Values are assumed to be ordered from oldest to newest, so values are removed from the
+    /// front.  If any values are removed, the `truncated` marker is set and the original number
+    /// of values is recorded in the meta data of `values`.  Returns the number of removed values.
+    pub fn truncate(&mut self, n: usize) -> usize {
+        let Annotated(ref mut values, ref mut meta) = self.values;
+        let values = match *values {
+            Some(ref mut values) if values.len() > n => values,
+            _ => return 0,
+        };
+
+        let removed = values.len() - n;
+        if meta.original_length.is_none() {
+            meta.original_length = Some(values.len() as u32);
+        }
+
+        values.drain(..removed);
+        self.truncated.set_value(Some(Some(true)));
+        removed
+    }
+
+    /// Returns the number of valid values in the collection.
+    ///
+    /// Values are valid if they could be deserialized or have not been removed.
+    pub fn len_valid(&self) -> usize {
+        self.iter_valid().count()
+    }
+
+    /// Iterates over all valid values, skipping entries without a value.
+    pub fn iter_valid(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.values
+            .value()
+            .into_iter()
+            .flat_map(|values| values.iter().filter_map(Annotated::value))
+    }
+
+    /// Iterates mutably over all valid values, skipping entries without a value.
+    pub fn iter_valid_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> {
+        self.values
+            .value_mut()
+            .into_iter()
+            .flat_map(|values| values.iter_mut().filter_map(Annotated::value_mut))
+    }
+
+    /// Iterates over the `n` most recent valid values, from oldest to newest.
+    pub fn last_n(&self, n: usize) -> impl Iterator<Item = &T> {
+        let skip = self.len_valid().saturating_sub(n);
+        self.iter_valid().skip(skip)
+    }
+
+    /// Retains only the valid values for which the predicate returns `true`.
+    ///
+    /// Entries without a value are retained, since their meta data may explain why the value is
+    /// missing.  Unlike `truncate`, this does not mark the collection as truncated.  Returns the
+    /// number of removed values.
+    pub fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let values = match self.values.value_mut() {
+            Some(values) => values,
+            None => return 0,
+        };
+
+        let len = values.len();
+        values.retain(|annotated| annotated.value().map_or(true, |value| f(value)));
+        len - values.len()
+    }
 }
 
 impl<T> Default for Values<T> {
@@ -362,7 +734,9 @@ impl<T> From<Annotated<Array<T>>> for Values<T> {
     fn from(values: Annotated<Array<T>>) -> Values<T> {
         Values {
             values,
+            truncated: None.into(),
             other: Map::new().into(),
+            shape: ValuesShape::Qualified,
         }
     }
 }
@@ -384,6 +758,8 @@ where
         enum Repr<T> {
             Qualified {
                 values: Annotated<Array<T>>,
+                #[serde(default)]
+                truncated: Annotated<Option<bool>>,
                 #[serde(flatten)]
                 other: Annotated<Map<Value>>,
             },
@@ -392,9 +768,18 @@ where
         }
 
         Deserialize::deserialize(deserializer).map(|x| match x {
-            Repr::Qualified { values, other } => Values { values, other },
-            Repr::Unqualified(values) => values.into(),
-            Repr::Single(value) => vec![value].into(),
+            Repr::Qualified {
+                values,
+                truncated,
+                other,
+            } => Values {
+                values,
+                truncated,
+                other,
+                shape: ValuesShape::Qualified,
+            },
+            Repr::Unqualified(values) => Values::from_shape(values, ValuesShape::Array),
+            Repr::Single(value) => Values::from_shape(vec![value], ValuesShape::Single),
         })
     }
 }
@@ -406,22 +791,47 @@ mod tests {
 
     #[test]
     fn test_array() {
-        let values = Values {
-            values: vec![
+        let values = Values::from_shape(
+            vec![
                 Value::from(1u64).into(),
                 Value::from(2u64).into(),
                 Value::from(3u64).into(),
-            ].into(),
-            other: Map::new().into(),
-        };
+            ],
+            ValuesShape::Array,
+        );
 
         assert_eq_dbg!(values, serde_json::from_str("[1,2,3]").unwrap());
+        assert_eq!(values.shape, ValuesShape::Array);
         assert_eq_str!(
             serde_json::to_string(&values).unwrap(),
             "{\"values\":[1,2,3]}"
         );
     }
 
+    #[test]
+    fn test_single() {
+        let values: Values<Value> = serde_json::from_str("{\"type\":\"Error\"}").unwrap();
+        assert_eq!(values.shape, ValuesShape::Single);
+        assert_eq!(values.values.value().unwrap().len(), 1);
+        assert!(values.values.meta().is_empty());
+        assert_eq_str!(
+            serde_json::to_string(&values).unwrap(),
+            "{\"values\":[{\"type\":\"Error\"}]}"
+        );
+
+        let values: Values<Value> = serde_json::from_str("{\"values\":[1]}").unwrap();
+        assert_eq!(values.shape, ValuesShape::Qualified);
+    }
+
+    #[test]
+    fn test_shape_equality() {
+        let values: Values<Value> = serde_json::from_str("[{\"type\":\"Error\"}]").unwrap();
+        let json = serde_json::to_string(&values).unwrap();
+        let reparsed: Values<Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(reparsed.shape, ValuesShape::Qualified);
+        assert_eq_dbg!(reparsed, values);
+    }
+
     #[test]
     fn test_object() {
         let values = Values {
@@ -430,7 +840,9 @@ mod tests {
                 Value::from(2u64).into(),
                 Value::from(3u64).into(),
             ].into(),
+            truncated: None.into(),
             other: Map::new().into(),
+            shape: ValuesShape::Qualified,
         };
 
         assert_eq_dbg!(
@@ -452,11 +864,13 @@ mod tests {
                 Value::from(2u64).into(),
                 Value::from(3u64).into(),
             ].into(),
+            truncated: None.into(),
             other: {
                 let mut m = Map::new();
                 m.insert("foo".to_string(), Annotated::from(Value::from("bar")));
                 Annotated::from(m)
             },
+            shape: ValuesShape::Qualified,
         };
 
         assert_eq_dbg!(
@@ -483,4 +897,218 @@ mod tests {
         assert!(Values::<u32>::new().is_empty());
         assert!(!Values::from(vec![1.into(), 2.into(), 3.into()]).is_empty())
     }
+
+    #[test]
+    fn test_truncate() {
+        let mut values = Values::<u32>::from(vec![1.into(), 2.into(), 3.into()]);
+        assert_eq!(values.truncate(2), 1);
+
+        assert_eq_dbg!(values.values.value().unwrap(), &vec![2.into(), 3.into()]);
+        assert_eq_dbg!(values.values.meta().original_length(), Some(3));
+        assert_eq_str!(
+            serde_json::to_string(&values).unwrap(),
+            "{\"values\":[2,3],\"truncated\":true}"
+        );
+    }
+
+    #[test]
+    fn test_truncate_noop() {
+        let mut values = Values::<u32>::from(vec![1.into(), 2.into()]);
+        assert_eq!(values.truncate(2), 0);
+
+        assert!(values.truncated.value().unwrap().is_none());
+        assert!(values.values.meta().original_length().is_none());
+    }
+
+    #[test]
+    fn test_valid_accessors() {
+        let mut values = Values::<u32>::from(vec![
+            1.into(),
+            Annotated::from_error("invalid"),
+            2.into(),
+            3.into(),
+        ]);
+
+        assert_eq!(values.len_valid(), 3);
+        assert_eq_dbg!(values.iter_valid().collect::<Vec<_>>(), vec![&1, &2, &3]);
+        assert_eq_dbg!(values.last_n(2).collect::<Vec<_>>(), vec![&2, &3]);
+        assert_eq_dbg!(values.last_n(5).collect::<Vec<_>>(), vec![&1, &2, &3]);
+
+        for value in values.iter_valid_mut() {
+            *value *= 10;
+        }
+
+        assert_eq!(values.retain(|&value| value != 20), 1);
+        assert_eq_str!(
+            serde_json::to_string(&values).unwrap(),
+            "{\"values\":[10,null,30]}"
+        );
+    }
+
+    #[test]
+    fn test_as_u64_lossy() {
+        assert_eq!(Value::I64(42).as_u64_lossy(), Some(42));
+        assert_eq!(Value::I32(-1).as_u64_lossy(), None);
+        assert_eq!(Value::F64(42.0).as_u64_lossy(), Some(42));
+        assert_eq!(Value::F64(42.5).as_u64_lossy(), None);
+        assert_eq!(Value::from(" 42 ").as_u64_lossy(), Some(42));
+        assert_eq!(Value::from("42a").as_u64_lossy(), None);
+        assert_eq!(Value::Bool(true).as_u64_lossy(), None);
+    }
+
+    #[test]
+    fn test_large_integers() {
+        use serde::de::value::Error;
+        use serde::de::IntoDeserializer;
+
+        let big = u128::from(u64::max_value()) + 1;
+        let value = Value::deserialize(IntoDeserializer::<Error>::into_deserializer(big)).unwrap();
+        assert_eq_dbg!(value, Value::BigNumber("18446744073709551616".to_string()));
+
+        let small = IntoDeserializer::<Error>::into_deserializer(-42i128);
+        assert_eq_dbg!(Value::deserialize(small).unwrap(), Value::I64(-42));
+    }
+
+    #[test]
+    fn test_large_integers_json() {
+        use protocol::Event;
+
+        let json = r#"{
+  "extra": {
+    "id": 18446744073709551616,
+    "ids": [1, -9223372036854775809],
+    "float": 1e20
+  }
+}"#;
+
+        let event = Annotated::<Event>::from_json(json).unwrap();
+        let extra = event.value().unwrap().extra.value().unwrap();
+        assert_eq_dbg!(
+            extra["id"].value(),
+            Some(&Value::BigNumber("18446744073709551616".to_string()))
+        );
+        assert_eq_dbg!(
+            extra["ids"].value(),
+            Some(&Value::Array(vec![
+                Annotated::from(Value::U64(1)),
+                Annotated::from(Value::BigNumber("-9223372036854775809".to_string())),
+            ]))
+        );
+        assert_eq_dbg!(extra["float"].value(), Some(&Value::F64(1e20)));
+
+        let serialized = event.to_json().unwrap();
+        assert!(serialized.contains(r#""id":18446744073709551616"#));
+        assert!(serialized.contains("-9223372036854775809]"));
+
+        let reparsed = Annotated::<Event>::from_json(&serialized).unwrap();
+        assert_eq_dbg!(reparsed, event);
+    }
+
+    #[test]
+    fn test_serialize_non_finite() {
+        assert_eq_str!(
+            serde_json::to_string(&Value::F64(::std::f64::NAN)).unwrap(),
+            "null"
+        );
+        assert_eq_str!(
+            serde_json::to_string(&Value::F32(::std::f32::NEG_INFINITY)).unwrap(),
+            "null"
+        );
+        assert_eq_str!(serde_json::to_string(&Value::F64(1.5)).unwrap(), "1.5");
+    }
+
+    #[test]
+    fn test_as_string_lossy() {
+        assert_eq!(
+            Value::from("foo").as_string_lossy(),
+            Some("foo".to_string())
+        );
+        assert_eq!(Value::U64(42).as_string_lossy(), Some("42".to_string()));
+        assert_eq!(
+            Value::Bool(false).as_string_lossy(),
+            Some("false".to_string())
+        );
+        assert_eq!(Value::Null.as_string_lossy(), None);
+        assert_eq!(Value::Array(vec![]).as_string_lossy(), None);
+    }
+
+    #[test]
+    fn test_as_bool_lossy() {
+        assert_eq!(Value::Bool(true).as_bool_lossy(), Some(true));
+        assert_eq!(Value::U32(0).as_bool_lossy(), Some(false));
+        assert_eq!(Value::U32(2).as_bool_lossy(), None);
+        assert_eq!(Value::from("Yes").as_bool_lossy(), Some(true));
+        assert_eq!(Value::from("off").as_bool_lossy(), Some(false));
+        assert_eq!(Value::from("maybe").as_bool_lossy(), None);
+    }
+
+    #[test]
+    fn test_coerce() {
+        let mut annotated = Annotated::from(Value::I32(42));
+        assert_eq!(annotated.coerce_u64(), Some(42));
+        assert_eq_dbg!(annotated, Annotated::from(Value::U64(42)));
+
+        let mut annotated = Annotated::from(Value::from("42"));
+        assert_eq!(annotated.coerce_u64(), Some(42));
+        assert_eq_dbg!(annotated.value(), Some(&Value::U64(42)));
+        assert_eq_dbg!(
+            annotated.meta().errors().collect::<Vec<_>>(),
+            vec!["coerced string to integer"]
+        );
+
+        let mut annotated = Annotated::from(Value::U64(1));
+        assert_eq!(annotated.coerce_string(), Some("1"));
+        assert_eq!(annotated.coerce_bool(), Some(true));
+        assert_eq_dbg!(annotated.value(), Some(&Value::Bool(true)));
+        assert_eq!(annotated.meta().errors().count(), 2);
+
+        let mut annotated = Annotated::from(Value::Null);
+        assert_eq!(annotated.coerce_bool(), None);
+        assert_eq_dbg!(annotated, Annotated::from(Value::Null));
+    }
+
+    #[test]
+    fn test_truncated_roundtrip() {
+        let json = "{\"values\":[1],\"truncated\":true}";
+        let values: Values<u32> = serde_json::from_str(json).unwrap();
+
+        assert_eq_dbg!(values.truncated.value(), Some(&Some(true)));
+        assert!(values.other.value().unwrap().is_empty());
+        assert_eq_str!(serde_json::to_string(&values).unwrap(), json);
+    }
+
+    #[test]
+    #[cfg(not(feature = "preserve_order"))]
+    fn test_map_order() {
+        let map: Map<Value> = serde_json::from_str(r#"{"b":1,"c":2,"a":3}"#).unwrap();
+        assert_eq_str!(
+            serde_json::to_string(&map).unwrap(),
+            r#"{"a":3,"b":1,"c":2}"#
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "preserve_order")]
+    fn test_map_order() {
+        let json = r#"{"b":1,"c":2,"a":3}"#;
+        let map: Map<Value> = serde_json::from_str(json).unwrap();
+        assert_eq_str!(serde_json::to_string(&map).unwrap(), json);
+    }
+
+    #[test]
+    fn test_remove_key() {
+        let mut map: Map<Value> = serde_json::from_str(r#"{"b":1,"c":2,"a":3}"#).unwrap();
+        assert_eq_dbg!(
+            remove_key(&mut map, "b"),
+            Some(Annotated::from(Value::U64(1)))
+        );
+        assert_eq_dbg!(remove_key(&mut map, "b"), None);
+
+        let keys: Vec<_> = map.keys().map(String::as_str).collect();
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(keys, vec!["c", "a"]);
+        } else {
+            assert_eq!(keys, vec!["a", "c"]);
+        }
+    }
 }
@@ -0,0 +1,285 @@
+//! Path level comparison of events before and after processing.
+
+use serde_json;
+
+use super::common::{remove_key, Value};
+use super::meta::{Annotated, Meta, Remark, RemarkType};
+use super::types::Event;
+
+/// The kind of change applied to a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldChange {
+    /// The value was not modified.
+    Unchanged,
+    /// The value was removed entirely.
+    Removed,
+    /// The value or parts of it were masked.
+    Masked,
+    /// The value or parts of it were substituted, pseudonymized or otherwise replaced.
+    Replaced,
+    /// The value was shortened.
+    Truncated,
+}
+
+/// A change to a single field of an event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldDiff {
+    /// Dot separated path to the field, for instance `"user.email"`.
+    pub path: String,
+    /// The kind of change.
+    pub change: FieldChange,
+    /// IDs of the rules that caused the change.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_ids: Vec<String>,
+}
+
+/// Compares an event before and after processing.
+///
+/// Returns one entry for every leaf value in the original event, in path order.  Changes are
+/// classified by the remarks that processing added to the meta data of the new value.  If an
+/// entire object or array was removed, only its path is reported.
+///
+/// Fails if either event cannot be serialized with its meta data.
+pub fn diff(
+    before: &Annotated<Event>,
+    after: &Annotated<Event>,
+) -> Result<Vec<FieldDiff>, serde_json::Error> {
+    let mut rv = vec![];
+    diff_values(
+        String::new(),
+        Some(&value_tree(before)?),
+        Some(&value_tree(after)?),
+        &mut rv,
+    );
+    Ok(rv)
+}
+
+/// Converts an event into a tree of values that retains meta data on every level.
+fn value_tree(event: &Annotated<Event>) -> Result<Annotated<Value>, serde_json::Error> {
+    let json = serde_json::to_value(SerializeWithMeta(event))?;
+    let mut tree = Annotated::<Value>::deserialize_with_meta(json)?;
+
+    if let Some(&mut Value::Map(ref mut map)) = tree.value_mut() {
+        remove_key(map, "_meta");
+    }
+
+    Ok(tree)
+}
+
+struct SerializeWithMeta<'a>(&'a Annotated<Event>);
+
+impl<'a> ::serde::Serialize for SerializeWithMeta<'a> {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_with_meta(serializer)
+    }
+}
+
+fn diff_values(
+    path: String,
+    before: Option<&Annotated<Value>>,
+    after: Option<&Annotated<Value>>,
+    rv: &mut Vec<FieldDiff>,
+) {
+    let before = match before {
+        Some(&Annotated(Some(ref value), ref meta)) if !is_null(value) => (value, meta),
+        _ => return,
+    };
+
+    let (after_value, after_meta) = match after {
+        Some(&Annotated(Some(ref value), ref meta)) if !is_null(value) => (Some(value), meta),
+        Some(&Annotated(_, ref meta)) => (None, meta),
+        None => (None, before.1),
+    };
+
+    let rule_ids = added_rules(before.1, after_meta);
+
+    match (before.0, after_value) {
+        (_, None) => rv.push(FieldDiff {
+            path,
+            change: FieldChange::Removed,
+            rule_ids,
+        }),
+        (&Value::Map(ref before_map), Some(&Value::Map(ref after_map))) => {
+            // maps may retain insertion order, so sort keys to report fields in path order
+            let mut keys: Vec<_> = before_map.keys().collect();
+            keys.sort();
+            for key in keys {
+                diff_values(
+                    join(&path, key),
+                    before_map.get(key),
+                    after_map.get(key),
+                    rv,
+                );
+            }
+        }
+        (&Value::Array(ref before_array), Some(&Value::Array(ref after_array))) => {
+            for (index, value) in before_array.iter().enumerate() {
+                let key = index.to_string();
+                diff_values(join(&path, &key), Some(value), after_array.get(index), rv);
+            }
+        }
+        (before_value, Some(after_value)) => {
+            let change = classify(before.1, after_meta);
+            let change = match change {
+                FieldChange::Unchanged if before_value != after_value => FieldChange::Replaced,
+                change => change,
+            };
+
+            rv.push(FieldDiff {
+                path,
+                change,
+                rule_ids,
+            });
+        }
+    }
+}
+
+/// Classifies the change to a value by the remarks added to its meta data.
+fn classify(before: &Meta, after: &Meta) -> FieldChange {
+    let mut change = FieldChange::Unchanged;
+
+    for remark in added_remarks(before, after) {
+        change = match remark.ty() {
+            RemarkType::Removed => return FieldChange::Removed,
+            RemarkType::Masked => FieldChange::Masked,
+            RemarkType::Substituted | RemarkType::Pseudonymized | RemarkType::Encrypted => {
+                match change {
+                    FieldChange::Masked => FieldChange::Masked,
+                    _ => FieldChange::Replaced,
+                }
+            }
+            RemarkType::Annotated => change,
+        };
+    }
+
+    if change == FieldChange::Unchanged
+        && after.original_length().is_some()
+        && before.original_length().is_none()
+    {
+        FieldChange::Truncated
+    } else {
+        change
+    }
+}
+
+fn added_remarks<'a>(before: &'a Meta, after: &'a Meta) -> impl Iterator<Item = &'a Remark> {
+    after
+        .remarks()
+        .filter(move |remark| !before.remarks.contains(remark))
+}
+
+fn added_rules(before: &Meta, after: &Meta) -> Vec<String> {
+    let mut rule_ids: Vec<String> = vec![];
+    for remark in added_remarks(before, after) {
+        let rule_id = remark.rule_id().to_string();
+        if !rule_ids.contains(&rule_id) {
+            rule_ids.push(rule_id);
+        }
+    }
+    rule_ids
+}
+
+fn is_null(value: &Value) -> bool {
+    match *value {
+        Value::Null => true,
+        _ => false,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(path: &str, change: FieldChange, rule_ids: &[&str]) -> FieldDiff {
+        FieldDiff {
+            path: path.to_string(),
+            change,
+            rule_ids: rule_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let event = Annotated::<Event>::from_json(r#"{"logger":"foo","extra":{"a":1}}"#).unwrap();
+        assert_eq_dbg!(
+            diff(&event, &event).unwrap(),
+            vec![
+                field("extra.a", FieldChange::Unchanged, &[]),
+                field("logger", FieldChange::Unchanged, &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = Annotated::<Event>::from_json(
+            r#"{
+  "extra": {
+    "email": "foo@example.com",
+    "password": "hunter2",
+    "token": "secret",
+    "long": "abcdefghij",
+    "list": [1, 2, 3]
+  }
+}"#,
+        ).unwrap();
+
+        let after = Annotated::<Event>::from_json(
+            r#"{
+  "extra": {
+    "email": "[email]",
+    "password": null,
+    "token": "******",
+    "long": "abc...",
+    "list": [1, 2]
+  },
+  "_meta": {
+    "extra": {
+      "email": {"": {"rem": [["@email", "s", 0, 7]], "len": 15}},
+      "password": {"": {"rem": [["@password", "x"]]}},
+      "token": {"": {"rem": [["@token", "m", 0, 6]]}},
+      "long": {"": {"len": 10}},
+      "list": {"": {"len": 3}}
+    }
+  }
+}"#,
+        ).unwrap();
+
+        assert_eq_dbg!(
+            diff(&before, &after).unwrap(),
+            vec![
+                field("extra.email", FieldChange::Replaced, &["@email"]),
+                field("extra.list.0", FieldChange::Unchanged, &[]),
+                field("extra.list.1", FieldChange::Unchanged, &[]),
+                field("extra.list.2", FieldChange::Removed, &[]),
+                field("extra.long", FieldChange::Truncated, &[]),
+                field("extra.password", FieldChange::Removed, &["@password"]),
+                field("extra.token", FieldChange::Masked, &["@token"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removed_object() {
+        let before =
+            Annotated::<Event>::from_json(r#"{"user":{"id":"1","email":"foo@example.com"}}"#)
+                .unwrap();
+        let after = Annotated::<Event>::from_json(
+            r#"{"user":null,"_meta":{"user":{"":{"rem":[["@user","x"]]}}}}"#,
+        ).unwrap();
+
+        assert_eq_dbg!(
+            diff(&before, &after).unwrap(),
+            vec![field("user", FieldChange::Removed, &["@user"])]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Arbitrary protocol values for property based testing and fuzzing.
+//!
+//! Values are generated as JSON payloads that resemble the shape of the respective interface.
+//! Every field has a chance of receiving a value of the wrong type, so that the generated
+//! values also exercise errors in meta data.  The payloads are deserialized just like real
+//! events, which means that `any::<Annotated<Event>>()` covers the same code paths as parsing
+//! an event from JSON.
+
+use proptest::prelude::*;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use super::meta::Annotated;
+use super::types::{Breadcrumb, Event, Exception, Frame, Request, Stacktrace, Thread, User};
+
+type JsonStrategy = BoxedStrategy<JsonValue>;
+
+/// Deserializes a generated payload.
+fn from_json_value<T: DeserializeOwned>(json: JsonValue) -> Annotated<T> {
+    Annotated::<T>::deserialize_with_meta(json)
+        .unwrap_or_else(|error| Annotated::from_error(error.to_string()))
+}
+
+/// Strings that are mostly readable and sometimes contain PII or arbitrary unicode.
+fn arb_text() -> BoxedStrategy<String> {
+    prop_oneof![
+        6 => "[a-zA-Z0-9 ._/@:=-]{0,24}",
+        1 => Just("foo@example.com".to_string()),
+        1 => Just("127.0.0.1".to_string()),
+        1 => Just("4111 1111 1111 1111".to_string()),
+        1 => Just("/Users/foo/bar.txt".to_string()),
+        1 => any::<String>(),
+    ].boxed()
+}
+
+/// Keys of maps and objects.
+fn arb_key() -> BoxedStrategy<String> {
+    prop_oneof![
+        4 => "[a-z_]{1,10}",
+        1 => Just("password".to_string()),
+        1 => any::<String>(),
+    ].boxed()
+}
+
+/// Arbitrary JSON values up to a small nesting depth.
+pub fn arb_json() -> JsonStrategy {
+    let leaf = prop_oneof![
+        Just(JsonValue::Null),
+        any::<bool>().prop_map(JsonValue::Bool),
+        any::<i64>().prop_map(JsonValue::from),
+        any::<u64>().prop_map(JsonValue::from),
+        any::<f64>().prop_map(JsonValue::from),
+        arb_text().prop_map(JsonValue::String),
+    ];
+
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(JsonValue::Array),
+            prop::collection::btree_map(arb_key(), inner, 0..4)
+                .prop_map(|map| JsonValue::Object(map.into_iter().collect())),
+        ]
+    }).boxed()
+}
+
+/// Mostly uses the given strategy, and sometimes arbitrary JSON instead.
+fn mixed(strategy: JsonStrategy) -> JsonStrategy {
+    prop_oneof![8 => strategy, 1 => arb_json()].boxed()
+}
+
+fn string() -> JsonStrategy {
+    mixed(arb_text().prop_map(JsonValue::String).boxed())
+}
+
+fn number() -> JsonStrategy {
+    mixed(any::<u32>().prop_map(JsonValue::from).boxed())
+}
+
+fn boolean() -> JsonStrategy {
+    mixed(any::<bool>().prop_map(JsonValue::Bool).boxed())
+}
+
+fn timestamp() -> JsonStrategy {
+    mixed(
+        prop_oneof![
+            (0..4_000_000_000u64).prop_map(JsonValue::from),
+            Just(JsonValue::from("2018-07-01T12:00:00Z")),
+        ].boxed(),
+    )
+}
+
+fn level() -> JsonStrategy {
+    mixed(
+        prop_oneof![
+            Just(JsonValue::from("debug")),
+            Just(JsonValue::from("info")),
+            Just(JsonValue::from("warning")),
+            Just(JsonValue::from("error")),
+            Just(JsonValue::from("fatal")),
+            (0..60u64).prop_map(JsonValue::from),
+        ].boxed(),
+    )
+}
+
+fn list(strategy: JsonStrategy, max: usize) -> JsonStrategy {
+    prop::collection::vec(strategy, 0..max)
+        .prop_map(JsonValue::Array)
+        .boxed()
+}
+
+/// Lists in both the plain and the `{"values": [...]}` notation.
+fn values(strategy: JsonStrategy, max: usize) -> JsonStrategy {
+    prop_oneof![
+        list(strategy.clone(), max),
+        list(strategy, max).prop_map(|list| {
+            let mut object = serde_json::Map::new();
+            object.insert("values".to_string(), list);
+            JsonValue::Object(object)
+        }),
+    ].boxed()
+}
+
+fn map_of(strategy: JsonStrategy) -> JsonStrategy {
+    mixed(
+        prop::collection::btree_map(arb_key(), strategy, 0..4)
+            .prop_map(|map| JsonValue::Object(map.into_iter().collect()))
+            .boxed(),
+    )
+}
+
+/// Generates objects in which each of the given fields is present with some probability.
+fn object(fields: Vec<(&'static str, JsonStrategy)>) -> JsonStrategy {
+    let fields: Vec<_> = fields
+        .into_iter()
+        .map(|(key, strategy)| {
+            prop::option::of(strategy).prop_map(move |value| value.map(|value| (key, value)))
+        })
+        .collect();
+
+    fields
+        .prop_map(|fields| {
+            JsonValue::Object(
+                fields
+                    .into_iter()
+                    .filter_map(|field| field)
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect(),
+            )
+        })
+        .boxed()
+}
+
+/// Payloads of the frame interface.
+pub fn arb_frame_json() -> JsonStrategy {
+    object(vec![
+        ("function", string()),
+        ("module", string()),
+        ("filename", string()),
+        ("abs_path", string()),
+        ("lineno", number()),
+        ("colno", number()),
+        ("in_app", boolean()),
+        ("context_line", string()),
+        ("pre_context", list(string(), 3)),
+        ("post_context", list(string(), 3)),
+        ("vars", map_of(arb_json())),
+        ("platform", string()),
+    ])
+}
+
+/// Payloads of the stack trace interface.
+pub fn arb_stacktrace_json() -> JsonStrategy {
+    object(vec![("frames", list(arb_frame_json(), 8))])
+}
+
+/// Payloads of the exception interface.
+pub fn arb_exception_json() -> JsonStrategy {
+    object(vec![
+        ("type", string()),
+        ("value", string()),
+        ("module", string()),
+        ("stacktrace", arb_stacktrace_json()),
+        ("thread_id", number()),
+        (
+            "mechanism",
+            object(vec![
+                ("type", string()),
+                ("handled", boolean()),
+                ("data", map_of(arb_json())),
+            ]),
+        ),
+    ])
+}
+
+/// Payloads of the breadcrumb interface.
+pub fn arb_breadcrumb_json() -> JsonStrategy {
+    object(vec![
+        ("timestamp", timestamp()),
+        ("type", string()),
+        ("category", string()),
+        ("level", level()),
+        ("message", string()),
+        ("data", map_of(arb_json())),
+    ])
+}
+
+/// Payloads of the request interface.
+pub fn arb_request_json() -> JsonStrategy {
+    object(vec![
+        ("url", string()),
+        ("method", string()),
+        ("data", arb_json()),
+        ("query_string", prop_oneof![string(), map_of(string())].boxed()),
+        ("cookies", prop_oneof![string(), map_of(string())].boxed()),
+        ("headers", map_of(string())),
+        ("env", map_of(arb_json())),
+    ])
+}
+
+/// Payloads of the user interface.
+pub fn arb_user_json() -> JsonStrategy {
+    object(vec![
+        ("id", string()),
+        ("email", string()),
+        ("ip_address", string()),
+        ("username", string()),
+        ("data", map_of(arb_json())),
+    ])
+}
+
+/// Payloads of the thread interface.
+pub fn arb_thread_json() -> JsonStrategy {
+    object(vec![
+        ("id", number()),
+        ("name", string()),
+        ("crashed", boolean()),
+        ("current", boolean()),
+        ("stacktrace", arb_stacktrace_json()),
+    ])
+}
+
+/// Payloads of entire events.
+pub fn arb_event_json() -> JsonStrategy {
+    object(vec![
+        ("event_id", mixed("[0-9a-f]{32}".prop_map(JsonValue::String).boxed())),
+        ("level", level()),
+        ("platform", string()),
+        ("message", string()),
+        ("logger", string()),
+        ("release", string()),
+        ("environment", string()),
+        ("timestamp", timestamp()),
+        ("tags", map_of(string())),
+        ("extra", map_of(arb_json())),
+        ("modules", map_of(string())),
+        ("exception", values(arb_exception_json(), 3)),
+        ("threads", values(arb_thread_json(), 3)),
+        ("breadcrumbs", values(arb_breadcrumb_json(), 8)),
+        ("stacktrace", arb_stacktrace_json()),
+        ("request", arb_request_json()),
+        ("user", arb_user_json()),
+    ])
+}
+
+macro_rules! impl_arbitrary {
+    ($ty:ty, $strategy:ident) => {
+        impl Arbitrary for Annotated<$ty> {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Annotated<$ty>>;
+
+            fn arbitrary_with(_args: ()) -> Self::Strategy {
+                $strategy().prop_map(from_json_value).boxed()
+            }
+        }
+    };
+}
+
+impl_arbitrary!(Event, arb_event_json);
+impl_arbitrary!(Exception, arb_exception_json);
+impl_arbitrary!(Stacktrace, arb_stacktrace_json);
+impl_arbitrary!(Frame, arb_frame_json);
+impl_arbitrary!(Breadcrumb, arb_breadcrumb_json);
+impl_arbitrary!(Request, arb_request_json);
+impl_arbitrary!(User, arb_user_json);
+impl_arbitrary!(Thread, arb_thread_json);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use processor::PiiConfig;
+
+    static PII_CONFIG: &str = r#"{
+  "applications": {
+    "freeform": ["@email", "@ip", "@creditcard", "@userpath"],
+    "databag": ["@password", "@email"],
+    "ip": ["@ip"],
+    "email": ["@email"],
+    "sensitive": ["@anything"]
+  }
+}"#;
+
+    proptest! {
+        #[test]
+        fn test_roundtrip_stable(event in any::<Annotated<Event>>()) {
+            let json = event.to_json().unwrap();
+            let reparsed = Annotated::<Event>::from_json(&json).unwrap();
+            prop_assert_eq!(reparsed.to_json().unwrap(), json);
+        }
+
+        #[test]
+        fn test_processing(event in any::<Annotated<Event>>()) {
+            let config = PiiConfig::from_json(PII_CONFIG).unwrap();
+            let processed = config.processor().process_root_value(event);
+            prop_assert!(processed.to_json().is_ok());
+        }
+    }
+}
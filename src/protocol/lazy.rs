@@ -0,0 +1,286 @@
+//! Deferred deserialization of large values.
+
+use std::cell::{Ref, RefCell};
+use std::fmt;
+use std::mem;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json;
+
+use super::meta::{Annotated, DeferredMeta};
+
+/// The raw payload of a value that has not been deserialized yet.
+#[derive(Clone, Debug)]
+struct Deferred {
+    raw: serde_json::Value,
+    meta: DeferredMeta,
+}
+
+impl Deferred {
+    fn deserialize<T: DeserializeOwned>(self) -> Annotated<T> {
+        let Deferred { raw, meta } = self;
+        meta.deserialize(raw)
+            .unwrap_or_else(|error| Annotated::from_error(error.to_string()))
+    }
+}
+
+/// The state of a lazy value.
+#[derive(Clone)]
+enum State<T> {
+    Deferred(Deferred),
+    Resolved(Annotated<T>),
+}
+
+/// A value that is deserialized on first access.
+///
+/// During deserialization, the raw payload and all meta data below the value are retained
+/// without parsing them into `T`.  The value is deserialized once it is accessed, compared,
+/// serialized or processed.  Consumers that never look at the value skip the cost of parsing
+/// it entirely.
+///
+/// `Event` always deserializes its interfaces.  To skip parsing heavy interfaces on a hot path,
+/// deserialize a partial view of the event that wraps those interfaces in `Lazy`:
+///
+/// ```
+/// # extern crate marshal;
+/// # #[macro_use] extern crate serde_derive;
+/// use marshal::protocol::{Annotated, Breadcrumb, Lazy, Level, Values};
+///
+/// #[derive(Deserialize)]
+/// struct Route {
+///     level: Annotated<Level>,
+///     breadcrumbs: Annotated<Lazy<Values<Breadcrumb>>>,
+/// }
+///
+/// # fn main() {
+/// let json = r#"{"level":"error","breadcrumbs":{"values":[{"message":"hello"}]}}"#;
+/// let route = Annotated::<Route>::from_json(json).unwrap();
+/// let breadcrumbs = route.value().unwrap().breadcrumbs.value().unwrap();
+/// assert!(!breadcrumbs.is_resolved());
+/// # }
+/// ```
+///
+/// Errors that occur while deserializing the value are reported in the meta data of the
+/// annotated value returned by `Annotated::<Lazy<T>>::resolve`.
+pub struct Lazy<T> {
+    state: RefCell<State<T>>,
+}
+
+impl<T> Lazy<T> {
+    /// Creates a lazy value that has already been deserialized.
+    pub fn new(value: T) -> Lazy<T> {
+        Lazy {
+            state: RefCell::new(State::Resolved(Annotated::from(value))),
+        }
+    }
+
+    /// Returns `true` if the value has been deserialized.
+    pub fn is_resolved(&self) -> bool {
+        match *self.state.borrow() {
+            State::Resolved(_) => true,
+            State::Deferred(_) => false,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Lazy<T> {
+    /// Deserializes the value in place if it has not been deserialized yet.
+    fn resolve_in_place(&self) {
+        if self.is_resolved() {
+            return;
+        }
+
+        let mut state = self.state.borrow_mut();
+        let annotated = match mem::replace(&mut *state, State::Resolved(Annotated::empty())) {
+            State::Deferred(deferred) => deferred.deserialize(),
+            State::Resolved(annotated) => annotated,
+        };
+        *state = State::Resolved(annotated);
+    }
+
+    /// Returns the annotated value, deserializing it if necessary.
+    fn annotated(&self) -> Ref<Annotated<T>> {
+        self.resolve_in_place();
+        Ref::map(self.state.borrow(), |state| match *state {
+            State::Resolved(ref annotated) => annotated,
+            State::Deferred(_) => unreachable!("lazy value was not resolved"),
+        })
+    }
+
+    /// Returns the value, deserializing it if necessary.
+    ///
+    /// Returns `None` if the value could not be deserialized.
+    pub fn get(&self) -> Option<Ref<T>> {
+        let annotated = self.annotated();
+        if annotated.value().is_none() {
+            return None;
+        }
+
+        Some(Ref::map(annotated, |annotated| annotated.value().unwrap()))
+    }
+
+    /// Returns the value mutably, deserializing it if necessary.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.resolve_in_place();
+        match *self.state.get_mut() {
+            State::Resolved(ref mut annotated) => annotated.value_mut(),
+            State::Deferred(_) => unreachable!("lazy value was not resolved"),
+        }
+    }
+
+    /// Deserializes the value and returns it along with deserialization errors.
+    pub fn into_annotated(self) -> Annotated<T> {
+        match self.state.into_inner() {
+            State::Deferred(deferred) => deferred.deserialize(),
+            State::Resolved(annotated) => annotated,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Annotated<Lazy<T>> {
+    /// Deserializes the lazy value and merges deserialization errors into the meta data.
+    pub fn resolve(self) -> Annotated<T> {
+        match self {
+            Annotated(Some(lazy), mut meta) => {
+                let Annotated(value, inner) = lazy.into_annotated();
+                meta.merge(inner);
+                Annotated(value, meta)
+            }
+            Annotated(None, meta) => Annotated(None, meta),
+        }
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Lazy<T> {
+        Lazy::new(T::default())
+    }
+}
+
+impl<T: Clone> Clone for Lazy<T> {
+    fn clone(&self) -> Lazy<T> {
+        Lazy {
+            state: RefCell::new(self.state.borrow().clone()),
+        }
+    }
+}
+
+impl<T: DeserializeOwned + fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Lazy").field(&*self.annotated()).finish()
+    }
+}
+
+impl<T: DeserializeOwned + PartialEq> PartialEq for Lazy<T> {
+    fn eq(&self, other: &Lazy<T>) -> bool {
+        *self.annotated() == *other.annotated()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let meta = DeferredMeta::capture(&deserializer);
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        Ok(Lazy {
+            state: RefCell::new(State::Deferred(Deferred { raw, meta })),
+        })
+    }
+}
+
+impl<T: DeserializeOwned + Serialize> Serialize for Lazy<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.annotated().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::*;
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    struct Route {
+        level: Annotated<Level>,
+        breadcrumbs: Annotated<Lazy<Values<Breadcrumb>>>,
+    }
+
+    #[test]
+    fn test_deferred() {
+        let route = Annotated::<Route>::from_json(
+            r#"{
+  "level": "error",
+  "breadcrumbs": {"values": [{"timestamp": 42, "message": "hello"}]}
+}"#,
+        ).unwrap();
+
+        let route = route.value().unwrap();
+        assert_eq!(route.level.value(), Some(&Level::Error));
+
+        let breadcrumbs = route.breadcrumbs.value().unwrap();
+        assert!(!breadcrumbs.is_resolved());
+
+        assert_eq!(breadcrumbs.get().unwrap().values.value().unwrap().len(), 1);
+        assert!(breadcrumbs.is_resolved());
+    }
+
+    #[test]
+    fn test_deferred_meta() {
+        let route = Annotated::<Route>::from_json(
+            r#"{
+  "breadcrumbs": {"values": [{"timestamp": 42, "message": null}]},
+  "_meta": {
+    "breadcrumbs": {"values": {"0": {"message": {"": {"err": ["some error"]}}}}}
+  }
+}"#,
+        ).unwrap();
+
+        let breadcrumbs = route.0.unwrap().breadcrumbs.resolve();
+        let breadcrumb = &breadcrumbs.value().unwrap().values.value().unwrap()[0];
+        let message = &breadcrumb.value().unwrap().message;
+        assert_eq!(message.meta().errors().collect::<Vec<_>>(), vec!["some error"]);
+        assert_eq!(message.meta().path(), Some("breadcrumbs.values.0.message"));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let json = r#"{"values":[{"timestamp":42,"message":"hello"}]}"#;
+        let mut lazy: Annotated<Lazy<Values<Breadcrumb>>> = Annotated::from_json(json).unwrap();
+
+        let values = lazy.value_mut().unwrap().get_mut().unwrap();
+        values.values.value_mut().unwrap().clear();
+
+        let lazy = lazy.value().unwrap();
+        assert!(lazy.is_resolved());
+        assert!(lazy.get().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone() {
+        let json = r#"{"values":[{"timestamp":42,"message":"hello"}]}"#;
+        let lazy: Annotated<Lazy<Values<Breadcrumb>>> = Annotated::from_json(json).unwrap();
+        let lazy = lazy.value().unwrap();
+
+        let cloned = lazy.clone();
+        assert!(!cloned.is_resolved());
+        assert_eq!(&cloned, lazy);
+        assert!(cloned.is_resolved());
+        assert!(lazy.is_resolved());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let lazy: Annotated<Lazy<Breadcrumb>> = Annotated::from_json(r#""invalid""#).unwrap();
+        let resolved = lazy.resolve();
+        assert!(resolved.value().is_none());
+        assert_eq!(resolved.meta().errors().count(), 1);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let json = r#"{"values":[{"timestamp":42,"type":"default","level":"info","message":"hello"}]}"#;
+        let lazy: Annotated<Lazy<Values<Breadcrumb>>> = Annotated::from_json(json).unwrap();
+        assert_eq_str!(lazy.to_json().unwrap(), json);
+    }
+}
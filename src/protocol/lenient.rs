@@ -0,0 +1,350 @@
+//! Lenient parsing of JSON documents emitted by non-conforming producers.
+
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::str::CharIndices;
+
+/// Literals for non-finite numbers, as emitted by Python's `json` module and some JavaScript
+/// serializers.  `-Infinity` is listed first so that it is not matched as `Infinity`.
+const NON_FINITE_LITERALS: &[&str] = &["-Infinity", "Infinity", "NaN"];
+
+/// The minimum number of digits of integers that do not fit into 64 bits.
+const BIG_INTEGER_DIGITS: usize = 19;
+
+/// A key or index on the path to a value in a JSON document.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PathSegment {
+    /// A key in an object.
+    Key(String),
+    /// An index in an array.
+    Index(usize),
+}
+
+/// A segment of the path to the current position in a JSON document.
+enum Segment {
+    /// A key in an object, if it has been read completely.
+    Key(Option<String>),
+    /// An index in an array.
+    Index(usize),
+}
+
+/// Scans a JSON document and tracks the path to the current position.
+///
+/// Iterating yields all characters outside of strings along with their byte offset, including
+/// the structural characters that update the path.  The document does not need to be valid JSON.
+struct JsonScanner<'a> {
+    chars: CharIndices<'a>,
+    stack: Vec<Segment>,
+    string: String,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(source: &'a str) -> Self {
+        JsonScanner {
+            chars: source.char_indices(),
+            stack: vec![],
+            string: String::new(),
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Skips the given number of characters, which must not contain structural characters.
+    fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.chars.next();
+        }
+    }
+
+    /// Returns the keys and indexes leading to the current position.
+    fn segments(&self) -> Vec<PathSegment> {
+        self.stack
+            .iter()
+            .filter_map(|segment| match *segment {
+                Segment::Key(ref key) => key.clone().map(PathSegment::Key),
+                Segment::Index(index) => Some(PathSegment::Index(index)),
+            })
+            .collect()
+    }
+
+    /// Returns the path of the current position like the paths in `MetaMap`.
+    ///
+    /// Returns `None` at the root of the document.
+    fn path(&self) -> Option<String> {
+        let segments: Vec<_> = self
+            .segments()
+            .into_iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => key,
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect();
+
+        if segments.is_empty() {
+            None
+        } else {
+            Some(segments.join("."))
+        }
+    }
+}
+
+impl<'a> Iterator for JsonScanner<'a> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, c)) = self.chars.next() {
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                    self.string.push(c);
+                } else if c == '\\' {
+                    self.escaped = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                } else {
+                    self.string.push(c);
+                }
+                continue;
+            }
+
+            match c {
+                '"' => {
+                    self.in_string = true;
+                    self.string.clear();
+                }
+                '{' => self.stack.push(Segment::Key(None)),
+                '[' => self.stack.push(Segment::Index(0)),
+                '}' | ']' => {
+                    self.stack.pop();
+                }
+                ':' => {
+                    if let Some(&mut Segment::Key(ref mut key)) = self.stack.last_mut() {
+                        *key = Some(self.string.clone());
+                    }
+                }
+                ',' => match self.stack.last_mut() {
+                    Some(&mut Segment::Key(ref mut key)) => *key = None,
+                    Some(&mut Segment::Index(ref mut index)) => *index += 1,
+                    None => (),
+                },
+                _ => (),
+            }
+
+            return Some((index, c));
+        }
+
+        None
+    }
+}
+
+/// Returns the path of the value at the given line and column of a JSON document.
+///
+/// The document is scanned up to the location, which does not need to be valid JSON.  Returns
+/// `None` for locations at the root of the document.
+pub fn json_path_at(source: &str, line: usize, column: usize) -> Option<String> {
+    if line == 0 {
+        return None;
+    }
+
+    let line_start: usize = source
+        .split('\n')
+        .take(line - 1)
+        .map(|text| text.len() + 1)
+        .sum();
+    // the column points at the offending character, which is excluded
+    let mut offset = cmp::min(line_start + column.saturating_sub(1), source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let mut scanner = JsonScanner::new(&source[..offset]);
+    while scanner.next().is_some() {}
+    scanner.path()
+}
+
+/// Replaces `NaN`, `Infinity` and `-Infinity` literals outside of strings with `null`.
+///
+/// Returns the resulting document along with the path and literal of every replaced value.  If
+/// the document does not contain any of these literals, it is returned unchanged.
+pub fn replace_non_finite(source: &str) -> (Cow<str>, Vec<(String, &'static str)>) {
+    let mut output = String::new();
+    let mut copied = 0;
+    let mut replaced = vec![];
+
+    let mut scanner = JsonScanner::new(source);
+    while let Some((index, c)) = scanner.next() {
+        if c != 'N' && c != 'I' && c != '-' {
+            continue;
+        }
+
+        let rest = &source[index..];
+        if let Some(literal) = NON_FINITE_LITERALS.iter().find(|l| rest.starts_with(**l)) {
+            output.push_str(&source[copied..index]);
+            output.push_str("null");
+            copied = index + literal.len();
+
+            let path = scanner.path().unwrap_or_else(|| ".".to_string());
+            replaced.push((path, *literal));
+
+            // all literals are ASCII, so every byte is a char
+            scanner.skip(literal.len() - 1);
+        }
+    }
+
+    if replaced.is_empty() {
+        return (Cow::Borrowed(source), replaced);
+    }
+
+    output.push_str(&source[copied..]);
+    (Cow::Owned(output), replaced)
+}
+
+/// Integer literals of a JSON document that do not fit into 64 bits, keyed by their path.
+///
+/// JSON parsers read such literals as floats, which loses precision.  These are stored in the
+/// state of the deserializer, so that `Value` can retain the original digits in a
+/// `Value::BigNumber`.  Paths are stored as segments, since keys may contain dots.
+#[derive(Debug, Default)]
+pub struct BigIntegers(BTreeMap<Vec<PathSegment>, String>);
+
+impl BigIntegers {
+    /// Finds all integer literals outside of strings that do not fit into 64 bits, but fit into
+    /// 128 bits.
+    pub fn find(source: &str) -> BigIntegers {
+        let mut integers = BTreeMap::new();
+        if !has_digit_run(source, BIG_INTEGER_DIGITS) {
+            return BigIntegers(integers);
+        }
+
+        let mut scanner = JsonScanner::new(source);
+        while let Some((index, c)) = scanner.next() {
+            if c != '-' && !c.is_ascii_digit() {
+                continue;
+            }
+
+            // the first char is ASCII, so the rest starts at a char boundary
+            let rest = &source[index..];
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |end| end + 1);
+            let literal = &rest[..len];
+            let is_float = rest[len..].starts_with(|c| c == '.' || c == 'e' || c == 'E');
+
+            if len >= BIG_INTEGER_DIGITS
+                && !is_float
+                && literal.parse::<i64>().is_err()
+                && literal.parse::<u64>().is_err()
+                && (literal.parse::<i128>().is_ok() || literal.parse::<u128>().is_ok())
+            {
+                integers.insert(scanner.segments(), literal.to_string());
+            }
+
+            // all digits are ASCII, so every byte is a char
+            scanner.skip(len - 1);
+        }
+
+        BigIntegers(integers)
+    }
+
+    /// Returns `true` if the document does not contain any big integers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the digits of the big integer at the given path.
+    pub fn get(&self, path: &[PathSegment]) -> Option<&str> {
+        self.0.get(path).map(String::as_str)
+    }
+}
+
+/// Checks whether the source contains at least `len` consecutive ASCII digits.
+fn has_digit_run(source: &str, len: usize) -> bool {
+    let mut run = 0;
+    for byte in source.bytes() {
+        if byte.is_ascii_digit() {
+            run += 1;
+            if run >= len {
+                return true;
+            }
+        } else {
+            run = 0;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_non_finite() {
+        let (json, replaced) =
+            replace_non_finite(r#"{"a": NaN, "b": [1, -Infinity], "c": "NaN", "d": -1.5}"#);
+        assert_eq_str!(
+            json,
+            r#"{"a": null, "b": [1, null], "c": "NaN", "d": -1.5}"#
+        );
+        assert_eq_dbg!(
+            replaced,
+            vec![("a".to_string(), "NaN"), ("b.1".to_string(), "-Infinity")]
+        );
+
+        let (json, replaced) = replace_non_finite("Infinity");
+        assert_eq_str!(json, "null");
+        assert_eq_dbg!(replaced, vec![(".".to_string(), "Infinity")]);
+    }
+
+    #[test]
+    fn test_valid_json_unchanged() {
+        let source = r#"{"NaN": "-Infinity", "x": -1}"#;
+        let (json, replaced) = replace_non_finite(source);
+        assert!(replaced.is_empty());
+        match json {
+            Cow::Borrowed(json) => assert_eq_str!(json, source),
+            Cow::Owned(_) => panic!("expected the source to be borrowed"),
+        }
+    }
+
+    fn key(key: &str) -> PathSegment {
+        PathSegment::Key(key.to_string())
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let json = r#"{"a": 18446744073709551616, "b": [1, -9223372036854775809], "c": "1"}"#;
+        let integers = BigIntegers::find(json);
+        assert_eq!(integers.get(&[key("a")]), Some("18446744073709551616"));
+        assert_eq!(
+            integers.get(&[key("b"), PathSegment::Index(1)]),
+            Some("-9223372036854775809")
+        );
+        assert_eq!(integers.get(&[key("b"), PathSegment::Index(0)]), None);
+        assert_eq!(integers.get(&[key("c")]), None);
+
+        let json = r#"{"a": 18446744073709551615, "b": -9223372036854775808, "c": 1e100}"#;
+        assert!(BigIntegers::find(json).is_empty());
+
+        let json = r#"{"a": 10000000000000000000.5, "b": "100000000000000000000"}"#;
+        assert!(BigIntegers::find(json).is_empty());
+
+        let integers = BigIntegers::find("100000000000000000000");
+        assert_eq!(integers.get(&[]), Some("100000000000000000000"));
+
+        // beyond 128 bits, the lossy float is kept
+        let json = r#"{"a": 1000000000000000000000000000000000000000}"#;
+        assert!(BigIntegers::find(json).is_empty());
+    }
+
+    #[test]
+    fn test_big_integers_dotted_keys() {
+        let json = r#"{"a.b": 18446744073709551616, "a": {"b": 1}}"#;
+        let integers = BigIntegers::find(json);
+        assert_eq!(integers.get(&[key("a.b")]), Some("18446744073709551616"));
+        assert_eq!(integers.get(&[key("a"), key("b")]), None);
+    }
+}
@@ -2,17 +2,22 @@
 
 use std::borrow;
 use std::cell::RefCell;
+use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::io;
 use std::iter::FromIterator;
+use std::mem;
 use std::rc::Rc;
+use std::str;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use serde::de::{self, Deserialize, Deserializer, IgnoredAny};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, IgnoredAny};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 use serde_json;
 
 use super::buffer::{Content, ContentDeserializer, ContentRefDeserializer, ContentRepr};
+use super::lenient::{replace_non_finite, BigIntegers};
 use super::meta_ser::{serialize_annotated_meta, MetaError, MetaSerializer, MetaTree};
 use super::serde::{CustomDeserialize, CustomSerialize, DefaultDeserialize, DefaultSerialize};
 use super::tracked::{Path, TrackedDeserializer};
@@ -20,6 +25,25 @@ use super::tracked::{Path, TrackedDeserializer};
 /// Internal synchronization for meta data serialization.
 thread_local!(static SERIALIZE_META: AtomicBool = AtomicBool::new(false));
 
+/// The version of the meta data format written to the `_meta` key.
+///
+/// Meta data is serialized into a tree of objects mirroring the structure of the value, where
+/// the empty key `""` holds the meta data of the value at that path.  Each entry is an object
+/// with the optional keys:
+///
+///  - `rem`: remarks, each written as `[rule_id, type, start, end, note]`.  Range and note are
+///    trailing and omitted if missing; an empty range is written as `null, null` before a note.
+///  - `err`: a list of error messages.
+///  - `len`: the original length of a modified value.
+///  - `src`: the provenance of the value, omitted for values sent by the SDK.
+///
+/// This version is incremented whenever the serialized shape changes in a way that existing
+/// consumers cannot read:
+///
+///  - Version 2 added the note of remarks.  Consumers of version 1 fail to read remarks with a
+///    note but no range, since they expect numbers after the remark type.
+pub const META_FORMAT_VERSION: u32 = 2;
+
 /// The start (inclusive) and end (exclusive) indices of a `Remark`.
 pub type Range = (usize, usize);
 
@@ -52,6 +76,7 @@ pub struct Remark {
     ty: RemarkType,
     rule_id: String,
     range: Option<Range>,
+    note: Option<String>,
 }
 
 impl Remark {
@@ -61,6 +86,7 @@ impl Remark {
             rule_id: rule_id.into(),
             ty,
             range: None,
+            note: None,
         }
     }
 
@@ -70,14 +96,26 @@ impl Remark {
             rule_id: rule_id.into(),
             ty,
             range: Some(range),
+            note: None,
         }
     }
 
-    /// The note of this remark.
+    /// Attaches a note with details on the modification, such as the names of redacted fields.
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// The rule id of this remark.
     pub fn rule_id(&self) -> &str {
         &self.rule_id
     }
 
+    /// The note of this remark, if any.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_ref().map(|x| x.as_str())
+    }
+
     /// The range of this remark.
     pub fn range(&self) -> Option<&Range> {
         self.range.as_ref()
@@ -115,8 +153,9 @@ impl<'de> de::Visitor<'de> for RemarkVisitor {
         let ty = seq
             .next_element()?
             .ok_or_else(|| de::Error::custom("missing required remark-type"))?;
-        let start = seq.next_element()?;
-        let end = seq.next_element()?;
+        let start = seq.next_element::<Option<usize>>()?.and_then(|x| x);
+        let end = seq.next_element::<Option<usize>>()?.and_then(|x| x);
+        let note = seq.next_element::<Option<String>>()?.and_then(|x| x);
 
         // Drain the sequence
         while let Some(IgnoredAny) = seq.next_element()? {}
@@ -126,7 +165,12 @@ impl<'de> de::Visitor<'de> for RemarkVisitor {
             _ => None,
         };
 
-        Ok(Remark { ty, rule_id, range })
+        Ok(Remark {
+            ty,
+            rule_id,
+            range,
+            note,
+        })
     }
 }
 
@@ -144,11 +188,43 @@ impl Serialize for Remark {
         if let Some(range) = self.range() {
             seq.serialize_element(&range.0)?;
             seq.serialize_element(&range.1)?;
+        } else if self.note.is_some() {
+            // the note is positional, so an empty range has to be written before it
+            seq.serialize_element(&None::<usize>)?;
+            seq.serialize_element(&None::<usize>)?;
+        }
+        if let Some(note) = self.note() {
+            seq.serialize_element(note)?;
         }
         seq.end()
     }
 }
 
+/// The origin of a value in the event payload.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provenance {
+    /// The value was sent by the SDK.
+    Sdk,
+    /// The value was set by the server, for instance the time an event was received.
+    Server,
+    /// The value was derived from other values in the event during normalization.
+    Inferred,
+}
+
+impl Provenance {
+    /// Returns `true` if the value was sent by the SDK.
+    pub fn is_sdk(&self) -> bool {
+        *self == Provenance::Sdk
+    }
+}
+
+impl Default for Provenance {
+    fn default() -> Provenance {
+        Provenance::Sdk
+    }
+}
+
 /// Meta information for a data field in the event payload.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Meta {
@@ -164,6 +240,10 @@ pub struct Meta {
     #[serde(default, skip_serializing_if = "Option::is_none", rename = "len")]
     pub original_length: Option<u32>,
 
+    /// The origin of the value, if it was not sent by the SDK.
+    #[serde(default, skip_serializing_if = "Provenance::is_sdk", rename = "src")]
+    pub provenance: Provenance,
+
     /// Path at which the annotated value was deserialized.
     #[serde(skip)]
     pub path: Option<String>,
@@ -174,6 +254,7 @@ impl PartialEq for Meta {
         self.remarks == other.remarks
             && self.errors == other.errors
             && self.original_length == other.original_length
+            && self.provenance == other.provenance
     }
 }
 
@@ -184,6 +265,7 @@ impl Meta {
             remarks: Vec::new(),
             errors: vec![message.into()],
             original_length: None,
+            provenance: Provenance::Sdk,
             path: None,
         }
     }
@@ -233,9 +315,22 @@ impl Meta {
         self.has_errors() || self.has_remarks()
     }
 
+    /// The origin of the value.
+    pub fn provenance(&self) -> Provenance {
+        self.provenance
+    }
+
+    /// Marks the origin of the value.
+    pub fn set_provenance(&mut self, provenance: Provenance) {
+        self.provenance = provenance;
+    }
+
     /// Indicates whether this field has meta data attached.
     pub fn is_empty(&self) -> bool {
-        self.original_length.is_none() && self.remarks.is_empty() && self.errors.is_empty()
+        self.original_length.is_none()
+            && self.remarks.is_empty()
+            && self.errors.is_empty()
+            && self.provenance.is_sdk()
     }
 
     /// The path at which the annotated value was deserialized.
@@ -243,6 +338,37 @@ impl Meta {
         self.path.as_ref().map(|x| x.as_str())
     }
 
+    /// Merges meta data from another processing pass into this one.
+    ///
+    /// Remarks and errors of `other` are appended unless they are already present, so that
+    /// repeated passes accumulate annotations without duplicating them.  If both contain an
+    /// original length, the one in `self` is retained since it refers to the earlier value.
+    pub fn merge(&mut self, other: Meta) {
+        for remark in other.remarks {
+            if !self.remarks.contains(&remark) {
+                self.remarks.push(remark);
+            }
+        }
+
+        for error in other.errors {
+            if !self.errors.contains(&error) {
+                self.errors.push(error);
+            }
+        }
+
+        if self.original_length.is_none() {
+            self.original_length = other.original_length;
+        }
+
+        if self.provenance.is_sdk() {
+            self.provenance = other.provenance;
+        }
+
+        if self.path.is_none() {
+            self.path = other.path;
+        }
+    }
+
     /// Sets the path at which the annotated value was deserialized.
     fn set_path(&mut self, path: Option<Rc<Path>>) {
         self.path = path.map(|x| x.to_string())
@@ -255,6 +381,7 @@ impl Default for Meta {
             remarks: Vec::new(),
             errors: Vec::new(),
             original_length: None,
+            provenance: Provenance::Sdk,
             path: None,
         }
     }
@@ -268,6 +395,17 @@ impl<'de, T: Deserialize<'de>> Annotated<T> {
     /// Deserializes an annotated from a deserializer
     pub fn deserialize_with_meta<D: Deserializer<'de>>(
         deserializer: D,
+    ) -> Result<Annotated<T>, D::Error> {
+        Self::deserialize_with_extra_meta(deserializer, vec![], BigIntegers::default())
+    }
+
+    /// Deserializes an annotated and merges the given meta data with the `_meta` key.
+    ///
+    /// Big integers found in the source document are retained in values at their paths.
+    fn deserialize_with_extra_meta<D: Deserializer<'de>>(
+        deserializer: D,
+        extra_meta: Vec<(String, Meta)>,
+        integers: BigIntegers,
     ) -> Result<Annotated<T>, D::Error> {
         #[derive(Debug, Deserialize)]
         struct MetaDeserializeHelper {
@@ -278,17 +416,80 @@ impl<'de, T: Deserialize<'de>> Annotated<T> {
         let content = Content::deserialize(deserializer)?;
         let helper = MetaDeserializeHelper::deserialize(ContentRefDeserializer::new(&content))?;
         let meta_map = helper.meta.unwrap_or_default();
-        deserialize_meta(ContentDeserializer::new(content), meta_map)
+        for (path, meta) in extra_meta {
+            meta_map.merge(path, meta);
+        }
+        deserialize_tracked(ContentDeserializer::new(content), meta_map, integers)
     }
 
     /// Deserializes an annotated from a JSON string.
+    ///
+    /// Integers that do not fit into 64 bits are deserialized into `Value::BigNumber` to retain
+    /// their precision.
     pub fn from_json(s: &'de str) -> Result<Annotated<T>, serde_json::Error> {
-        Self::deserialize_with_meta(&mut serde_json::Deserializer::from_str(s))
+        Self::deserialize_with_extra_meta(
+            &mut serde_json::Deserializer::from_str(s),
+            vec![],
+            BigIntegers::find(s),
+        )
     }
 
     /// Deserializes an annotated from JSON bytes.
+    ///
+    /// Like `from_json`, this retains the digits of integers that do not fit into 64 bits.
     pub fn from_json_bytes(b: &'de [u8]) -> Result<Annotated<T>, serde_json::Error> {
-        Self::deserialize_with_meta(&mut serde_json::Deserializer::from_slice(b))
+        let integers = str::from_utf8(b).map(BigIntegers::find).unwrap_or_default();
+        Self::deserialize_with_extra_meta(
+            &mut serde_json::Deserializer::from_slice(b),
+            vec![],
+            integers,
+        )
+    }
+
+    /// Deserializes an annotated from a JSON value.
+    ///
+    /// This is cheaper than serializing the value into a string for `from_json`.  Like there,
+    /// invalid fields are recorded as errors in the meta data and meta data is read from the
+    /// `_meta` key.
+    pub fn from_value(value: serde_json::Value) -> Result<Annotated<T>, serde_json::Error> {
+        Self::deserialize_with_meta(value)
+    }
+
+    /// Deserializes an annotated from separate value and meta data documents.
+    ///
+    /// This is the inverse of `split_serialize`.  The meta data document uses the same format as
+    /// the `_meta` key in `to_json`.
+    pub fn from_split_json(value: &'de str, meta: &str) -> Result<Annotated<T>, serde_json::Error> {
+        let meta_map: MetaMap = serde_json::from_str(meta)?;
+        deserialize_tracked(
+            &mut serde_json::Deserializer::from_str(value),
+            meta_map,
+            BigIntegers::find(value),
+        )
+    }
+}
+
+impl<T: DeserializeOwned> Annotated<T> {
+    /// Deserializes an annotated from a JSON string that may contain non-finite numbers.
+    ///
+    /// Some producers emit the literals `NaN`, `Infinity` and `-Infinity`, which are not valid
+    /// JSON and fail to parse with `from_json`.  This replaces them with `null` and adds an error
+    /// to the meta data of the respective value.  Otherwise, this behaves like `from_json`.
+    pub fn from_json_lenient(s: &str) -> Result<Annotated<T>, serde_json::Error> {
+        let (json, replaced) = replace_non_finite(s);
+        let extra_meta = replaced
+            .into_iter()
+            .map(|(path, literal)| {
+                let error = format!("expected a finite number, found {}", literal);
+                (path, Meta::from_error(error))
+            })
+            .collect();
+
+        Self::deserialize_with_extra_meta(
+            &mut serde_json::Deserializer::from_str(&json),
+            extra_meta,
+            BigIntegers::find(&json),
+        )
     }
 }
 
@@ -324,6 +525,82 @@ impl<T: Serialize> Annotated<T> {
         self.serialize_with_meta(&mut ser)?;
         Ok(unsafe { String::from_utf8_unchecked(ser.into_inner()) })
     }
+
+    /// Serializes an annotated value with its meta data into a writer.
+    ///
+    /// The output is equivalent to `to_json`, but written directly into the writer without
+    /// buffering the entire document.  The meta data is still collected upfront, since it is
+    /// written after the value.  Wrap unbuffered writers in a `BufWriter`.
+    pub fn serialize_to_writer_with_meta<W: io::Write>(
+        &self,
+        writer: W,
+    ) -> Result<(), serde_json::Error> {
+        self.serialize_with_meta(&mut serde_json::Serializer::new(writer))
+    }
+
+    /// Serializes the value into a writer without any meta data.
+    ///
+    /// The output is equivalent to the value part of `split_serialize`.
+    pub fn serialize_to_writer<W: io::Write>(&self, writer: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer(writer, self)
+    }
+
+    /// Serializes an annotated value into a JSON value.
+    ///
+    /// The result is equivalent to parsing the output of `to_json`.
+    pub fn to_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        self.serialize_with_meta(serde_json::value::Serializer)
+    }
+
+    /// Serializes the value and its meta data into two separate JSON strings.
+    ///
+    /// The first string contains the plain value without any meta data.  The second contains
+    /// the meta data in the same format as the `_meta` key written by `to_json`, or `{}` if there
+    /// is no meta data.  This allows to store meta data separately and to strip it cheaply.
+    pub fn split_serialize(&self) -> Result<(String, String), serde_json::Error> {
+        use serde::ser::Error;
+
+        let value = serde_json::to_string(self)?;
+        let meta = serialize_meta(self).map_err(serde_json::Error::custom)?;
+        Ok((value, serde_json::to_string(&meta)?))
+    }
+
+    /// Invokes the callback with the path and meta data of every value that carries meta data.
+    ///
+    /// The path of the root value is empty.  Unlike `split_serialize`, this only serializes the
+    /// meta data and never writes it to a string.
+    pub(crate) fn for_each_meta<F>(&self, mut f: F) -> Result<(), MetaError>
+    where
+        F: FnMut(&str, Meta),
+    {
+        serialize_meta(self)?.walk("", &mut |path, meta| {
+            if let Ok(meta) = Meta::deserialize(meta) {
+                f(path, meta);
+            }
+        });
+        Ok(())
+    }
+
+    /// Serializes the value into a JSON string without any meta data.
+    ///
+    /// Unlike `split_serialize`, this also removes a `_meta` key from the root object, which
+    /// holds embedded meta data if the value contains an entire payload.  Keys in nested
+    /// objects, such as free-form data, are retained even if they are empty or named `_meta`.
+    /// Use this to store payloads after their meta data has been consumed.
+    pub fn to_json_without_meta(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&value_without_meta(self)?)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Annotated<T> {
+    /// Returns a copy of the value with the meta data of all nested values removed.
+    ///
+    /// The value is rebuilt from the output of `to_json_without_meta`.  Returns `None` if there
+    /// is no value.
+    pub fn strip_meta(&self) -> Result<Option<T>, serde_json::Error> {
+        let Annotated(value, _) = Annotated::<T>::deserialize(value_without_meta(self)?)?;
+        Ok(value)
+    }
 }
 
 impl<T> Annotated<T> {
@@ -351,7 +628,7 @@ impl<T> Annotated<T> {
     {
         // TODO: Meta data is only fetched if the key is present. Otherwise, serde will simply use
         // the value provided by `serde(default)` and skip this function alltogether. See
-        // `utils::skip_if` for more information.
+        // `ser_utils::skip_if` for more information.
         //
         // THIS IS A BUG AND NEEDS TO BE FIXED WITH CUSTOM DESERIALIZATION!
 
@@ -362,7 +639,7 @@ impl<T> Annotated<T> {
             let meta_map: Option<&Rc<MetaMap>> = deserializer.state().get();
             if let (Some(path), Some(meta_map)) = (path, meta_map) {
                 if let Some(meta) = meta_map.remove(&path.to_string()) {
-                    *annotated.meta_mut() = meta;
+                    annotated.meta_mut().merge(meta);
                 }
             }
 
@@ -388,7 +665,9 @@ impl<T> Annotated<T> {
         // value as None and add the error to the meta data.
         match C::deserialize(ContentDeserializer::<D::Error>::new(content)) {
             Ok(value) => annotated.set_value(Some(value)),
-            Err(err) => annotated.meta_mut().errors_mut().push(err.to_string()),
+            Err(err) => annotated
+                .meta_mut()
+                .merge(Meta::from_error(err.to_string())),
         }
 
         Ok(annotated)
@@ -440,6 +719,51 @@ impl<T> Annotated<T> {
         Annotated(self.0.map(f), self.1)
     }
 
+    /// Transforms the value if it's set, or a default value otherwise.
+    pub fn map_value_or_default<F: FnOnce(T) -> T>(self, f: F) -> Self
+    where
+        T: Default,
+    {
+        Annotated(Some(f(self.0.unwrap_or_default())), self.1)
+    }
+
+    /// Transforms the value into another annotated value if it's set.
+    ///
+    /// The meta data of the returned value is merged into the existing meta data, see
+    /// `Meta::merge`.
+    pub fn and_then<U, F: FnOnce(T) -> Annotated<U>>(self, f: F) -> Annotated<U> {
+        let Annotated(value, mut meta) = self;
+        match value {
+            Some(value) => {
+                let Annotated(value, other) = f(value);
+                meta.merge(other);
+                Annotated(value, meta)
+            }
+            None => Annotated(None, meta),
+        }
+    }
+
+    /// Applies a function to a reference of the value, or returns the default if it's missing.
+    pub fn map_or<U, F: FnOnce(&T) -> U>(&self, default: U, f: F) -> U {
+        self.0.as_ref().map_or(default, f)
+    }
+
+    /// Inserts the result of a function if the value is missing and returns the value.
+    pub fn unwrap_or_insert_with<F: FnOnce() -> T>(&mut self, f: F) -> &mut T {
+        self.0.get_or_insert_with(f)
+    }
+
+    /// Records an error if the value is missing.
+    ///
+    /// No error is added if the meta data already explains the missing value with an error or
+    /// remark.
+    pub fn require<S: Into<String>>(mut self, error: S) -> Self {
+        if self.0.is_none() && !self.1.null_is_valid() {
+            self.1.errors_mut().push(error.into());
+        }
+        self
+    }
+
     /// Removes a value and writes a well known remark.
     pub fn with_removed_value(mut self, remark: Remark) -> Self {
         if self.0.is_some() {
@@ -495,6 +819,15 @@ impl MetaMap {
         self.inner.borrow_mut().insert(path, meta)
     }
 
+    /// Merges a meta entry into the entry for the given path.
+    pub fn merge(&self, path: String, meta: Meta) {
+        self.inner
+            .borrow_mut()
+            .entry(path)
+            .or_insert_with(Meta::default)
+            .merge(meta);
+    }
+
     /// Moves the meta entry for the given path to the caller.
     pub fn remove<P>(&self, path: &P) -> Option<Meta>
     where
@@ -503,6 +836,29 @@ impl MetaMap {
     {
         self.inner.borrow_mut().remove(path)
     }
+
+    /// Moves all meta entries at or below the given path to the caller.
+    ///
+    /// The root path `"."` moves all entries.
+    pub fn remove_subtree(&self, path: &str) -> Vec<(String, Meta)> {
+        let mut inner = self.inner.borrow_mut();
+        if path == "." {
+            return mem::replace(&mut *inner, BTreeMap::new())
+                .into_iter()
+                .collect();
+        }
+
+        let prefix = format!("{}.", path);
+        let keys: Vec<String> = inner
+            .keys()
+            .filter(|key| key.as_str() == path || key.starts_with(&prefix))
+            .cloned()
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| inner.remove(&key).map(|meta| (key, meta)))
+            .collect()
+    }
 }
 
 impl FromIterator<(String, Meta)> for MetaMap {
@@ -510,8 +866,18 @@ impl FromIterator<(String, Meta)> for MetaMap {
     where
         I: IntoIterator<Item = (String, Meta)>,
     {
+        let mut inner = BTreeMap::new();
+        for (path, meta) in iter {
+            match inner.entry(path) {
+                Entry::Vacant(entry) => {
+                    entry.insert(meta);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().merge(meta),
+            }
+        }
+
         MetaMap {
-            inner: RefCell::new(iter.into_iter().collect()),
+            inner: RefCell::new(inner),
         }
     }
 }
@@ -564,22 +930,100 @@ impl<'de> Deserialize<'de> for MetaMap {
 
 /// Deserializes an annotated value with given meta data.
 fn deserialize_meta<'de, D, T>(deserializer: D, meta_map: MetaMap) -> Result<Annotated<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserialize_tracked(deserializer, meta_map, BigIntegers::default())
+}
+
+/// Deserializes an annotated value with given meta data and big integers of the source.
+fn deserialize_tracked<'de, D, T>(
+    deserializer: D,
+    meta_map: MetaMap,
+    integers: BigIntegers,
+) -> Result<Annotated<T>, D::Error>
 where
     D: Deserializer<'de>,
     T: Deserialize<'de>,
 {
     let mut state = de::State::default();
     state.set(Rc::new(meta_map));
+    if !integers.is_empty() {
+        state.set(Rc::new(integers));
+    }
 
     let tracked = TrackedDeserializer::new(deserializer, state);
     Annotated::<T>::deserialize(tracked)
 }
 
+/// Meta data of a value whose deserialization has been deferred.
+///
+/// Captures the path of the value and all meta entries below it, so that the value can be
+/// deserialized later as if it had been deserialized in place.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeferredMeta {
+    path: Option<String>,
+    entries: Vec<(String, Meta)>,
+}
+
+impl DeferredMeta {
+    /// Moves the meta data for the current path of the deserializer out of its state.
+    pub fn capture<'de, D: Deserializer<'de>>(deserializer: &D) -> DeferredMeta {
+        let path: Option<&Rc<Path>> = deserializer.state().get();
+        let meta_map: Option<&Rc<MetaMap>> = deserializer.state().get();
+
+        let path = path.map(|path| path.to_string());
+        let entries = match (&path, meta_map) {
+            (&Some(ref path), Some(meta_map)) => meta_map.remove_subtree(path),
+            _ => Vec::new(),
+        };
+
+        // values at the root are resumed without a path
+        let path = path.and_then(|path| if path == "." { None } else { Some(path) });
+        DeferredMeta { path, entries }
+    }
+
+    /// Deserializes a value at the captured path, attaching the captured meta data.
+    pub fn deserialize<'de, D, T>(self, deserializer: D) -> Result<Annotated<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        let mut state = de::State::default();
+        state.set(Rc::new(self.entries.into_iter().collect::<MetaMap>()));
+        if let Some(path) = self.path {
+            // A single map segment renders the same as the original path.
+            state.set(Rc::new(Path::Map {
+                parent: Rc::new(Path::Root),
+                key: path,
+            }));
+        }
+
+        let tracked = TrackedDeserializer::resume(deserializer, state);
+        Annotated::<T>::deserialize(tracked)
+    }
+}
+
 /// Indicates whether Annotated's meta data or values should be serialized.
 pub(crate) fn should_serialize_meta() -> bool {
     SERIALIZE_META.with(|b| b.load(Ordering::Relaxed))
 }
 
+/// Serializes the value into a JSON value and removes meta data embedded at the root.
+///
+/// Meta data is only ever embedded in the `_meta` key of the root object.  Empty keys and `_meta`
+/// keys of nested objects are part of the data and retained.
+fn value_without_meta<T: Serialize>(
+    value: &Annotated<T>,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let mut value = serde_json::to_value(value)?;
+    if let serde_json::Value::Object(ref mut map) = value {
+        map.remove("_meta");
+    }
+    Ok(value)
+}
+
 /// Serializes meta data of an annotated value into a nested map structure.
 fn serialize_meta<T>(value: &Annotated<T>) -> Result<MetaTree, MetaError>
 where
@@ -664,6 +1108,20 @@ mod test_annotated_with_meta {
         assert_eq_dbg!(value, deserialize_meta(deserializer, meta_map).unwrap());
     }
 
+    #[test]
+    fn test_invalid_repeated() {
+        let deserializer = &mut Deserializer::from_str("\"invalid\"");
+        let mut meta_map = MetaMap::new();
+        meta_map.insert(
+            ".".to_string(),
+            Meta::from_error("invalid type: string \"invalid\", expected i32"),
+        );
+
+        // It should not duplicate the error of a previous pass
+        let value = Annotated::<i32>::from_error("invalid type: string \"invalid\", expected i32");
+        assert_eq_dbg!(value, deserialize_meta(deserializer, meta_map).unwrap());
+    }
+
     #[test]
     fn test_missing() {
         let deserializer = &mut Deserializer::from_str("null");
@@ -758,6 +1216,133 @@ mod test_annotated_without_meta {
     }
 }
 
+#[cfg(test)]
+mod test_lenient {
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Test {
+        answer: Annotated<f64>,
+        other: i32,
+    }
+
+    #[test]
+    fn test_non_finite() {
+        let json = r#"{"answer":-Infinity,"other":21}"#;
+        assert!(Annotated::<Test>::from_json(json).is_err());
+
+        let annotated = Annotated::<Test>::from_json_lenient(json).unwrap();
+        assert_eq_dbg!(
+            annotated.value().unwrap().answer,
+            Annotated::from_error("expected a finite number, found -Infinity")
+        );
+        assert!(annotated.to_json().unwrap().starts_with(r#"{"answer":null,"other":21,"#));
+    }
+
+    #[test]
+    fn test_valid() {
+        let json = r#"{"answer":4.2,"other":21}"#;
+        assert_eq_dbg!(
+            Annotated::<Test>::from_json_lenient(json).unwrap(),
+            Annotated::<Test>::from_json(json).unwrap()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_json_value {
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Test {
+        answer: Annotated<i32>,
+        other: i32,
+    }
+
+    #[test]
+    fn test_from_value() {
+        let json = r#"{"answer":"invalid","other":21}"#;
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+
+        let annotated = Annotated::<Test>::from_value(value).unwrap();
+        assert_eq_dbg!(annotated, Annotated::<Test>::from_json(json).unwrap());
+        assert_eq_dbg!(
+            annotated.value().unwrap().answer,
+            Annotated::from_error("invalid type: string \"invalid\", expected i32")
+        );
+    }
+
+    #[test]
+    fn test_to_value() {
+        let annotated = Annotated::from(Test {
+            answer: Annotated::from_error("invalid"),
+            other: 21,
+        });
+
+        let json = annotated.to_json().unwrap();
+        let value = annotated.to_value().unwrap();
+        assert_eq_dbg!(value, serde_json::from_str::<serde_json::Value>(&json).unwrap());
+        assert_eq_dbg!(Annotated::<Test>::from_value(value).unwrap(), annotated);
+    }
+}
+
+#[cfg(test)]
+mod test_strip_meta {
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct Test {
+        answer: Annotated<i32>,
+        data: Annotated<serde_json::Value>,
+    }
+
+    fn annotated() -> Annotated<Test> {
+        let data = r#"{"a": 1, "b": [{"c": 2}]}"#;
+        Annotated::new(
+            Test {
+                answer: Annotated::new(42, Meta::from_error("inner error")),
+                data: Annotated::new(
+                    serde_json::from_str(data).unwrap(),
+                    Meta::from_error("data error"),
+                ),
+            },
+            Meta::from_error("outer error"),
+        )
+    }
+
+    #[test]
+    fn test_to_json_without_meta() {
+        assert_eq_str!(
+            annotated().to_json_without_meta().unwrap(),
+            r#"{"answer":42,"data":{"a":1,"b":[{"c":2}]}}"#
+        );
+    }
+
+    #[test]
+    fn test_to_json_without_meta_keeps_data() {
+        let json = r#"{"a":{"":1,"_meta":2},"_meta":{"a":{"":{"err":["x"]}}}}"#;
+        let value = Annotated::from(serde_json::from_str::<serde_json::Value>(json).unwrap());
+        assert_eq_str!(
+            value.to_json_without_meta().unwrap(),
+            r#"{"a":{"":1,"_meta":2}}"#
+        );
+    }
+
+    #[test]
+    fn test_strip_meta() {
+        let stripped = annotated().strip_meta().unwrap().unwrap();
+        assert_eq_dbg!(stripped.answer, Annotated::from(42));
+        assert!(stripped.data.meta().is_empty());
+        assert_eq_str!(
+            serde_json::to_string(&stripped.data).unwrap(),
+            r#"{"a":1,"b":[{"c":2}]}"#
+        );
+
+        let empty = Annotated::<Test>::from_error("invalid");
+        assert_eq_dbg!(empty.strip_meta().unwrap(), None);
+    }
+}
+
 #[cfg(test)]
 mod test_meta_paths {
     use super::*;
@@ -838,6 +1423,116 @@ mod test_meta_map {
 
         assert_eq_dbg!(map, serde_json::from_str(json).unwrap());
     }
+
+    #[test]
+    fn test_duplicate_paths() {
+        let map: MetaMap = vec![
+            ("foo".to_string(), Meta::from_error("a")),
+            ("foo".to_string(), Meta::from_error("b")),
+        ].into_iter()
+            .collect();
+
+        let mut meta = Meta::from_error("a");
+        meta.errors.push("b".to_string());
+        assert_eq_dbg!(map.remove("foo"), Some(meta));
+    }
+}
+
+#[cfg(test)]
+mod test_meta_merge {
+    use super::*;
+
+    #[test]
+    fn test_merge_remarks() {
+        let mut meta = Meta::default();
+        meta.remarks.push(Remark::new(RemarkType::Removed, "a"));
+
+        let mut other = Meta::default();
+        other.remarks.push(Remark::new(RemarkType::Removed, "a"));
+        other.remarks.push(Remark::new(RemarkType::Masked, "b"));
+
+        meta.merge(other);
+        assert_eq_dbg!(
+            meta.remarks,
+            vec![
+                Remark::new(RemarkType::Removed, "a"),
+                Remark::new(RemarkType::Masked, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        let mut meta = Meta::from_error("a");
+        meta.merge(Meta::from_error("a"));
+        meta.merge(Meta::from_error("b"));
+        assert_eq_dbg!(meta.errors, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_original_length() {
+        let mut meta = Meta::default();
+        meta.merge(Meta {
+            original_length: Some(42),
+            ..Default::default()
+        });
+        assert_eq_dbg!(meta.original_length, Some(42));
+
+        meta.merge(Meta {
+            original_length: Some(21),
+            ..Default::default()
+        });
+        assert_eq_dbg!(meta.original_length, Some(42));
+    }
+}
+
+#[cfg(test)]
+mod test_combinators {
+    use super::*;
+
+    #[test]
+    fn test_and_then() {
+        let annotated = Annotated::new(42, Meta::from_error("a"));
+        let result = annotated.and_then(|x| Annotated::new(x.to_string(), Meta::from_error("b")));
+        assert_eq_dbg!(result.0, Some("42".to_string()));
+        assert_eq_dbg!(result.1.errors, vec!["a".to_string(), "b".to_string()]);
+
+        let result = Annotated::<i32>::empty().and_then(|x| Annotated::from(x + 1));
+        assert_eq_dbg!(result, Annotated::empty());
+    }
+
+    #[test]
+    fn test_map_or() {
+        let annotated = Annotated::from(Some(42));
+        assert!(annotated.map_or(false, Option::is_some));
+        assert!(Annotated::<Option<i32>>::empty().map_or(true, Option::is_none));
+    }
+
+    #[test]
+    fn test_map_value_or_default() {
+        let annotated = Annotated::<i32>::empty().map_value_or_default(|x| x + 1);
+        assert_eq_dbg!(annotated, Annotated::from(1));
+    }
+
+    #[test]
+    fn test_unwrap_or_insert_with() {
+        let mut annotated = Annotated::<Vec<i32>>::empty();
+        annotated.unwrap_or_insert_with(Vec::new).push(42);
+        annotated.unwrap_or_insert_with(|| unreachable!()).push(23);
+        assert_eq_dbg!(annotated, Annotated::from(vec![42, 23]));
+    }
+
+    #[test]
+    fn test_require() {
+        let annotated = Annotated::<i32>::empty().require("missing");
+        assert_eq_dbg!(annotated, Annotated::from_error("missing"));
+
+        let annotated = Annotated::<i32>::from_error("invalid").require("missing");
+        assert_eq_dbg!(annotated, Annotated::from_error("invalid"));
+
+        let annotated = Annotated::from(42).require("missing");
+        assert_eq_dbg!(annotated, Annotated::from(42));
+    }
 }
 
 #[cfg(test)]
@@ -880,6 +1575,22 @@ mod test_remarks {
         assert_eq_dbg!(remark, serde_json::from_str(input).unwrap());
         assert_eq_str!(output, &serde_json::to_string(&remark).unwrap());
     }
+
+    #[test]
+    fn test_with_note() {
+        let json = r#"["@test","s",21,42,"claims: sub"]"#;
+        let remark =
+            Remark::with_range(RemarkType::Substituted, "@test", (21, 42)).with_note("claims: sub");
+
+        assert_eq_dbg!(remark, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, &serde_json::to_string(&remark).unwrap());
+
+        let json = r#"["@test","x",null,null,"claims: sub"]"#;
+        let remark = Remark::new(RemarkType::Removed, "@test").with_note("claims: sub");
+
+        assert_eq_dbg!(remark, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, &serde_json::to_string(&remark).unwrap());
+    }
 }
 
 #[cfg(test)]
@@ -947,4 +1658,159 @@ mod test_serialize_meta {
             r#"{"0":{"":{"err":["a"]}},"1":{"":{"err":["b"]}}}"#
         );
     }
+
+    #[test]
+    fn test_split_serialize() {
+        let value = Annotated::from(Test {
+            answer: Annotated::new(42, Meta::from_error("some error")),
+        });
+
+        let (value_json, meta_json) = value.split_serialize().unwrap();
+        assert_eq_str!(value_json, r#"{"answer":42}"#);
+        assert_eq_str!(meta_json, r#"{"answer":{"":{"err":["some error"]}}}"#);
+
+        let restored = Annotated::<Test>::from_split_json(&value_json, &meta_json).unwrap();
+        assert_eq_dbg!(restored, value);
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        let value = Annotated::from(Test {
+            answer: Annotated::new(42, Meta::from_error("some error")),
+        });
+
+        let mut output = Vec::new();
+        value.serialize_to_writer_with_meta(&mut output).unwrap();
+        assert_eq_str!(String::from_utf8(output).unwrap(), value.to_json().unwrap());
+
+        let mut output = Vec::new();
+        value.serialize_to_writer(&mut output).unwrap();
+        assert_eq_str!(String::from_utf8(output).unwrap(), r#"{"answer":42}"#);
+    }
+
+    #[test]
+    fn test_split_serialize_empty() {
+        let value = Annotated::from(Test {
+            answer: Annotated::from(42),
+        });
+
+        let (value_json, meta_json) = value.split_serialize().unwrap();
+        assert_eq_str!(value_json, r#"{"answer":42}"#);
+        assert_eq_str!(meta_json, "{}");
+    }
+}
+
+#[cfg(test)]
+mod test_meta_format {
+    use super::*;
+
+    const REMARK_TYPES: &[(RemarkType, &str)] = &[
+        (RemarkType::Annotated, "a"),
+        (RemarkType::Removed, "x"),
+        (RemarkType::Substituted, "s"),
+        (RemarkType::Masked, "m"),
+        (RemarkType::Pseudonymized, "p"),
+        (RemarkType::Encrypted, "e"),
+    ];
+
+    fn assert_roundtrip<T>(value: &T, json: &str)
+    where
+        T: fmt::Debug + PartialEq + Serialize + DeserializeOwned,
+    {
+        assert_eq_str!(serde_json::to_string(value).unwrap(), json);
+        assert_eq_dbg!(&serde_json::from_str::<T>(json).unwrap(), value);
+    }
+
+    #[test]
+    fn test_remark_types() {
+        for &(ty, name) in REMARK_TYPES {
+            let remark = Remark::new(ty, "rule");
+            assert_roundtrip(&remark, &format!(r#"["rule","{}"]"#, name));
+
+            let remark = Remark::with_range(ty, "rule", (1, 2));
+            assert_roundtrip(&remark, &format!(r#"["rule","{}",1,2]"#, name));
+
+            let remark = Remark::new(ty, "rule").with_note("note");
+            assert_roundtrip(&remark, &format!(r#"["rule","{}",null,null,"note"]"#, name));
+
+            let remark = Remark::with_range(ty, "rule", (1, 2)).with_note("note");
+            assert_roundtrip(&remark, &format!(r#"["rule","{}",1,2,"note"]"#, name));
+        }
+    }
+
+    #[test]
+    fn test_meta_fields() {
+        let provenances = &[
+            (Provenance::Sdk, None),
+            (Provenance::Server, Some(r#""src":"server""#)),
+            (Provenance::Inferred, Some(r#""src":"inferred""#)),
+        ];
+
+        for mask in 0..8 {
+            for &(provenance, src) in provenances {
+                let mut meta = Meta::default();
+                let mut fields = vec![];
+
+                if mask & 1 != 0 {
+                    meta.remarks_mut()
+                        .push(Remark::new(RemarkType::Removed, "rule"));
+                    fields.push(r#""rem":[["rule","x"]]"#);
+                }
+                if mask & 2 != 0 {
+                    meta.errors_mut().push("error".to_string());
+                    fields.push(r#""err":["error"]"#);
+                }
+                if mask & 4 != 0 {
+                    meta.set_original_length(Some(42));
+                    fields.push(r#""len":42"#);
+                }
+                meta.set_provenance(provenance);
+                fields.extend(src);
+
+                let json = format!("{{{}}}", fields.join(","));
+                assert_roundtrip(&meta, &json);
+                assert_eq!(meta.is_empty(), fields.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_meta_tree() {
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Test {
+            list: Annotated<Vec<Annotated<String>>>,
+            map: Annotated<BTreeMap<String, Annotated<String>>>,
+        }
+
+        let mut meta = Meta::default();
+        meta.remarks_mut()
+            .push(Remark::with_range(RemarkType::Masked, "rule", (0, 3)));
+        meta.set_original_length(Some(5));
+
+        let value = Annotated::new(
+            Test {
+                list: Annotated::new(
+                    vec![
+                        Annotated::from("a".to_string()),
+                        Annotated::new("***".to_string(), meta),
+                    ],
+                    Meta::from_error("list error"),
+                ),
+                map: Annotated::from(
+                    vec![("key".to_string(), Annotated::from_error("value error"))]
+                        .into_iter()
+                        .collect(),
+                ),
+            },
+            Meta::from_error("root error"),
+        );
+
+        let (_, meta_json) = value.split_serialize().unwrap();
+        assert_eq_str!(
+            meta_json,
+            r#"{"":{"err":["root error"]},"list":{"":{"err":["list error"]},"#.to_string()
+                + r#""1":{"":{"rem":[["rule","m",0,3]],"len":5}}},"#
+                + r#""map":{"key":{"":{"err":["value error"]}}}}"#
+        );
+    }
 }
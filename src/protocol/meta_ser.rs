@@ -66,6 +66,23 @@ impl MetaTree {
     pub fn insert(&mut self, key: String, value: MetaTree) {
         self.children.insert(key, value);
     }
+
+    /// Invokes the callback with the path and meta data of every node in the tree.
+    ///
+    /// Paths of children are joined to the given path with dots.
+    pub fn walk<F: FnMut(&str, &Value)>(&self, path: &str, f: &mut F) {
+        if let Some(ref meta) = self.meta {
+            f(path, meta);
+        }
+
+        for (key, child) in &self.children {
+            if path.is_empty() {
+                child.walk(key, f);
+            } else {
+                child.walk(&format!("{}.{}", path, key), f);
+            }
+        }
+    }
 }
 
 impl Serialize for MetaTree {
@@ -166,6 +183,14 @@ impl Serializer for MetaKeySerializer {
         Ok(v.to_string())
     }
 
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
         Ok(v.to_string())
     }
@@ -337,6 +362,14 @@ impl Serializer for MetaSerializer {
         Ok(None)
     }
 
+    fn serialize_i128(self, _: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
+    fn serialize_u128(self, _: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(None)
+    }
+
     fn serialize_f32(self, _: f32) -> Result<Self::Ok, Self::Error> {
         Ok(None)
     }
@@ -4,15 +4,37 @@
 mod macros;
 
 mod buffer;
+mod builder;
 mod common;
+mod diff;
+#[cfg(feature = "fuzzing")]
+mod fuzzing;
+mod lazy;
+mod lenient;
 mod meta;
 mod meta_ser;
+mod path;
+#[cfg(feature = "schema")]
+mod schema;
+pub mod ser_utils;
 mod serde;
 mod serde_chrono;
+mod size;
 mod tracked;
 mod types;
-mod utils;
+mod version;
 
+pub use self::builder::*;
 pub use self::common::*;
+pub use self::diff::*;
+#[cfg(feature = "fuzzing")]
+pub use self::fuzzing::*;
+pub use self::lazy::*;
+pub(crate) use self::lenient::json_path_at;
 pub use self::meta::*;
+pub use self::path::*;
+#[cfg(feature = "schema")]
+pub use self::schema::*;
+pub use self::size::*;
 pub use self::types::*;
+pub use self::version::*;
@@ -0,0 +1,536 @@
+//! Access to individual fields of annotated values by path.
+//!
+//! Paths are dot separated lists of keys, for instance `"user.email"` or
+//! `"exception.values.0.type"`.  Keys address struct fields by their serialized name, entries
+//! in maps (including the `other` map of a struct) and elements of arrays by their index.
+
+use chrono::{DateTime, Utc};
+use debugid::DebugId;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use uuid::Uuid;
+
+use super::common::{remove_key, Array, Map, Value, Values};
+use super::meta::Annotated;
+use super::serde::{CustomDeserialize, ForwardSerialize};
+use super::serde_chrono::SerdeDateTime;
+use super::types::*;
+
+/// An error returned when accessing values by path.
+#[derive(Debug, Fail)]
+pub enum PathError {
+    /// The path does not point to an existing value.
+    #[fail(display = "no value at path '{}'", _0)]
+    NotFound(String),
+    /// The value does not match the type of the field.
+    #[fail(display = "invalid value: {}", _0)]
+    InvalidValue(String),
+}
+
+impl From<serde_json::Error> for PathError {
+    fn from(error: serde_json::Error) -> PathError {
+        PathError::InvalidValue(error.to_string())
+    }
+}
+
+/// A type whose children can be accessed by key and that converts from and to `Value`.
+///
+/// This is implemented for `Event` and all interfaces.  Implementations for protocol structs
+/// are derived and honor serde renames and flattened `other` maps.
+pub trait Traverse {
+    /// Returns the child with the given key.
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        let _key = key;
+        None
+    }
+
+    /// Returns the child with the given key mutably.
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        let _key = key;
+        None
+    }
+
+    /// Converts this value into a `Value`.
+    fn to_value(&self) -> Result<Value, PathError>;
+
+    /// Converts a `Value` back into this type.
+    fn from_value(value: Value) -> Result<Self, PathError>
+    where
+        Self: Sized;
+}
+
+/// An annotated value within a tree that can be accessed by path.
+pub trait PathNode {
+    /// Returns the child with the given key.
+    fn child(&self, key: &str) -> Option<&PathNode>;
+
+    /// Returns the child with the given key mutably.
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode>;
+
+    /// Returns a copy of the value including its meta data.
+    fn get_value(&self) -> Annotated<Value>;
+
+    /// Replaces the value and its meta data.
+    fn set_value(&mut self, value: Annotated<Value>) -> Result<(), PathError>;
+}
+
+impl<T: Traverse> PathNode for Annotated<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        self.value().and_then(|value| value.child(key))
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        self.value_mut().and_then(|value| value.child_mut(key))
+    }
+
+    fn get_value(&self) -> Annotated<Value> {
+        // values that cannot be represented are treated as missing
+        let value = self.value().and_then(|value| value.to_value().ok());
+        Annotated(value, self.meta().clone())
+    }
+
+    fn set_value(&mut self, value: Annotated<Value>) -> Result<(), PathError> {
+        let Annotated(value, meta) = value;
+        let value = match value {
+            Some(value) => Some(T::from_value(value)?),
+            None => None,
+        };
+        *self = Annotated(value, meta);
+        Ok(())
+    }
+}
+
+impl<T: Traverse> Annotated<T> {
+    /// Returns a copy of the value at the given path.
+    ///
+    /// The returned value is converted into a `Value` along with its meta data.  Returns `None`
+    /// if the path does not exist.
+    pub fn get_path(&self, path: &str) -> Option<Annotated<Value>> {
+        let mut node: &PathNode = self;
+        for key in split_path(path) {
+            node = node.child(key)?;
+        }
+        Some(node.get_value())
+    }
+
+    /// Replaces the value at the given path.
+    ///
+    /// The value is converted into the type of the field.  The path must point to an existing
+    /// field, map entry or array element.
+    pub fn set_path(&mut self, path: &str, value: Annotated<Value>) -> Result<(), PathError> {
+        let mut node: &mut PathNode = self;
+        for key in split_path(path) {
+            let parent = node;
+            node = match parent.child_mut(key) {
+                Some(child) => child,
+                None => return Err(PathError::NotFound(path.to_string())),
+            };
+        }
+        node.set_value(value)
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('.').filter(|key| !key.is_empty())
+}
+
+/// Converts a serializable value into a `Value`.
+pub(crate) fn serde_to_value<T: Serialize>(value: &T) -> Result<Value, PathError> {
+    Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+}
+
+/// Converts a `Value` into a deserializable value.
+pub(crate) fn serde_from_value<T: DeserializeOwned>(value: Value) -> Result<T, PathError> {
+    Ok(serde_json::from_value(serde_json::to_value(&value)?)?)
+}
+
+macro_rules! impl_traverse_serde {
+    ($ty:ty) => {
+        impl Traverse for $ty {
+            fn to_value(&self) -> Result<Value, PathError> {
+                serde_to_value(self)
+            }
+
+            fn from_value(value: Value) -> Result<Self, PathError> {
+                serde_from_value(value)
+            }
+        }
+    };
+}
+
+impl_traverse_serde!(bool);
+impl_traverse_serde!(u32);
+impl_traverse_serde!(i32);
+impl_traverse_serde!(u64);
+impl_traverse_serde!(i64);
+impl_traverse_serde!(f32);
+impl_traverse_serde!(f64);
+impl_traverse_serde!(String);
+impl_traverse_serde!(Uuid);
+impl_traverse_serde!(DebugId);
+impl_traverse_serde!(Level);
+impl_traverse_serde!(EventType);
+impl_traverse_serde!(RegVal);
+impl_traverse_serde!(Addr);
+impl_traverse_serde!(ThreadId);
+impl_traverse_serde!(TraceId);
+impl_traverse_serde!(SpanId);
+impl_traverse_serde!(Ip);
+impl_traverse_serde!((u64, u64));
+impl_traverse_serde!(Vec<String>);
+
+impl Traverse for DateTime<Utc> {
+    fn to_value(&self) -> Result<Value, PathError> {
+        serde_to_value(&ForwardSerialize(self, SerdeDateTime))
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        let value = serde_json::to_value(&value)?;
+        Ok(<SerdeDateTime as CustomDeserialize<'static, DateTime<Utc>>>::deserialize(value)?)
+    }
+}
+
+impl Traverse for Value {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        match *self {
+            Value::Array(ref array) => array.child(key),
+            Value::Map(ref map) => map.child(key),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        match *self {
+            Value::Array(ref mut array) => array.child_mut(key),
+            Value::Map(ref mut map) => map.child_mut(key),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        Ok(self.clone())
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        Ok(value)
+    }
+}
+
+impl<T: Traverse> Traverse for Option<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        self.as_ref().and_then(|value| value.child(key))
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        self.as_mut().and_then(|value| value.child_mut(key))
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        match *self {
+            Some(ref value) => value.to_value(),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: Traverse> Traverse for Box<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        (**self).child(key)
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        (**self).child_mut(key)
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        (**self).to_value()
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        T::from_value(value).map(Box::new)
+    }
+}
+
+/// Converts annotated children into annotated values.
+fn annotated_to_value<T: Traverse>(
+    annotated: &Annotated<T>,
+) -> Result<Annotated<Value>, PathError> {
+    let value = match annotated.value() {
+        Some(value) => Some(value.to_value()?),
+        None => None,
+    };
+    Ok(Annotated(value, annotated.meta().clone()))
+}
+
+/// Converts annotated values into annotated children.
+fn annotated_from_value<T: Traverse>(
+    annotated: Annotated<Value>,
+) -> Result<Annotated<T>, PathError> {
+    let Annotated(value, meta) = annotated;
+    let value = match value {
+        Some(Value::Null) | None => None,
+        Some(value) => Some(T::from_value(value)?),
+    };
+    Ok(Annotated(value, meta))
+}
+
+impl<T: Traverse> Traverse for Array<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        let index = key.parse::<usize>().ok()?;
+        self.get(index).map(|item| item as &PathNode)
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        let index = key.parse::<usize>().ok()?;
+        self.get_mut(index).map(|item| item as &mut PathNode)
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        let array = self
+            .iter()
+            .map(annotated_to_value)
+            .collect::<Result<_, _>>()?;
+        Ok(Value::Array(array))
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        match value {
+            Value::Array(array) => array.into_iter().map(annotated_from_value).collect(),
+            _ => Err(PathError::InvalidValue("expected an array".to_string())),
+        }
+    }
+}
+
+impl<T: Traverse> Traverse for Map<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        self.get(key).map(|item| item as &PathNode)
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        self.get_mut(key).map(|item| item as &mut PathNode)
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        let map = self
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), annotated_to_value(value)?)))
+            .collect::<Result<_, PathError>>()?;
+        Ok(Value::Map(map))
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        match value {
+            Value::Map(map) => map
+                .into_iter()
+                .map(|(key, value)| Ok((key, annotated_from_value(value)?)))
+                .collect(),
+            _ => Err(PathError::InvalidValue("expected an object".to_string())),
+        }
+    }
+}
+
+impl<T: Traverse> Traverse for Values<T> {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        match key {
+            "values" => Some(&self.values),
+            "truncated" => Some(&self.truncated),
+            _ if key.parse::<usize>().is_ok() => self.values.child(key),
+            _ => self.other.child(key),
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        match key {
+            "values" => Some(&mut self.values),
+            "truncated" => Some(&mut self.truncated),
+            _ if key.parse::<usize>().is_ok() => self.values.child_mut(key),
+            _ => self.other.child_mut(key),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        let mut map = self.other.value().cloned().unwrap_or_default();
+        map.insert("values".to_string(), annotated_to_value(&self.values)?);
+        if self.truncated.value().map_or(false, Option::is_some) {
+            map.insert("truncated".to_string(), annotated_to_value(&self.truncated)?);
+        }
+        Ok(Value::Map(map))
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        let mut rv = Values::new();
+        match value {
+            Value::Map(mut map) => {
+                if let Some(values) = remove_key(&mut map, "values") {
+                    rv.values = annotated_from_value(values)?;
+                }
+                if let Some(truncated) = remove_key(&mut map, "truncated") {
+                    rv.truncated = annotated_from_value(truncated)?;
+                }
+                rv.other = map.into();
+            }
+            value => rv.values = Annotated::from(Array::<T>::from_value(value)?),
+        }
+        Ok(rv)
+    }
+}
+
+impl Traverse for Context {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        match *self {
+            Context::Device(ref context) => context.child(key),
+            Context::Os(ref context) => context.child(key),
+            Context::Runtime(ref context) => context.child(key),
+            Context::App(ref context) => context.child(key),
+            Context::Browser(ref context) => context.child(key),
+            Context::Trace(ref context) => context.child(key),
+            Context::Other(_, ref context) => context.child(key),
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        match *self {
+            Context::Device(ref mut context) => context.child_mut(key),
+            Context::Os(ref mut context) => context.child_mut(key),
+            Context::Runtime(ref mut context) => context.child_mut(key),
+            Context::App(ref mut context) => context.child_mut(key),
+            Context::Browser(ref mut context) => context.child_mut(key),
+            Context::Trace(ref mut context) => context.child_mut(key),
+            Context::Other(_, ref mut context) => context.child_mut(key),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        serde_to_value(self)
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        serde_from_value(value)
+    }
+}
+
+impl Traverse for DebugImage {
+    fn child(&self, key: &str) -> Option<&PathNode> {
+        match *self {
+            DebugImage::Apple(ref image) => image.child(key),
+            DebugImage::Symbolic(ref image) => image.child(key),
+            DebugImage::Proguard(ref image) => image.child(key),
+            DebugImage::Other(_, ref image) => image.child(key),
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut PathNode> {
+        match *self {
+            DebugImage::Apple(ref mut image) => image.child_mut(key),
+            DebugImage::Symbolic(ref mut image) => image.child_mut(key),
+            DebugImage::Proguard(ref mut image) => image.child_mut(key),
+            DebugImage::Other(_, ref mut image) => image.child_mut(key),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value, PathError> {
+        serde_to_value(self)
+    }
+
+    fn from_value(value: Value) -> Result<Self, PathError> {
+        serde_from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Meta;
+
+    fn event() -> Annotated<Event> {
+        Annotated::<Event>::from_json(
+            r#"{
+            "user": {"email": "foo@example.com"},
+            "exception": {"values": [{"type": "ValueError", "value": "bad"}]},
+            "extra": {"nested": [1, 2]},
+            "custom": "value",
+            "_meta": {
+                "user": {"email": {"": {"err": ["some error"]}}}
+            }
+        }"#,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_get_path() {
+        let event = event();
+
+        assert_eq_dbg!(
+            event.get_path("user.email"),
+            Some(Annotated::new(
+                Value::String("foo@example.com".to_string()),
+                Meta::from_error("some error"),
+            ))
+        );
+        assert_eq_dbg!(
+            event.get_path("exception.values.0.type"),
+            Some(Annotated::from(Value::String("ValueError".to_string())))
+        );
+        assert_eq_dbg!(
+            event.get_path("exception.0.value"),
+            Some(Annotated::from(Value::String("bad".to_string())))
+        );
+        assert_eq_dbg!(event.get_path("extra.nested.1"), Some(Annotated::from(Value::U64(2))));
+        assert_eq_dbg!(
+            event.get_path("custom"),
+            Some(Annotated::from(Value::String("value".to_string())))
+        );
+        assert_eq_dbg!(event.get_path("user.missing"), None);
+        assert_eq_dbg!(event.get_path("request.url"), None);
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut event = event();
+
+        event
+            .set_path(
+                "user.email",
+                Annotated::from(Value::String("bar@example.com".to_string())),
+            )
+            .unwrap();
+        event
+            .set_path("exception.values.0.type", Annotated::from_error("removed"))
+            .unwrap();
+
+        let event = event.0.unwrap();
+        let user = event.user.0.unwrap().unwrap();
+        assert_eq_dbg!(user.email, Annotated::from(Some("bar@example.com".to_string())));
+        assert_eq_dbg!(user.email.meta(), &Meta::default());
+
+        let exception = &event.exceptions.value().unwrap().values.value().unwrap()[0];
+        assert_eq_dbg!(
+            exception.value().unwrap().ty,
+            Annotated::from_error("removed")
+        );
+    }
+
+    #[test]
+    fn test_set_path_errors() {
+        let mut event = event();
+
+        assert!(
+            event
+                .set_path("user.missing", Annotated::from(Value::Null))
+                .is_err()
+        );
+        assert!(
+            event
+                .set_path("user.email", Annotated::from(Value::Bool(true)))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! JSON Schema generation for the protocol types.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use debugid::DebugId;
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
+use serde_json;
+use uuid::Uuid;
+
+use processor::{Cap, PiiKind};
+
+use super::common::{Value, Values};
+use super::lazy::Lazy;
+use super::meta::Annotated;
+use super::types::*;
+
+/// A JSON Schema document.
+pub type Schema = serde_json::Value;
+
+/// A type that can describe its JSON representation as JSON Schema.
+///
+/// This is implemented for `Event` and all interfaces.  Implementations for protocol structs
+/// are derived and honor serde renames, defaults and flattened `other` maps.
+pub trait JsonSchema {
+    /// Returns the JSON Schema of this type.
+    fn json_schema() -> Schema;
+
+    /// Appends the fields nested in this type to the registry, prefixed with the given path.
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        let _path = path;
+        let _registry = registry;
+    }
+}
+
+/// A field of the protocol, as listed by `schema_registry`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldSchema {
+    /// The path of the field, with `*` in place of array indices and map keys.
+    pub path: String,
+    /// The JSON type of the field, or several types separated by `|`.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The kind of PII applied to the field when processing events.
+    pub pii_kind: Option<PiiKind>,
+    /// The size cap applied to the field when trimming events.
+    pub cap: Option<Cap>,
+    /// Whether the field is required in the JSON Schema of its parent.
+    pub required: bool,
+}
+
+/// Adds a field of type `T` and all fields nested in it to the registry.
+#[doc(hidden)]
+pub fn register_field<T: JsonSchema>(
+    registry: &mut Vec<FieldSchema>,
+    parent: &str,
+    name: &str,
+    pii_kind: Option<PiiKind>,
+    cap: Option<Cap>,
+    required: bool,
+) {
+    let path = join_path(parent, name);
+    registry.push(FieldSchema {
+        path: path.clone(),
+        ty: schema_type(&T::json_schema()),
+        pii_kind,
+        cap,
+        required,
+    });
+    T::register_fields(&path, registry);
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+/// Returns the JSON type described by a schema, ignoring `null`.
+fn schema_type(schema: &Schema) -> String {
+    if let Some(ty) = schema["type"].as_str() {
+        return ty.to_string();
+    }
+
+    let mut types = vec![];
+    for variant in schema["anyOf"].as_array().into_iter().flat_map(|v| v) {
+        let ty = schema_type(variant);
+        if ty != "null" && !types.contains(&ty) {
+            types.push(ty);
+        }
+    }
+
+    if types.is_empty() {
+        "any".to_string()
+    } else {
+        types.join("|")
+    }
+}
+
+/// Creates an object schema from a list of `(name, schema, required)` properties.
+///
+/// Objects always allow additional properties, since unknown fields are either retained in
+/// `other` or discarded during deserialization.
+pub fn object_schema(properties: Vec<(&str, Schema, bool)>) -> Schema {
+    let mut schema_properties = serde_json::Map::new();
+    let mut required = vec![];
+
+    for (name, schema, is_required) in properties {
+        if is_required {
+            required.push(Schema::from(name));
+        }
+        schema_properties.insert(name.to_string(), schema);
+    }
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": schema_properties,
+    });
+
+    if !required.is_empty() {
+        schema["required"] = Schema::Array(required);
+    }
+
+    schema
+}
+
+/// Adds a required `type` tag to an object schema.
+fn tagged_schema(mut schema: Schema, tag: &str) -> Schema {
+    schema["properties"]["type"] = json!({ "type": "string", "enum": [tag] });
+    schema
+}
+
+/// Returns the JSON Schema of the full event payload.
+///
+/// The schema describes the canonical protocol as emitted after normalization, including all
+/// interfaces.  Since all values can be annotated, every field is nullable.
+pub fn event_json_schema() -> Schema {
+    let mut schema = Event::json_schema();
+    schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    schema["title"] = json!("Event");
+    schema
+}
+
+lazy_static! {
+    static ref SCHEMA_REGISTRY: Vec<FieldSchema> = {
+        let mut registry = vec![];
+        Event::register_fields("", &mut registry);
+        registry
+    };
+}
+
+/// Returns all fields of the event payload and its interfaces.
+///
+/// Fields are listed in the order of their declaration, each followed by the fields nested in
+/// it.  Paths use the serialized names of fields, like `Annotated::get_path`, with `*` in place of
+/// array indices and map keys.  Free-form data, such as `extra`, as well as contexts and debug
+/// images, whose fields depend on their type, are listed without nested fields.  Request headers
+/// list the headers carrying credentials.  Tooling can use the registry to check that configured
+/// field selectors exist.
+pub fn schema_registry() -> &'static [FieldSchema] {
+    &SCHEMA_REGISTRY
+}
+
+macro_rules! impl_schema {
+    ($ty:ty, $schema:expr) => {
+        impl JsonSchema for $ty {
+            fn json_schema() -> Schema {
+                $schema
+            }
+        }
+    };
+}
+
+impl_schema!(bool, json!({ "type": "boolean" }));
+impl_schema!(u32, json!({ "type": "integer", "minimum": 0 }));
+impl_schema!(i32, json!({ "type": "integer" }));
+impl_schema!(u64, json!({ "type": "integer", "minimum": 0 }));
+impl_schema!(i64, json!({ "type": "integer" }));
+impl_schema!(f32, json!({ "type": "number" }));
+impl_schema!(f64, json!({ "type": "number" }));
+impl_schema!(String, json!({ "type": "string" }));
+impl_schema!(Value, json!({}));
+impl_schema!(Uuid, json!({ "type": "string", "format": "uuid" }));
+impl_schema!(DebugId, json!({ "type": "string" }));
+impl_schema!(
+    DateTime<Utc>,
+    json!({
+        "anyOf": [
+            { "type": "number" },
+            { "type": "string", "format": "date-time" },
+        ]
+    })
+);
+impl_schema!(
+    Level,
+    json!({
+        "anyOf": [
+            { "type": "string", "enum": ["debug", "info", "log", "warning", "error", "fatal"] },
+            { "type": "integer", "enum": [10, 20, 30, 40, 50] },
+        ]
+    })
+);
+impl_schema!(
+    EventType,
+    json!({
+        "type": "string",
+        "enum": ["default", "error", "csp", "hpkp", "expectct", "expectstaple", "transaction"]
+    })
+);
+impl_schema!(
+    RegVal,
+    json!({
+        "anyOf": [
+            { "type": "string", "pattern": "^0[xX][0-9a-fA-F]+$" },
+            { "type": "integer", "minimum": 0 },
+        ]
+    })
+);
+impl_schema!(Addr, RegVal::json_schema());
+impl_schema!(
+    TraceId,
+    json!({ "type": "string", "pattern": "^[0-9a-fA-F]{32}$" })
+);
+impl_schema!(
+    SpanId,
+    json!({ "type": "string", "pattern": "^[0-9a-fA-F]{16}$" })
+);
+impl_schema!(
+    Ip,
+    json!({
+        "anyOf": [
+            { "type": "string", "format": "ipv4" },
+            { "type": "string", "format": "ipv6" },
+        ]
+    })
+);
+impl_schema!(
+    ThreadId,
+    json!({
+        "anyOf": [
+            { "type": "integer", "minimum": 0 },
+            { "type": "string" },
+        ]
+    })
+);
+impl_schema!(
+    Context,
+    json!({
+        "anyOf": [
+            tagged_schema(DeviceContext::json_schema(), "device"),
+            tagged_schema(OsContext::json_schema(), "os"),
+            tagged_schema(RuntimeContext::json_schema(), "runtime"),
+            tagged_schema(AppContext::json_schema(), "app"),
+            tagged_schema(BrowserContext::json_schema(), "browser"),
+            tagged_schema(TraceContext::json_schema(), "trace"),
+            { "type": "object" },
+        ]
+    })
+);
+impl_schema!(
+    DebugImage,
+    json!({
+        "anyOf": [
+            tagged_schema(AppleDebugImage::json_schema(), "apple"),
+            tagged_schema(SymbolicDebugImage::json_schema(), "symbolic"),
+            tagged_schema(ProguardDebugImage::json_schema(), "proguard"),
+            { "type": "object" },
+        ]
+    })
+);
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Schema {
+        T::json_schema()
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(path, registry)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Box<T> {
+    fn json_schema() -> Schema {
+        T::json_schema()
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(path, registry)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Lazy<T> {
+    fn json_schema() -> Schema {
+        T::json_schema()
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(path, registry)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> Schema {
+        json!({ "type": "array", "items": T::json_schema() })
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(&join_path(path, "*"), registry)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeMap<String, T> {
+    fn json_schema() -> Schema {
+        json!({ "type": "object", "additionalProperties": T::json_schema() })
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(&join_path(path, "*"), registry)
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<T: JsonSchema> JsonSchema for IndexMap<String, T> {
+    fn json_schema() -> Schema {
+        json!({ "type": "object", "additionalProperties": T::json_schema() })
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(&join_path(path, "*"), registry)
+    }
+}
+
+impl<A: JsonSchema, B: JsonSchema> JsonSchema for (A, B) {
+    fn json_schema() -> Schema {
+        json!({
+            "type": "array",
+            "items": [A::json_schema(), B::json_schema()],
+            "minItems": 2,
+            "maxItems": 2,
+        })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Annotated<T> {
+    fn json_schema() -> Schema {
+        json!({ "anyOf": [T::json_schema(), { "type": "null" }] })
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        T::register_fields(path, registry)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Values<T> {
+    fn json_schema() -> Schema {
+        let array = Vec::<Annotated<T>>::json_schema();
+        json!({
+            "anyOf": [
+                object_schema(vec![
+                    ("values", array.clone(), true),
+                    ("truncated", Annotated::<bool>::json_schema(), false),
+                ]),
+                array,
+                T::json_schema(),
+            ]
+        })
+    }
+
+    fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+        register_field::<Vec<Annotated<T>>>(registry, path, "values", None, None, true);
+        register_field::<Annotated<bool>>(registry, path, "truncated", None, None, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Map;
+
+    #[test]
+    fn test_renamed_fields() {
+        let schema = event_json_schema();
+        let properties = &schema["properties"];
+
+        assert_eq!(schema["type"], "object");
+        assert!(properties.get("event_id").is_some());
+        assert!(properties.get("exception").is_some());
+        assert!(properties.get("sdk").is_some());
+        assert!(properties.get("id").is_none());
+        assert!(properties.get("other").is_none());
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let schema = Breadcrumb::json_schema();
+        assert_eq!(schema["required"], json!(["timestamp"]));
+
+        let schema = Request::json_schema();
+        assert!(schema.get("required").is_none());
+    }
+
+    #[test]
+    fn test_schema_registry() {
+        let registry = schema_registry();
+        let field = |path: &str| {
+            registry
+                .iter()
+                .find(|field| field.path == path)
+                .unwrap_or_else(|| panic!("missing field {}", path))
+        };
+
+        let email = field("user.email");
+        assert_eq_str!(email.ty, "string");
+        assert_eq!(email.pii_kind, Some(PiiKind::Email));
+        assert!(!email.required);
+
+        let message = field("logentry.message");
+        assert_eq!(message.cap, Some(Cap::Message));
+
+        let message = field("breadcrumbs.values.*.message");
+        assert_eq!(message.pii_kind, Some(PiiKind::Freeform));
+        assert_eq!(message.cap, Some(Cap::Message));
+
+        let authorization = field("request.headers.Authorization");
+        assert_eq!(authorization.pii_kind, Some(PiiKind::AuthToken));
+
+        assert_eq!(field("breadcrumbs.values.*.timestamp").required, true);
+        assert_eq_str!(field("exception.values.*.stacktrace.frames").ty, "array");
+        assert_eq!(field("extra").pii_kind, Some(PiiKind::Databag));
+
+        assert_eq_str!(field("event_id").ty, "string");
+        assert!(registry
+            .iter()
+            .all(|field| field.path.split('.').all(|key| key != "other")));
+    }
+
+    #[test]
+    fn test_newtype() {
+        assert_eq!(
+            Headers::json_schema(),
+            Map::<String>::json_schema()
+        );
+    }
+}
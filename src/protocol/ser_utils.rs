@@ -0,0 +1,159 @@
+//! Predicates for `skip_serializing_if` on annotated values.
+//!
+//! These helpers are used by the protocol types and can be used by custom types that derive
+//! `Serialize` and `ProcessAnnotatedValue` outside of this crate:
+//!
+//! ```
+//! # extern crate marshal;
+//! # #[macro_use] extern crate serde_derive;
+//! use marshal::protocol::{ser_utils, Annotated, Array};
+//!
+//! #[derive(Serialize)]
+//! struct Extension {
+//!     #[serde(skip_serializing_if = "ser_utils::is_empty_array")]
+//!     items: Annotated<Array<String>>,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! A value is only skipped if it has no meta data attached.  Otherwise, the meta data would get
+//! lost when the value is deserialized again.  While serializing meta data, values are never
+//! skipped.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap;
+
+use super::common::{Array, Map, Values};
+use super::meta::{should_serialize_meta, Annotated};
+
+/// Values that can be empty.
+///
+/// This is used by the generic `is_empty` predicate.
+pub trait Empty {
+    /// Returns `true` if the value is empty and can be skipped during serialization.
+    fn is_empty(&self) -> bool;
+}
+
+impl<T> Empty for Option<T> {
+    fn is_empty(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl Empty for String {
+    fn is_empty(&self) -> bool {
+        String::is_empty(self)
+    }
+}
+
+impl<T> Empty for Vec<T> {
+    fn is_empty(&self) -> bool {
+        Vec::is_empty(self)
+    }
+}
+
+impl<K, V> Empty for BTreeMap<K, V> {
+    fn is_empty(&self) -> bool {
+        BTreeMap::is_empty(self)
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K, V> Empty for IndexMap<K, V> {
+    fn is_empty(&self) -> bool {
+        IndexMap::is_empty(self)
+    }
+}
+
+impl<T> Empty for Values<T> {
+    fn is_empty(&self) -> bool {
+        Values::is_empty(self)
+    }
+}
+
+/// Returns `true` if the annotated value can be skipped according to the predicate.
+///
+/// Missing values are always skipped unless they carry meta data.
+pub fn skip_if<T, F>(annotated: &Annotated<T>, predicate: F) -> bool
+where
+    F: FnOnce(&T) -> bool,
+{
+    // There are two serialization modes:
+    //  1. Data serialization (default). If there is meta data attached, we must not skip this
+    //     value, as otherwise deserialization in the next relay will not pick it up later.
+    //     Otherwise, we can safely execute the predicate.
+    //  2. Meta serialization. We can never skip, and the MetaTreeSerializer will recursively prune
+    //     empty meta nodes.
+
+    !should_serialize_meta()
+        && annotated.meta().is_empty()
+        && annotated.value().map_or(true, predicate)
+}
+
+/// Skips missing and empty values of any type implementing `Empty`.
+pub fn is_empty<T: Empty>(annotated: &Annotated<T>) -> bool {
+    skip_if(annotated, Empty::is_empty)
+}
+
+/// Skips missing values and values equal to their default.
+pub fn is_default<T: Default + PartialEq>(annotated: &Annotated<T>) -> bool {
+    skip_if(annotated, |value| *value == T::default())
+}
+
+/// Skips missing values.
+pub fn is_missing<T>(annotated: &Annotated<T>) -> bool {
+    skip_if(annotated, |_| false)
+}
+
+/// Skips missing and `false` values.
+pub fn is_false(annotated: &Annotated<bool>) -> bool {
+    skip_if(annotated, |b| !b)
+}
+
+/// Skips missing and `None` values.
+pub fn is_none<T>(annotated: &Annotated<Option<T>>) -> bool {
+    skip_if(annotated, Option::is_none)
+}
+
+/// Skips missing and empty value lists.
+pub fn is_empty_values<T>(annotated: &Annotated<Values<T>>) -> bool {
+    skip_if(annotated, Values::is_empty)
+}
+
+/// Skips missing and empty arrays.
+pub fn is_empty_array<V>(annotated: &Annotated<Array<V>>) -> bool {
+    skip_if(annotated, Array::is_empty)
+}
+
+/// Skips missing and empty maps.
+pub fn is_empty_map<V>(annotated: &Annotated<Map<V>>) -> bool {
+    skip_if(annotated, Map::is_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::Meta;
+
+    #[test]
+    fn test_is_empty() {
+        assert!(is_empty(&Annotated::<String>::empty()));
+        assert!(is_empty(&Annotated::from(String::new())));
+        assert!(is_empty(&Annotated::from(Vec::<u32>::new())));
+        assert!(!is_empty(&Annotated::from(vec![1])));
+        assert!(!is_empty(&Annotated::new(
+            String::new(),
+            Meta::from_error("some error"),
+        )));
+    }
+
+    #[test]
+    fn test_is_default() {
+        assert!(is_default(&Annotated::from(0u32)));
+        assert!(!is_default(&Annotated::from(42u32)));
+        assert!(is_missing(&Annotated::<u32>::empty()));
+        assert!(!is_missing(&Annotated::from(0u32)));
+    }
+}
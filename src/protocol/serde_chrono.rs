@@ -3,15 +3,29 @@
 use std::fmt;
 
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::de::Unexpected;
 use serde::{de, ser};
 
 use super::meta::Annotated;
 use super::serde::{CustomDeserialize, CustomSerialize, ForwardSerialize};
 
-pub fn timestamp_to_datetime(ts: f64) -> DateTime<Utc> {
-    let secs = ts as i64;
-    let micros = (ts.fract() * 1_000_000f64) as u32;
-    Utc.timestamp_opt(secs, micros * 1000).unwrap()
+/// Converts fractional seconds since the UNIX epoch into a `DateTime`.
+///
+/// Negative timestamps refer to dates before 1970.  Returns `None` if the timestamp is not finite
+/// or exceeds the range of `DateTime`.
+pub fn timestamp_to_datetime(ts: f64) -> Option<DateTime<Utc>> {
+    if !ts.is_finite() {
+        return None;
+    }
+
+    // round towards negative infinity so that the fraction is always positive
+    let secs = ts.floor();
+    if secs < i64::min_value() as f64 || secs > i64::max_value() as f64 {
+        return None;
+    }
+
+    let micros = ((ts - secs) * 1_000_000f64) as u32;
+    Utc.timestamp_opt(secs as i64, micros * 1000).single()
 }
 
 struct SecondsTimestampVisitor;
@@ -24,15 +38,24 @@ impl<'de> de::Visitor<'de> for SecondsTimestampVisitor {
     }
 
     fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
-        Ok(timestamp_to_datetime(value))
+        timestamp_to_datetime(value)
+            .ok_or_else(|| E::invalid_value(Unexpected::Float(value), &self))
     }
 
     fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
-        Ok(Utc.timestamp_opt(value, 0).unwrap())
+        Utc.timestamp_opt(value, 0)
+            .single()
+            .ok_or_else(|| E::invalid_value(Unexpected::Signed(value), &self))
     }
 
     fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
-        Ok(Utc.timestamp_opt(value as i64, 0).unwrap())
+        let datetime = if value <= i64::max_value() as u64 {
+            Utc.timestamp_opt(value as i64, 0).single()
+        } else {
+            None
+        };
+
+        datetime.ok_or_else(|| E::invalid_value(Unexpected::Unsigned(value), &self))
     }
 
     fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
@@ -134,6 +157,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_negative_timestamp() {
+        let deserializer = &mut Deserializer::from_str("-1.5");
+        assert_eq_dbg!(
+            deserialize(deserializer).unwrap(),
+            Annotated::from(Utc.ymd(1969, 12, 31).and_hms_milli(23, 59, 58, 500))
+        );
+
+        let deserializer = &mut Deserializer::from_str("-86400");
+        assert_eq_dbg!(
+            deserialize(deserializer).unwrap(),
+            Annotated::from(Utc.ymd(1969, 12, 31).and_hms(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_timestamp_out_of_range() {
+        let deserializer = &mut Deserializer::from_str("9223372036854775807");
+        assert_eq_dbg!(
+            deserialize::<DateTime<Utc>, _>(deserializer).unwrap(),
+            Annotated::from_error(
+                "invalid value: integer `9223372036854775807`, expected a unix timestamp"
+            )
+        );
+    }
+
     #[test]
     fn test_date() {
         let deserializer = &mut Deserializer::from_str("\"2000-01-01T00:00:00Z\"");
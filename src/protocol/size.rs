@@ -0,0 +1,234 @@
+//! Size estimation of protocol values.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::Serialize;
+use serde_json;
+
+use super::common::Values;
+use super::meta::Annotated;
+use super::types::Event;
+
+/// A writer that discards all data and only counts the bytes written.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Estimates the size of a value's JSON serialization in bytes.
+///
+/// Meta data is not included in the estimate.  If the value cannot be serialized, the size of
+/// the output written up to the error is returned.
+pub fn estimate_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).ok();
+    counter.0
+}
+
+/// Estimated sizes of an event and its top-level fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventSizes {
+    /// The estimated size of the entire event in bytes.
+    pub total: usize,
+    /// The estimated sizes of all top-level fields by their serialized name.
+    pub fields: BTreeMap<String, usize>,
+}
+
+impl EventSizes {
+    /// Returns the estimated size of the given top-level field or `0` if it is missing.
+    pub fn get(&self, field: &str) -> usize {
+        self.fields.get(field).cloned().unwrap_or(0)
+    }
+}
+
+/// Presence and size of a single interface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceStat {
+    /// Number of entries in the interface, for instance exceptions or breadcrumbs.
+    ///
+    /// This is `1` for interfaces with a single value, such as `request`.
+    pub count: usize,
+    /// Estimated size of the serialized interface in bytes.
+    pub size: usize,
+}
+
+impl InterfaceStat {
+    /// Returns `true` if the interface is present in the event.
+    pub fn is_present(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Summary of the interfaces present in an event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// Exceptions in the exception interface.
+    pub exception: InterfaceStat,
+    /// The event stack trace.
+    pub stacktrace: InterfaceStat,
+    /// Threads in the threads interface.
+    pub threads: InterfaceStat,
+    /// Breadcrumbs in the breadcrumbs interface.
+    pub breadcrumbs: InterfaceStat,
+    /// The request interface.
+    pub request: InterfaceStat,
+    /// The user interface.
+    pub user: InterfaceStat,
+    /// Contexts in the contexts interface.
+    pub contexts: InterfaceStat,
+    /// The template interface.
+    pub template: InterfaceStat,
+    /// Debug images in the debug meta interface.
+    pub debug_meta: InterfaceStat,
+    /// The log entry interface.
+    pub logentry: InterfaceStat,
+    /// Spans of a transaction.
+    pub spans: InterfaceStat,
+}
+
+fn option_count<T>(annotated: &Annotated<Option<T>>) -> usize {
+    match annotated.value() {
+        Some(&Some(_)) => 1,
+        _ => 0,
+    }
+}
+
+fn values_count<T>(annotated: &Annotated<Values<T>>) -> usize {
+    annotated
+        .value()
+        .and_then(|values| values.values.value())
+        .map_or(0, Vec::len)
+}
+
+impl Event {
+    /// Returns which interfaces are present in this event.
+    ///
+    /// Counts and sizes reflect the current state of the event.  Sizes are estimated like in
+    /// `estimate_sizes`, but only for interfaces that are present.
+    pub fn interface_stats(&self) -> InterfaceStats {
+        fn stat<T: Serialize>(interface: &T, count: usize) -> InterfaceStat {
+            let size = if count > 0 {
+                estimate_size(interface)
+            } else {
+                0
+            };
+            InterfaceStat { count, size }
+        }
+
+        let debug_images = match self.debug_meta.value() {
+            Some(&Some(ref debug_meta)) => debug_meta.images.value().map_or(0, Vec::len),
+            _ => 0,
+        };
+
+        InterfaceStats {
+            exception: stat(&self.exceptions, values_count(&self.exceptions)),
+            stacktrace: stat(&self.stacktrace, option_count(&self.stacktrace)),
+            threads: stat(&self.threads, values_count(&self.threads)),
+            breadcrumbs: stat(&self.breadcrumbs, values_count(&self.breadcrumbs)),
+            request: stat(&self.request, option_count(&self.request)),
+            user: stat(&self.user, option_count(&self.user)),
+            contexts: stat(&self.contexts, self.contexts.value().map_or(0, |c| c.len())),
+            template: stat(&self.template_info, option_count(&self.template_info)),
+            debug_meta: stat(&self.debug_meta, debug_images),
+            logentry: stat(&self.logentry, option_count(&self.logentry)),
+            spans: stat(&self.spans, self.spans.value().map_or(0, Vec::len)),
+        }
+    }
+
+    /// Estimates the serialized size of this event and its top-level fields.
+    ///
+    /// Fields are reported by their serialized name (e.g. `"exception"`), including unknown
+    /// fields in `other`.  Fields that are skipped during serialization are not reported.
+    pub fn estimate_sizes(&self) -> EventSizes {
+        let mut sizes = EventSizes::default();
+
+        let map = match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => return sizes,
+        };
+
+        // The total accounts for the surrounding braces, separating commas and all keys.
+        sizes.total = 2 + map.len().saturating_sub(1);
+        for (key, value) in map {
+            let size = estimate_size(&value);
+            sizes.total += estimate_size(&key) + 1 + size;
+            sizes.fields.insert(key, size);
+        }
+
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::*;
+
+    #[test]
+    fn test_estimate_size() {
+        assert_eq!(estimate_size(&Value::Null), 4);
+        assert_eq!(estimate_size(&Value::from("hello")), 7);
+        assert_eq!(estimate_size(&Annotated::<Value>::empty()), 4);
+    }
+
+    #[test]
+    fn test_event_sizes() {
+        let event = Annotated::<Event>::from_json(
+            r#"{
+                "message": "hello",
+                "extra": {"a": 1}
+            }"#,
+        ).unwrap();
+
+        let event = event.value().unwrap();
+        let sizes = event.estimate_sizes();
+
+        assert_eq!(sizes.get("message"), 7);
+        assert_eq!(sizes.get("extra"), 7);
+        assert_eq!(sizes.get("breadcrumbs"), 0);
+        assert_eq!(sizes.fields.len(), 2);
+        assert_eq!(sizes.total, serde_json::to_string(event).unwrap().len());
+    }
+
+    #[test]
+    fn test_empty_event_sizes() {
+        let event = Annotated::<Event>::from_json("{}").unwrap();
+        let sizes = event.value().unwrap().estimate_sizes();
+        assert_eq!(sizes.total, 2);
+        assert!(sizes.fields.is_empty());
+    }
+
+    #[test]
+    fn test_interface_stats() {
+        let event = Annotated::<Event>::from_json(
+            r#"{
+                "exception": {"values": [{"type": "A"}, {"type": "B"}]},
+                "sentry.interfaces.User": {"id": "1"},
+                "breadcrumbs": {"values": [{"message": "hello"}]}
+            }"#,
+        ).unwrap();
+
+        let stats = event.value().unwrap().interface_stats();
+        assert_eq!(stats.exception.count, 2);
+        assert_eq!(stats.exception.size, 38);
+        assert_eq!(stats.user, InterfaceStat { count: 1, size: 10 });
+        assert_eq!(stats.breadcrumbs.count, 1);
+        assert!(!stats.request.is_present());
+        assert_eq!(stats.request.size, 0);
+    }
+
+    #[test]
+    fn test_interface_stats_constructed() {
+        let event = Event::default();
+        assert_eq!(event.interface_stats(), InterfaceStats::default());
+    }
+}
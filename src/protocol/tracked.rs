@@ -4,6 +4,8 @@ use serde::de::{self, DeserializeSeed, Deserializer, Error, State, Visitor};
 use std::fmt;
 use std::rc::Rc;
 
+use super::lenient::PathSegment;
+
 fn state_with_parent_path<F: FnOnce(Rc<Path>) -> Rc<Path>>(state: &State, f: F) -> State {
     let mut rv = state.clone();
     let parent = state
@@ -37,6 +39,34 @@ impl Path {
             Path::NewtypeVariant { parent } => parent.key(),
         }
     }
+
+    /// Returns the keys and indexes leading to this path, skipping options and newtypes.
+    pub fn segments(&self) -> Vec<PathSegment> {
+        let mut segments = vec![];
+        let mut path = self;
+        loop {
+            path = match *path {
+                Path::Root => break,
+                Path::Seq { ref parent, index } => {
+                    segments.push(PathSegment::Index(index));
+                    &**parent
+                }
+                Path::Map {
+                    ref parent,
+                    ref key,
+                } => {
+                    segments.push(PathSegment::Key(key.clone()));
+                    &**parent
+                }
+                Path::Some { ref parent }
+                | Path::NewtypeStruct { ref parent }
+                | Path::NewtypeVariant { ref parent } => &**parent,
+            };
+        }
+
+        segments.reverse();
+        segments
+    }
 }
 
 impl fmt::Display for Path {
@@ -82,6 +112,14 @@ impl<D> TrackedDeserializer<D> {
         state.set(Rc::new(Path::Root));
         TrackedDeserializer { de, state }
     }
+
+    /// Creates a deserializer that continues at the path stored in the given state.
+    pub fn resume(de: D, mut state: State) -> Self {
+        if state.get::<Rc<Path>>().is_none() {
+            state.set(Rc::new(Path::Root));
+        }
+        TrackedDeserializer { de, state }
+    }
 }
 
 impl<'de, D> Deserializer<'de> for TrackedDeserializer<D>
@@ -144,6 +182,16 @@ where
             .deserialize_i64(TrackedVisitor::new(visitor, &self.state))
     }
 
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.de
+            .deserialize_i128(TrackedVisitor::new(visitor, &self.state))
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.de
+            .deserialize_u128(TrackedVisitor::new(visitor, &self.state))
+    }
+
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
         self.de
             .deserialize_f32(TrackedVisitor::new(visitor, &self.state))
@@ -329,6 +377,14 @@ where
         self.delegate.visit_u64(v)
     }
 
+    fn visit_i128<E: Error>(self, v: i128) -> Result<Self::Value, E> {
+        self.delegate.visit_i128(v)
+    }
+
+    fn visit_u128<E: Error>(self, v: u128) -> Result<Self::Value, E> {
+        self.delegate.visit_u128(v)
+    }
+
     fn visit_f32<E: Error>(self, v: f32) -> Result<Self::Value, E> {
         self.delegate.visit_f32(v)
     }
@@ -551,6 +607,16 @@ where
             .deserialize_i64(CaptureKey::new(visitor, self.key))
     }
 
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, X::Error> {
+        self.delegate
+            .deserialize_i128(CaptureKey::new(visitor, self.key))
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, X::Error> {
+        self.delegate
+            .deserialize_u128(CaptureKey::new(visitor, self.key))
+    }
+
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, X::Error> {
         self.delegate
             .deserialize_f32(CaptureKey::new(visitor, self.key))
@@ -721,6 +787,14 @@ where
         self.delegate.visit_u64(v)
     }
 
+    fn visit_i128<E: Error>(self, v: i128) -> Result<Self::Value, E> {
+        self.delegate.visit_i128(v)
+    }
+
+    fn visit_u128<E: Error>(self, v: u128) -> Result<Self::Value, E> {
+        self.delegate.visit_u128(v)
+    }
+
     fn visit_f32<E: Error>(self, v: f32) -> Result<Self::Value, E> {
         self.delegate.visit_f32(v)
     }
@@ -1,17 +1,20 @@
 //! Types of the sentry protocol.
 
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::{fmt, str};
 
 use chrono::{DateTime, Utc};
 use debugid::DebugId;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json;
 use uuid::Uuid;
 
 use super::buffer::{Content, ContentDeserializer};
 use super::common::{Array, Map, Value, Values};
-use super::meta::Annotated;
+use super::meta::{Annotated, Meta};
 use super::serde::CustomSerialize;
-use super::{serde_chrono, utils};
+use super::{ser_utils, serde_chrono};
 
 /// An error used when parsing `Level`.
 #[derive(Debug, Fail)]
@@ -43,12 +46,12 @@ impl str::FromStr for Level {
     type Err = ParseLevelError;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        Ok(match string {
+        Ok(match string.to_lowercase().as_str() {
             "debug" => Level::Debug,
             "info" | "log" => Level::Info,
-            "warning" => Level::Warning,
+            "warning" | "warn" => Level::Warning,
             "error" => Level::Error,
-            "fatal" => Level::Fatal,
+            "fatal" | "critical" => Level::Fatal,
             _ => return Err(ParseLevelError),
         })
     }
@@ -70,39 +73,108 @@ mod level {
     use super::*;
     use serde::de;
 
+    /// A level along with a note if the level was coerced from an unknown value.
+    pub struct CoercedLevel(pub Level, pub Option<String>);
+
+    impl CoercedLevel {
+        /// Maps a Python logging level to the nearest severity level.
+        #[cfg_attr(feature = "cargo-clippy", allow(float_cmp))]
+        fn from_number(v: f64) -> CoercedLevel {
+            // Log level from: https://docs.python.org/2/library/logging.html#logging-levels
+            let level = match v {
+                v if v == 10.0 => return CoercedLevel(Level::Debug, None),
+                v if v == 20.0 => return CoercedLevel(Level::Info, None),
+                v if v == 30.0 => return CoercedLevel(Level::Warning, None),
+                v if v == 40.0 => return CoercedLevel(Level::Error, None),
+                v if v == 50.0 => return CoercedLevel(Level::Fatal, None),
+                v if v < 15.0 => Level::Debug,
+                v if v < 25.0 => Level::Info,
+                v if v < 35.0 => Level::Warning,
+                v if v < 45.0 => Level::Error,
+                _ => Level::Fatal,
+            };
+
+            CoercedLevel(level, Some(format!("coerced level from {}", v)))
+        }
+    }
+
     struct LevelVisitor;
 
     impl<'de> de::Visitor<'de> for LevelVisitor {
-        type Value = Level;
+        type Value = CoercedLevel;
 
         fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
             write!(f, "a severity level")
         }
 
         fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
-            // Log level from: https://docs.python.org/2/library/logging.html#logging-levels
-            Ok(match v {
-                10 => Level::Debug,
-                20 => Level::Info,
-                30 => Level::Warning,
-                40 => Level::Error,
-                50 => Level::Fatal,
-                _ => return Err(E::custom(ParseLevelError)),
-            })
+            Ok(CoercedLevel::from_number(v as f64))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(CoercedLevel::from_number(v as f64))
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+            if v.is_finite() {
+                Ok(CoercedLevel::from_number(v))
+            } else {
+                Err(E::custom(ParseLevelError))
+            }
         }
 
         fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
-            v.parse().map_err(E::custom)
+            match v.parse() {
+                Ok(level) => Ok(CoercedLevel(level, None)),
+                Err(err) => match v.trim().parse::<f64>() {
+                    Ok(number) => self.visit_f64(number),
+                    Err(_) => Err(E::custom(err)),
+                },
+            }
         }
     }
 
-    impl<'de> Deserialize<'de> for Level {
+    impl<'de> Deserialize<'de> for CoercedLevel {
         fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
             deserializer.deserialize_any(LevelVisitor)
         }
     }
 
+    impl<'de> Deserialize<'de> for Level {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(CoercedLevel::deserialize(deserializer)?.0)
+        }
+    }
+
     impl_str_ser!(Level);
+
+    fn annotate(meta: &mut Meta, level: CoercedLevel) -> Level {
+        if let Some(note) = level.1 {
+            meta.errors_mut().push(note);
+        }
+        level.0
+    }
+
+    /// Deserializes an optional level, recording coerced values in the meta data.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Annotated<Option<Level>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Annotated(value, mut meta) =
+            Annotated::<Option<CoercedLevel>>::deserialize(deserializer)?;
+        let value = value.map(|level| level.map(|level| annotate(&mut meta, level)));
+        Ok(Annotated(value, meta))
+    }
+
+    /// Deserializes a level, recording coerced values in the meta data.
+    pub fn deserialize_required<'de, D>(deserializer: D) -> Result<Annotated<Level>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Annotated(value, mut meta) = Annotated::<CoercedLevel>::deserialize(deserializer)?;
+        let value = value.map(|level| annotate(&mut meta, level));
+        Ok(Annotated(value, meta))
+    }
 }
 
 #[cfg(test)]
@@ -119,20 +191,73 @@ mod test_level {
     fn test_numeric() {
         assert_eq_dbg!(Level::Warning, serde_json::from_str("30").unwrap());
     }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert_eq_dbg!(Level::Warning, serde_json::from_str("\"WARNING\"").unwrap());
+        assert_eq_dbg!(Level::Fatal, serde_json::from_str("\"Critical\"").unwrap());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(serde_json::from_str::<Level>("\"verbose\"").is_err());
+    }
+
+    fn deserialize(json: &str) -> Annotated<Option<Level>> {
+        super::level::deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap()
+    }
+
+    #[test]
+    fn test_coerced_numeric() {
+        assert_eq_dbg!(deserialize("40"), Annotated::from(Some(Level::Error)));
+        assert_eq_dbg!(
+            deserialize("35"),
+            Annotated::new(Some(Level::Error), Meta::from_error("coerced level from 35"))
+        );
+        assert_eq_dbg!(
+            deserialize("0"),
+            Annotated::new(Some(Level::Debug), Meta::from_error("coerced level from 0"))
+        );
+        assert_eq_dbg!(
+            deserialize("-5"),
+            Annotated::new(Some(Level::Debug), Meta::from_error("coerced level from -5"))
+        );
+        assert_eq_dbg!(
+            deserialize("\"99\""),
+            Annotated::new(Some(Level::Fatal), Meta::from_error("coerced level from 99"))
+        );
+        assert_eq_dbg!(deserialize("null"), Annotated::from(None));
+    }
+
+    #[test]
+    fn test_event_level() {
+        let event = Annotated::<Event>::from_json(r#"{"level": 25}"#).unwrap();
+        assert_eq_dbg!(
+            event.value().unwrap().level,
+            Annotated::new(Some(Level::Warning), Meta::from_error("coerced level from 25"))
+        );
+    }
 }
 
 /// A log entry message.
 ///
 /// A log message is similar to the `message` attribute on the event itself but
-/// can additionally hold optional parameters.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+/// can additionally hold optional parameters.  During normalization, the parameters are
+/// interpolated into the message to fill in `formatted` if it is missing.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct LogEntry {
     /// The log message with parameter placeholders (required).
     #[process_annotated_value(pii_kind = "freeform", cap = "message")]
     pub message: Annotated<String>,
 
+    /// The log message with all parameters interpolated.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(pii_kind = "freeform", cap = "message")]
+    pub formatted: Annotated<Option<String>>,
+
     /// Positional parameters to be interpolated into the log message.
-    #[serde(default, skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_array")]
     #[process_annotated_value(pii_kind = "databag")]
     pub params: Annotated<Array<Value>>,
 
@@ -160,6 +285,7 @@ mod test_logentry {
 
         let entry = LogEntry {
             message: "Hello, %s %s!".to_string().into(),
+            formatted: None.into(),
             params: vec![
                 Value::String("World".to_string()).into(),
                 Value::U64(1).into(),
@@ -183,6 +309,7 @@ mod test_logentry {
         let json = r#"{"message":"mymessage"}"#;
         let entry = LogEntry {
             message: "mymessage".to_string().into(),
+            formatted: None.into(),
             params: Default::default(),
             other: Default::default(),
         };
@@ -199,17 +326,18 @@ mod test_logentry {
 }
 
 /// Reference to a source code repository.
-#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct RepoReference {
     /// Name of the repository as registered in Sentry (required).
     pub name: Annotated<String>,
 
     /// Prefix to apply to source code when pairing it with files in the repository.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub prefix: Annotated<Option<String>>,
 
     /// Current reivision of the repository at build time.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub revision: Annotated<Option<String>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -270,29 +398,249 @@ mod test_repos {
     }
 }
 
+/// Geographical location of the end user or device.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Geo {
+    /// Two-letter country code (ISO 3166-1 alpha-2).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(pii_kind = "location")]
+    pub country_code: Annotated<Option<String>>,
+
+    /// Human readable city name.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(pii_kind = "location")]
+    pub city: Annotated<Option<String>>,
+
+    /// Human readable region name or code.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(pii_kind = "location")]
+    pub region: Annotated<Option<String>>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    #[process_annotated_value(pii_kind = "location")]
+    pub other: Annotated<Map<Value>>,
+}
+
+#[cfg(test)]
+mod test_geo {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_roundtrip() {
+        let json = r#"{
+  "country_code": "US",
+  "city": "San Francisco",
+  "region": "CA",
+  "other": "value"
+}"#;
+        let geo = Geo {
+            country_code: Some("US".to_string()).into(),
+            city: Some("San Francisco".to_string()).into(),
+            region: Some("CA".to_string()).into(),
+            other: {
+                let mut map = Map::new();
+                map.insert(
+                    "other".to_string(),
+                    Value::String("value".to_string()).into(),
+                );
+                Annotated::from(map)
+            },
+        };
+
+        assert_eq_dbg!(geo, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string_pretty(&geo).unwrap());
+    }
+
+    #[test]
+    fn test_default_values() {
+        let json = "{}";
+        let geo = Geo {
+            country_code: None.into(),
+            city: None.into(),
+            region: None.into(),
+            other: Default::default(),
+        };
+
+        assert_eq_dbg!(geo, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string(&geo).unwrap());
+    }
+}
+
+/// An error used when parsing `Ip`.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid ip address")]
+pub struct ParseIpError;
+
+/// The network scope of an IP address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IpClass {
+    /// A loopback address, such as `127.0.0.1` or `::1`.
+    Loopback,
+    /// An address that is not globally routable, such as addresses in private networks,
+    /// link-local and unique local addresses.
+    Private,
+    /// Any other address, which is presumed to be globally routable.
+    Public,
+}
+
+/// An IPv4 or IPv6 address.
+///
+/// Addresses are parsed with `std::net::IpAddr` and written in their canonical form.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ip(pub IpAddr);
+
+impl Ip {
+    /// Returns the network scope of this address.
+    ///
+    /// IPv4 addresses mapped to IPv6 (`::ffff:a.b.c.d`) are classified like the IPv4 address.
+    pub fn class(&self) -> IpClass {
+        match self.0 {
+            IpAddr::V4(addr) => classify_ipv4(addr),
+            IpAddr::V6(addr) => classify_ipv6(addr),
+        }
+    }
+
+    /// Returns `true` if this address is presumed to be globally routable.
+    pub fn is_public(&self) -> bool {
+        self.class() == IpClass::Public
+    }
+}
+
+fn classify_ipv4(addr: Ipv4Addr) -> IpClass {
+    let octets = addr.octets();
+    if addr.is_loopback() {
+        IpClass::Loopback
+    } else if addr.is_private()
+        || addr.is_link_local()
+        || addr.is_unspecified()
+        || addr.is_broadcast()
+        // shared address space of carrier-grade NATs (100.64.0.0/10)
+        || (octets[0] == 100 && octets[1] & 0xc0 == 64)
+    {
+        IpClass::Private
+    } else {
+        IpClass::Public
+    }
+}
+
+fn classify_ipv6(addr: Ipv6Addr) -> IpClass {
+    let segments = addr.segments();
+    if addr.is_loopback() {
+        IpClass::Loopback
+    } else if segments[..5].iter().all(|&s| s == 0) && segments[5] == 0xffff {
+        let octets = addr.octets();
+        classify_ipv4(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ))
+    } else if addr.is_unspecified()
+        // unique local (fc00::/7) and link-local (fe80::/10) addresses
+        || segments[0] & 0xfe00 == 0xfc00
+        || segments[0] & 0xffc0 == 0xfe80
+    {
+        IpClass::Private
+    } else {
+        IpClass::Public
+    }
+}
+
+impl str::FromStr for Ip {
+    type Err = ParseIpError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        string.parse().map(Ip).map_err(|_| ParseIpError)
+    }
+}
+
+impl fmt::Display for Ip {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl_str_serde!(Ip);
+
+#[cfg(test)]
+mod test_ip {
+    use protocol::*;
+    use serde_json;
+
+    fn class(ip: &str) -> IpClass {
+        ip.parse::<Ip>().unwrap().class()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let ip: Ip = serde_json::from_str("\"2001:DB8:0:0::1\"").unwrap();
+        assert_eq_str!(serde_json::to_string(&ip).unwrap(), "\"2001:db8::1\"");
+
+        let ip: Ip = serde_json::from_str("\"10.0.0.1\"").unwrap();
+        assert_eq_str!(ip.to_string(), "10.0.0.1");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("256.0.0.1".parse::<Ip>().is_err());
+        assert!("::1::".parse::<Ip>().is_err());
+        assert!(" 127.0.0.1".parse::<Ip>().is_err());
+        assert!("{{auto}}".parse::<Ip>().is_err());
+    }
+
+    #[test]
+    fn test_class() {
+        assert_eq!(class("127.0.0.1"), IpClass::Loopback);
+        assert_eq!(class("::1"), IpClass::Loopback);
+        assert_eq!(class("::ffff:127.0.0.1"), IpClass::Loopback);
+
+        assert_eq!(class("10.1.2.3"), IpClass::Private);
+        assert_eq!(class("172.16.0.1"), IpClass::Private);
+        assert_eq!(class("192.168.1.1"), IpClass::Private);
+        assert_eq!(class("169.254.0.1"), IpClass::Private);
+        assert_eq!(class("100.64.0.1"), IpClass::Private);
+        assert_eq!(class("0.0.0.0"), IpClass::Private);
+        assert_eq!(class("fd00::1"), IpClass::Private);
+        assert_eq!(class("fe80::1"), IpClass::Private);
+        assert_eq!(class("::ffff:192.168.0.1"), IpClass::Private);
+
+        assert_eq!(class("8.8.8.8"), IpClass::Public);
+        assert_eq!(class("100.128.0.1"), IpClass::Public);
+        assert_eq!(class("2001:4860:4860::8888"), IpClass::Public);
+        assert_eq!(class("::ffff:8.8.8.8"), IpClass::Public);
+        assert!("1.1.1.1".parse::<Ip>().unwrap().is_public());
+    }
+}
+
 /// Information about the user who triggered an event.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct User {
     /// Unique identifier of the user.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "id")]
     pub id: Annotated<Option<String>>,
 
     /// Email address of the user.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "email")]
     pub email: Annotated<Option<String>>,
 
     /// Remote IP address of the user. Defaults to "{{auto}}".
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "ip")]
     pub ip_address: Annotated<Option<String>>,
 
     /// Human readable name of the user.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "username")]
     pub username: Annotated<Option<String>>,
 
+    /// Approximate geographical location of the user.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value]
+    pub geo: Annotated<Option<Geo>>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[serde(flatten)]
     #[process_annotated_value(pii_kind = "databag")]
@@ -311,6 +659,11 @@ mod test_user {
   "email": "mail@example.org",
   "ip_address": "{{auto}}",
   "username": "John Doe",
+  "geo": {
+    "country_code": "AT",
+    "city": "Vienna",
+    "region": "Vienna"
+  },
   "other": "value"
 }"#;
         let user = User {
@@ -318,6 +671,12 @@ mod test_user {
             email: Some("mail@example.org".to_string()).into(),
             ip_address: Some("{{auto}}".to_string()).into(),
             username: Some("John Doe".to_string()).into(),
+            geo: Some(Geo {
+                country_code: Some("AT".to_string()).into(),
+                city: Some("Vienna".to_string()).into(),
+                region: Some("Vienna".to_string()).into(),
+                other: Default::default(),
+            }).into(),
             other: {
                 let mut map = Map::new();
                 map.insert(
@@ -340,6 +699,7 @@ mod test_user {
             email: None.into(),
             ip_address: None.into(),
             username: None.into(),
+            geo: None.into(),
             other: Default::default(),
         };
 
@@ -349,31 +709,39 @@ mod test_user {
 }
 
 /// Wrapper type for query-string like maps.
-#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Query(pub Map<Value>);
 
 /// Wrapper type for request header maps.
-#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Cookies(pub Map<String>);
 
 /// Wrapper type for request header maps.
-#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize)]
+///
+/// Header names are stored in canonical casing (such as `Content-Type`) and can be looked up
+/// regardless of their case.  Repeated headers are merged into a single value.  Headers carrying
+/// credentials (such as `Authorization` or `Cookie`) are processed as `auth_token`, all other
+/// headers inherit the PII kind of the map.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Traverse)]
 pub struct Headers(pub Map<String>);
 
 /// Http request information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Request {
     /// URL of the request.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     // TODO: cap?
     pub url: Annotated<Option<String>>,
 
     /// HTTP request method.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub method: Annotated<Option<String>>,
 
     /// Request data in any format that makes sense.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "databag")]
     // TODO: cap?
     // TODO: Custom logic + info
@@ -395,7 +763,7 @@ pub struct Request {
     pub headers: Annotated<Headers>,
 
     /// Server environment data, such as CGI/WSGI.
-    #[serde(default, skip_serializing_if = "utils::is_empty_map")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_map")]
     #[process_annotated_value(pii_kind = "databag")]
     // TODO: cap?
     pub env: Annotated<Map<Value>>,
@@ -408,23 +776,38 @@ pub struct Request {
 
 mod request {
     use cookie::Cookie;
+    use processor::{PiiKind, ProcessAnnotatedValue, Processor, ValueInfo};
     use queryst;
     use serde::de;
     use serde_json;
 
-    use super::super::utils;
+    #[cfg(feature = "schema")]
+    use super::super::schema::{register_field, FieldSchema, JsonSchema, Schema};
+    use super::super::ser_utils;
     use super::*;
 
+    /// Names of headers that carry credentials.
+    const AUTH_HEADERS: &[&str] = &[
+        "Authorization",
+        "Proxy-Authorization",
+        "Cookie",
+        "Set-Cookie",
+        "X-Api-Key",
+        "X-Auth-Token",
+        "X-Csrftoken",
+        "X-Xsrf-Token",
+    ];
+
     pub fn is_empty_query(annotated: &Annotated<Query>) -> bool {
-        utils::skip_if(annotated, |query| query.0.is_empty())
+        ser_utils::skip_if(annotated, |query| query.0.is_empty())
     }
 
     pub fn is_empty_cookies(annotated: &Annotated<Cookies>) -> bool {
-        utils::skip_if(annotated, |cookies| cookies.0.is_empty())
+        ser_utils::skip_if(annotated, |cookies| cookies.0.is_empty())
     }
 
     pub fn is_empty_headers(annotated: &Annotated<Headers>) -> bool {
-        utils::skip_if(annotated, |headers| headers.0.is_empty())
+        ser_utils::skip_if(annotated, |headers| headers.0.is_empty())
     }
 
     struct ParseQueryError(String);
@@ -514,8 +897,7 @@ mod request {
         }
     }
 
-    #[cfg_attr(feature = "cargo-clippy", allow(needless_pass_by_value))]
-    fn capitalize_header(header: String) -> String {
+    fn capitalize_header(header: &str) -> String {
         header
             .split('-')
             .enumerate()
@@ -537,27 +919,132 @@ mod request {
             })
     }
 
+    /// Appends the value of a repeated header to the existing value.
+    fn merge_header(existing: &mut Annotated<String>, other: Annotated<String>, separator: &str) {
+        let Annotated(value, meta) = other;
+        existing.meta_mut().merge(meta);
+
+        if let Some(value) = value {
+            if let Some(ref mut current) = existing.0 {
+                current.push_str(separator);
+                current.push_str(&value);
+                return;
+            }
+            existing.set_value(Some(value));
+        }
+    }
+
+    impl Headers {
+        /// Returns the header with the given name, ignoring case.
+        pub fn get(&self, name: &str) -> Option<&Annotated<String>> {
+            self.0
+                .iter()
+                .find(|&(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        }
+
+        /// Returns a mutable reference to the header with the given name, ignoring case.
+        pub fn get_mut(&mut self, name: &str) -> Option<&mut Annotated<String>> {
+            self.0
+                .iter_mut()
+                .find(|&(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        }
+
+        /// Inserts a header and converts its name to canonical casing.
+        ///
+        /// If the header is already present, the values are joined with a comma, as HTTP permits
+        /// for repeated headers.  `Cookie` values are joined with a semicolon instead.
+        pub fn insert<K: Into<String>>(&mut self, name: K, value: Annotated<String>) {
+            let name = capitalize_header(&name.into());
+            let separator = if name == "Cookie" { "; " } else { ", " };
+
+            if let Some(existing) = self.get_mut(&name) {
+                merge_header(existing, value, separator);
+                return;
+            }
+
+            self.0.insert(name, value);
+        }
+    }
+
     struct HeadersVisitor;
 
     impl<'de> de::Visitor<'de> for HeadersVisitor {
         type Value = Headers;
 
         fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "a headers map")
+            write!(f, "a headers map or list of pairs")
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut headers = Headers::default();
+            while let Some((name, value)) = seq.next_element::<(String, Annotated<String>)>()? {
+                headers.insert(name, value);
+            }
+            Ok(headers)
         }
 
         fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-            let mut headers = Map::new();
-            while let Some(entry) = map.next_entry()? {
-                headers.insert(capitalize_header(entry.0), entry.1);
+            let mut headers = Headers::default();
+            while let Some((name, value)) = map.next_entry::<String, Annotated<String>>()? {
+                headers.insert(name, value);
             }
-            Ok(Headers(headers))
+            Ok(headers)
         }
     }
 
     impl<'de> Deserialize<'de> for Headers {
         fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-            deserializer.deserialize_map(HeadersVisitor)
+            deserializer.deserialize_any(HeadersVisitor)
+        }
+    }
+
+    #[cfg(feature = "schema")]
+    impl JsonSchema for Headers {
+        fn json_schema() -> Schema {
+            Map::<String>::json_schema()
+        }
+
+        fn register_fields(path: &str, registry: &mut Vec<FieldSchema>) {
+            for name in AUTH_HEADERS {
+                let pii_kind = Some(PiiKind::AuthToken);
+                register_field::<String>(registry, path, name, pii_kind, None, false);
+            }
+        }
+    }
+
+    fn is_auth_header(name: &str) -> bool {
+        AUTH_HEADERS
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case(name))
+    }
+
+    impl ProcessAnnotatedValue for Headers {
+        fn process_annotated_value(
+            annotated: Annotated<Self>,
+            processor: &Processor,
+            info: &ValueInfo,
+        ) -> Annotated<Self> {
+            annotated.map(|Headers(headers)| {
+                let headers = headers
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let info = if is_auth_header(&name) {
+                            ValueInfo {
+                                pii_kind: Some(PiiKind::AuthToken),
+                                ..Default::default()
+                            }
+                        } else {
+                            info.derive()
+                        };
+                        let value =
+                            ProcessAnnotatedValue::process_annotated_value(value, processor, &info);
+                        (name, value)
+                    })
+                    .collect();
+                Headers(headers)
+            })
         }
     }
 }
@@ -743,78 +1230,140 @@ mod test_request {
         let query = Annotated::from(Headers(map));
         assert_eq_dbg!(query, serde_json::from_str(json).unwrap());
     }
+
+    #[test]
+    fn test_header_duplicates() {
+        let headers: Headers = serde_json::from_str(
+            r#"{
+            "accept": "text/html",
+            "Accept": "application/json",
+            "cookie": "a=1",
+            "Cookie": "b=2"
+        }"#,
+        ).unwrap();
+        assert_eq_str!(
+            headers.get("ACCEPT").unwrap().value().unwrap(),
+            "text/html, application/json"
+        );
+        assert_eq_str!(headers.get("cookie").unwrap().value().unwrap(), "a=1; b=2");
+        assert_eq!(headers.0.len(), 2);
+    }
+
+    #[test]
+    fn test_header_pairs() {
+        let headers: Headers = serde_json::from_str(
+            r#"[["x-forwarded-for", "10.0.0.1"], ["X-Forwarded-For", "10.0.0.2"]]"#,
+        ).unwrap();
+
+        let mut map = Map::new();
+        map.insert(
+            "X-Forwarded-For".to_string(),
+            "10.0.0.1, 10.0.0.2".to_string().into(),
+        );
+        assert_eq_dbg!(headers, Headers(map));
+    }
+
+    #[test]
+    fn test_header_auth_tokens() {
+        use processor::PiiConfig;
+
+        let cfg = PiiConfig::from_json(
+            r#"{
+            "applications": {
+                "auth_token": ["@anything:remove"]
+            }
+        }"#,
+        ).unwrap();
+
+        let headers = Annotated::<Headers>::from_json(
+            r#"{
+            "authorization": "Bearer 1234",
+            "referer": "https://google.com/"
+        }"#,
+        ).unwrap();
+
+        let processed = cfg.processor().process_root_value(headers);
+        let headers = &processed.value().unwrap().0;
+
+        assert!(headers.get("Authorization").unwrap().value().is_none());
+        assert_eq_str!(
+            headers.get("Referer").unwrap().value().unwrap(),
+            "https://google.com/"
+        );
+    }
 }
 
 /// Device information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct DeviceContext {
     /// Name of the device.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub name: Annotated<Option<String>>,
 
     /// Family of the device model.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub family: Annotated<Option<String>>,
 
     /// Device model (human readable).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub model: Annotated<Option<String>>,
 
     /// Device model (internal identifier).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub model_id: Annotated<Option<String>>,
 
     /// Native cpu architecture of the device.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub arch: Annotated<Option<String>>,
 
     /// Current battery level (0-100).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub battery_level: Annotated<Option<f32>>,
 
     /// Current screen orientation.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub orientation: Annotated<Option<String>>,
 
     /// Simulator/prod indicator.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub simulator: Annotated<Option<bool>>,
 
     /// Total memory available in bytes.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub memory_size: Annotated<Option<u64>>,
 
     /// How much memory is still available in bytes.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub free_memory: Annotated<Option<u64>>,
 
     /// How much memory is usable for the app in bytes.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub usable_memory: Annotated<Option<u64>>,
 
     /// Total storage size of the device in bytes.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub storage_size: Annotated<Option<u64>>,
 
     /// How much storage is free in bytes.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub free_storage: Annotated<Option<u64>>,
 
     /// Total size of the attached external storage in bytes (eg: android SDK card).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub external_storage_size: Annotated<Option<u64>>,
 
     /// Free size of the attached external storage in bytes (eg: android SDK card).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub external_free_storage: Annotated<Option<u64>>,
 
     /// Indicator when the device was booted.
-    #[serde(default, with = "serde_chrono", skip_serializing_if = "utils::is_none")]
+    #[serde(default, with = "serde_chrono", skip_serializing_if = "ser_utils::is_none")]
     pub boot_time: Annotated<Option<DateTime<Utc>>>,
 
     /// Timezone of the device.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub timezone: Annotated<Option<String>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -824,34 +1373,35 @@ pub struct DeviceContext {
 }
 
 /// Operating system information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct OsContext {
     /// Name of the operating system.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub name: Annotated<Option<String>>,
 
     /// Version of the operating system.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub version: Annotated<Option<String>>,
 
     /// Internal build number of the operating system.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub build: Annotated<Option<String>>,
 
     /// Current kernel version.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub kernel_version: Annotated<Option<String>>,
 
     /// Indicator if the OS is rooted (mobile mostly).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub rooted: Annotated<Option<bool>>,
 
     /// Unprocessed operating system info.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub raw_description: Annotated<Option<String>>,
 
@@ -862,20 +1412,21 @@ pub struct OsContext {
 }
 
 /// Runtime information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct RuntimeContext {
     /// Runtime name.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub name: Annotated<Option<String>>,
 
     /// Runtime version.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub version: Annotated<Option<String>>,
 
     /// Unprocessed runtime info.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub raw_description: Annotated<Option<String>>,
 
@@ -886,36 +1437,37 @@ pub struct RuntimeContext {
 }
 
 /// Application information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct AppContext {
     /// Start time of the app.
-    #[serde(default, with = "serde_chrono", skip_serializing_if = "utils::is_none")]
+    #[serde(default, with = "serde_chrono", skip_serializing_if = "ser_utils::is_none")]
     pub app_start_time: Annotated<Option<DateTime<Utc>>>,
 
     /// Device app hash (app specific device ID)
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "id", cap = "summary")]
     pub device_app_hash: Annotated<Option<String>>,
 
     /// Build identicator.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub build_type: Annotated<Option<String>>,
 
     /// App identifier (dotted bundle id).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub app_identifier: Annotated<Option<String>>,
 
     /// Application name as it appears on the platform.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub app_name: Annotated<Option<String>>,
 
     /// Application version as it appears on the platform.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub app_version: Annotated<Option<String>>,
 
     /// Internal build ID as it appears on the platform.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub app_build: Annotated<Option<String>>,
 
@@ -926,15 +1478,16 @@ pub struct AppContext {
 }
 
 /// Web browser information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct BrowserContext {
     /// Runtime name.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub name: Annotated<Option<String>>,
 
     /// Runtime version.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub version: Annotated<Option<String>>,
 
@@ -944,6 +1497,96 @@ pub struct BrowserContext {
     pub other: Annotated<Map<Value>>,
 }
 
+/// An error used when parsing `TraceId` or `SpanId`.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid id: expected {} hex characters", length)]
+pub struct ParseTracingIdError {
+    length: usize,
+}
+
+/// Parses a fixed-length hex identifier into lowercase.
+fn parse_tracing_id(string: &str, length: usize) -> Result<String, ParseTracingIdError> {
+    if string.len() != length || !string.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseTracingIdError { length });
+    }
+
+    Ok(string.to_ascii_lowercase())
+}
+
+/// A 32-character hex string identifying a trace.
+#[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub struct TraceId(pub String);
+
+impl str::FromStr for TraceId {
+    type Err = ParseTracingIdError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        parse_tracing_id(string, 32).map(TraceId)
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl_str_serde!(TraceId);
+
+/// A 16-character hex string identifying a span.
+#[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub struct SpanId(pub String);
+
+impl str::FromStr for SpanId {
+    type Err = ParseTracingIdError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        parse_tracing_id(string, 16).map(SpanId)
+    }
+}
+
+impl fmt::Display for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl_str_serde!(SpanId);
+
+/// Trace context of a distributed trace.
+///
+/// Invalid trace or span ids are removed and recorded as errors in the meta data.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct TraceContext {
+    /// The trace id (32 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub trace_id: Annotated<Option<TraceId>>,
+
+    /// The id of the span (16 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub span_id: Annotated<Option<SpanId>>,
+
+    /// The id of the parent span (16 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub parent_span_id: Annotated<Option<SpanId>>,
+
+    /// Operation name of the span.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(cap = "summary")]
+    pub op: Annotated<Option<String>>,
+
+    /// Status of the operation.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(cap = "summary")]
+    pub status: Annotated<Option<String>>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    #[process_annotated_value(pii_kind = "databag")]
+    pub other: Annotated<Map<Value>>,
+}
+
 /// Contexts describing the environment (e.g. device, os or browser).
 #[derive(Debug, Clone, PartialEq)]
 pub enum Context {
@@ -957,6 +1600,8 @@ pub enum Context {
     App(Box<AppContext>),
     /// Web browser information.
     Browser(Box<BrowserContext>),
+    /// Information about a distributed trace.
+    Trace(Box<TraceContext>),
     /// A context type that is unknown to this protocol specification.
     Other(String, Map<Value>),
 }
@@ -994,6 +1639,7 @@ mod context {
                 "runtime" => Context::Runtime(Deserialize::deserialize(deserializer)?),
                 "app" => Context::App(Deserialize::deserialize(deserializer)?),
                 "browser" => Context::Browser(Deserialize::deserialize(deserializer)?),
+                "trace" => Context::Trace(Deserialize::deserialize(deserializer)?),
                 _ => Context::Other(ty, Deserialize::deserialize(deserializer)?),
             })
         }
@@ -1030,6 +1676,10 @@ mod context {
                     t: "browser",
                     context: browser,
                 }.serialize(serializer),
+                Context::Trace(ref trace) => S {
+                    t: "trace",
+                    context: trace,
+                }.serialize(serializer),
                 Context::Other(ref ty, ref other) => S {
                     t: ty,
                     context: other,
@@ -1080,6 +1730,13 @@ mod context {
                         info,
                     ).map(Context::Browser)
                 }
+                Annotated(Some(Context::Trace(context)), meta) => {
+                    ProcessAnnotatedValue::process_annotated_value(
+                        Annotated::new(context, meta),
+                        processor,
+                        info,
+                    ).map(Context::Trace)
+                }
                 Annotated(Some(Context::Other(name, context)), meta) => {
                     let Annotated(context, meta) = ProcessAnnotatedValue::process_annotated_value(
                         Annotated::new(context, meta),
@@ -1364,6 +2021,73 @@ mod test_contexts {
         assert_eq_str!(json, serde_json::to_string(&context).unwrap());
     }
 
+    #[test]
+    fn test_trace_roundtrip() {
+        let json = r#"{
+  "type": "trace",
+  "trace_id": "4c79f60c11214eb38604f4ae0781bfb2",
+  "span_id": "fa90fdead5f74052",
+  "parent_span_id": "fa90fdead5f74053",
+  "op": "http.request",
+  "status": "ok",
+  "other": "value"
+}"#;
+        let context = Context::Trace(Box::new(TraceContext {
+            trace_id: Some(TraceId("4c79f60c11214eb38604f4ae0781bfb2".to_string())).into(),
+            span_id: Some(SpanId("fa90fdead5f74052".to_string())).into(),
+            parent_span_id: Some(SpanId("fa90fdead5f74053".to_string())).into(),
+            op: Some("http.request".to_string()).into(),
+            status: Some("ok".to_string()).into(),
+            other: {
+                let mut map = Map::new();
+                map.insert(
+                    "other".to_string(),
+                    Value::String("value".to_string()).into(),
+                );
+                Annotated::from(map)
+            },
+        }));
+
+        assert_eq_dbg!(context, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string_pretty(&context).unwrap());
+    }
+
+    #[test]
+    fn test_trace_default_values() {
+        let json = r#"{"type":"trace"}"#;
+        let context = Context::Trace(Box::new(TraceContext {
+            trace_id: None.into(),
+            span_id: None.into(),
+            parent_span_id: None.into(),
+            op: None.into(),
+            status: None.into(),
+            other: Default::default(),
+        }));
+
+        assert_eq_dbg!(context, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string(&context).unwrap());
+    }
+
+    #[test]
+    fn test_trace_invalid_ids() {
+        let json = r#"{
+  "type": "trace",
+  "trace_id": "4C79F60C11214EB38604F4AE0781BFB2",
+  "span_id": "fa90fdead5f7405",
+  "parent_span_id": "zz90fdead5f74053"
+}"#;
+        let context = Context::Trace(Box::new(TraceContext {
+            trace_id: Some(TraceId("4c79f60c11214eb38604f4ae0781bfb2".to_string())).into(),
+            span_id: Annotated::from_error("invalid id: expected 16 hex characters"),
+            parent_span_id: Annotated::from_error("invalid id: expected 16 hex characters"),
+            op: None.into(),
+            status: None.into(),
+            other: Default::default(),
+        }));
+
+        assert_eq_dbg!(context, serde_json::from_str(json).unwrap());
+    }
+
     #[test]
     fn test_other_roundtrip() {
         let json = r#"{"type":"mytype","other":"value"}"#;
@@ -1428,7 +2152,11 @@ fn default_breadcrumb_type() -> Annotated<String> {
 }
 
 /// A breadcrumb.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+///
+/// Breadcrumbs of type `http` carry the `url`, `method` and `status_code` of a request in their
+/// data.  When stripping PII, only the query of a `url` in the data is processed.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Breadcrumb {
     /// The timestamp of the breadcrumb (required).
     #[serde(with = "serde_chrono")]
@@ -1439,21 +2167,21 @@ pub struct Breadcrumb {
     pub ty: Annotated<String>,
 
     /// The optional category of the breadcrumb.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub category: Annotated<Option<String>>,
 
     /// Severity level of the breadcrumb (required).
-    #[serde(default)]
+    #[serde(default, deserialize_with = "level::deserialize_required")]
     pub level: Annotated<Level>,
 
     /// Human readable message for the breadcrumb.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "message")]
     pub message: Annotated<Option<String>>,
 
     /// Custom user-defined data of this breadcrumb.
-    #[serde(default, skip_serializing_if = "utils::is_empty_map")]
-    #[process_annotated_value(pii_kind = "databag")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_map")]
+    #[process_annotated_value(pii_kind = "databag", process_with = "breadcrumb::process_data")]
     pub data: Annotated<Map<Value>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -1462,6 +2190,135 @@ pub struct Breadcrumb {
     pub other: Annotated<Map<Value>>,
 }
 
+impl Breadcrumb {
+    /// Returns `true` if this breadcrumb describes an HTTP request.
+    pub fn is_http(&self) -> bool {
+        self.ty.value().map_or(false, |ty| ty == "http")
+    }
+
+    fn http_data(&self, key: &str) -> Option<&Value> {
+        if !self.is_http() {
+            return None;
+        }
+
+        self.data.value()?.get(key)?.value()
+    }
+
+    /// Returns the requested URL of an `http` breadcrumb.
+    pub fn http_url(&self) -> Option<&str> {
+        match self.http_data("url") {
+            Some(&Value::String(ref url)) => Some(url),
+            _ => None,
+        }
+    }
+
+    /// Returns the request method of an `http` breadcrumb as sent by the client.
+    pub fn http_method(&self) -> Option<&str> {
+        match self.http_data("method") {
+            Some(&Value::String(ref method)) => Some(method),
+            _ => None,
+        }
+    }
+
+    /// Returns the response status code of an `http` breadcrumb.
+    ///
+    /// Status codes may be sent as numbers or numeric strings, see `Value::as_u64_lossy`.
+    /// Returns `None` if the status code is missing or outside of the range of valid status
+    /// codes, `100` to `599`.
+    pub fn http_status_code(&self) -> Option<u16> {
+        let status_code = self.http_data("status_code")?.as_u64_lossy()?;
+
+        if status_code >= 100 && status_code < 600 {
+            Some(status_code as u16)
+        } else {
+            None
+        }
+    }
+}
+
+mod breadcrumb {
+    use processor::{ProcessAnnotatedValue, Processor, ValueInfo};
+
+    use super::super::common::remove_key;
+    use super::super::meta::Remark;
+    use super::*;
+
+    /// Processes the data of a breadcrumb, restricting the URL of requests to its query.
+    pub fn process_data(
+        annotated: Annotated<Map<Value>>,
+        processor: &Processor,
+        info: &ValueInfo,
+    ) -> Annotated<Map<Value>> {
+        let Annotated(map, meta) = annotated;
+        let mut map = match map {
+            Some(map) => map,
+            None => return Annotated(None, meta),
+        };
+
+        let url = remove_key(&mut map, "url");
+        let mut data = ProcessAnnotatedValue::process_annotated_value(
+            Annotated::new(map, meta),
+            processor,
+            info,
+        );
+        if let (Some(url), Some(map)) = (url, data.value_mut()) {
+            map.insert(
+                "url".to_string(),
+                process_url_query(url, processor, &info.derive()),
+            );
+        }
+        data
+    }
+
+    /// Processes the query of a URL, leaving its scheme, host, path and fragment intact.
+    ///
+    /// Remarks of the query are moved to their position in the entire URL.
+    fn process_url_query(
+        annotated: Annotated<Value>,
+        processor: &Processor,
+        info: &ValueInfo,
+    ) -> Annotated<Value> {
+        let (url, mut meta) = match annotated {
+            Annotated(Some(Value::String(url)), meta) => (url, meta),
+            other => return ProcessAnnotatedValue::process_annotated_value(other, processor, info),
+        };
+
+        let start = match url.find('?') {
+            Some(index) => index + 1,
+            None => return Annotated(Some(Value::String(url)), meta),
+        };
+        let end = url[start..]
+            .find('#')
+            .map_or(url.len(), |index| start + index);
+
+        let query = Annotated::from(url[start..end].to_string());
+        let Annotated(query, query_meta) = processor.process_string(query, info);
+
+        let offset = url[..start].chars().count();
+        for remark in query_meta.remarks() {
+            meta.remarks_mut().push(match remark.range() {
+                Some(&(from, to)) => {
+                    Remark::with_range(remark.ty(), remark.rule_id(), (from + offset, to + offset))
+                }
+                None => remark.clone(),
+            });
+        }
+        meta.errors_mut()
+            .extend(query_meta.errors().map(str::to_string));
+        if query_meta.original_length().is_some() && meta.original_length().is_none() {
+            meta.set_original_length(Some(url.chars().count() as u32));
+        }
+
+        let url = format!(
+            "{}{}{}",
+            &url[..start],
+            query.as_ref().map_or("", String::as_str),
+            &url[end..]
+        );
+        Annotated(Some(Value::String(url)), meta)
+    }
+}
+
 #[cfg(test)]
 mod test_breadcrumb {
     use chrono::{TimeZone, Utc};
@@ -1530,9 +2387,74 @@ mod test_breadcrumb {
     }
 
     #[test]
-    fn test_invalid() {
-        let breadcrumb: Annotated<Breadcrumb> = Annotated::from_error("missing field `timestamp`");
-        assert_eq_dbg!(breadcrumb, serde_json::from_str("{}").unwrap());
+    fn test_invalid() {
+        let breadcrumb: Annotated<Breadcrumb> = Annotated::from_error("missing field `timestamp`");
+        assert_eq_dbg!(breadcrumb, serde_json::from_str("{}").unwrap());
+    }
+
+    #[test]
+    fn test_http_accessors() {
+        let json = r#"{
+  "timestamp": 946684800,
+  "type": "http",
+  "data": {
+    "url": "https://example.com/api",
+    "method": "post",
+    "status_code": "404"
+  }
+}"#;
+
+        let breadcrumb: Breadcrumb = serde_json::from_str(json).unwrap();
+        assert!(breadcrumb.is_http());
+        assert_eq!(breadcrumb.http_url(), Some("https://example.com/api"));
+        assert_eq!(breadcrumb.http_method(), Some("post"));
+        assert_eq!(breadcrumb.http_status_code(), Some(404));
+
+        let json = r#"{"timestamp": 946684800, "data": {"url": "https://example.com/"}}"#;
+        let breadcrumb: Breadcrumb = serde_json::from_str(json).unwrap();
+        assert!(!breadcrumb.is_http());
+        assert_eq!(breadcrumb.http_url(), None);
+    }
+
+    #[test]
+    fn test_http_invalid_status_code() {
+        let json = r#"{"timestamp": 946684800, "type": "http", "data": {"status_code": 42}}"#;
+        let breadcrumb: Breadcrumb = serde_json::from_str(json).unwrap();
+        assert_eq!(breadcrumb.http_status_code(), None);
+    }
+
+    #[test]
+    fn test_url_query_pii() {
+        use processor::PiiConfig;
+
+        let cfg = PiiConfig::from_json(r#"{"applications": {"databag": ["@ip"]}}"#).unwrap();
+        let json = r#"{
+  "timestamp": 0,
+  "type": "http",
+  "data": {"url": "https://10.0.0.1/api?ip=10.0.0.2#top", "host": "10.0.0.1"}
+}"#;
+        let breadcrumb = Annotated::<Breadcrumb>::from_json(json).unwrap();
+        let breadcrumb = cfg.processor().process_root_value(breadcrumb);
+        let data = breadcrumb.0.unwrap().data.0.unwrap();
+
+        let url = &data["url"];
+        assert_eq_dbg!(
+            url.value(),
+            Some(&Value::String("https://10.0.0.1/api?ip=[ip]#top".to_string()))
+        );
+        assert_eq_dbg!(
+            url.meta().remarks().collect::<Vec<_>>(),
+            vec![&Remark::with_range(
+                RemarkType::Substituted,
+                "@ip:replace",
+                (24, 28),
+            )]
+        );
+
+        assert_eq_dbg!(
+            data["host"].value(),
+            Some(&Value::String("[ip]".to_string()))
+        );
     }
 }
 
@@ -1549,10 +2471,11 @@ pub struct Addr(pub u64);
 impl_hex_serde!(Addr, u64);
 
 /// Single frame in a stack trace.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Frame {
     /// Name of the frame's function. This might include the name of a class.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub function: Annotated<Option<String>>,
 
     /// Potentially mangled name of the symbol as it appears in an executable.
@@ -1560,14 +2483,14 @@ pub struct Frame {
     /// This is different from a function name by generally being the mangled
     /// name that appears natively in the binary.  This is relevant for languages
     /// like Swift, C++ or Rust.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub symbol: Annotated<Option<String>>,
 
     /// Name of the module the frame is contained in.
     ///
     /// Note that this might also include a class name if that is something the
     /// language natively considers to be part of the stack (for instance in Java).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform")]
     // TODO: Cap? This can be a FS path or a dotted path
     pub module: Annotated<Option<String>>,
@@ -1576,60 +2499,60 @@ pub struct Frame {
     ///
     /// For instance this can be a dylib for native languages, the name of the jar
     /// or .NET assembly.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform")]
     // TODO: Cap? This can be a FS path or a dotted path
     pub package: Annotated<Option<String>>,
 
     /// The source file name (basename only).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "short_path")]
     pub filename: Annotated<Option<String>>,
 
     /// Absolute path to the source file.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "path")]
     pub abs_path: Annotated<Option<String>>,
 
     /// Line number within the source file.
-    #[serde(default, rename = "lineno", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "lineno", skip_serializing_if = "ser_utils::is_none")]
     pub line: Annotated<Option<u64>>,
 
     /// Column number within the source file.
-    #[serde(default, rename = "colno", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "colno", skip_serializing_if = "ser_utils::is_none")]
     pub column: Annotated<Option<u64>>,
 
     /// Source code leading up to the current line.
-    #[serde(default, rename = "pre_context", skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, rename = "pre_context", skip_serializing_if = "ser_utils::is_empty_array")]
     pub pre_lines: Annotated<Array<String>>,
 
     /// Source code of the current line.
-    #[serde(default, rename = "context_line", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "context_line", skip_serializing_if = "ser_utils::is_none")]
     pub current_line: Annotated<Option<String>>,
 
     /// Source code of the lines after the current line.
-    #[serde(default, rename = "post_context", skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, rename = "post_context", skip_serializing_if = "ser_utils::is_empty_array")]
     pub post_lines: Annotated<Array<String>>,
 
     /// Override whether this frame should be considered in-app.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub in_app: Annotated<Option<bool>>,
 
     /// Local variables in a convenient format.
-    #[serde(default, skip_serializing_if = "utils::is_empty_map")]
-    #[process_annotated_value(pii_kind = "databag")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_map")]
+    #[process_annotated_value(pii_kind = "vars")]
     pub vars: Annotated<Map<Value>>,
 
     /// Start address of the containing code module (image).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub image_addr: Annotated<Option<Addr>>,
 
     /// Absolute address of the frame's CPU instruction.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub instruction_addr: Annotated<Option<Addr>>,
 
     /// Start address of the frame's function.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub symbol_addr: Annotated<Option<Addr>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -1737,18 +2660,19 @@ mod test_frame {
 }
 
 /// Stack trace containing a thread's frames.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Stacktrace {
     /// List of frames in this stack trace (required).
     #[process_annotated_value]
     pub frames: Annotated<Array<Frame>>,
 
     /// Omitted segment of frames (start, end).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub frames_omitted: Annotated<Option<(u64, u64)>>,
 
     /// Register values of the thread (top frame).
-    #[serde(default, skip_serializing_if = "utils::is_empty_map")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_map")]
     pub registers: Annotated<Map<RegVal>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -1825,18 +2749,20 @@ mod test_stacktrace {
 }
 
 /// POSIX signal with optional extended data.
-#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct CError {
     /// The error code as specified by ISO C99, POSIX.1-2001 or POSIX.1-2008.
     pub number: Annotated<i32>,
 
     /// Optional name of the errno constant.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub name: Annotated<Option<String>>,
 }
 
 /// Mach exception information.
-#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct MachException {
     /// The mach exception type.
     #[serde(rename = "exception")]
@@ -1849,42 +2775,44 @@ pub struct MachException {
     pub subcode: Annotated<u64>,
 
     /// Optional name of the mach exception.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub name: Annotated<Option<String>>,
 }
 
 /// POSIX signal with optional extended data.
-#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct PosixSignal {
     /// The POSIX signal number.
     pub number: Annotated<i32>,
 
     /// An optional signal code present on Apple systems.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub code: Annotated<Option<i32>>,
 
     /// Optional name of the errno constant.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub name: Annotated<Option<String>>,
 
     /// Optional name of the errno constant.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub code_name: Annotated<Option<String>>,
 }
 
 /// Operating system or runtime meta information to an exception mechanism.
-#[derive(Debug, Clone, Default, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct MechanismMeta {
     /// Optional ISO C standard error code.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub errno: Annotated<Option<CError>>,
 
     /// Optional POSIX signal number.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub signal: Annotated<Option<PosixSignal>>,
 
     /// Optional mach exception information.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub mach_exception: Annotated<Option<MachException>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -1894,39 +2822,45 @@ pub struct MechanismMeta {
 
 impl MechanismMeta {
     fn is_empty(&self) -> bool {
-        utils::is_none(&self.errno)
-            && utils::is_none(&self.signal)
-            && utils::is_none(&self.mach_exception)
-            && utils::is_empty_map(&self.other)
+        ser_utils::is_none(&self.errno)
+            && ser_utils::is_none(&self.signal)
+            && ser_utils::is_none(&self.mach_exception)
+            && ser_utils::is_empty_map(&self.other)
     }
 
     fn is_empty_annotated(annotated: &Annotated<Self>) -> bool {
-        utils::skip_if(annotated, MechanismMeta::is_empty)
+        ser_utils::skip_if(annotated, MechanismMeta::is_empty)
     }
 }
 
 /// The mechanism by which an exception was generated and handled.
-#[derive(Debug, Clone, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Mechanism {
     /// Mechanism type (required).
     #[serde(rename = "type")]
     pub ty: Annotated<String>,
 
     /// Human readable detail description.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "message")]
     pub description: Annotated<Option<String>>,
 
     /// Link to online resources describing this error.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub help_link: Annotated<Option<String>>,
 
     /// Flag indicating whether this exception was handled.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub handled: Annotated<Option<bool>>,
 
+    /// Flag indicating that the exception was not raised by the application, but created from
+    /// other data of the event, such as its message.
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
+    pub synthetic: Annotated<Option<bool>>,
+
     /// Additional attributes depending on the mechanism type.
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
     #[process_annotated_value(pii_kind = "databag")]
     pub data: Annotated<Map<Value>>,
 
@@ -1950,6 +2884,7 @@ mod mechanism {
         let mut description = None;
         let mut help_link = None;
         let mut handled = None;
+        let mut synthetic = None;
         let mut data = None;
         let mut meta = None;
         let mut other: Map<Value> = Default::default();
@@ -1961,6 +2896,7 @@ mod mechanism {
                 "description" => description = Some(Deserialize::deserialize(deserializer)?),
                 "help_link" => help_link = Some(Deserialize::deserialize(deserializer)?),
                 "handled" => handled = Some(Deserialize::deserialize(deserializer)?),
+                "synthetic" => synthetic = Some(Deserialize::deserialize(deserializer)?),
                 "data" => data = Some(Deserialize::deserialize(deserializer)?),
                 "meta" => meta = Some(Deserialize::deserialize(deserializer)?),
                 _ => {
@@ -1974,6 +2910,7 @@ mod mechanism {
             description: description.unwrap_or_default(),
             help_link: help_link.unwrap_or_default(),
             handled: handled.unwrap_or_default(),
+            synthetic: synthetic.unwrap_or_default(),
             data: data.unwrap_or_default(),
             meta: meta.unwrap_or_default(),
             other: Annotated::from(other),
@@ -2048,6 +2985,7 @@ mod mechanism {
             description: None.into(),
             help_link: None.into(),
             handled: None.into(),
+            synthetic: None.into(),
             data: data.into(),
             meta: meta.into(),
             other: Default::default(),
@@ -2109,6 +3047,7 @@ mod test_mechanism {
                 "https://developer.apple.com/library/content/qa/qa1367/_index.html".to_string(),
             ).into(),
             handled: Some(false).into(),
+            synthetic: None.into(),
             data: {
                 let mut map = Map::new();
                 map.insert(
@@ -2165,6 +3104,7 @@ mod test_mechanism {
             description: None.into(),
             help_link: None.into(),
             handled: None.into(),
+            synthetic: None.into(),
             data: Map::new().into(),
             meta: MechanismMeta::default().into(),
             other: Default::default(),
@@ -2195,6 +3135,7 @@ mod test_mechanism {
             description: None.into(),
             help_link: None.into(),
             handled: None.into(),
+            synthetic: None.into(),
             data: Map::new().into(),
             meta: MechanismMeta {
                 errno: Annotated::from_error("missing field `number`"),
@@ -2251,6 +3192,7 @@ mod test_mechanism {
             description: None.into(),
             help_link: None.into(),
             handled: None.into(),
+            synthetic: None.into(),
             data: {
                 let mut map = Map::new();
                 map.insert(
@@ -2294,38 +3236,39 @@ pub enum ThreadId {
 }
 
 /// An exception (error).
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Exception {
     /// Exception type (required).
     #[serde(rename = "type")]
     pub ty: Annotated<String>,
 
     /// Human readable display value.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "message")]
     pub value: Annotated<Option<String>>,
 
     /// Module name of this exception.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform")]
     pub module: Annotated<Option<String>>,
 
     /// Stack trace containing frames of this exception.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub stacktrace: Annotated<Option<Stacktrace>>,
 
     /// Optional unprocessed stack trace.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub raw_stacktrace: Annotated<Option<Stacktrace>>,
 
     /// Identifier of the thread this exception occurred in.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub thread_id: Annotated<Option<ThreadId>>,
 
     /// Mechanism by which this exception was generated and handled.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub mechanism: Annotated<Option<Mechanism>>,
 
@@ -2398,36 +3341,37 @@ mod test_exception {
 }
 
 /// Template debug information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct TemplateInfo {
     /// The file name (basename only).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "short_path")]
     pub filename: Annotated<Option<String>>,
 
     /// Absolute path to the file.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "path")]
     pub abs_path: Annotated<Option<String>>,
 
     /// Line number within the source file.
-    #[serde(default, rename = "lineno", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "lineno", skip_serializing_if = "ser_utils::is_none")]
     pub line: Annotated<Option<u64>>,
 
     /// Column number within the source file.
-    #[serde(default, rename = "colno", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "colno", skip_serializing_if = "ser_utils::is_none")]
     pub column: Annotated<Option<u64>>,
 
     /// Source code leading up to the current line.
-    #[serde(default, rename = "pre_context", skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, rename = "pre_context", skip_serializing_if = "ser_utils::is_empty_array")]
     pub pre_lines: Annotated<Array<String>>,
 
     /// Source code of the current line.
-    #[serde(default, rename = "context_line", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "context_line", skip_serializing_if = "ser_utils::is_none")]
     pub current_line: Annotated<Option<String>>,
 
     /// Source code of the lines after the current line.
-    #[serde(default, rename = "post_context", skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, rename = "post_context", skip_serializing_if = "ser_utils::is_empty_array")]
     pub post_lines: Annotated<Array<String>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -2499,33 +3443,34 @@ mod test_template_info {
 }
 
 /// A process thread of an event.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Thread {
     /// Identifier of this thread within the process (usually an integer).
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub id: Annotated<Option<ThreadId>>,
 
     /// Display name of this thread.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(cap = "summary")]
     pub name: Annotated<Option<String>>,
 
     /// Stack trace containing frames of this exception.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub stacktrace: Annotated<Option<Stacktrace>>,
 
     /// Optional unprocessed stack trace.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub raw_stacktrace: Annotated<Option<Stacktrace>>,
 
     /// Indicates that this thread requested the event (usually by crashing).
-    #[serde(default, skip_serializing_if = "utils::is_false")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_false")]
     pub crashed: Annotated<bool>,
 
     /// Indicates that the thread was not suspended when the event was created.
-    #[serde(default, skip_serializing_if = "utils::is_false")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_false")]
     pub current: Annotated<bool>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -2592,7 +3537,8 @@ mod thread {
 ///
 /// This is relevant for iOS and other platforms that have a system
 /// SDK.  Not to be confused with the client SDK.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SystemSdkInfo {
     /// The internal name of the SDK.
     pub sdk_name: Annotated<String>,
@@ -2613,21 +3559,22 @@ pub struct SystemSdkInfo {
 }
 
 /// Apple debug image in
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct AppleDebugImage {
     /// Path and name of the debug image (required).
     pub name: Annotated<String>,
 
     /// CPU architecture target.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub arch: Annotated<Option<String>>,
 
     /// MachO CPU type identifier.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub cpu_type: Annotated<Option<u32>>,
 
     /// MachO CPU subtype identifier.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub cpu_subtype: Annotated<Option<u32>>,
 
     /// Starting memory address of the image (required).
@@ -2650,13 +3597,14 @@ pub struct AppleDebugImage {
 }
 
 /// Any debug information file supported by symbolic.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct SymbolicDebugImage {
     /// Path and name of the debug image (required).
     pub name: Annotated<String>,
 
     /// CPU architecture target.
-    #[serde(default, skip_serializing_if = "utils::is_none")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
     pub arch: Annotated<Option<String>>,
 
     /// Starting memory address of the image (required).
@@ -2679,7 +3627,8 @@ pub struct SymbolicDebugImage {
 }
 
 /// Proguard mapping file.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct ProguardDebugImage {
     /// UUID computed from the file contents.
     pub uuid: Annotated<Uuid>,
@@ -3016,15 +3965,16 @@ mod test_debug_image {
 }
 
 /// Debugging and processing meta information.
-#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct DebugMeta {
     /// Information about the system SDK (e.g. iOS SDK).
-    #[serde(default, rename = "sdk_info", skip_serializing_if = "utils::is_none")]
+    #[serde(default, rename = "sdk_info", skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub system_sdk: Annotated<Option<SystemSdkInfo>>,
 
     /// List of debug information files (debug images).
-    #[serde(default, skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_array")]
     #[process_annotated_value]
     pub images: Annotated<Array<DebugImage>>,
 
@@ -3097,7 +4047,8 @@ mod test_debug_meta {
 }
 
 /// Information about the Sentry SDK.
-#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct ClientSdkInfo {
     /// Unique SDK name.
     pub name: Annotated<String>,
@@ -3106,7 +4057,7 @@ pub struct ClientSdkInfo {
     pub version: Annotated<String>,
 
     /// List of integrations that are enabled in the SDK.
-    #[serde(default, skip_serializing_if = "utils::is_empty_array")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_array")]
     pub integrations: Annotated<Array<String>>,
 
     /// Additional arbitrary fields for forwards compatibility.
@@ -3172,6 +4123,130 @@ mod test_client_sdk {
     }
 }
 
+/// A timed operation within a transaction.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Span {
+    /// Timestamp when the span ended (required).
+    #[serde(with = "serde_chrono")]
+    pub timestamp: Annotated<DateTime<Utc>>,
+
+    /// Timestamp when the span started (required).
+    #[serde(with = "serde_chrono")]
+    pub start_timestamp: Annotated<DateTime<Utc>>,
+
+    /// Human readable description of the span.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(pii_kind = "freeform", cap = "message")]
+    pub description: Annotated<Option<String>>,
+
+    /// Operation type of the span.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    #[process_annotated_value(cap = "summary")]
+    pub op: Annotated<Option<String>>,
+
+    /// The id of the span (16 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub span_id: Annotated<Option<SpanId>>,
+
+    /// The id of the parent span (16 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub parent_span_id: Annotated<Option<SpanId>>,
+
+    /// The id of the trace this span belongs to (32 hex characters).
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub trace_id: Annotated<Option<TraceId>>,
+
+    /// Arbitrary data associated with the span.
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_map")]
+    #[process_annotated_value(pii_kind = "databag")]
+    pub data: Annotated<Map<Value>>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    #[process_annotated_value(pii_kind = "databag")]
+    pub other: Annotated<Map<Value>>,
+}
+
+#[cfg(test)]
+mod test_span {
+    use chrono::{TimeZone, Utc};
+    use protocol::*;
+    use serde_json;
+
+    #[test]
+    fn test_roundtrip() {
+        let json = r#"{
+  "timestamp": 946684810,
+  "start_timestamp": 946684800,
+  "description": "GET /api/users",
+  "op": "http",
+  "span_id": "fa90fdead5f74052",
+  "parent_span_id": "fa90fdead5f74053",
+  "trace_id": "4c79f60c11214eb38604f4ae0781bfb2",
+  "data": {
+    "a": "b"
+  },
+  "c": "d"
+}"#;
+
+        let span = Annotated::from(Span {
+            timestamp: Utc.ymd(2000, 1, 1).and_hms(0, 0, 10).into(),
+            start_timestamp: Utc.ymd(2000, 1, 1).and_hms(0, 0, 0).into(),
+            description: Some("GET /api/users".to_string()).into(),
+            op: Some("http".to_string()).into(),
+            span_id: Some(SpanId("fa90fdead5f74052".to_string())).into(),
+            parent_span_id: Some(SpanId("fa90fdead5f74053".to_string())).into(),
+            trace_id: Some(TraceId("4c79f60c11214eb38604f4ae0781bfb2".to_string())).into(),
+            data: {
+                let mut map = Map::new();
+                map.insert(
+                    "a".to_string(),
+                    Annotated::from(Value::String("b".to_string())),
+                );
+                Annotated::from(map)
+            },
+            other: {
+                let mut map = Map::new();
+                map.insert(
+                    "c".to_string(),
+                    Annotated::from(Value::String("d".to_string())),
+                );
+                Annotated::from(map)
+            },
+        });
+
+        assert_eq_dbg!(span, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string_pretty(&span).unwrap());
+    }
+
+    #[test]
+    fn test_default_values() {
+        let json = r#"{"timestamp":946684810,"start_timestamp":946684800}"#;
+        let span = Annotated::from(Span {
+            timestamp: Utc.ymd(2000, 1, 1).and_hms(0, 0, 10).into(),
+            start_timestamp: Utc.ymd(2000, 1, 1).and_hms(0, 0, 0).into(),
+            description: None.into(),
+            op: None.into(),
+            span_id: None.into(),
+            parent_span_id: None.into(),
+            trace_id: None.into(),
+            data: Default::default(),
+            other: Default::default(),
+        });
+
+        assert_eq_dbg!(span, serde_json::from_str(json).unwrap());
+        assert_eq_str!(json, serde_json::to_string(&span).unwrap());
+    }
+
+    #[test]
+    fn test_missing_timestamps() {
+        let json = r#"{"timestamp":946684810}"#;
+        let span: Annotated<Span> = Annotated::from_error("missing field `start_timestamp`");
+        assert_eq_dbg!(span, serde_json::from_str(json).unwrap());
+    }
+}
+
 mod fingerprint {
     use serde::de;
 
@@ -3339,10 +4414,170 @@ mod test_fingerprint {
     }
 }
 
+/// An error used when parsing `EventType`.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid event type")]
+pub struct ParseEventTypeError;
+
+/// The type of an event, which determines the interfaces it carries.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventType {
+    /// Events without an exception, such as log messages.
+    Default,
+    /// Events carrying an exception.
+    Error,
+    /// Content Security Policy violation reports.
+    Csp,
+    /// HTTP Public Key Pinning violation reports.
+    Hpkp,
+    /// Certificate Transparency violation reports (`Expect-CT`).
+    ExpectCt,
+    /// OCSP stapling violation reports (`Expect-Staple`).
+    ExpectStaple,
+    /// Performance monitoring transactions with spans.
+    Transaction,
+}
+
+impl EventType {
+    /// Returns `true` for security reports.
+    pub fn is_security(self) -> bool {
+        match self {
+            EventType::Csp | EventType::Hpkp | EventType::ExpectCt | EventType::ExpectStaple => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the name of the interface required by security reports.
+    pub fn security_interface(self) -> Option<&'static str> {
+        match self {
+            EventType::Csp => Some("csp"),
+            EventType::Hpkp => Some("hpkp"),
+            EventType::ExpectCt => Some("expectct"),
+            EventType::ExpectStaple => Some("expectstaple"),
+            _ => None,
+        }
+    }
+}
+
+impl Default for EventType {
+    fn default() -> Self {
+        EventType::Default
+    }
+}
+
+impl str::FromStr for EventType {
+    type Err = ParseEventTypeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(match string {
+            "default" => EventType::Default,
+            "error" => EventType::Error,
+            "csp" => EventType::Csp,
+            "hpkp" => EventType::Hpkp,
+            "expectct" => EventType::ExpectCt,
+            "expectstaple" => EventType::ExpectStaple,
+            "transaction" => EventType::Transaction,
+            _ => return Err(ParseEventTypeError),
+        })
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EventType::Default => write!(f, "default"),
+            EventType::Error => write!(f, "error"),
+            EventType::Csp => write!(f, "csp"),
+            EventType::Hpkp => write!(f, "hpkp"),
+            EventType::ExpectCt => write!(f, "expectct"),
+            EventType::ExpectStaple => write!(f, "expectstaple"),
+            EventType::Transaction => write!(f, "transaction"),
+        }
+    }
+}
+
+impl_str_serde!(EventType);
+
+#[cfg(test)]
+mod test_event_type {
+    use protocol::*;
+    use serde_json;
+
+    #[test]
+    fn test_roundtrip() {
+        let ty: EventType = serde_json::from_str("\"expectct\"").unwrap();
+        assert_eq_dbg!(ty, EventType::ExpectCt);
+        assert!(ty.is_security());
+        assert_eq_str!(serde_json::to_string(&ty).unwrap(), "\"expectct\"");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let ty = Annotated::<EventType>::from_json("\"nope\"").unwrap();
+        assert_eq_dbg!(ty, Annotated::from_error("invalid event type"));
+    }
+}
+
+/// An error that occurred while processing an event.
+///
+/// Processing errors explain why fields of the event were removed or modified.  They are
+/// collected from the meta data of the event after processing.
+#[derive(Debug, Clone, Deserialize, PartialEq, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct EventProcessingError {
+    /// The type of the error, such as `invalid_data` or `value_too_long` (required).
+    #[serde(rename = "type")]
+    pub ty: Annotated<String>,
+
+    /// Path of the affected field.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub name: Annotated<Option<String>>,
+
+    /// Details of the error, such as an error message or the original length of the value.
+    #[serde(default, skip_serializing_if = "ser_utils::is_none")]
+    pub value: Annotated<Option<Value>>,
+
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    pub other: Annotated<Map<Value>>,
+}
+
+impl EventProcessingError {
+    /// Creates a processing error of the given type for a field.
+    pub fn new<S: Into<String>>(ty: S, name: Option<String>, value: Option<Value>) -> Self {
+        EventProcessingError {
+            ty: Annotated::from(ty.into()),
+            name: name.into(),
+            value: value.into(),
+            other: Map::new().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_event_processing_error {
+    use protocol::*;
+    use serde_json;
+
+    #[test]
+    fn test_roundtrip() {
+        let json = r#"{"type":"value_too_long","name":"message","value":1024,"other":"value"}"#;
+        let error: EventProcessingError = serde_json::from_str(json).unwrap();
+
+        assert_eq_str!(error.ty.value().unwrap(), "value_too_long");
+        assert_eq_dbg!(error.name.value(), Some(&Some("message".to_string())));
+        assert_eq_dbg!(error.value.value(), Some(&Some(Value::U64(1024))));
+        assert!(error.other.value().unwrap().contains_key("other"));
+        assert_eq_str!(serde_json::to_string(&error).unwrap(), json);
+    }
+}
+
 mod event {
     use std::collections::BTreeMap;
 
-    use super::super::utils;
+    use super::super::ser_utils;
     use super::*;
 
     pub fn serialize_id<S: Serializer>(
@@ -3371,13 +4606,13 @@ mod event {
     }
 
     pub fn is_default_fingerprint(annotated: &Annotated<Vec<String>>) -> bool {
-        utils::skip_if(annotated, |f| {
+        ser_utils::skip_if(annotated, |f| {
             f.len() == 1 && (f[0] == "{{default}}" || f[0] == "{{ default }}")
         })
     }
 
     pub fn is_default_platform(annotated: &Annotated<String>) -> bool {
-        utils::skip_if(annotated, |p| p == "other")
+        ser_utils::skip_if(annotated, |p| p == "other")
     }
 
     impl<'de> Deserialize<'de> for Event {
@@ -3387,12 +4622,15 @@ mod event {
             let mut fingerprint = None;
             let mut culprit = None;
             let mut transaction = None;
+            let mut ty = None;
             let mut message = None;
             let mut logentry = None;
             let mut logger = None;
             let mut modules = None;
             let mut platform = None;
             let mut timestamp = None;
+            let mut start_timestamp = None;
+            let mut received = None;
             let mut server_name = None;
             let mut release = None;
             let mut dist = None;
@@ -3406,11 +4644,14 @@ mod event {
             let mut stacktrace = None;
             let mut template_info = None;
             let mut threads = None;
+            let mut spans = None;
             let mut tags = None;
             let mut extra = None;
             let mut debug_meta = None;
             let mut client_sdk = None;
+            let mut errors = None;
             let mut other: Map<Value> = Default::default();
+            let mut origin_keys = OriginKeys::default();
 
             for (key, content) in BTreeMap::<String, Content>::deserialize(deserializer)? {
                 if key.starts_with('_') {
@@ -3420,63 +4661,110 @@ mod event {
                 let deserializer = ContentDeserializer::new(content);
                 match key.as_str() {
                     "event_id" => id = Some(Deserialize::deserialize(deserializer)?),
-                    "level" => level = Some(Deserialize::deserialize(deserializer)?),
+                    "level" => level = Some(level::deserialize(deserializer)?),
                     "fingerprint" => fingerprint = Some(fingerprint::deserialize(deserializer)?),
                     "culprit" => culprit = Some(Deserialize::deserialize(deserializer)?),
                     "transaction" => transaction = Some(Deserialize::deserialize(deserializer)?),
+                    "type" => ty = Some(Deserialize::deserialize(deserializer)?),
                     "message" => message = Some(Deserialize::deserialize(deserializer)?),
-                    "logentry" => logentry = Some(Deserialize::deserialize(deserializer)?),
+                    "logentry" => {
+                        logentry = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("logentry");
+                    }
                     "sentry.interfaces.Message" => if logentry.is_none() {
-                        logentry = Some(Deserialize::deserialize(deserializer)?)
+                        logentry = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("logentry", &key);
                     },
                     "logger" => logger = Some(Deserialize::deserialize(deserializer)?),
                     "modules" => modules = Some(Deserialize::deserialize(deserializer)?),
                     "platform" => platform = Some(Deserialize::deserialize(deserializer)?),
                     "timestamp" => timestamp = Some(serde_chrono::deserialize(deserializer)?),
+                    "start_timestamp" => {
+                        start_timestamp = Some(serde_chrono::deserialize(deserializer)?)
+                    }
+                    "received" => received = Some(serde_chrono::deserialize(deserializer)?),
                     "server_name" => server_name = Some(Deserialize::deserialize(deserializer)?),
                     "release" => release = Some(Deserialize::deserialize(deserializer)?),
                     "dist" => dist = Some(Deserialize::deserialize(deserializer)?),
                     "repos" => repos = Some(Deserialize::deserialize(deserializer)?),
                     "environment" => environment = Some(Deserialize::deserialize(deserializer)?),
-                    "user" => user = Some(Deserialize::deserialize(deserializer)?),
+                    "user" => {
+                        user = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("user");
+                    }
                     "sentry.interfaces.User" => if user.is_none() {
                         user = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("user", &key);
                     },
-                    "request" => request = Some(Deserialize::deserialize(deserializer)?),
+                    "request" => {
+                        request = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("request");
+                    }
                     "sentry.interfaces.Http" => if request.is_none() {
                         request = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("request", &key);
                     },
-                    "contexts" => contexts = Some(Deserialize::deserialize(deserializer)?),
+                    "contexts" => {
+                        contexts = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("contexts");
+                    }
                     "sentry.interfaces.Contexts" => if contexts.is_none() {
                         contexts = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("contexts", &key);
                     },
-                    "breadcrumbs" => breadcrumbs = Some(Deserialize::deserialize(deserializer)?),
+                    "breadcrumbs" => {
+                        breadcrumbs = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("breadcrumbs");
+                    }
                     "sentry.interfaces.Breadcrumbs" => if breadcrumbs.is_none() {
                         breadcrumbs = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("breadcrumbs", &key);
                     },
-                    "exception" => exceptions = Some(Deserialize::deserialize(deserializer)?),
+                    "exception" => {
+                        exceptions = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("exception");
+                    }
                     "sentry.interfaces.Exception" => if exceptions.is_none() {
-                        exceptions = Some(Deserialize::deserialize(deserializer)?)
+                        exceptions = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("exception", &key);
                     },
-                    "stacktrace" => stacktrace = Some(Deserialize::deserialize(deserializer)?),
+                    "stacktrace" => {
+                        stacktrace = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("stacktrace");
+                    }
                     "sentry.interfaces.Stacktrace" => if stacktrace.is_none() {
-                        stacktrace = Some(Deserialize::deserialize(deserializer)?)
+                        stacktrace = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("stacktrace", &key);
                     },
-                    "template" => template_info = Some(Deserialize::deserialize(deserializer)?),
+                    "template" => {
+                        template_info = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("template");
+                    }
                     "sentry.interfaces.Template" => if template_info.is_none() {
-                        template_info = Some(Deserialize::deserialize(deserializer)?)
+                        template_info = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("template", &key);
                     },
-                    "threads" => threads = Some(Deserialize::deserialize(deserializer)?),
+                    "threads" => {
+                        threads = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("threads");
+                    }
                     "sentry.interfaces.Threads" => if threads.is_none() {
-                        threads = Some(Deserialize::deserialize(deserializer)?)
+                        threads = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("threads", &key);
                     },
+                    "spans" => spans = Some(Deserialize::deserialize(deserializer)?),
                     "tags" => tags = Some(Deserialize::deserialize(deserializer)?),
                     "extra" => extra = Some(Deserialize::deserialize(deserializer)?),
-                    "debug_meta" => debug_meta = Some(Deserialize::deserialize(deserializer)?),
+                    "debug_meta" => {
+                        debug_meta = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.clear("debug_meta");
+                    }
                     "sentry.interfaces.DebugMeta" => if debug_meta.is_none() {
-                        debug_meta = Some(Deserialize::deserialize(deserializer)?)
+                        debug_meta = Some(Deserialize::deserialize(deserializer)?);
+                        origin_keys.record("debug_meta", &key);
                     },
                     "sdk" => client_sdk = Some(Deserialize::deserialize(deserializer)?),
+                    "errors" => errors = Some(Deserialize::deserialize(deserializer)?),
                     _ => {
                         other.insert(key, Deserialize::deserialize(deserializer)?);
                     }
@@ -3489,12 +4777,15 @@ mod event {
                 fingerprint: fingerprint.unwrap_or_else(fingerprint::default),
                 culprit: culprit.unwrap_or_default(),
                 transaction: transaction.unwrap_or_default(),
+                ty: ty.unwrap_or_default(),
                 message: message.unwrap_or_default(),
                 logentry: logentry.unwrap_or_default(),
                 logger: logger.unwrap_or_default(),
                 modules: modules.unwrap_or_default(),
                 platform: platform.unwrap_or_else(default_platform),
                 timestamp: timestamp.unwrap_or_default(),
+                start_timestamp: start_timestamp.unwrap_or_default(),
+                received: received.unwrap_or_default(),
                 server_name: server_name.unwrap_or_default(),
                 release: release.unwrap_or_default(),
                 dist: dist.unwrap_or_default(),
@@ -3508,29 +4799,35 @@ mod event {
                 stacktrace: stacktrace.unwrap_or_default(),
                 template_info: template_info.unwrap_or_default(),
                 threads: threads.unwrap_or_default(),
+                spans: spans.unwrap_or_default(),
                 tags: tags.unwrap_or_default(),
                 extra: extra.unwrap_or_default(),
                 debug_meta: debug_meta.unwrap_or_default(),
                 client_sdk: client_sdk.unwrap_or_default(),
+                errors: errors.unwrap_or_default(),
                 other: Annotated::from(other),
+                origin_keys,
             })
         }
     }
 }
 
 /// Represents a full event for Sentry.
-#[derive(Debug, Clone, Default, PartialEq, ProcessAnnotatedValue, Serialize)]
+///
+/// Events compare equal regardless of their `origin_keys`.
+#[derive(Debug, Clone, Default, ProcessAnnotatedValue, Serialize, Traverse)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Event {
     /// Unique identifier of this event.
     #[serde(
         rename = "event_id",
-        skip_serializing_if = "utils::is_none",
+        skip_serializing_if = "ser_utils::is_none",
         serialize_with = "event::serialize_id"
     )]
     pub id: Annotated<Option<Uuid>>,
 
     /// Severity level of the event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub level: Annotated<Option<Level>>,
 
     /// Manual fingerprint override.
@@ -3539,30 +4836,36 @@ pub struct Event {
     pub fingerprint: Annotated<Vec<String>>,
 
     /// Custom culprit of the event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub culprit: Annotated<Option<String>>,
 
     /// Transaction name of the event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub transaction: Annotated<Option<String>>,
 
+    /// Type of the event (for instance "transaction").
+    ///
+    /// Use `Event::event_type` to get the type of events that do not declare it.
+    #[serde(rename = "type", skip_serializing_if = "ser_utils::is_none")]
+    pub ty: Annotated<Option<EventType>>,
+
     /// Custom message for this event.
     // TODO: Consider to normalize this right away into logentry
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "freeform", cap = "message")]
     pub message: Annotated<Option<String>>,
 
     /// Custom parameterized message for this event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub logentry: Annotated<Option<LogEntry>>,
 
     /// Logger that created the event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub logger: Annotated<Option<String>>,
 
     /// Name and versions of installed modules.
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
     pub modules: Annotated<Map<String>>,
 
     /// Platform identifier of this event (defaults to "other").
@@ -3570,93 +4873,330 @@ pub struct Event {
     pub platform: Annotated<String>,
 
     /// Timestamp when the event was created.
-    #[serde(with = "serde_chrono", skip_serializing_if = "utils::is_none")]
+    #[serde(with = "serde_chrono", skip_serializing_if = "ser_utils::is_none")]
     pub timestamp: Annotated<Option<DateTime<Utc>>>,
 
+    /// Timestamp when a transaction started.
+    #[serde(with = "serde_chrono", skip_serializing_if = "ser_utils::is_none")]
+    pub start_timestamp: Annotated<Option<DateTime<Utc>>>,
+
+    /// Timestamp when the event was received by the server or relay.
+    #[serde(with = "serde_chrono", skip_serializing_if = "ser_utils::is_none")]
+    pub received: Annotated<Option<DateTime<Utc>>>,
+
     /// Server or device name the event was generated on.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value(pii_kind = "hostname")]
     pub server_name: Annotated<Option<String>>,
 
     /// Program's release identifier.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub release: Annotated<Option<String>>,
 
     /// Program's distribution identifier.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub dist: Annotated<Option<String>>,
 
     /// References to source code repositories.
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
     pub repos: Annotated<Map<RepoReference>>,
 
     /// Environment the environment was generated in ("production" or "development").
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     pub environment: Annotated<Option<String>>,
 
     /// Information about the user who triggered this event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub user: Annotated<Option<User>>,
 
     /// Information about a web request that occurred during the event.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub request: Annotated<Option<Request>>,
 
     /// Contexts describing the environment (e.g. device, os or browser).
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
     #[process_annotated_value]
     pub contexts: Annotated<Map<Context>>,
 
     /// List of breadcrumbs recorded before this event.
-    #[serde(skip_serializing_if = "utils::is_empty_values")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_values")]
     #[process_annotated_value]
     pub breadcrumbs: Annotated<Values<Breadcrumb>>,
 
     /// One or multiple chained (nested) exceptions.
-    #[serde(rename = "exception", skip_serializing_if = "utils::is_empty_values")]
+    #[serde(rename = "exception", skip_serializing_if = "ser_utils::is_empty_values")]
     #[process_annotated_value]
     pub exceptions: Annotated<Values<Exception>>,
 
     /// Deprecated event stacktrace.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub stacktrace: Annotated<Option<Stacktrace>>,
 
     /// Simplified template error location information.
-    #[serde(rename = "template", skip_serializing_if = "utils::is_none")]
+    #[serde(rename = "template", skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub template_info: Annotated<Option<TemplateInfo>>,
 
     /// Threads that were active when the event occurred.
-    #[serde(default, skip_serializing_if = "utils::is_empty_values")]
+    #[serde(default, skip_serializing_if = "ser_utils::is_empty_values")]
     #[process_annotated_value]
     pub threads: Annotated<Values<Thread>>,
 
+    /// Timed operations recorded within a transaction.
+    #[serde(skip_serializing_if = "ser_utils::is_empty_array")]
+    #[process_annotated_value]
+    pub spans: Annotated<Array<Span>>,
+
     /// Custom tags for this event.
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
     #[process_annotated_value(pii_kind = "databag")]
     pub tags: Annotated<Map<String>>,
 
     /// Arbitrary extra information set by the user.
-    #[serde(skip_serializing_if = "utils::is_empty_map")]
-    #[process_annotated_value(pii_kind = "databag")]
+    #[serde(skip_serializing_if = "ser_utils::is_empty_map")]
+    #[process_annotated_value(pii_kind = "databag", cap = "databag")]
     pub extra: Annotated<Map<Value>>,
 
     /// Meta data for event processing and debugging.
-    #[serde(skip_serializing_if = "utils::is_none")]
+    #[serde(skip_serializing_if = "ser_utils::is_none")]
     #[process_annotated_value]
     pub debug_meta: Annotated<Option<DebugMeta>>,
 
     /// Information about the Sentry SDK that generated this event.
-    #[serde(rename = "sdk", skip_serializing_if = "utils::is_none")]
+    #[serde(rename = "sdk", skip_serializing_if = "ser_utils::is_none")]
     pub client_sdk: Annotated<Option<ClientSdkInfo>>,
 
+    /// Errors that occurred while processing the event.
+    #[serde(skip_serializing_if = "ser_utils::is_empty_array")]
+    #[process_annotated_value]
+    pub errors: Annotated<Array<EventProcessingError>>,
+
     /// Additional arbitrary fields for forwards compatibility.
     #[serde(flatten)]
     #[process_annotated_value(pii_kind = "databag")]
     pub other: Annotated<Map<Value>>,
+
+    /// Legacy keys under which interfaces were sent in the original payload.
+    #[serde(skip)]
+    pub origin_keys: OriginKeys,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        // origin keys describe the layout of the original payload, not the event.
+        let Event {
+            ref id,
+            ref level,
+            ref fingerprint,
+            ref culprit,
+            ref transaction,
+            ref ty,
+            ref message,
+            ref logentry,
+            ref logger,
+            ref modules,
+            ref platform,
+            ref timestamp,
+            ref start_timestamp,
+            ref received,
+            ref server_name,
+            ref release,
+            ref dist,
+            ref repos,
+            ref environment,
+            ref user,
+            ref request,
+            ref contexts,
+            ref breadcrumbs,
+            ref exceptions,
+            ref stacktrace,
+            ref template_info,
+            ref threads,
+            ref spans,
+            ref tags,
+            ref extra,
+            ref debug_meta,
+            ref client_sdk,
+            ref errors,
+            ref other,
+            origin_keys: _,
+        } = *self;
+
+        *id == other.id
+            && *level == other.level
+            && *fingerprint == other.fingerprint
+            && *culprit == other.culprit
+            && *transaction == other.transaction
+            && *ty == other.ty
+            && *message == other.message
+            && *logentry == other.logentry
+            && *logger == other.logger
+            && *modules == other.modules
+            && *platform == other.platform
+            && *timestamp == other.timestamp
+            && *start_timestamp == other.start_timestamp
+            && *received == other.received
+            && *server_name == other.server_name
+            && *release == other.release
+            && *dist == other.dist
+            && *repos == other.repos
+            && *environment == other.environment
+            && *user == other.user
+            && *request == other.request
+            && *contexts == other.contexts
+            && *breadcrumbs == other.breadcrumbs
+            && *exceptions == other.exceptions
+            && *stacktrace == other.stacktrace
+            && *template_info == other.template_info
+            && *threads == other.threads
+            && *spans == other.spans
+            && *tags == other.tags
+            && *extra == other.extra
+            && *debug_meta == other.debug_meta
+            && *client_sdk == other.client_sdk
+            && *errors == other.errors
+            && *other == other.other
+    }
+}
+
+impl Event {
+    /// Returns the type of this event.
+    ///
+    /// If the event does not declare a valid type, it is `error` if the event carries an
+    /// exception and `default` otherwise.
+    pub fn event_type(&self) -> EventType {
+        if let Some(&Some(ty)) = self.ty.value() {
+            return ty;
+        }
+
+        let has_exception = self
+            .exceptions
+            .value()
+            .and_then(|values| values.values.value())
+            .map_or(false, |exceptions| !exceptions.is_empty());
+
+        if has_exception {
+            EventType::Error
+        } else {
+            EventType::Default
+        }
+    }
+
+    /// Checks whether two events contain the same data.
+    ///
+    /// Unlike `PartialEq`, this ignores meta data, the order of keys in objects and the
+    /// representation of numbers, so that `1` and `1.0` are equal.  Keys with a `null` value are
+    /// considered equal to missing keys.  The order of arrays is significant.
+    pub fn semantically_equals(&self, other: &Event) -> bool {
+        match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(a), Ok(b)) => json_semantically_equals(&a, &b),
+            _ => false,
+        }
+    }
+}
+
+/// Compares two JSON values for `Event::semantically_equals`.
+fn json_semantically_equals(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value;
+
+    fn is_significant((key, value): (&String, &Value)) -> bool {
+        !value.is_null() && !key.is_empty() && key != "_meta"
+    }
+
+    match (a, b) {
+        (&Value::Number(ref a), &Value::Number(ref b)) => {
+            if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+                a == b
+            } else if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+                a == b
+            } else {
+                a.as_f64() == b.as_f64()
+            }
+        }
+        (&Value::Array(ref a), &Value::Array(ref b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_semantically_equals(a, b))
+        }
+        (&Value::Object(ref a), &Value::Object(ref b)) => {
+            a.iter().filter(|&e| is_significant(e)).count()
+                == b.iter().filter(|&e| is_significant(e)).count()
+                && a.iter()
+                    .filter(|&e| is_significant(e))
+                    .all(|(key, a)| b.get(key).map_or(false, |b| json_semantically_equals(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Legacy keys under which interfaces were sent in the original payload of an event.
+///
+/// Interfaces sent under legacy names like `"sentry.interfaces.Http"` are moved to their
+/// canonical fields during deserialization.  The original keys are recorded here, so that
+/// `Annotated::<Event>::to_json_with_origin_keys` can restore them for debugging.  Since they
+/// describe the payload rather than the event, they are ignored when comparing events.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OriginKeys(BTreeMap<&'static str, String>);
+
+impl OriginKeys {
+    /// Returns the original key of the given field, if it was sent under a legacy key.
+    ///
+    /// Fields are identified by their serialized names, for instance `"exception"`.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0.get(field).map(String::as_str)
+    }
+
+    /// Returns `true` if all interfaces were sent under their canonical keys.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates serialized field names along with their original keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.0.iter().map(|(field, key)| (*field, key.as_str()))
+    }
+
+    /// Records the original key of a field.
+    pub(crate) fn record(&mut self, field: &'static str, key: &str) {
+        self.0.insert(field, key.to_string());
+    }
+
+    /// Forgets the original key of a field, once it was sent under its canonical key.
+    pub(crate) fn clear(&mut self, field: &str) {
+        self.0.remove(field);
+    }
+}
+
+/// Renames the canonical keys of an object to the original keys of the event.
+fn restore_origin_keys(object: &mut serde_json::Map<String, serde_json::Value>, keys: &OriginKeys) {
+    for (field, key) in keys.iter() {
+        if let Some(value) = object.remove(field) {
+            object.insert(key.to_string(), value);
+        }
+    }
+}
+
+impl Annotated<Event> {
+    /// Serializes the event with meta data, restoring the original keys of legacy interfaces.
+    ///
+    /// Interfaces that were sent under legacy names are written under these names, both in the
+    /// event and in its meta data.  This is intended for debugging the normalization of legacy
+    /// payloads.  The output should not be forwarded, since it is not in the canonical format.
+    pub fn to_json_with_origin_keys(&self) -> Result<String, serde_json::Error> {
+        let mut value = self.to_value()?;
+
+        if let (Some(event), &mut serde_json::Value::Object(ref mut object)) =
+            (self.value(), &mut value)
+        {
+            restore_origin_keys(object, &event.origin_keys);
+            if let Some(&mut serde_json::Value::Object(ref mut meta)) = object.get_mut("_meta") {
+                restore_origin_keys(meta, &event.origin_keys);
+            }
+        }
+
+        serde_json::to_string(&value)
+    }
 }
 
 #[cfg(test)]
@@ -3724,6 +5264,7 @@ mod test_event {
             fingerprint: Annotated::from(vec!["myprint".to_string()]),
             culprit: Some("myculprit".to_string()).into(),
             transaction: Some("mytransaction".to_string()).into(),
+            ty: None.into(),
             message: Some("mymessage".to_string()).into(),
             logentry: None.into(),
             logger: Some("mylogger".to_string()).into(),
@@ -3734,6 +5275,8 @@ mod test_event {
             },
             platform: "myplatform".to_string().into(),
             timestamp: Some(Utc.ymd(2000, 1, 1).and_hms(0, 0, 0)).into(),
+            start_timestamp: None.into(),
+            received: None.into(),
             server_name: Some("myhost".to_string()).into(),
             release: Some("myrelease".to_string()).into(),
             dist: Some("mydist".to_string()).into(),
@@ -3747,6 +5290,7 @@ mod test_event {
             stacktrace: None.into(),
             template_info: None.into(),
             threads: Default::default(),
+            spans: Default::default(),
             tags: {
                 let mut map = Map::new();
                 map.insert("tag".to_string(), "value".to_string().into());
@@ -3762,6 +5306,7 @@ mod test_event {
             },
             debug_meta: None.into(),
             client_sdk: None.into(),
+            errors: Default::default(),
             other: {
                 let mut map = Map::new();
                 map.insert(
@@ -3770,6 +5315,7 @@ mod test_event {
                 );
                 Annotated::from(map)
             },
+            origin_keys: Default::default(),
         });
 
         assert_eq_dbg!(event, deserialize(json).unwrap());
@@ -3785,12 +5331,15 @@ mod test_event {
             fingerprint: vec!["{{ default }}".to_string()].into(),
             culprit: None.into(),
             transaction: None.into(),
+            ty: None.into(),
             message: None.into(),
             logentry: None.into(),
             logger: None.into(),
             modules: Default::default(),
             platform: "other".to_string().into(),
             timestamp: None.into(),
+            start_timestamp: None.into(),
+            received: None.into(),
             server_name: None.into(),
             release: None.into(),
             dist: None.into(),
@@ -3804,11 +5353,14 @@ mod test_event {
             stacktrace: None.into(),
             template_info: None.into(),
             threads: Default::default(),
+            spans: Default::default(),
             tags: Default::default(),
             extra: Default::default(),
             debug_meta: None.into(),
             client_sdk: None.into(),
+            errors: Default::default(),
             other: Default::default(),
+            origin_keys: Default::default(),
         });
 
         assert_eq_dbg!(event, serde_json::from_str(json).unwrap());
@@ -3860,12 +5412,15 @@ mod test_event {
             ),
             culprit: None.into(),
             transaction: None.into(),
+            ty: None.into(),
             message: None.into(),
             logentry: None.into(),
             logger: None.into(),
             modules: Default::default(),
             platform: Annotated::new("other".to_string(), Meta::from_error("some error")),
             timestamp: None.into(),
+            start_timestamp: None.into(),
+            received: None.into(),
             server_name: None.into(),
             release: None.into(),
             dist: None.into(),
@@ -3879,14 +5434,110 @@ mod test_event {
             stacktrace: None.into(),
             template_info: None.into(),
             threads: Default::default(),
+            spans: Default::default(),
             tags: Default::default(),
             extra: Default::default(),
             debug_meta: None.into(),
             client_sdk: None.into(),
+            errors: Default::default(),
             other: Default::default(),
+            origin_keys: Default::default(),
         });
 
         assert_eq_dbg!(event, deserialize(json).unwrap());
         assert_eq_str!(json, serialize(&event).unwrap());
     }
+
+    #[test]
+    fn test_transaction_spans() {
+        let json = r#"{
+  "type": "transaction",
+  "start_timestamp": 946684800,
+  "timestamp": 946684860,
+  "spans": [
+    {"start_timestamp": 946684810, "timestamp": 946684820, "op": "inside"},
+    {"start_timestamp": 946684790, "timestamp": 946684820, "op": "early"},
+    {"start_timestamp": 946684830, "timestamp": 946684820, "op": "reversed"}
+  ]
+}"#;
+
+        let event = deserialize(json).unwrap().0.unwrap();
+        assert_eq_dbg!(event.ty, Annotated::from(Some(EventType::Transaction)));
+        assert_eq_dbg!(event.event_type(), EventType::Transaction);
+        assert_eq_dbg!(
+            event.start_timestamp,
+            Annotated::from(Some(Utc.ymd(2000, 1, 1).and_hms(0, 0, 0)))
+        );
+
+        // spans are validated during normalization, not when parsing
+        let spans = event.spans.0.unwrap();
+        let ops: Vec<_> = spans
+            .iter()
+            .map(|span| span.value().unwrap().op.value().unwrap().as_ref().unwrap())
+            .collect();
+        assert_eq_dbg!(ops, vec!["inside", "early", "reversed"]);
+        assert!(spans.iter().all(|span| !span.meta().has_errors()));
+    }
+
+    #[test]
+    fn test_origin_keys() {
+        let json = r#"{
+  "sentry.interfaces.User": {"id": "1"},
+  "sentry.interfaces.Exception": {"values": [{"type": "Error"}]},
+  "request": {"url": "https://example.com/"},
+  "sentry.interfaces.Http": {"url": "https://example.org/"},
+  "sentry.interfaces.Threads": {"values": [{"id": 1}]},
+  "threads": {"values": [{"id": 2}]}
+}"#;
+
+        let event = deserialize(json).unwrap();
+        let origin_keys = &event.value().unwrap().origin_keys;
+        assert_eq!(origin_keys.get("user"), Some("sentry.interfaces.User"));
+        assert_eq!(
+            origin_keys.get("exception"),
+            Some("sentry.interfaces.Exception")
+        );
+        assert_eq!(origin_keys.get("request"), None);
+        assert_eq!(origin_keys.get("threads"), None);
+
+        let canonical = event.to_json().unwrap();
+        assert!(canonical.contains(r#""user":"#));
+        assert!(!canonical.contains("sentry.interfaces"));
+        assert_eq_dbg!(event, deserialize(&canonical).unwrap());
+
+        let restored = event.to_json_with_origin_keys().unwrap();
+        assert!(restored.contains(r#""sentry.interfaces.User":{"#));
+        assert!(restored.contains(r#""sentry.interfaces.Exception":{"#));
+        assert!(restored.contains(r#""request":{"#));
+        assert!(!restored.contains(r#""user":"#));
+        assert!(!restored.contains("example.org"));
+        assert!(restored.contains(r#""threads":{"#));
+    }
+
+    #[test]
+    fn test_semantically_equals() {
+        let event = deserialize(
+            r#"{
+  "logger": "mylogger",
+  "extra": {"a": 1, "b": [1, 2], "c": null},
+  "other": "value",
+  "_meta": {"logger": {"": {"err": ["some error"]}}}
+}"#,
+        ).unwrap();
+        let event = event.value().unwrap();
+
+        let same = deserialize(
+            r#"{"other": "value", "extra": {"b": [1.0, 2], "a": 1.0}, "logger": "mylogger"}"#,
+        ).unwrap();
+        assert!(event.semantically_equals(same.value().unwrap()));
+        assert!(same.value().unwrap().semantically_equals(event));
+
+        let reordered = deserialize(
+            r#"{"other": "value", "extra": {"b": [2, 1], "a": 1}, "logger": "mylogger"}"#,
+        ).unwrap();
+        assert!(!event.semantically_equals(reordered.value().unwrap()));
+
+        let missing = deserialize(r#"{"extra": {"a": 1, "b": [1, 2]}, "logger": "mylogger"}"#);
+        assert!(!event.semantically_equals(missing.unwrap().value().unwrap()));
+    }
 }
@@ -0,0 +1,148 @@
+//! Versions of the event protocol for interoperability with older consumers.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json;
+
+use super::meta::Annotated;
+use super::types::{Context, Event};
+
+/// An error used when parsing `ProtocolVersion`.
+#[derive(Debug, Fail)]
+#[fail(display = "unknown protocol version")]
+pub struct ParseProtocolVersionError;
+
+/// A version of the event protocol.
+///
+/// Consumers of serialized events may only understand fields up to a certain version.  Events
+/// can be serialized for such consumers with `Annotated::<Event>::to_json_for_version`, which
+/// omits all fields introduced after the requested version.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    /// The legacy protocol, sent as `sentry_version=6`, which predates transactions.
+    V6,
+    /// The current protocol, sent as `sentry_version=7`.
+    ///
+    /// Adds transactions with `start_timestamp`, `spans` and the `trace` context.
+    V7,
+}
+
+impl ProtocolVersion {
+    /// Returns the latest protocol version, which includes all fields.
+    pub fn latest() -> ProtocolVersion {
+        ProtocolVersion::V7
+    }
+
+    /// Returns the numeric version, as sent in the `sentry_version` field of the auth header.
+    pub fn as_u32(self) -> u32 {
+        match self {
+            ProtocolVersion::V6 => 6,
+            ProtocolVersion::V7 => 7,
+        }
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> ProtocolVersion {
+        ProtocolVersion::latest()
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_u32())
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = ParseProtocolVersionError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(match string.trim() {
+            "6" => ProtocolVersion::V6,
+            "7" => ProtocolVersion::V7,
+            _ => return Err(ParseProtocolVersionError),
+        })
+    }
+}
+
+impl Event {
+    /// Removes all fields that were introduced after the given protocol version.
+    ///
+    /// Meta data of removed fields is removed along with them.
+    pub fn downgrade(&mut self, version: ProtocolVersion) {
+        if version < ProtocolVersion::V7 {
+            self.start_timestamp = Annotated::empty();
+            self.spans = Annotated::empty();
+            if let Some(contexts) = self.contexts.value_mut() {
+                contexts.retain(|_, context| match context.value() {
+                    Some(&Context::Trace(_)) => false,
+                    _ => true,
+                });
+            }
+        }
+    }
+}
+
+impl Annotated<Event> {
+    /// Serializes the event with meta data for consumers of the given protocol version.
+    ///
+    /// Fields introduced after the requested version are omitted.  See `Event::downgrade`.
+    pub fn to_json_for_version(
+        &self,
+        version: ProtocolVersion,
+    ) -> Result<String, serde_json::Error> {
+        if version >= ProtocolVersion::latest() {
+            return self.to_json();
+        }
+
+        let mut annotated = self.clone();
+        if let Some(event) = annotated.value_mut() {
+            event.downgrade(version);
+        }
+        annotated.to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!("6".parse::<ProtocolVersion>().unwrap(), ProtocolVersion::V6);
+        assert_eq!("7".parse::<ProtocolVersion>().unwrap(), ProtocolVersion::V7);
+        assert_eq!(ProtocolVersion::V6.to_string(), "6");
+        assert!("5".parse::<ProtocolVersion>().is_err());
+        assert!("8".parse::<ProtocolVersion>().is_err());
+        assert!(ProtocolVersion::V6 < ProtocolVersion::latest());
+        assert_eq!(ProtocolVersion::latest(), ProtocolVersion::V7);
+    }
+
+    #[test]
+    fn test_to_json_for_version() {
+        let json = r#"{
+  "event_id": "52df9022835246eeb317dbd739ccd059",
+  "start_timestamp": 946684800,
+  "spans": [{"span_id": "fa90fdead5f74052", "trace_id": "4c79f60c11214eb38604f4ae0781bfb2"}],
+  "contexts": {
+    "os": {"type": "os", "name": "Linux"},
+    "trace": {"type": "trace", "trace_id": "4c79f60c11214eb38604f4ae0781bfb2"}
+  }
+}"#;
+        let event = Annotated::<Event>::from_json(json).unwrap();
+
+        let legacy = event.to_json_for_version(ProtocolVersion::V6).unwrap();
+        assert!(legacy.contains("event_id"));
+        assert!(legacy.contains(r#""os""#));
+        assert!(!legacy.contains("start_timestamp"));
+        assert!(!legacy.contains("spans"));
+        assert!(!legacy.contains(r#""trace""#));
+
+        let v7 = event.to_json_for_version(ProtocolVersion::V7).unwrap();
+        assert!(v7.contains("spans"));
+        assert!(v7.contains(r#""trace""#));
+        assert_eq_str!(v7, event.to_json().unwrap());
+    }
+}
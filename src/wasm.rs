@@ -0,0 +1,48 @@
+//! JavaScript bindings for running PII stripping in WebAssembly.
+//!
+//! Build with `make wasm` and generate the JavaScript glue with `wasm-bindgen`.
+
+use wasm_bindgen::prelude::*;
+
+use error::Error;
+use processor::PiiConfig;
+use protocol::{Annotated, Event};
+
+/// Parses an event, applies the PII config and serializes it including meta data.
+fn scrub_event_json(json: &str, config: &str) -> Result<String, Error> {
+    let config = PiiConfig::from_json(config).map_err(Error::invalid_config)?;
+    let event = Annotated::<Event>::from_json(json).map_err(|e| Error::parse(e, json))?;
+    let event = config.processor().process_event(event);
+    Ok(event.to_json()?)
+}
+
+/// Strips PII from an event JSON payload according to a PII config.
+///
+/// Both the event and the config are passed as JSON strings.  The returned event contains
+/// `_meta` data describing all modifications.  Throws an error if either payload is invalid.
+#[wasm_bindgen]
+pub fn scrub_event(json: &str, config: &str) -> Result<String, JsValue> {
+    scrub_event_json(json, config).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_event() {
+        let json = scrub_event_json(
+            r#"{"message":"hi foo@example.com"}"#,
+            r#"{"applications": {"freeform": ["@email"]}}"#,
+        ).unwrap();
+
+        assert!(json.contains(r#""message":"hi [email]""#));
+        assert!(json.contains(r#""_meta""#));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = scrub_event_json("{}", "[]").unwrap_err();
+        assert!(error.to_string().starts_with("invalid config"));
+    }
+}
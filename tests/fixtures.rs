@@ -0,0 +1,22 @@
+#[macro_use]
+extern crate marshal;
+
+use marshal::processor::{PiiConfig, PipelineConfig};
+
+static PII_CONFIG: &str = r#"{
+  "applications": {
+    "freeform": ["@email", "@userpath"],
+    "databag": ["@password"]
+  }
+}"#;
+
+fn config() -> PipelineConfig {
+    PipelineConfig {
+        pii: Some(PiiConfig::from_json(PII_CONFIG).expect("could not parse PII config")),
+        ..Default::default()
+    }
+}
+
+// Snapshots are stored in tests/payloads/snapshots and must be committed.  To record new
+// snapshots or accept changes, run with MARSHAL_UPDATE_SNAPSHOTS=1.
+run_fixture_dir!(payloads, "tests/payloads", config());
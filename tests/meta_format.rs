@@ -0,0 +1,76 @@
+extern crate marshal;
+
+use marshal::protocol::{Annotated, Event, Provenance, Remark, RemarkType, Value};
+
+// Meta data is consumed by clients that parse the `_meta` key themselves.  If any of these tests
+// fail, the serialized format has changed.  Unless the change is backwards compatible, increment
+// `META_FORMAT_VERSION`, update the expected documents below and the format in the README.
+
+static VALUE: &str = concat!(
+    r#"{"message":"mail [email]","logger":"my.lo...ger","release":null,"#,
+    r#""user":{"ip_address":"127.0.0.1"},"extra":{"token":null}}"#
+);
+
+static META: &str = concat!(
+    r#"{"extra":{"token":{"":{"rem":[["remove_token","x"]]}}},"#,
+    r#""logger":{"":{"rem":[["!limit","s",5,8]],"len":20}},"#,
+    r#""message":{"":{"rem":[["@email","s",5,12]],"len":21}},"#,
+    r#""release":{"":{"err":["invalid release"]}},"#,
+    r#""user":{"ip_address":{"":{"src":"inferred"}}}}"#
+);
+
+#[test]
+fn test_read_meta() {
+    let event = Annotated::<Event>::from_split_json(VALUE, META).unwrap();
+    let event = event.value().unwrap();
+
+    let message = event.message.meta();
+    assert_eq!(
+        message.remarks().collect::<Vec<_>>(),
+        vec![&Remark::with_range(
+            RemarkType::Substituted,
+            "@email",
+            (5, 12)
+        )]
+    );
+    assert_eq!(message.original_length(), Some(21));
+
+    let logger = event.logger.meta();
+    assert_eq!(logger.remarks().count(), 1);
+    assert_eq!(logger.original_length(), Some(20));
+
+    let release = event.release.meta();
+    assert_eq!(
+        release.errors().collect::<Vec<_>>(),
+        vec!["invalid release"]
+    );
+
+    let user = event.user.value().unwrap().as_ref().unwrap();
+    assert_eq!(user.ip_address.meta().provenance(), Provenance::Inferred);
+
+    let extra = event.extra.value().unwrap();
+    let token = &extra["token"];
+    assert_eq!(token.value(), None::<&Value>);
+    assert_eq!(
+        token.meta().remarks().collect::<Vec<_>>(),
+        vec![&Remark::new(RemarkType::Removed, "remove_token")]
+    );
+}
+
+#[test]
+fn test_write_meta() {
+    let event = Annotated::<Event>::from_split_json(VALUE, META).unwrap();
+    let (_, meta) = event.split_serialize().unwrap();
+    assert_eq!(meta, META);
+}
+
+#[test]
+fn test_embedded_meta() {
+    let event = Annotated::<Event>::from_split_json(VALUE, META).unwrap();
+    let json = event.to_json().unwrap();
+    let meta = format!(r#","_meta":{}}}"#, META);
+    assert!(json.ends_with(&meta), "unexpected meta data in {}", json);
+
+    let reparsed = Annotated::<Event>::from_json(&json).unwrap();
+    assert_eq!(reparsed.split_serialize().unwrap().1, META);
+}